@@ -4,6 +4,7 @@ import android.view.KeyEvent
 import io.github.sds100.keymapper.base.BuildConfig
 import io.github.sds100.keymapper.base.system.inputmethod.ImeInputEventInjector
 import io.github.sds100.keymapper.common.models.EvdevDeviceInfo
+import io.github.sds100.keymapper.common.models.EvdevKeyTrigger
 import io.github.sds100.keymapper.common.models.GrabTargetKeyCode
 import io.github.sds100.keymapper.common.models.GrabbedDeviceHandle
 import io.github.sds100.keymapper.common.utils.KMError
@@ -111,10 +112,11 @@ class InputEventHubImpl @Inject constructor(
         code: Int,
         value: Int,
         androidCode: Int,
+        flags: Int,
     ): Boolean {
         val info = evdevDevicesDelegate.getGrabbedDeviceInfo(deviceId) ?: return false
         val evdevEvent =
-            KMEvdevEvent(deviceId, info, type, code, value, androidCode, timeSec, timeUsec)
+            KMEvdevEvent(deviceId, info, type, code, value, androidCode, timeSec, timeUsec, flags)
 
         return onInputEvent(evdevEvent, InputEventDetectionSource.EVDEV)
     }
@@ -219,8 +221,10 @@ class InputEventHubImpl @Inject constructor(
 
     override fun unregisterClient(clientId: String) {
         Timber.d("InputEventHub: Unregistering client $clientId")
-        clients.remove(clientId)
+        val removedClient = clients.remove(clientId)
         invalidateGrabbedDevices()
+
+        removedClient?.keyTriggers?.keys?.forEach { deviceId -> invalidateKeyTriggers(deviceId) }
     }
 
     override fun setGrabTargets(clientId: String, devices: List<GrabTargetKeyCode>) {
@@ -260,6 +264,29 @@ class InputEventHubImpl @Inject constructor(
         invalidateGrabbedDevices()
     }
 
+    override fun setKeyTriggers(
+        clientId: String,
+        deviceId: Int,
+        triggers: List<EvdevKeyTrigger>,
+    ): KMResult<Unit> {
+        if (!clients.containsKey(clientId)) {
+            throw IllegalArgumentException(
+                "This client $clientId is not registered when trying to set key triggers!",
+            )
+        }
+
+        val client = clients[clientId]!!
+        val keyTriggers = if (triggers.isEmpty()) {
+            client.keyTriggers - deviceId
+        } else {
+            client.keyTriggers + (deviceId to triggers.toSet())
+        }
+
+        clients[clientId] = client.copy(keyTriggers = keyTriggers)
+
+        return invalidateKeyTriggers(deviceId)
+    }
+
     override fun injectEvdevEvent(
         deviceId: Int,
         type: Int,
@@ -352,6 +379,34 @@ class InputEventHubImpl @Inject constructor(
             .firstBlocking()
     }
 
+    override fun onEvdevAxisEvent(
+        deviceId: Int,
+        timeSec: Long,
+        timeUsec: Long,
+        type: Int,
+        code: Int,
+        value: Int,
+        axes: IntArray?,
+        axisValues: FloatArray?,
+    ): Boolean {
+        // No grab targets forward axes yet so pass the event through to the uinput device.
+        return false
+    }
+
+    override fun onEvdevKeyTriggerFired(deviceId: Int, triggerId: Int) {
+        if (logInputEventsEnabled.value) {
+            Timber.d("Evdev key trigger fired: deviceId=$deviceId triggerId=$triggerId")
+        }
+
+        for (clientContext in clients.values) {
+            val triggers = clientContext.keyTriggers[deviceId] ?: continue
+
+            if (triggers.any { it.id == triggerId }) {
+                clientContext.callback.onEvdevKeyTriggerFired(deviceId, triggerId)
+            }
+        }
+    }
+
     override fun onGrabbedDevicesChanged(devices: Array<out GrabbedDeviceHandle?>?) {
         val devicesList = devices?.filterNotNull()?.toList() ?: emptyList()
         evdevDevicesDelegate.onGrabbedDevicesChanged(devicesList)
@@ -367,6 +422,25 @@ class InputEventHubImpl @Inject constructor(
         evdevDevicesDelegate.setGrabTargets(devicesToGrab.toList())
     }
 
+    /**
+     * The system bridge only has one set of triggers for each device so send the triggers
+     * from every client.
+     */
+    private fun invalidateKeyTriggers(deviceId: Int): KMResult<Unit> {
+        val triggers = clients.values.flatMap { it.keyTriggers[deviceId] ?: emptySet() }.toSet()
+
+        return systemBridgeConnManager.run { bridge ->
+            bridge.setKeyTriggers(deviceId, triggers.toTypedArray())
+        }.then { upstreamSuccess ->
+            if (upstreamSuccess) {
+                Success(Unit)
+            } else {
+                Timber.e("Failed to set key triggers: deviceId=$deviceId")
+                SystemBridgeError.SetKeyTriggersFailed
+            }
+        }
+    }
+
     private data class ClientContext(
         val callback: InputEventHubCallback,
         /**
@@ -374,6 +448,11 @@ class InputEventHubImpl @Inject constructor(
          */
         val grabRequests: Set<GrabTargetKeyCode>,
         val evdevEventTypes: Set<Int>,
+        /**
+         * The chord and sequence triggers that this client wants detected for each grabbed
+         * device ID.
+         */
+        val keyTriggers: Map<Int, Set<EvdevKeyTrigger>> = emptyMap(),
     ) {
         private val devicesSet: Set<EvdevDeviceInfo> = grabRequests.map {
             EvdevDeviceInfo(name = it.name, bus = it.bus, vendor = it.vendor, product = it.product)
@@ -404,6 +483,17 @@ interface InputEventHub {
     fun setGrabTargets(clientId: String, devices: List<GrabTargetKeyCode>)
     fun grabAllEvdevDevices(clientId: String)
 
+    /**
+     * Replace the chord and sequence triggers that this client wants the system bridge to
+     * detect on a grabbed device. [InputEventHubCallback.onEvdevKeyTriggerFired] is called
+     * when one of them fires. The trigger IDs must be unique across clients.
+     */
+    fun setKeyTriggers(
+        clientId: String,
+        deviceId: Int,
+        triggers: List<EvdevKeyTrigger>,
+    ): KMResult<Unit>
+
     /**
      * Inject a key event. This may either use the key event relay service or the system
      * bridge depending on the permissions granted to Key Mapper.
@@ -7,4 +7,9 @@ interface InputEventHubCallback {
      * @return whether to consume the event.
      */
     fun onInputEvent(event: KMInputEvent, detectionSource: InputEventDetectionSource): Boolean
+
+    /**
+     * Called when a trigger that this client set with [InputEventHub.setKeyTriggers] fires.
+     */
+    fun onEvdevKeyTriggerFired(deviceId: Int, triggerId: Int) {}
 }
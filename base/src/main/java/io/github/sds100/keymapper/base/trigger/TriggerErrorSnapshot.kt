@@ -117,7 +117,15 @@ data class TriggerErrorSnapshot(
                 return TriggerError.SYSTEM_BRIDGE_DISCONNECTED
             }
 
-            if (evdevDevices != null && !evdevDevices.contains(key.device)) {
+            // Trigger keys do not save the phys, uniq and sysfs path of the device.
+            val isDeviceConnected = evdevDevices?.any { device ->
+                device.name == key.device.name &&
+                    device.bus == key.device.bus &&
+                    device.vendor == key.device.vendor &&
+                    device.product == key.device.product
+            }
+
+            if (isDeviceConnected == false) {
                 return TriggerError.EVDEV_DEVICE_NOT_FOUND
             }
         }
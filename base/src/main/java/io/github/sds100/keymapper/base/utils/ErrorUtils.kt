@@ -570,6 +570,9 @@ fun KMError.getFullMessage(resourceProvider: ResourceProvider): String {
         is SystemBridgeError.WriteEvdevEventFailed ->
             resourceProvider.getString(R.string.error_write_evdev_event_failed)
 
+        is SystemBridgeError.SetKeyTriggersFailed ->
+            resourceProvider.getString(R.string.error_set_key_triggers_failed)
+
         is KMError.MediaActionUnsupported ->
             resourceProvider.getString(R.string.error_media_action_unsupported)
 
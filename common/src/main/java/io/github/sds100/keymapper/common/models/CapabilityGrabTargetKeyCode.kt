@@ -0,0 +1,75 @@
+package io.github.sds100.keymapper.common.models
+
+import android.os.Parcelable
+import kotlinx.parcelize.Parcelize
+
+/**
+ * Grab every device that supports some evdev event codes, such as every gamepad. The system
+ * bridge checks this again whenever a device is connected so new devices are grabbed too.
+ */
+@Parcelize
+data class CapabilityGrabTargetKeyCode(
+    /**
+     * One of [CLASS_ALPHABETIC_KEYBOARD], [CLASS_GAMEPAD] or [CLASS_EVENT_CODE].
+     */
+    val type: Int,
+    /**
+     * The evdev event type that devices must support if [type] is [CLASS_EVENT_CODE].
+     */
+    val eventType: Int = 0,
+    /**
+     * The evdev event code that devices must support if [type] is [CLASS_EVENT_CODE].
+     */
+    val eventCode: Int = 0,
+    /**
+     * The Android key codes to add support for from the grabbed devices.
+     *
+     * See [GrabTargetKeyCode.extraKeyCodes].
+     */
+    val extraKeyCodes: IntArray = intArrayOf(),
+    /**
+     * See [GrabTargetKeyCode.forwardAxes].
+     */
+    val forwardAxes: Boolean = false,
+) : Parcelable {
+    companion object {
+        /**
+         * Devices that have every letter key.
+         */
+        const val CLASS_ALPHABETIC_KEYBOARD = 0
+
+        /**
+         * Devices that have BTN_SOUTH.
+         */
+        const val CLASS_GAMEPAD = 1
+
+        /**
+         * Devices that support [eventType] and [eventCode], such as EV_KEY and KEY_VOLUMEUP.
+         */
+        const val CLASS_EVENT_CODE = 2
+    }
+
+    override fun equals(other: Any?): Boolean {
+        if (this === other) return true
+        if (javaClass != other?.javaClass) return false
+
+        other as CapabilityGrabTargetKeyCode
+
+        if (type != other.type) return false
+        if (eventType != other.eventType) return false
+        if (eventCode != other.eventCode) return false
+        if (!extraKeyCodes.contentEquals(other.extraKeyCodes)) return false
+        if (forwardAxes != other.forwardAxes) return false
+
+        return true
+    }
+
+    override fun hashCode(): Int {
+        var result = type
+        result = 31 * result + eventType
+        result = 31 * result + eventCode
+        result = 31 * result + extraKeyCodes.contentHashCode()
+        result = 31 * result + forwardAxes.hashCode()
+        return result
+    }
+}
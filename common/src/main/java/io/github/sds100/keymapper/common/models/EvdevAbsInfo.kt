@@ -0,0 +1,33 @@
+package io.github.sds100.keymapper.common.models
+
+import android.os.Parcelable
+import kotlinx.parcelize.Parcelize
+
+/**
+ * The range and resolution of an evdev EV_ABS axis.
+ */
+@Parcelize
+data class EvdevAbsInfo(
+    /**
+     * The EV_ABS code of the axis, such as ABS_X.
+     */
+    val code: Int,
+    /**
+     * The latest value that the axis reported.
+     */
+    val value: Int,
+    val minimum: Int,
+    val maximum: Int,
+    /**
+     * Changes smaller than this are filtered out as noise.
+     */
+    val fuzz: Int,
+    /**
+     * Values within this distance of the center are reported as the center.
+     */
+    val flat: Int,
+    /**
+     * The units per millimeter, or units per radian for rotational axes.
+     */
+    val resolution: Int,
+) : Parcelable
@@ -0,0 +1,36 @@
+package io.github.sds100.keymapper.common.models
+
+import android.os.Parcelable
+import kotlinx.parcelize.Parcelize
+
+/**
+ * Presses keys when an evdev axis, such as a hat switch, thumbstick or analog trigger, moves
+ * far enough from its resting position. The keys are handled like the device's own keys so
+ * key triggers and the evdev callback work with analog controls.
+ */
+@Parcelize
+data class EvdevAxisKeyMapping(
+    /**
+     * The EV_ABS code of the axis.
+     */
+    val absCode: Int,
+    /**
+     * The scan code that is pressed when the axis moves towards its minimum, or the low axis
+     * of a split axis.
+     */
+    val lowScanCode: Int? = null,
+    /**
+     * The scan code that is pressed when the axis moves towards its maximum, the high axis
+     * of a split axis, or a trigger is pressed.
+     */
+    val highScanCode: Int? = null,
+    /**
+     * How far the axis must move from rest to press a key, between 0 and 1.
+     */
+    val pressThreshold: Float = 0.5f,
+    /**
+     * A pressed key is released once the axis moves back below this. Keep it lower than
+     * [pressThreshold] so the key does not bounce when the axis jitters.
+     */
+    val releaseThreshold: Float = 0.4f,
+) : Parcelable
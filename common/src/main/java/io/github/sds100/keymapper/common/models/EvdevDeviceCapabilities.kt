@@ -0,0 +1,78 @@
+package io.github.sds100.keymapper.common.models
+
+import android.os.Parcelable
+import kotlinx.parcelize.Parcelize
+
+/**
+ * Everything that an evdev device can report. This is what the device can physically
+ * produce so it can be used to decide which extra key codes a grab target needs.
+ */
+@Parcelize
+data class EvdevDeviceCapabilities(
+    /**
+     * The event types that the device supports at least one code of, such as EV_KEY.
+     */
+    val eventTypes: IntArray,
+    /**
+     * The type of each code in [eventCodes].
+     */
+    val eventCodeTypes: IntArray,
+    /**
+     * Every supported event code ordered by type and code.
+     */
+    val eventCodes: IntArray,
+    val absInfo: Array<EvdevAbsInfo>,
+    /**
+     * The input properties, such as INPUT_PROP_DIRECT for touchscreens.
+     */
+    val properties: IntArray,
+    /**
+     * The time in milliseconds before a held key repeats, or null if the device does not
+     * support key repeat.
+     */
+    val keyRepeatDelay: Int?,
+    /**
+     * The time in milliseconds between each key repeat, or null if the device does not
+     * support key repeat.
+     */
+    val keyRepeatPeriod: Int?,
+    /**
+     * The EV_LED codes of the LEDs that the device has, such as LED_CAPSL.
+     */
+    val leds: IntArray,
+) : Parcelable {
+
+    fun hasEventCode(type: Int, code: Int): Boolean {
+        return eventCodes.indices.any { eventCodeTypes[it] == type && eventCodes[it] == code }
+    }
+
+    override fun equals(other: Any?): Boolean {
+        if (this === other) return true
+        if (javaClass != other?.javaClass) return false
+
+        other as EvdevDeviceCapabilities
+
+        if (!eventTypes.contentEquals(other.eventTypes)) return false
+        if (!eventCodeTypes.contentEquals(other.eventCodeTypes)) return false
+        if (!eventCodes.contentEquals(other.eventCodes)) return false
+        if (!absInfo.contentEquals(other.absInfo)) return false
+        if (!properties.contentEquals(other.properties)) return false
+        if (keyRepeatDelay != other.keyRepeatDelay) return false
+        if (keyRepeatPeriod != other.keyRepeatPeriod) return false
+        if (!leds.contentEquals(other.leds)) return false
+
+        return true
+    }
+
+    override fun hashCode(): Int {
+        var result = eventTypes.contentHashCode()
+        result = 31 * result + eventCodeTypes.contentHashCode()
+        result = 31 * result + eventCodes.contentHashCode()
+        result = 31 * result + absInfo.contentHashCode()
+        result = 31 * result + properties.contentHashCode()
+        result = 31 * result + (keyRepeatDelay ?: 0)
+        result = 31 * result + (keyRepeatPeriod ?: 0)
+        result = 31 * result + leds.contentHashCode()
+        return result
+    }
+}
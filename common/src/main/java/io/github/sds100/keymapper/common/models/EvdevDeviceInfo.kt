@@ -6,5 +6,22 @@ import kotlinx.serialization.Serializable
 
 @Serializable
 @Parcelize
-data class EvdevDeviceInfo(val name: String, val bus: Int, val vendor: Int, val product: Int) :
-    Parcelable
+data class EvdevDeviceInfo(
+    val name: String,
+    val bus: Int,
+    val vendor: Int,
+    val product: Int,
+    /**
+     * The physical location of the device, such as the USB port it is plugged in to.
+     */
+    val phys: String = "",
+    /**
+     * The unique identifier of the device, such as the MAC address of a Bluetooth device.
+     * This is the only reliable way to tell apart identical devices and is often empty.
+     */
+    val uniq: String = "",
+    /**
+     * The /sys/class/input/eventN directory of the device.
+     */
+    val sysfsPath: String = "",
+) : Parcelable
@@ -0,0 +1,39 @@
+package io.github.sds100.keymapper.common.models
+
+import android.os.Parcelable
+import kotlinx.parcelize.Parcelize
+
+/**
+ * Changes what an evdev key inputs depending on how it is pressed. This is resolved by the
+ * system bridge so the timing is not affected by the latency of the evdev callback.
+ */
+@Parcelize
+data class EvdevKeyBehaviour(
+    val scanCode: Int,
+    /**
+     * Either [TYPE_TAP_HOLD], [TYPE_LONG_PRESS] or [TYPE_DOUBLE_TAP].
+     */
+    val type: Int,
+    /**
+     * The scan code that is input when the key is tapped once.
+     */
+    val tapScanCode: Int,
+    /**
+     * The scan code that is input when the key is held, long pressed or double tapped.
+     */
+    val alternateScanCode: Int,
+    /**
+     * How long to wait before deciding that the key is being held or that it will not be
+     * tapped a second time.
+     */
+    val timeoutMillis: Long,
+) : Parcelable {
+    companion object {
+        /**
+         * Pressing another key while this key is down also counts as holding it.
+         */
+        const val TYPE_TAP_HOLD = 0
+        const val TYPE_LONG_PRESS = 1
+        const val TYPE_DOUBLE_TAP = 2
+    }
+}
@@ -0,0 +1,53 @@
+package io.github.sds100.keymapper.common.models
+
+import android.os.Parcelable
+import kotlinx.parcelize.Parcelize
+
+/**
+ * A chord or sequence of evdev keys that is detected by the system bridge and reported as a
+ * single trigger instead of the individual key events.
+ */
+@Parcelize
+data class EvdevKeyTrigger(
+    /**
+     * The ID that is passed to IEvdevCallback.onEvdevKeyTriggerFired when the trigger fires.
+     */
+    val id: Int,
+    /**
+     * Either [TYPE_CHORD] or [TYPE_SEQUENCE].
+     */
+    val type: Int,
+    val scanCodes: IntArray,
+    /**
+     * For chords this is the window in which all the keys must be pressed. For sequences
+     * this is the maximum time between each key press.
+     */
+    val timeoutMillis: Long,
+) : Parcelable {
+    companion object {
+        const val TYPE_CHORD = 0
+        const val TYPE_SEQUENCE = 1
+    }
+
+    override fun equals(other: Any?): Boolean {
+        if (this === other) return true
+        if (javaClass != other?.javaClass) return false
+
+        other as EvdevKeyTrigger
+
+        if (id != other.id) return false
+        if (type != other.type) return false
+        if (!scanCodes.contentEquals(other.scanCodes)) return false
+        if (timeoutMillis != other.timeoutMillis) return false
+
+        return true
+    }
+
+    override fun hashCode(): Int {
+        var result = id
+        result = 31 * result + type
+        result = 31 * result + scanCodes.contentHashCode()
+        result = 31 * result + timeoutMillis.hashCode()
+        return result
+    }
+}
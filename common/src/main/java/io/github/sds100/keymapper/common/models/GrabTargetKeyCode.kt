@@ -19,7 +19,43 @@ data class GrabTargetKeyCode(
      * inputs a key code that isn't supported by the original evdev device.
      */
     val extraKeyCodes: IntArray,
+    /**
+     * How [name] is matched against the name of the device. Some Bluetooth devices change
+     * the suffix of their name across firmware versions or reconnects.
+     */
+    val nameMatchType: Int = NAME_MATCH_EXACT,
+    /**
+     * Only grab devices with this version if it is set.
+     */
+    val version: Int? = null,
+    /**
+     * Only grab devices whose physical location starts with this if it is set.
+     */
+    val physPrefix: String? = null,
+    /**
+     * Only grab the device with this unique identifier, such as a Bluetooth MAC address,
+     * if it is set.
+     */
+    val uniq: String? = null,
+    /**
+     * Also send the EV_ABS and EV_REL events of the device to the evdev callback, converted
+     * to Android axes. This is needed to remap joysticks, triggers and scroll wheels.
+     */
+    val forwardAxes: Boolean = false,
 ) : Parcelable {
+    companion object {
+        const val NAME_MATCH_EXACT = 0
+
+        /**
+         * `*` matches any number of characters and `?` matches one character.
+         */
+        const val NAME_MATCH_WILDCARD = 1
+
+        /**
+         * A regular expression that must match the whole name.
+         */
+        const val NAME_MATCH_REGEX = 2
+    }
 
     constructor(
         device: EvdevDeviceInfo,
@@ -43,6 +79,11 @@ data class GrabTargetKeyCode(
         if (vendor != other.vendor) return false
         if (product != other.product) return false
         if (!extraKeyCodes.contentEquals(other.extraKeyCodes)) return false
+        if (nameMatchType != other.nameMatchType) return false
+        if (version != other.version) return false
+        if (physPrefix != other.physPrefix) return false
+        if (uniq != other.uniq) return false
+        if (forwardAxes != other.forwardAxes) return false
 
         return true
     }
@@ -53,6 +94,11 @@ data class GrabTargetKeyCode(
         result = 31 * result + vendor.hashCode()
         result = 31 * result + product.hashCode()
         result = 31 * result + extraKeyCodes.contentHashCode()
+        result = 31 * result + nameMatchType
+        result = 31 * result + (version ?: 0)
+        result = 31 * result + (physPrefix?.hashCode() ?: 0)
+        result = 31 * result + (uniq?.hashCode() ?: 0)
+        result = 31 * result + forwardAxes.hashCode()
         return result
     }
 }
@@ -10,4 +10,9 @@ data class GrabbedDeviceHandle(
     val bus: Int,
     val vendor: Int,
     val product: Int,
+    /**
+     * The path of the evdev device node. Identical devices have the same name and ids so
+     * this tells them apart.
+     */
+    val path: String,
 ) : Parcelable
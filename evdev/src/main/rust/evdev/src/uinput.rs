@@ -23,7 +23,11 @@ impl UInputDevice {
     /// Create a uinput device based on the given libevdev device.
     ///
     /// The uinput device will be an exact copy of the libevdev device, minus
-    /// the bits that uinput doesn't allow to be set.
+    /// the bits that uinput doesn't allow to be set, such as EV_REP.
+    ///
+    /// To create a device that is not a copy of an existing one, describe it
+    /// with an `UninitDevice` by setting its name and ids and enabling its
+    /// event codes, then pass it to this function.
     pub fn create_from_device<T: DeviceWrapper>(device: &T) -> io::Result<UInputDevice> {
         let mut libevdev_uinput = std::ptr::null_mut();
         let result = unsafe {
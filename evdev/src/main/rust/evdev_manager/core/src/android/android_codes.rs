@@ -626,6 +626,82 @@ Since CLASSIFICATION_MULTI_FINGER_SWIPE is a hidden API, so is this axis. It is
 the first pointer in a motion event.*/
 pub const AMOTION_EVENT_AXIS_GESTURE_SWIPE_FINGER_COUNT: c_uint = 53;
 
+/* Meta key / modifier state. */
+
+// No meta keys are pressed.
+pub const AMETA_NONE: c_uint = 0;
+// This mask is used to check whether one of the ALT meta keys is pressed.
+pub const AMETA_ALT_ON: c_uint = 0x02;
+// This mask is used to check whether the left ALT meta key is pressed.
+pub const AMETA_ALT_LEFT_ON: c_uint = 0x10;
+// This mask is used to check whether the right ALT meta key is pressed.
+pub const AMETA_ALT_RIGHT_ON: c_uint = 0x20;
+// This mask is used to check whether one of the SHIFT meta keys is pressed.
+pub const AMETA_SHIFT_ON: c_uint = 0x01;
+// This mask is used to check whether the left SHIFT meta key is pressed.
+pub const AMETA_SHIFT_LEFT_ON: c_uint = 0x40;
+// This mask is used to check whether the right SHIFT meta key is pressed.
+pub const AMETA_SHIFT_RIGHT_ON: c_uint = 0x80;
+// This mask is used to check whether the SYM meta key is pressed.
+pub const AMETA_SYM_ON: c_uint = 0x04;
+// This mask is used to check whether the FUNCTION meta key is pressed.
+pub const AMETA_FUNCTION_ON: c_uint = 0x08;
+// This mask is used to check whether one of the CTRL meta keys is pressed.
+pub const AMETA_CTRL_ON: c_uint = 0x1000;
+// This mask is used to check whether the left CTRL meta key is pressed.
+pub const AMETA_CTRL_LEFT_ON: c_uint = 0x2000;
+// This mask is used to check whether the right CTRL meta key is pressed.
+pub const AMETA_CTRL_RIGHT_ON: c_uint = 0x4000;
+// This mask is used to check whether one of the META meta keys is pressed.
+pub const AMETA_META_ON: c_uint = 0x10000;
+// This mask is used to check whether the left META meta key is pressed.
+pub const AMETA_META_LEFT_ON: c_uint = 0x20000;
+// This mask is used to check whether the right META meta key is pressed.
+pub const AMETA_META_RIGHT_ON: c_uint = 0x40000;
+// This mask is used to check whether the CAPS LOCK meta key is on.
+pub const AMETA_CAPS_LOCK_ON: c_uint = 0x100000;
+// This mask is used to check whether the NUM LOCK meta key is on.
+pub const AMETA_NUM_LOCK_ON: c_uint = 0x200000;
+// This mask is used to check whether the SCROLL LOCK meta key is on.
+pub const AMETA_SCROLL_LOCK_ON: c_uint = 0x400000;
+
+// LED codes that can be set in a key layout file. These are defined in AOSP's input.h.
+pub const ALED_NUM_LOCK: c_uint = 0x00;
+pub const ALED_CAPS_LOCK: c_uint = 0x01;
+pub const ALED_SCROLL_LOCK: c_uint = 0x02;
+pub const ALED_COMPOSE: c_uint = 0x03;
+pub const ALED_KANA: c_uint = 0x04;
+pub const ALED_SLEEP: c_uint = 0x05;
+pub const ALED_SUSPEND: c_uint = 0x06;
+pub const ALED_MUTE: c_uint = 0x07;
+pub const ALED_MISC: c_uint = 0x08;
+pub const ALED_MAIL: c_uint = 0x09;
+pub const ALED_CHARGING: c_uint = 0x0a;
+pub const ALED_CONTROLLER_1: c_uint = 0x10;
+pub const ALED_CONTROLLER_2: c_uint = 0x11;
+pub const ALED_CONTROLLER_3: c_uint = 0x12;
+pub const ALED_CONTROLLER_4: c_uint = 0x13;
+
+// Sensor types that can be set in a key layout file. The values are the same as
+// android.hardware.Sensor and AOSP's InputDeviceSensorType.
+pub const ASENSOR_TYPE_ACCELEROMETER: c_uint = 1;
+pub const ASENSOR_TYPE_MAGNETIC_FIELD: c_uint = 2;
+pub const ASENSOR_TYPE_ORIENTATION: c_uint = 3;
+pub const ASENSOR_TYPE_GYROSCOPE: c_uint = 4;
+pub const ASENSOR_TYPE_LIGHT: c_uint = 5;
+pub const ASENSOR_TYPE_PRESSURE: c_uint = 6;
+pub const ASENSOR_TYPE_TEMPERATURE: c_uint = 7;
+pub const ASENSOR_TYPE_PROXIMITY: c_uint = 8;
+pub const ASENSOR_TYPE_GRAVITY: c_uint = 9;
+pub const ASENSOR_TYPE_LINEAR_ACCELERATION: c_uint = 10;
+pub const ASENSOR_TYPE_ROTATION_VECTOR: c_uint = 11;
+pub const ASENSOR_TYPE_RELATIVE_HUMIDITY: c_uint = 12;
+pub const ASENSOR_TYPE_AMBIENT_TEMPERATURE: c_uint = 13;
+pub const ASENSOR_TYPE_MAGNETIC_FIELD_UNCALIBRATED: c_uint = 14;
+pub const ASENSOR_TYPE_GAME_ROTATION_VECTOR: c_uint = 15;
+pub const ASENSOR_TYPE_GYROSCOPE_UNCALIBRATED: c_uint = 16;
+pub const ASENSOR_TYPE_SIGNIFICANT_MOTION: c_uint = 17;
+
 /* These flags originate in RawEvents and are generally set in the key map.
  * NOTE: If you want a flag to be able to set in a keylayout file, then you must add it to
  * InputEventLabels.h as well. */
@@ -0,0 +1,147 @@
+//! Converts evdev axis events to Android motion axis values.
+//!
+//! EV_ABS values are scaled the same way as JoystickInputMapper in the AOSP InputReader:
+//! https://cs.android.com/android/platform/superproject/+/android-latest-release:frameworks/native/services/inputflinger/reader/mapper/JoystickInputMapper.cpp
+
+use crate::android::android_codes::{
+    AMOTION_EVENT_AXIS_HAT_X, AMOTION_EVENT_AXIS_HAT_Y, AMOTION_EVENT_AXIS_HSCROLL,
+    AMOTION_EVENT_AXIS_ORIENTATION, AMOTION_EVENT_AXIS_RELATIVE_X, AMOTION_EVENT_AXIS_RELATIVE_Y,
+    AMOTION_EVENT_AXIS_RUDDER, AMOTION_EVENT_AXIS_RX, AMOTION_EVENT_AXIS_RY, AMOTION_EVENT_AXIS_RZ,
+    AMOTION_EVENT_AXIS_VSCROLL, AMOTION_EVENT_AXIS_WHEEL, AMOTION_EVENT_AXIS_X,
+    AMOTION_EVENT_AXIS_Y, AMOTION_EVENT_AXIS_Z,
+};
+use crate::android::keylayout::key_layout_map::{KeyLayoutAxisInfo, KeyLayoutAxisMode};
+use evdev::enums::{EventCode, EV_REL};
+use evdev::{AbsInfo, InputEvent};
+
+/// Axes that rest in the middle of their range. Their values are scaled to -1..1 and the
+/// values of other axes, such as triggers, are scaled to 0..1.
+const CENTERED_AXES: [u32; 11] = [
+    AMOTION_EVENT_AXIS_X,
+    AMOTION_EVENT_AXIS_Y,
+    AMOTION_EVENT_AXIS_Z,
+    AMOTION_EVENT_AXIS_RX,
+    AMOTION_EVENT_AXIS_RY,
+    AMOTION_EVENT_AXIS_RZ,
+    AMOTION_EVENT_AXIS_HAT_X,
+    AMOTION_EVENT_AXIS_HAT_Y,
+    AMOTION_EVENT_AXIS_ORIENTATION,
+    AMOTION_EVENT_AXIS_RUDDER,
+    AMOTION_EVENT_AXIS_WHEEL,
+];
+
+/// The value of an Android motion axis, such as AMOTION_EVENT_AXIS_X.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AndroidAxisValue {
+    pub axis: u32,
+    pub value: f32,
+}
+
+/// Convert an EV_ABS or EV_REL event to the Android axis values that it changes.
+///
+/// EV_ABS events need the axis from the key layout and the range of the axis. EV_REL
+/// events are mapped like a mouse so they do not need either. Returns an empty list if the
+/// event is not an axis that Android knows about.
+pub fn map_axis_event(
+    axis_info: Option<&KeyLayoutAxisInfo>,
+    abs_info: Option<&AbsInfo>,
+    event: &InputEvent,
+) -> Vec<AndroidAxisValue> {
+    match event.event_code {
+        EventCode::EV_ABS(_) => match (axis_info, abs_info) {
+            (Some(axis_info), Some(abs_info)) => map_abs_value(axis_info, abs_info, event.value),
+            _ => Vec::new(),
+        },
+        EventCode::EV_REL(code) => map_rel_value(code, event.value).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Scale a raw EV_ABS value with the axis mode from the key layout. Split axes report a
+/// value for both of their axes.
+pub fn map_abs_value(
+    axis_info: &KeyLayoutAxisInfo,
+    abs_info: &AbsInfo,
+    raw_value: i32,
+) -> Vec<AndroidAxisValue> {
+    let min = abs_info.minimum as f32;
+    let max = abs_info.maximum as f32;
+
+    // The device reported a range that can't be scaled.
+    if max <= min {
+        return Vec::new();
+    }
+
+    let raw_value = raw_value as f32;
+
+    if axis_info.mode == KeyLayoutAxisMode::Split {
+        let (Some(high_axis), Some(split_value)) = (axis_info.high_axis, axis_info.split_value)
+        else {
+            return Vec::new();
+        };
+
+        let split_value = split_value as f32;
+
+        let (low_value, high_value) = if raw_value < split_value {
+            ((split_value - raw_value) / (split_value - min), 0.0)
+        } else if raw_value > split_value {
+            (0.0, (raw_value - split_value) / (max - split_value))
+        } else {
+            (0.0, 0.0)
+        };
+
+        return vec![
+            AndroidAxisValue {
+                axis: axis_info.axis,
+                value: low_value,
+            },
+            AndroidAxisValue {
+                axis: high_axis,
+                value: high_value,
+            },
+        ];
+    }
+
+    let raw_value = if axis_info.mode == KeyLayoutAxisMode::Invert {
+        max - raw_value + min
+    } else {
+        raw_value
+    };
+
+    let (scale, offset) = if CENTERED_AXES.contains(&axis_info.axis) {
+        let scale = 2.0 / (max - min);
+        (scale, -(min + max) / 2.0 * scale)
+    } else {
+        (1.0 / (max - min), 0.0)
+    };
+
+    let mut value = raw_value * scale + offset;
+
+    // Report values in the dead zone as the resting position so a stick that is let go
+    // does not keep sending small values.
+    let flat = axis_info.flat_override.unwrap_or(abs_info.flat) as f32 * scale;
+    if value.abs() < flat {
+        value = 0.0;
+    }
+
+    vec![AndroidAxisValue {
+        axis: axis_info.axis,
+        value,
+    }]
+}
+
+/// Map an EV_REL code like a mouse. The value is the number of units that it moved.
+pub fn map_rel_value(code: EV_REL, value: i32) -> Option<AndroidAxisValue> {
+    let axis = match code {
+        EV_REL::REL_X => AMOTION_EVENT_AXIS_RELATIVE_X,
+        EV_REL::REL_Y => AMOTION_EVENT_AXIS_RELATIVE_Y,
+        EV_REL::REL_WHEEL => AMOTION_EVENT_AXIS_VSCROLL,
+        EV_REL::REL_HWHEEL => AMOTION_EVENT_AXIS_HSCROLL,
+        _ => return None,
+    };
+
+    Some(AndroidAxisValue {
+        axis,
+        value: value as f32,
+    })
+}
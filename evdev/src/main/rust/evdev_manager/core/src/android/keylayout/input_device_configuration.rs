@@ -0,0 +1,149 @@
+//! Parser for Android input device configuration (.idc) files.
+//!
+//! An input device configuration file is a list of `key = value` properties that tell the
+//! system how to treat an input device, such as which key layout it uses.
+//!
+//! See https://source.android.com/docs/core/interaction/input/input-device-configuration-files
+//! and PropertyMap.cpp in AOSP.
+
+use crate::android::keylayout::key_layout_map::parse_int;
+use crate::android::keylayout::tokenizer::Tokenizer;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const WHITESPACE: &str = " \t\r";
+const WHITESPACE_OR_PROPERTY_DELIMITER: &str = " \t\r=";
+
+/// The properties of an input device configuration file.
+///
+/// This object is immutable after it has been loaded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputDeviceConfiguration {
+    properties: HashMap<String, String>,
+}
+
+impl InputDeviceConfiguration {
+    /// Load an input device configuration from a file path.
+    pub fn load_from_file(file_path: PathBuf) -> Result<Self, String> {
+        let tokenizer = Tokenizer::from_file(file_path)?;
+        Self::load(tokenizer)
+    }
+
+    /// Load an input device configuration from file contents (useful for testing).
+    pub fn load_from_contents(contents: &str) -> Result<Self, String> {
+        let tokenizer = Tokenizer::from_contents(PathBuf::new(), contents);
+        Self::load(tokenizer)
+    }
+
+    fn load(mut tokenizer: Tokenizer) -> Result<Self, String> {
+        let mut configuration = InputDeviceConfiguration::default();
+
+        parse(&mut tokenizer, &mut configuration.properties)
+            .inspect_err(|err| error!("Failed to parse input device configuration: {:?}", err))?;
+
+        Ok(configuration)
+    }
+
+    /// Get the raw value of a property.
+    pub fn get_string(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+
+    /// Get a property that is an integer. Returns None if it is missing or not an integer.
+    pub fn get_int(&self, key: &str) -> Option<i32> {
+        self.get_string(key).and_then(parse_int)
+    }
+
+    /// Get a property that is a boolean, where any non-zero integer is true.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get_int(key).map(|value| value != 0)
+    }
+
+    /// Get a property that is a floating point number.
+    pub fn get_float(&self, key: &str) -> Option<f32> {
+        self.get_string(key).and_then(|value| value.parse().ok())
+    }
+
+    /// The name of the key layout file to use instead of searching by the device's identity.
+    pub fn keyboard_layout(&self) -> Option<&str> {
+        self.get_string("keyboard.layout")
+    }
+
+    /// The name of the key character map file to use instead of searching by the device's
+    /// identity.
+    pub fn keyboard_character_map(&self) -> Option<&str> {
+        self.get_string("keyboard.characterMap")
+    }
+
+    /// The kind of device, such as `touchScreen` or `rotaryEncoder`.
+    pub fn device_type(&self) -> Option<&str> {
+        self.get_string("device.type")
+    }
+
+    /// The kind of touch device, such as `touchScreen`, `touchPad` or `pointer`.
+    pub fn touch_device_type(&self) -> Option<&str> {
+        self.get_string("touch.deviceType")
+    }
+}
+
+fn parse(
+    tokenizer: &mut Tokenizer,
+    properties: &mut HashMap<String, String>,
+) -> Result<(), String> {
+    while !tokenizer.is_eof() {
+        tokenizer.skip_delimiters(WHITESPACE);
+
+        if !tokenizer.is_eol() && tokenizer.peek_char() != '#' {
+            let key_token = tokenizer.next_token(WHITESPACE_OR_PROPERTY_DELIMITER);
+            if key_token.is_empty() {
+                return Err(format!(
+                    "{}: Expected non-empty property key.",
+                    tokenizer.get_location()
+                ));
+            }
+
+            tokenizer.skip_delimiters(WHITESPACE);
+
+            if tokenizer.next_char() != '=' {
+                return Err(format!(
+                    "{}: Expected '=' between property key and value.",
+                    tokenizer.get_location()
+                ));
+            }
+
+            tokenizer.skip_delimiters(WHITESPACE);
+
+            let value_token = tokenizer.next_token(WHITESPACE);
+            if value_token.contains(['\\', '"']) {
+                return Err(format!(
+                    "{}: Found reserved character '\\' or '\"' in property value.",
+                    tokenizer.get_location()
+                ));
+            }
+
+            tokenizer.skip_delimiters(WHITESPACE);
+
+            if !tokenizer.is_eol() {
+                return Err(format!(
+                    "{}: Expected end of line, got '{}'.",
+                    tokenizer.get_location(),
+                    tokenizer.peek_remainder_of_line()
+                ));
+            }
+
+            if properties.contains_key(&key_token) {
+                return Err(format!(
+                    "{}: Duplicate property value for key '{}'.",
+                    tokenizer.get_location(),
+                    key_token
+                ));
+            }
+
+            properties.insert(key_token, value_token);
+        }
+
+        tokenizer.next_line();
+    }
+
+    Ok(())
+}
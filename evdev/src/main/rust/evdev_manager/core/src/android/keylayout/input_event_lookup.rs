@@ -1,7 +1,7 @@
 //! Input event label lookup functionality.
 //!
 //! Provides lookup functions for converting between string labels and numeric values
-//! for Android key codes, flags, and axes.
+//! for Android key codes, flags, axes, LEDs and sensors.
 //!
 //! Uses Android NDK constants (AKEYCODE_*, AMOTION_EVENT_AXIS_*) from the generated bindings.
 //! POLICY_FLAG_* constants are defined locally as they come from the local Input.h header.
@@ -31,6 +31,20 @@ macro_rules! define_flag {
     };
 }
 
+// Macro to define LED entry using ALED_* constant
+macro_rules! define_led {
+    ($name:ident) => {
+        (stringify!($name).to_string(), android_codes::$name as u32)
+    };
+}
+
+// Macro to define sensor type entry using ASENSOR_TYPE_* constant
+macro_rules! define_sensor {
+    ($name:ident) => {
+        (stringify!($name).to_string(), android_codes::$name as u32)
+    };
+}
+
 // Build the keycodes map
 fn build_keycodes_map() -> HashMap<String, u32> {
     let mut map = HashMap::new();
@@ -441,10 +455,68 @@ fn build_flags_map() -> HashMap<String, u32> {
     map
 }
 
+// Build the LEDs map
+fn build_leds_map() -> HashMap<String, u32> {
+    let mut map = HashMap::new();
+    macro_rules! insert_led {
+        ($name:ident) => {
+            let (name, value) = define_led!($name);
+            map.insert(name, value);
+        };
+    }
+    insert_led!(ALED_NUM_LOCK);
+    insert_led!(ALED_CAPS_LOCK);
+    insert_led!(ALED_SCROLL_LOCK);
+    insert_led!(ALED_COMPOSE);
+    insert_led!(ALED_KANA);
+    insert_led!(ALED_SLEEP);
+    insert_led!(ALED_SUSPEND);
+    insert_led!(ALED_MUTE);
+    insert_led!(ALED_MISC);
+    insert_led!(ALED_MAIL);
+    insert_led!(ALED_CHARGING);
+    insert_led!(ALED_CONTROLLER_1);
+    insert_led!(ALED_CONTROLLER_2);
+    insert_led!(ALED_CONTROLLER_3);
+    insert_led!(ALED_CONTROLLER_4);
+    map
+}
+
+// Build the sensor types map
+fn build_sensors_map() -> HashMap<String, u32> {
+    let mut map = HashMap::new();
+    macro_rules! insert_sensor {
+        ($name:ident) => {
+            let (name, value) = define_sensor!($name);
+            map.insert(name, value);
+        };
+    }
+    insert_sensor!(ASENSOR_TYPE_ACCELEROMETER);
+    insert_sensor!(ASENSOR_TYPE_MAGNETIC_FIELD);
+    insert_sensor!(ASENSOR_TYPE_ORIENTATION);
+    insert_sensor!(ASENSOR_TYPE_GYROSCOPE);
+    insert_sensor!(ASENSOR_TYPE_LIGHT);
+    insert_sensor!(ASENSOR_TYPE_PRESSURE);
+    insert_sensor!(ASENSOR_TYPE_TEMPERATURE);
+    insert_sensor!(ASENSOR_TYPE_PROXIMITY);
+    insert_sensor!(ASENSOR_TYPE_GRAVITY);
+    insert_sensor!(ASENSOR_TYPE_LINEAR_ACCELERATION);
+    insert_sensor!(ASENSOR_TYPE_ROTATION_VECTOR);
+    insert_sensor!(ASENSOR_TYPE_RELATIVE_HUMIDITY);
+    insert_sensor!(ASENSOR_TYPE_AMBIENT_TEMPERATURE);
+    insert_sensor!(ASENSOR_TYPE_MAGNETIC_FIELD_UNCALIBRATED);
+    insert_sensor!(ASENSOR_TYPE_GAME_ROTATION_VECTOR);
+    insert_sensor!(ASENSOR_TYPE_GYROSCOPE_UNCALIBRATED);
+    insert_sensor!(ASENSOR_TYPE_SIGNIFICANT_MOTION);
+    map
+}
+
 // Static lookup tables (lazily initialized)
 static KEYCODES: OnceLock<HashMap<String, u32>> = OnceLock::new();
 static AXES: OnceLock<HashMap<String, u32>> = OnceLock::new();
 static FLAGS: OnceLock<HashMap<String, u32>> = OnceLock::new();
+static LEDS: OnceLock<HashMap<String, u32>> = OnceLock::new();
+static SENSORS: OnceLock<HashMap<String, u32>> = OnceLock::new();
 
 fn get_keycodes() -> &'static HashMap<String, u32> {
     KEYCODES.get_or_init(build_keycodes_map)
@@ -458,6 +530,14 @@ fn get_flags() -> &'static HashMap<String, u32> {
     FLAGS.get_or_init(build_flags_map)
 }
 
+fn get_leds() -> &'static HashMap<String, u32> {
+    LEDS.get_or_init(build_leds_map)
+}
+
+fn get_sensors() -> &'static HashMap<String, u32> {
+    SENSORS.get_or_init(build_sensors_map)
+}
+
 /// Look up a key code by its label.
 pub fn get_key_code_by_label(label: &str) -> Option<u32> {
     get_keycodes().get(&format!("AKEYCODE_{}", label)).copied()
@@ -474,3 +554,59 @@ pub fn get_axis_by_label(label: &str) -> Option<u32> {
 pub fn get_key_flag_by_label(label: &str) -> Option<u32> {
     get_flags().get(&format!("POLICY_FLAG_{}", label)).copied()
 }
+
+/// Look up an LED code by its label.
+pub fn get_led_by_label(label: &str) -> Option<u32> {
+    get_leds().get(&format!("ALED_{}", label)).copied()
+}
+
+/// Look up a sensor type by its label.
+pub fn get_sensor_type_by_label(label: &str) -> Option<u32> {
+    get_sensors()
+        .get(&format!("ASENSOR_TYPE_{}", label))
+        .copied()
+}
+
+/// Look up the label of a key code, such as "HOME". This is the reverse of
+/// `get_key_code_by_label`.
+pub fn get_key_code_label(key_code: u32) -> Option<String> {
+    find_label(get_keycodes(), "AKEYCODE_", key_code)
+}
+
+/// Look up the label of an axis.
+pub fn get_axis_label(axis: u32) -> Option<String> {
+    find_label(get_axes(), "AMOTION_EVENT_AXIS_", axis)
+}
+
+/// Look up the labels of the key flags that are set in a POLICY_FLAG_* bit mask, sorted
+/// alphabetically.
+pub fn get_key_flag_labels(flags: u32) -> Vec<String> {
+    let mut labels: Vec<String> = get_flags()
+        .iter()
+        .filter(|(_, flag)| flags & **flag != 0)
+        .map(|(label, _)| label.trim_start_matches("POLICY_FLAG_").to_string())
+        .collect();
+
+    labels.sort();
+    labels
+}
+
+/// Look up the label of an LED code.
+pub fn get_led_label(led_code: u32) -> Option<String> {
+    find_label(get_leds(), "ALED_", led_code)
+}
+
+/// Look up the label of a sensor type.
+pub fn get_sensor_type_label(sensor_type: u32) -> Option<String> {
+    find_label(get_sensors(), "ASENSOR_TYPE_", sensor_type)
+}
+
+/// Find the label of a value without its prefix. If several labels have the value then the
+/// first one alphabetically is returned so the result does not depend on the map's order.
+fn find_label(map: &HashMap<String, u32>, prefix: &str, value: u32) -> Option<String> {
+    map.iter()
+        .filter(|(_, code)| **code == value)
+        .map(|(label, _)| label.trim_start_matches(prefix))
+        .min()
+        .map(str::to_string)
+}
@@ -0,0 +1,741 @@
+//! Key character map parser for Android key character map (.kcm) files.
+//!
+//! A key character map describes which character each key produces with a combination of
+//! modifiers, and which key a key falls back to if an app does not handle it.
+//!
+//! See https://source.android.com/docs/core/interaction/input/key-character-map-files
+//! and KeyCharacterMap.cpp in AOSP.
+
+use crate::android::android_codes::{
+    AMETA_ALT_LEFT_ON, AMETA_ALT_ON, AMETA_ALT_RIGHT_ON, AMETA_CAPS_LOCK_ON, AMETA_CTRL_LEFT_ON,
+    AMETA_CTRL_ON, AMETA_CTRL_RIGHT_ON, AMETA_FUNCTION_ON, AMETA_META_LEFT_ON, AMETA_META_ON,
+    AMETA_META_RIGHT_ON, AMETA_NONE, AMETA_NUM_LOCK_ON, AMETA_SCROLL_LOCK_ON, AMETA_SHIFT_LEFT_ON,
+    AMETA_SHIFT_ON, AMETA_SHIFT_RIGHT_ON, AMETA_SYM_ON,
+};
+use crate::android::keylayout::input_event_lookup::get_key_code_by_label;
+use crate::android::keylayout::key_layout_map::parse_int;
+use crate::android::keylayout::tokenizer::Tokenizer;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// The type of keyboard that a key character map describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardType {
+    /// A numeric (12-key) keyboard.
+    Numeric,
+    /// A keyboard with all the letters but with more than one letter per key.
+    Predictive,
+    /// A keyboard with all the letters, and maybe some numbers.
+    Alpha,
+    /// A full PC-style keyboard.
+    Full,
+    /// A keyboard that is only used to perform system control functions rather than typing.
+    SpecialFunction,
+    /// Overlays the key character map of a built-in keyboard. It can only remap keys.
+    Overlay,
+}
+
+/// What a key does when it is pressed with a combination of modifiers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyCharacterBehavior {
+    /// The modifiers that must be pressed, as AMETA_* flags.
+    pub meta_state: u32,
+    /// The character that is typed. This is None for keys that type nothing with these
+    /// modifiers.
+    pub character: Option<char>,
+    /// The key code that apps receive if they don't handle the key.
+    pub fallback_key_code: Option<u32>,
+    /// The key code that this key is replaced with before it reaches apps.
+    pub replacement_key_code: Option<u32>,
+}
+
+/// A key that is not handled by an app and what the system should try instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FallbackAction {
+    pub key_code: u32,
+    /// The modifiers that are still pressed after the modifiers of the behavior are removed.
+    pub meta_state: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Key {
+    label: Option<char>,
+    number: Option<char>,
+    /// The behaviors in the order they were declared. Later behaviors take precedence.
+    behaviors: Vec<KeyCharacterBehavior>,
+}
+
+/// Describes the characters that the keys of a keyboard produce.
+///
+/// This object is immutable after it has been loaded.
+#[derive(Debug)]
+pub struct KeyCharacterMap {
+    keyboard_type: KeyboardType,
+    keys: HashMap<u32, Key>,
+    /// Maps scan codes to key codes. Overlays use this to remap keys.
+    keys_by_scan_code: HashMap<u32, u32>,
+}
+
+const WHITESPACE: &str = " \t\r";
+const WHITESPACE_OR_PROPERTY_DELIMITER: &str = " \t\r,:";
+
+/// Meta states that a behavior must match exactly if they are pressed. Shift and the lock
+/// keys are not included so a behavior without them still applies.
+const EXACT_META_STATES: u32 = AMETA_CTRL_ON
+    | AMETA_CTRL_LEFT_ON
+    | AMETA_CTRL_RIGHT_ON
+    | AMETA_ALT_ON
+    | AMETA_ALT_LEFT_ON
+    | AMETA_ALT_RIGHT_ON
+    | AMETA_META_ON
+    | AMETA_META_LEFT_ON
+    | AMETA_META_RIGHT_ON;
+
+const MODIFIERS: [(&str, u32); 17] = [
+    ("shift", AMETA_SHIFT_ON),
+    ("lshift", AMETA_SHIFT_LEFT_ON),
+    ("rshift", AMETA_SHIFT_RIGHT_ON),
+    ("alt", AMETA_ALT_ON),
+    ("lalt", AMETA_ALT_LEFT_ON),
+    ("ralt", AMETA_ALT_RIGHT_ON),
+    ("ctrl", AMETA_CTRL_ON),
+    ("lctrl", AMETA_CTRL_LEFT_ON),
+    ("rctrl", AMETA_CTRL_RIGHT_ON),
+    ("meta", AMETA_META_ON),
+    ("lmeta", AMETA_META_LEFT_ON),
+    ("rmeta", AMETA_META_RIGHT_ON),
+    ("sym", AMETA_SYM_ON),
+    ("fn", AMETA_FUNCTION_ON),
+    ("capslock", AMETA_CAPS_LOCK_ON),
+    ("numlock", AMETA_NUM_LOCK_ON),
+    ("scrolllock", AMETA_SCROLL_LOCK_ON),
+];
+
+impl KeyCharacterMap {
+    /// Load a key character map from a file path.
+    pub fn load_from_file(file_path: PathBuf) -> Result<Self, String> {
+        let tokenizer = Tokenizer::from_file(file_path)?;
+        Self::load(tokenizer)
+    }
+
+    /// Load a key character map from file contents (useful for testing).
+    pub fn load_from_contents(contents: &str) -> Result<Self, String> {
+        let tokenizer = Tokenizer::from_contents(PathBuf::new(), contents);
+        Self::load(tokenizer)
+    }
+
+    fn load(mut tokenizer: Tokenizer) -> Result<Self, String> {
+        let mut parser = Parser::new(&mut tokenizer);
+        parser
+            .parse()
+            .inspect_err(|err| error!("Failed to parse key character map: {:?}", err))?;
+
+        let keyboard_type = parser.keyboard_type.ok_or_else(|| {
+            format!(
+                "{}: Missing required keyboard 'type' declaration.",
+                parser.tokenizer.get_location()
+            )
+        })?;
+
+        Ok(KeyCharacterMap {
+            keyboard_type,
+            keys: parser.keys,
+            keys_by_scan_code: parser.keys_by_scan_code,
+        })
+    }
+
+    pub fn keyboard_type(&self) -> KeyboardType {
+        self.keyboard_type
+    }
+
+    /// The character that is printed on the key.
+    pub fn get_display_label(&self, key_code: u32) -> Option<char> {
+        self.keys.get(&key_code).and_then(|key| key.label)
+    }
+
+    /// The character that the key types in a number field.
+    pub fn get_number(&self, key_code: u32) -> Option<char> {
+        self.keys.get(&key_code).and_then(|key| key.number)
+    }
+
+    /// The character that the key types with the given AMETA_* modifiers pressed.
+    pub fn get_character(&self, key_code: u32, meta_state: u32) -> Option<char> {
+        self.get_key_behavior(key_code, meta_state)
+            .and_then(|behavior| behavior.character)
+    }
+
+    /// The key that the system tries if an app does not handle the key with the given
+    /// modifiers pressed.
+    pub fn get_fallback_action(&self, key_code: u32, meta_state: u32) -> Option<FallbackAction> {
+        let meta_state = normalize_meta_state(meta_state);
+        let behavior = self.get_key_behavior(key_code, meta_state)?;
+
+        behavior
+            .fallback_key_code
+            .map(|fallback_key_code| FallbackAction {
+                key_code: fallback_key_code,
+                meta_state: meta_state & !behavior.meta_state,
+            })
+    }
+
+    /// Find the behavior of a key with the given AMETA_* modifiers pressed. Left and right
+    /// modifiers also count as the generic modifier, so AMETA_SHIFT_LEFT_ON matches a
+    /// behavior for `shift`.
+    pub fn get_key_behavior(
+        &self,
+        key_code: u32,
+        meta_state: u32,
+    ) -> Option<&KeyCharacterBehavior> {
+        let meta_state = normalize_meta_state(meta_state);
+
+        self.keys.get(&key_code).and_then(|key| {
+            key.behaviors
+                .iter()
+                .rev()
+                .find(|behavior| matches_meta_state(meta_state, behavior.meta_state))
+        })
+    }
+
+    /// Map a scan code to an Android key code with the `map key` declarations.
+    pub fn map_key(&self, scan_code: u32) -> Option<u32> {
+        self.keys_by_scan_code.get(&scan_code).copied()
+    }
+}
+
+/// Set the generic modifier flag if either the left or right modifier is pressed.
+pub fn normalize_meta_state(meta_state: u32) -> u32 {
+    let mut meta_state = meta_state;
+
+    for (either, left, right) in [
+        (AMETA_ALT_ON, AMETA_ALT_LEFT_ON, AMETA_ALT_RIGHT_ON),
+        (AMETA_SHIFT_ON, AMETA_SHIFT_LEFT_ON, AMETA_SHIFT_RIGHT_ON),
+        (AMETA_CTRL_ON, AMETA_CTRL_LEFT_ON, AMETA_CTRL_RIGHT_ON),
+        (AMETA_META_ON, AMETA_META_LEFT_ON, AMETA_META_RIGHT_ON),
+    ] {
+        if meta_state & (left | right) != 0 {
+            meta_state |= either;
+        }
+    }
+
+    meta_state
+}
+
+/// Whether a behavior applies with the modifiers that are pressed. All the modifiers of the
+/// behavior must be pressed. Ctrl, alt and meta must also not be pressed unless the behavior
+/// includes them, taking into account that a behavior can handle one, both or either of a
+/// left/right modifier pair.
+fn matches_meta_state(event_meta_state: u32, behavior_meta_state: u32) -> bool {
+    if event_meta_state & behavior_meta_state != behavior_meta_state {
+        return false;
+    }
+
+    let mut unmatched_meta_state = event_meta_state & !behavior_meta_state & EXACT_META_STATES;
+
+    for (either, left, right) in [
+        (AMETA_CTRL_ON, AMETA_CTRL_LEFT_ON, AMETA_CTRL_RIGHT_ON),
+        (AMETA_ALT_ON, AMETA_ALT_LEFT_ON, AMETA_ALT_RIGHT_ON),
+        (AMETA_META_ON, AMETA_META_LEFT_ON, AMETA_META_RIGHT_ON),
+    ] {
+        if behavior_meta_state & either != 0 {
+            unmatched_meta_state &= !(left | right);
+        } else if behavior_meta_state & (left | right) != 0 {
+            unmatched_meta_state &= !either;
+        }
+    }
+
+    unmatched_meta_state == 0
+}
+
+/// What a property name before the ':' in a key block sets.
+enum Property {
+    Label,
+    Number,
+    Meta(u32),
+}
+
+/// Parser for key character map files.
+struct Parser<'a> {
+    tokenizer: &'a mut Tokenizer,
+    keyboard_type: Option<KeyboardType>,
+    keys: HashMap<u32, Key>,
+    keys_by_scan_code: HashMap<u32, u32>,
+    /// The key code of the key block that is being parsed.
+    current_key: Option<u32>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(tokenizer: &'a mut Tokenizer) -> Self {
+        Self {
+            tokenizer,
+            keyboard_type: None,
+            keys: HashMap::new(),
+            keys_by_scan_code: HashMap::new(),
+            current_key: None,
+        }
+    }
+
+    fn parse(&mut self) -> Result<(), String> {
+        while !self.tokenizer.is_eof() {
+            self.tokenizer.skip_delimiters(WHITESPACE);
+
+            if !self.tokenizer.is_eol() && self.tokenizer.peek_char() != '#' {
+                match self.current_key {
+                    None => {
+                        let keyword_token = self.tokenizer.next_token(WHITESPACE);
+
+                        match keyword_token.as_str() {
+                            "type" => {
+                                self.tokenizer.skip_delimiters(WHITESPACE);
+                                self.parse_type()?;
+                            }
+                            "map" => {
+                                self.tokenizer.skip_delimiters(WHITESPACE);
+                                self.parse_map()?;
+                            }
+                            "key" => {
+                                self.tokenizer.skip_delimiters(WHITESPACE);
+                                self.parse_key()?;
+                            }
+                            _ => {
+                                return Err(format!(
+                                    "{}: Expected keyword, got '{}'.",
+                                    self.tokenizer.get_location(),
+                                    keyword_token
+                                ));
+                            }
+                        }
+                    }
+                    Some(key_code) => self.parse_key_property(key_code)?,
+                }
+
+                self.tokenizer.skip_delimiters(WHITESPACE);
+
+                if !self.tokenizer.is_eol() && self.tokenizer.peek_char() != '#' {
+                    return Err(format!(
+                        "{}: Expected end of line or trailing comment, got '{}'.",
+                        self.tokenizer.get_location(),
+                        self.tokenizer.peek_remainder_of_line()
+                    ));
+                }
+            }
+
+            self.tokenizer.next_line();
+        }
+
+        if self.current_key.is_some() {
+            return Err(format!(
+                "{}: Unterminated key description at end of file.",
+                self.tokenizer.get_location()
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn parse_type(&mut self) -> Result<(), String> {
+        if self.keyboard_type.is_some() {
+            return Err(format!(
+                "{}: Duplicate keyboard 'type' declaration.",
+                self.tokenizer.get_location()
+            ));
+        }
+
+        let type_token = self.tokenizer.next_token(WHITESPACE);
+
+        let keyboard_type = match type_token.as_str() {
+            "NUMERIC" => KeyboardType::Numeric,
+            "PREDICTIVE" => KeyboardType::Predictive,
+            "ALPHA" => KeyboardType::Alpha,
+            "FULL" => KeyboardType::Full,
+            "SPECIAL_FUNCTION" => KeyboardType::SpecialFunction,
+            "OVERLAY" => KeyboardType::Overlay,
+            _ => {
+                return Err(format!(
+                    "{}: Expected keyboard type label, got '{}'.",
+                    self.tokenizer.get_location(),
+                    type_token
+                ));
+            }
+        };
+
+        self.keyboard_type = Some(keyboard_type);
+        Ok(())
+    }
+
+    fn parse_map(&mut self) -> Result<(), String> {
+        let keyword_token = self.tokenizer.next_token(WHITESPACE);
+
+        match keyword_token.as_str() {
+            "key" => {
+                self.tokenizer.skip_delimiters(WHITESPACE);
+                self.parse_map_key()
+            }
+            "usage" => {
+                // Skip usage code entries - evdev only provides scan codes
+                self.tokenizer.next_line();
+                Ok(())
+            }
+            _ => Err(format!(
+                "{}: Expected keyword after 'map', got '{}'.",
+                self.tokenizer.get_location(),
+                keyword_token
+            )),
+        }
+    }
+
+    fn parse_map_key(&mut self) -> Result<(), String> {
+        let code_token = self.tokenizer.next_token(WHITESPACE);
+        let scan_code = parse_int(&code_token)
+            .filter(|scan_code| *scan_code >= 0)
+            .ok_or_else(|| {
+                format!(
+                    "{}: Expected key scan code number, got '{}'.",
+                    self.tokenizer.get_location(),
+                    code_token
+                )
+            })? as u32;
+
+        if self.keys_by_scan_code.contains_key(&scan_code) {
+            return Err(format!(
+                "{}: Duplicate entry for key scan code '{}'.",
+                self.tokenizer.get_location(),
+                code_token
+            ));
+        }
+
+        self.tokenizer.skip_delimiters(WHITESPACE);
+        let key_code_token = self.tokenizer.next_token(WHITESPACE);
+        let key_code = get_key_code_by_label(&key_code_token).ok_or_else(|| {
+            format!(
+                "{}: Expected key code label, got '{}'.",
+                self.tokenizer.get_location(),
+                key_code_token
+            )
+        })?;
+
+        self.keys_by_scan_code.insert(scan_code, key_code);
+        Ok(())
+    }
+
+    fn parse_key(&mut self) -> Result<(), String> {
+        let key_code_token = self.tokenizer.next_token(WHITESPACE);
+        let key_code = get_key_code_by_label(&key_code_token).ok_or_else(|| {
+            format!(
+                "{}: Expected key code label, got '{}'.",
+                self.tokenizer.get_location(),
+                key_code_token
+            )
+        })?;
+
+        if self.keys.contains_key(&key_code) {
+            return Err(format!(
+                "{}: Duplicate entry for key code '{}'.",
+                self.tokenizer.get_location(),
+                key_code_token
+            ));
+        }
+
+        self.tokenizer.skip_delimiters(WHITESPACE);
+        let open_brace_token = self.tokenizer.next_token(WHITESPACE);
+        if open_brace_token != "{" {
+            return Err(format!(
+                "{}: Expected '{{' after key code label, got '{}'.",
+                self.tokenizer.get_location(),
+                open_brace_token
+            ));
+        }
+
+        self.keys.insert(key_code, Key::default());
+        self.current_key = Some(key_code);
+        Ok(())
+    }
+
+    fn parse_key_property(&mut self, key_code: u32) -> Result<(), String> {
+        let mut token = self.tokenizer.next_token(WHITESPACE_OR_PROPERTY_DELIMITER);
+
+        if token == "}" {
+            self.current_key = None;
+            self.finish_key(key_code);
+            return Ok(());
+        }
+
+        // Parse all the comma separated property names up to the colon.
+        let mut properties: Vec<Property> = Vec::new();
+
+        loop {
+            let property = match token.as_str() {
+                "label" => Property::Label,
+                "number" => Property::Number,
+                _ => Property::Meta(self.parse_modifier(&token)?),
+            };
+            properties.push(property);
+
+            self.tokenizer.skip_delimiters(WHITESPACE);
+
+            match self.tokenizer.next_char() {
+                ':' => break,
+                ',' => {
+                    self.tokenizer.skip_delimiters(WHITESPACE);
+                    token = self.tokenizer.next_token(WHITESPACE_OR_PROPERTY_DELIMITER);
+                }
+                _ => {
+                    return Err(format!(
+                        "{}: Expected ',' or ':' after property name.",
+                        self.tokenizer.get_location()
+                    ));
+                }
+            }
+        }
+
+        let behavior = self.parse_behavior()?;
+        let key = self.keys.get_mut(&key_code).unwrap();
+
+        for property in properties {
+            match property {
+                Property::Label => {
+                    if key.label.is_some() {
+                        return Err(format!(
+                            "{}: Duplicate label for key.",
+                            self.tokenizer.get_location()
+                        ));
+                    }
+                    key.label = behavior.character;
+                }
+                Property::Number => {
+                    if key.number.is_some() {
+                        return Err(format!(
+                            "{}: Duplicate number for key.",
+                            self.tokenizer.get_location()
+                        ));
+                    }
+                    key.number = behavior.character;
+                }
+                Property::Meta(meta_state) => {
+                    if key
+                        .behaviors
+                        .iter()
+                        .any(|behavior| behavior.meta_state == meta_state)
+                    {
+                        return Err(format!(
+                            "{}: Duplicate key behavior for modifier.",
+                            self.tokenizer.get_location()
+                        ));
+                    }
+
+                    key.behaviors.push(KeyCharacterBehavior {
+                        meta_state,
+                        ..behavior.clone()
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parse the behavior after the ':' of a key property.
+    fn parse_behavior(&mut self) -> Result<KeyCharacterBehavior, String> {
+        let mut behavior = KeyCharacterBehavior {
+            meta_state: AMETA_NONE,
+            character: None,
+            fallback_key_code: None,
+            replacement_key_code: None,
+        };
+        let mut have_character = false;
+
+        self.tokenizer.skip_delimiters(WHITESPACE);
+
+        loop {
+            if self.tokenizer.peek_char() == '\'' {
+                let character = self.parse_character_literal()?;
+                self.check_can_add_character(have_character, &behavior)?;
+                behavior.character = Some(character);
+                have_character = true;
+            } else {
+                let token = self.tokenizer.next_token(WHITESPACE);
+
+                match token.as_str() {
+                    "none" => {
+                        self.check_can_add_character(have_character, &behavior)?;
+                        have_character = true;
+                    }
+                    "fallback" | "replace" => {
+                        self.tokenizer.skip_delimiters(WHITESPACE);
+                        let key_code_token = self.tokenizer.next_token(WHITESPACE);
+                        let key_code = get_key_code_by_label(&key_code_token).ok_or_else(|| {
+                            format!(
+                                "{}: Invalid key code label for {}, got '{}'.",
+                                self.tokenizer.get_location(),
+                                token,
+                                key_code_token
+                            )
+                        })?;
+
+                        if behavior.fallback_key_code.is_some()
+                            || behavior.replacement_key_code.is_some()
+                        {
+                            return Err(format!(
+                                "{}: Cannot combine multiple fallback/replacement key codes.",
+                                self.tokenizer.get_location()
+                            ));
+                        }
+
+                        if token == "fallback" {
+                            behavior.fallback_key_code = Some(key_code);
+                        } else if have_character {
+                            return Err(format!(
+                                "{}: Cannot combine character literal with replace action.",
+                                self.tokenizer.get_location()
+                            ));
+                        } else {
+                            behavior.replacement_key_code = Some(key_code);
+                        }
+                    }
+                    _ => {
+                        return Err(format!(
+                            "{}: Expected a key behavior after ':'.",
+                            self.tokenizer.get_location()
+                        ));
+                    }
+                }
+            }
+
+            self.tokenizer.skip_delimiters(WHITESPACE);
+
+            if self.tokenizer.is_eol() || self.tokenizer.peek_char() == '#' {
+                return Ok(behavior);
+            }
+        }
+    }
+
+    fn check_can_add_character(
+        &self,
+        have_character: bool,
+        behavior: &KeyCharacterBehavior,
+    ) -> Result<(), String> {
+        if have_character {
+            return Err(format!(
+                "{}: Cannot combine multiple character literals or 'none'.",
+                self.tokenizer.get_location()
+            ));
+        }
+
+        if behavior.replacement_key_code.is_some() {
+            return Err(format!(
+                "{}: Cannot combine character literal with replace action.",
+                self.tokenizer.get_location()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Parse a modifier combination such as `shift+ralt`. `base` means no modifiers.
+    fn parse_modifier(&self, token: &str) -> Result<u32, String> {
+        if token == "base" {
+            return Ok(AMETA_NONE);
+        }
+
+        let mut combined_meta = AMETA_NONE;
+
+        for name in token.split('+') {
+            let meta_state = MODIFIERS
+                .iter()
+                .find(|(label, _)| *label == name)
+                .map(|(_, meta_state)| *meta_state)
+                .ok_or_else(|| {
+                    format!(
+                        "{}: Expected a property name or modifier, got '{}'.",
+                        self.tokenizer.get_location(),
+                        token
+                    )
+                })?;
+
+            if combined_meta & meta_state != 0 {
+                return Err(format!(
+                    "{}: Duplicate modifier combination '{}'.",
+                    self.tokenizer.get_location(),
+                    token
+                ));
+            }
+
+            combined_meta |= meta_state;
+        }
+
+        Ok(combined_meta)
+    }
+
+    /// Parse a quoted character such as 'a', '\n' or 'ä'.
+    fn parse_character_literal(&mut self) -> Result<char, String> {
+        let malformed = |tokenizer: &Tokenizer| {
+            format!("{}: Malformed character literal.", tokenizer.get_location())
+        };
+
+        if self.tokenizer.next_char() != '\'' {
+            return Err(malformed(self.tokenizer));
+        }
+
+        let character = match self.tokenizer.next_char() {
+            '\\' => match self.tokenizer.next_char() {
+                'n' => '\n',
+                't' => '\t',
+                '0' => '\0',
+                '\\' => '\\',
+                '\'' => '\'',
+                '"' => '"',
+                'u' => {
+                    let mut code_point = 0;
+
+                    for _ in 0..4 {
+                        let digit = self
+                            .tokenizer
+                            .next_char()
+                            .to_digit(16)
+                            .ok_or_else(|| malformed(self.tokenizer))?;
+                        code_point = code_point << 4 | digit;
+                    }
+
+                    char::from_u32(code_point).ok_or_else(|| malformed(self.tokenizer))?
+                }
+                _ => return Err(malformed(self.tokenizer)),
+            },
+            ch @ ' '..='~' if ch != '\'' => ch,
+            _ => return Err(malformed(self.tokenizer)),
+        };
+
+        if self.tokenizer.next_char() != '\'' {
+            return Err(malformed(self.tokenizer));
+        }
+
+        Ok(character)
+    }
+
+    /// Give a key without a `number` property the digit or symbol it types so it can still
+    /// be used in number fields.
+    fn finish_key(&mut self, key_code: u32) {
+        let key = self.keys.get_mut(&key_code).unwrap();
+
+        if key.number.is_some() {
+            return;
+        }
+
+        let mut digit = None;
+        let mut symbol = None;
+
+        // Prefer the characters of the behaviors that were declared first.
+        for character in key
+            .behaviors
+            .iter()
+            .rev()
+            .filter_map(|behavior| behavior.character)
+        {
+            if character.is_ascii_digit() {
+                digit = Some(character);
+            } else if "()#*-+,.':;/".contains(character) {
+                symbol = Some(character);
+            }
+        }
+
+        key.number = digit.or(symbol);
+    }
+}
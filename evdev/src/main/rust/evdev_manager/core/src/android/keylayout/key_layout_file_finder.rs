@@ -1,7 +1,7 @@
 use std::{
     fs::{self, File},
     io::ErrorKind,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 /// Trait for finding key layout files.
@@ -10,47 +10,125 @@ pub trait KeyLayoutFileFinder: Send + Sync {
     /// Find a key layout file in the system by its name.
     fn find_system_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf>;
 
-    /// Find a key layout file shipped with Key Mapper by its name.
+    /// Find a key layout file shipped with Key Mapper by its name. These are searched after
+    /// the system key layout files.
     fn find_key_mapper_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf>;
+
+    /// Find a key layout file supplied by the user by its name. These are searched before
+    /// the system key layout files so they can override them.
+    fn find_user_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf>;
+
+    /// The directories that key layout files are searched in, so they can be watched for
+    /// changes.
+    fn key_layout_directories(&self) -> Vec<PathBuf>;
+
+    /// Find a key character map file in the system by its name.
+    fn find_system_key_character_map_file_by_name(&self, name: &str) -> Option<PathBuf>;
+
+    /// Find an input device configuration file in the system by its name.
+    fn find_system_input_device_configuration_file_by_name(&self, name: &str) -> Option<PathBuf>;
+
+    /// The directories that input device configuration files are searched in, so they can
+    /// be watched for changes.
+    fn input_device_configuration_directories(&self) -> Vec<PathBuf>;
 }
 
 /// Default implementation that uses the real file system.
-/// This searches the standard Android key layout file locations.
-pub struct AndroidKeyLayoutFileFinder;
+/// This searches the standard Android key layout file locations, and the directories of
+/// key layout files shipped with Key Mapper and supplied by the user if they are set.
+#[derive(Debug, Clone, Default)]
+pub struct AndroidKeyLayoutFileFinder {
+    bundled_key_layout_dir: Option<PathBuf>,
+    user_key_layout_dir: Option<PathBuf>,
+}
+
+impl AndroidKeyLayoutFileFinder {
+    pub fn new(
+        bundled_key_layout_dir: Option<PathBuf>,
+        user_key_layout_dir: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            bundled_key_layout_dir,
+            user_key_layout_dir,
+        }
+    }
+}
 
 impl KeyLayoutFileFinder for AndroidKeyLayoutFileFinder {
     fn find_system_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf> {
-        // See https://source.android.com/docs/core/interaction/input/key-layout-files#location
-        let path_prefixes = vec![
-            "/odm/usr/".to_string(),
-            "/vendor/usr/".to_string(),
-            "/system/usr/".to_string(),
-            "/data/system/devices/".to_string(),
-        ];
-
-        for prefix in &path_prefixes {
-            let path = PathBuf::new()
-                .join(prefix)
-                .join("keylayout")
-                .join(format!("{}.kl", name));
-
-            match fs::metadata(&path) {
-                Ok(metadata) if metadata.is_file() => {
-                    if File::open(&path).is_ok() {
-                        return Some(path);
-                    }
-                }
-                Err(e) if e.kind() != ErrorKind::NotFound => {
-                    debug!("Error accessing {:?}: {}", path, e);
-                }
-                _ => {}
-            }
-        }
+        find_system_file("keylayout", "kl", name)
+    }
 
-        None
+    fn find_key_mapper_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        let directory = self.bundled_key_layout_dir.as_ref()?;
+        find_file_in_directory(directory, "kl", name)
     }
 
-    fn find_key_mapper_key_layout_file_by_name(&self, _name: &str) -> Option<PathBuf> {
-        None
+    fn find_user_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        let directory = self.user_key_layout_dir.as_ref()?;
+        find_file_in_directory(directory, "kl", name)
     }
+
+    fn key_layout_directories(&self) -> Vec<PathBuf> {
+        let system_directories = SYSTEM_PATH_PREFIXES
+            .iter()
+            .map(|prefix| Path::new(prefix).join("keylayout"));
+
+        self.user_key_layout_dir
+            .iter()
+            .cloned()
+            .chain(system_directories)
+            .chain(self.bundled_key_layout_dir.iter().cloned())
+            .collect()
+    }
+
+    fn find_system_key_character_map_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        find_system_file("keychars", "kcm", name)
+    }
+
+    fn find_system_input_device_configuration_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        find_system_file("idc", "idc", name)
+    }
+
+    fn input_device_configuration_directories(&self) -> Vec<PathBuf> {
+        SYSTEM_PATH_PREFIXES
+            .iter()
+            .map(|prefix| Path::new(prefix).join("idc"))
+            .collect()
+    }
+}
+
+// See https://source.android.com/docs/core/interaction/input/key-layout-files#location
+pub const SYSTEM_PATH_PREFIXES: [&str; 4] = [
+    "/odm/usr/",
+    "/vendor/usr/",
+    "/system/usr/",
+    "/data/system/devices/",
+];
+
+/// Find an input device configuration file in one of the system directories.
+fn find_system_file(directory: &str, extension: &str, name: &str) -> Option<PathBuf> {
+    SYSTEM_PATH_PREFIXES.iter().find_map(|prefix| {
+        find_file_in_directory(&Path::new(prefix).join(directory), extension, name)
+    })
+}
+
+/// Find a file with the given name and extension directly inside a directory. Returns None
+/// if it does not exist or can not be opened.
+fn find_file_in_directory(directory: &Path, extension: &str, name: &str) -> Option<PathBuf> {
+    let path = directory.join(format!("{}.{}", name, extension));
+
+    match fs::metadata(&path) {
+        Ok(metadata) if metadata.is_file() => {
+            if File::open(&path).is_ok() {
+                return Some(path);
+            }
+        }
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            debug!("Error accessing {:?}: {}", path, e);
+        }
+        _ => {}
+    }
+
+    None
 }
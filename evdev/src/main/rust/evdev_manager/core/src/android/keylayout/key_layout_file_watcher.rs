@@ -0,0 +1,213 @@
+//! Watches the directories that key layout and input device configuration files are
+//! searched in so that changes to them take effect without restarting.
+
+use crate::evdev_error::{EvdevError, EvdevErrorCode};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub struct KeyLayoutFileWatcher {
+    watcher: Arc<Mutex<Option<RecommendedWatcher>>>,
+}
+
+impl KeyLayoutFileWatcher {
+    pub fn new() -> Self {
+        Self {
+            watcher: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Start watching the directories. `on_change` is called with the paths of the key
+    /// layout and input device configuration files that were added, edited or removed.
+    /// A directory that does not exist yet is watched once it is created, and the files
+    /// that are already in it then are reported as added. Calling this again replaces the
+    /// previous watch.
+    pub fn start(
+        &self,
+        directories: &[PathBuf],
+        on_change: impl Fn(&[PathBuf]) + Send + Sync + 'static,
+    ) -> Result<(), EvdevError> {
+        // Hold the lock until the directories are watched so a directory that is created
+        // in the meantime is not watched by another thread first.
+        let mut watcher_lock = self.watcher.lock().unwrap();
+
+        let on_change = Arc::new(on_change);
+        let missing_directories: Arc<Mutex<Vec<PathBuf>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let mut watcher = notify::recommended_watcher({
+            let watcher_slot = self.watcher.clone();
+            let missing_directories = missing_directories.clone();
+            let on_change = on_change.clone();
+
+            move |event_result: notify::Result<notify::Event>| match event_result {
+                Ok(event) => {
+                    if !is_file_change(&event.kind) {
+                        return;
+                    }
+
+                    if matches!(event.kind, EventKind::Create(_))
+                        && creates_missing_directory(&missing_directories, &event.paths)
+                    {
+                        // Watches can not be added on the watcher's own thread.
+                        let watcher_slot = watcher_slot.clone();
+                        let missing_directories = missing_directories.clone();
+                        let on_change = on_change.clone();
+
+                        thread::spawn(move || {
+                            watch_created_directories(
+                                &watcher_slot,
+                                &missing_directories,
+                                on_change.as_ref(),
+                            )
+                        });
+                    }
+
+                    let paths: Vec<PathBuf> = event
+                        .paths
+                        .into_iter()
+                        .filter(|path| is_watched_file(path))
+                        .collect();
+
+                    if !paths.is_empty() {
+                        on_change(&paths);
+                    }
+                }
+                Err(err) => error!("Failed to receive key layout file event: {}", err),
+            }
+        })
+        .map_err(|e| {
+            error!("Failed to create key layout file watcher: {}", e);
+            EvdevError::from_enum(EvdevErrorCode::IoError)
+        })?;
+
+        *missing_directories.lock().unwrap() = watch_directories(&mut watcher, directories)?;
+        *watcher_lock = Some(watcher);
+
+        Ok(())
+    }
+
+    /// Stop watching the directories.
+    pub fn stop(&self) {
+        *self.watcher.lock().unwrap() = None;
+    }
+}
+
+impl Default for KeyLayoutFileWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Watch the directories that exist. The closest ancestor that exists of every other
+/// directory is watched instead so it is known when the directory is created. Returns the
+/// directories that do not exist.
+fn watch_directories(
+    watcher: &mut RecommendedWatcher,
+    directories: &[PathBuf],
+) -> Result<Vec<PathBuf>, EvdevError> {
+    let mut missing_directories = Vec::new();
+
+    for directory in directories {
+        if directory.is_dir() {
+            watcher
+                .watch(directory, RecursiveMode::NonRecursive)
+                .map_err(|e| {
+                    error!("Failed to watch {:?}: {}", directory, e);
+                    EvdevError::from_enum(EvdevErrorCode::IoError)
+                })?;
+
+            continue;
+        }
+
+        missing_directories.push(directory.clone());
+
+        let Some(ancestor) = directory.ancestors().skip(1).find(|path| path.is_dir()) else {
+            continue;
+        };
+
+        debug!(
+            "Watching {:?} until {:?} is created because it does not exist",
+            ancestor, directory
+        );
+
+        // The ancestor may not be readable, such as /data, so the directory can only be
+        // watched if it exists when the watch is started.
+        watcher
+            .watch(ancestor, RecursiveMode::NonRecursive)
+            .inspect_err(|e| debug!("Failed to watch {:?}: {}", ancestor, e))
+            .ok();
+    }
+
+    Ok(missing_directories)
+}
+
+/// Whether a created path is one of the missing directories or one of their ancestors.
+fn creates_missing_directory(missing_directories: &Mutex<Vec<PathBuf>>, paths: &[PathBuf]) -> bool {
+    missing_directories
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|directory| paths.iter().any(|path| directory.starts_with(path)))
+}
+
+/// Watch the missing directories that have been created and report the files that are
+/// already in them.
+fn watch_created_directories(
+    watcher_slot: &Mutex<Option<RecommendedWatcher>>,
+    missing_directories: &Mutex<Vec<PathBuf>>,
+    on_change: &dyn Fn(&[PathBuf]),
+) {
+    let created_directories: Vec<PathBuf> = {
+        let mut watcher_lock = watcher_slot.lock().unwrap();
+
+        let Some(watcher) = watcher_lock.as_mut() else {
+            return;
+        };
+
+        let mut missing_directories = missing_directories.lock().unwrap();
+        let directories = std::mem::take(&mut *missing_directories);
+
+        match watch_directories(watcher, &directories) {
+            Ok(still_missing) => {
+                *missing_directories = still_missing;
+                directories
+                    .into_iter()
+                    .filter(|directory| !missing_directories.contains(directory))
+                    .collect()
+            }
+            Err(_) => {
+                *missing_directories = directories;
+                return;
+            }
+        }
+    };
+
+    let paths: Vec<PathBuf> = created_directories
+        .iter()
+        .filter_map(|directory| fs::read_dir(directory).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_watched_file(path))
+        .collect();
+
+    if !paths.is_empty() {
+        on_change(&paths);
+    }
+}
+
+/// Whether the event is for a file being added, written to, renamed or removed. Access
+/// events are ignored because loading a key layout file reads it.
+fn is_file_change(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    )
+}
+
+/// Whether the file is a key layout file or an input device configuration file.
+fn is_watched_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "kl" || extension == "idc")
+}
@@ -6,7 +6,13 @@
 //! AOSP keylayout files can be found at:
 //! https://cs.android.com/android/platform/superproject/+/android-latest-release:frameworks/base/data/keyboards/
 
-use crate::android::keylayout::input_event_lookup::{get_axis_by_label, get_key_code_by_label};
+use crate::android::keylayout::input_event_lookup::{
+    get_axis_by_label, get_key_code_by_label, get_key_flag_by_label, get_led_by_label,
+    get_sensor_type_by_label,
+};
+use crate::android::keylayout::key_layout_parse_error::{
+    KeyLayoutParseError, KeyLayoutParseErrorKind, KeyLayoutParseSeverity,
+};
 use crate::android::keylayout::tokenizer::Tokenizer;
 use std::collections::HashMap;
 use std::fmt;
@@ -18,10 +24,27 @@ use std::path::PathBuf;
 pub struct KeyLayoutMap {
     pub scan_codes: Vec<u32>,
     /// Maps scan codes to key codes.
-    keys_by_scan_code: HashMap<u32, u32>,
-    /// Maps key codes to their corresponding scan codes (reverse lookup).
-    scan_codes_by_key_code: HashMap<u32, u32>,
+    keys_by_scan_code: HashMap<u32, KeyLayoutKey>,
+    /// Maps key codes to every scan code that maps to them in the order they are in the
+    /// file (reverse lookup).
+    scan_codes_by_key_code: HashMap<u32, Vec<u32>>,
+    /// Maps HID usage codes to key codes.
+    keys_by_usage_code: HashMap<u32, KeyLayoutKey>,
     axes: HashMap<u32, KeyLayoutAxisInfo>,
+    /// Maps EV_LED codes to Android LED codes.
+    leds_by_scan_code: HashMap<u32, u32>,
+    /// Maps HID usage codes to Android LED codes.
+    leds_by_usage_code: HashMap<u32, u32>,
+    /// Maps the EV_ABS codes of a motion sensor device to the sensor value they report.
+    sensors_by_abs_code: HashMap<u32, KeyLayoutSensorInfo>,
+}
+
+/// The Android key code of a key and the flags that follow it in the key layout file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyLayoutKey {
+    pub key_code: u32,
+    /// The POLICY_FLAG_* bits of the key, such as POLICY_FLAG_FUNCTION or POLICY_FLAG_WAKE.
+    pub flags: u32,
 }
 
 /// Represents axis information for joystick/gamepad axes.
@@ -34,6 +57,37 @@ pub struct KeyLayoutAxisInfo {
     pub flat_override: Option<i32>,
 }
 
+/// The sensor value that an EV_ABS code of a motion sensor device reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyLayoutSensorInfo {
+    /// One of the ASENSOR_TYPE_* constants.
+    pub sensor_type: u32,
+    /// The index of the value in the sensor event. X is 0, Y is 1 and Z is 2.
+    pub data_index: u32,
+}
+
+/// The result of loading a key layout file leniently.
+#[derive(Debug)]
+pub struct KeyLayoutParseReport {
+    /// The entries that could be parsed.
+    pub map: KeyLayoutMap,
+    /// Every error and warning in the file, in the order they were found.
+    pub diagnostics: Vec<KeyLayoutParseError>,
+}
+
+impl KeyLayoutParseReport {
+    /// The problems that would stop the file from loading normally.
+    pub fn errors(&self) -> impl Iterator<Item = &KeyLayoutParseError> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity() == KeyLayoutParseSeverity::Error)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+}
+
 /// Axis mapping mode.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyLayoutAxisMode {
@@ -48,39 +102,138 @@ pub enum KeyLayoutAxisMode {
 const WHITESPACE: &str = " \t\r";
 
 impl KeyLayoutMap {
-    /// Load a key layout map from a file path.
-    pub fn load_from_file(file_path: PathBuf) -> Result<Self, String> {
-        let tokenizer = Tokenizer::from_file(file_path)?;
+    /// Load a key layout map from a file path. Fails at the first error in the file.
+    pub fn load_from_file(file_path: PathBuf) -> Result<Self, KeyLayoutParseError> {
+        let tokenizer = open_file(file_path)?;
         Self::load(tokenizer)
     }
 
     /// Load a key layout map from file contents (useful for testing).
-    pub fn load_from_contents(contents: &str) -> Result<Self, String> {
+    pub fn load_from_contents(contents: &str) -> Result<Self, KeyLayoutParseError> {
         let tokenizer = Tokenizer::from_contents(PathBuf::new(), contents);
         Self::load(tokenizer)
     }
 
-    fn load(mut tokenizer: Tokenizer) -> Result<Self, String> {
-        let mut map = KeyLayoutMap {
+    /// Load a key layout map from a file path, skipping the lines that have errors and
+    /// collecting every error and warning. Only fails if the file can not be read.
+    pub fn load_lenient_from_file(
+        file_path: PathBuf,
+    ) -> Result<KeyLayoutParseReport, KeyLayoutParseError> {
+        let tokenizer = open_file(file_path)?;
+        Ok(Self::load_lenient(tokenizer))
+    }
+
+    /// Load a key layout map from file contents, skipping the lines that have errors.
+    pub fn load_lenient_from_contents(contents: &str) -> KeyLayoutParseReport {
+        let tokenizer = Tokenizer::from_contents(PathBuf::new(), contents);
+        Self::load_lenient(tokenizer)
+    }
+
+    fn load(mut tokenizer: Tokenizer) -> Result<Self, KeyLayoutParseError> {
+        let mut map = Self::empty();
+
+        let mut parser = Parser::new(&mut map, &mut tokenizer, false);
+        parser
+            .parse()
+            .inspect_err(|err| error!("Failed to parse key layout map: {}", err))?;
+
+        Ok(map)
+    }
+
+    fn load_lenient(mut tokenizer: Tokenizer) -> KeyLayoutParseReport {
+        let mut map = Self::empty();
+
+        let mut parser = Parser::new(&mut map, &mut tokenizer, true);
+        // Errors are collected instead of returned when parsing leniently.
+        parser.parse().ok();
+        let diagnostics = parser.diagnostics;
+
+        KeyLayoutParseReport { map, diagnostics }
+    }
+
+    fn empty() -> Self {
+        KeyLayoutMap {
             scan_codes: Vec::with_capacity(16),
             keys_by_scan_code: HashMap::new(),
             scan_codes_by_key_code: HashMap::new(),
+            keys_by_usage_code: HashMap::new(),
             axes: HashMap::new(),
-        };
+            leds_by_scan_code: HashMap::new(),
+            leds_by_usage_code: HashMap::new(),
+            sensors_by_abs_code: HashMap::new(),
+        }
+    }
 
-        let mut parser = Parser::new(&mut map, &mut tokenizer);
-        parser
-            .parse()
-            .inspect_err(|err| error!("Failed to parse key layout map: {:?}", err))?;
+    /// The keys of the map by their scan code.
+    pub fn keys_by_scan_code(&self) -> &HashMap<u32, KeyLayoutKey> {
+        &self.keys_by_scan_code
+    }
 
-        Ok(map)
+    /// The keys of the map by their HID usage code.
+    pub fn keys_by_usage_code(&self) -> &HashMap<u32, KeyLayoutKey> {
+        &self.keys_by_usage_code
+    }
+
+    /// The scan codes that map to each key code in the order they are in the file.
+    pub fn scan_codes_by_key_code(&self) -> &HashMap<u32, Vec<u32>> {
+        &self.scan_codes_by_key_code
+    }
+
+    /// The axes of the map by their scan code.
+    pub fn axes(&self) -> &HashMap<u32, KeyLayoutAxisInfo> {
+        &self.axes
+    }
+
+    /// The Android LED codes by their EV_LED code.
+    pub fn leds_by_scan_code(&self) -> &HashMap<u32, u32> {
+        &self.leds_by_scan_code
+    }
+
+    /// The Android LED codes by their HID usage code.
+    pub fn leds_by_usage_code(&self) -> &HashMap<u32, u32> {
+        &self.leds_by_usage_code
+    }
+
+    /// The sensor values by their EV_ABS code.
+    pub fn sensors_by_abs_code(&self) -> &HashMap<u32, KeyLayoutSensorInfo> {
+        &self.sensors_by_abs_code
     }
 
     /// Map a scan code to an Android key code.
     ///
     /// Returns `Some(key_code)` on success, or `None` if not found.
     pub fn map_key(&self, scan_code: u32) -> Option<u32> {
-        self.keys_by_scan_code.get(&scan_code).copied()
+        self.keys_by_scan_code
+            .get(&scan_code)
+            .map(|key| key.key_code)
+    }
+
+    /// Map a HID usage code, which devices report with EV_MSC/MSC_SCAN, to an Android key code.
+    ///
+    /// Returns `Some(key_code)` on success, or `None` if not found.
+    pub fn map_usage(&self, usage_code: u32) -> Option<u32> {
+        self.keys_by_usage_code
+            .get(&usage_code)
+            .map(|key| key.key_code)
+    }
+
+    /// Map a key to an Android key code, preferring its HID usage code if the map has an
+    /// entry for it like Android's KeyLayoutMap does.
+    pub fn map_key_with_usage(&self, scan_code: u32, usage_code: Option<u32>) -> Option<u32> {
+        self.map_key_with_flags(scan_code, usage_code)
+            .map(|key| key.key_code)
+    }
+
+    /// Like `map_key_with_usage` but also returns the flags of the key.
+    pub fn map_key_with_flags(
+        &self,
+        scan_code: u32,
+        usage_code: Option<u32>,
+    ) -> Option<KeyLayoutKey> {
+        usage_code
+            .and_then(|usage_code| self.keys_by_usage_code.get(&usage_code))
+            .or_else(|| self.keys_by_scan_code.get(&scan_code))
+            .copied()
     }
 
     /// Map a scan code to axis information.
@@ -90,10 +243,76 @@ impl KeyLayoutMap {
         self.axes.get(&scan_code).cloned()
     }
 
-    /// Find all scan codes that map to the given key code.
+    /// Find the first scan code in the file that maps to the given key code. This is
+    /// typically the one without the FUNCTION flag.
     pub fn find_scan_code_for_key(&self, key_code: u32) -> Option<u32> {
-        self.scan_codes_by_key_code.get(&key_code).cloned()
+        self.scan_codes_by_key_code
+            .get(&key_code)
+            .and_then(|scan_codes| scan_codes.first())
+            .copied()
+    }
+
+    /// Find every scan code that maps to the given key code, with the key that it maps to,
+    /// in the order they are in the file.
+    pub fn find_scan_codes_for_key(&self, key_code: u32) -> Vec<(u32, KeyLayoutKey)> {
+        self.scan_codes_by_key_code
+            .get(&key_code)
+            .into_iter()
+            .flatten()
+            .filter_map(|scan_code| {
+                self.keys_by_scan_code
+                    .get(scan_code)
+                    .map(|key| (*scan_code, *key))
+            })
+            .collect()
+    }
+
+    /// Map an EV_LED code to an Android LED code.
+    pub fn map_led(&self, scan_code: u32) -> Option<u32> {
+        self.leds_by_scan_code.get(&scan_code).copied()
+    }
+
+    /// Map a HID usage code of an LED to an Android LED code.
+    pub fn map_led_usage(&self, usage_code: u32) -> Option<u32> {
+        self.leds_by_usage_code.get(&usage_code).copied()
+    }
+
+    /// Find the EV_LED code that drives the given Android LED code. If several do then
+    /// the lowest one is returned.
+    pub fn find_scan_code_for_led(&self, led_code: u32) -> Option<u32> {
+        find_code(&self.leds_by_scan_code, |code| *code == led_code)
+    }
+
+    /// Find the HID usage code that drives the given Android LED code.
+    pub fn find_usage_code_for_led(&self, led_code: u32) -> Option<u32> {
+        find_code(&self.leds_by_usage_code, |code| *code == led_code)
+    }
+
+    /// Map an EV_ABS code of a motion sensor device to the sensor value it reports.
+    pub fn map_sensor(&self, abs_code: u32) -> Option<KeyLayoutSensorInfo> {
+        self.sensors_by_abs_code.get(&abs_code).copied()
     }
+
+    /// Find the EV_ABS code that reports the given value of a sensor.
+    pub fn find_abs_code_for_sensor(&self, sensor_type: u32, data_index: u32) -> Option<u32> {
+        find_code(&self.sensors_by_abs_code, |sensor| {
+            sensor.sensor_type == sensor_type && sensor.data_index == data_index
+        })
+    }
+}
+
+fn open_file(file_path: PathBuf) -> Result<Tokenizer, KeyLayoutParseError> {
+    Tokenizer::from_file(file_path.clone())
+        .map_err(|message| KeyLayoutParseError::io(file_path, message))
+}
+
+/// Find the lowest code whose entry matches the predicate so reverse lookups do not depend
+/// on the iteration order of the map.
+fn find_code<T>(map: &HashMap<u32, T>, predicate: impl Fn(&T) -> bool) -> Option<u32> {
+    map.iter()
+        .filter(|(_, value)| predicate(value))
+        .map(|(code, _)| *code)
+        .min()
 }
 
 impl fmt::Debug for KeyLayoutMap {
@@ -101,6 +320,8 @@ impl fmt::Debug for KeyLayoutMap {
         let mut debug_struct = f.debug_struct("KeyLayoutMap");
         debug_struct.field("keys_count", &self.keys_by_scan_code.len());
         debug_struct.field("axes_count", &self.axes.len());
+        debug_struct.field("leds_count", &self.leds_by_scan_code.len());
+        debug_struct.field("sensors_count", &self.sensors_by_abs_code.len());
 
         // Show a sample of keys (first 5) if there are any
         if !self.keys_by_scan_code.is_empty() {
@@ -108,7 +329,7 @@ impl fmt::Debug for KeyLayoutMap {
                 .keys_by_scan_code
                 .iter()
                 .take(5)
-                .map(|(scan_code, key_code)| (*scan_code, *key_code))
+                .map(|(scan_code, key)| (*scan_code, key.key_code))
                 .collect();
             debug_struct.field("sample_keys", &sample_keys);
         }
@@ -127,98 +348,154 @@ impl fmt::Debug for KeyLayoutMap {
 struct Parser<'a> {
     map: &'a mut KeyLayoutMap,
     tokenizer: &'a mut Tokenizer,
+    /// Whether to skip lines with errors instead of stopping at the first one.
+    lenient: bool,
+    /// The warnings, and the errors if parsing leniently, in the order they were found.
+    diagnostics: Vec<KeyLayoutParseError>,
 }
 
 impl<'a> Parser<'a> {
-    fn new(map: &'a mut KeyLayoutMap, tokenizer: &'a mut Tokenizer) -> Self {
-        Self { map, tokenizer }
+    fn new(map: &'a mut KeyLayoutMap, tokenizer: &'a mut Tokenizer, lenient: bool) -> Self {
+        Self {
+            map,
+            tokenizer,
+            lenient,
+            diagnostics: Vec::new(),
+        }
     }
 
-    fn parse(&mut self) -> Result<(), String> {
+    fn parse(&mut self) -> Result<(), KeyLayoutParseError> {
         while !self.tokenizer.is_eof() {
             self.tokenizer.skip_delimiters(WHITESPACE);
 
             if !self.tokenizer.is_eol() && self.tokenizer.peek_char() != '#' {
-                let keyword_token = self.tokenizer.next_token(WHITESPACE);
-                let mut skipped_line: bool = false;
-
-                match keyword_token.as_str() {
-                    "key" => {
-                        self.tokenizer.skip_delimiters(WHITESPACE);
-                        let code_token = self.tokenizer.next_token(WHITESPACE);
-
-                        // Skip "usage" entries - we only support scan codes
-                        if code_token == "usage" {
-                            skipped_line = true;
-                        } else {
-                            self.parse_key(&code_token)?;
-                        }
-                    }
-                    "axis" => {
-                        self.tokenizer.skip_delimiters(WHITESPACE);
-                        self.parse_axis()?;
-                    }
-                    "led" | "sensor" => {
-                        // Skip LEDs and sensors, we don't need them
-                        self.tokenizer.next_line();
-                        continue;
-                    }
-                    "requires_kernel_config" => {
-                        self.tokenizer.next_line();
-                        continue;
-                    }
-                    "usage" => {
-                        // Skip usage code entries - evdev only provides scan codes
-                        self.tokenizer.next_line();
-                        continue;
-                    }
-                    _ => {
-                        return Err(format!(
-                            "{}: Expected keyword, got '{}'.",
-                            self.tokenizer.get_location(),
-                            keyword_token
-                        ));
+                if let Err(err) = self.parse_line() {
+                    if !self.lenient {
+                        return Err(err);
                     }
+
+                    self.diagnostics.push(err);
                 }
+            }
 
-                self.tokenizer.skip_delimiters(WHITESPACE);
+            self.tokenizer.next_line();
+        }
 
-                if !skipped_line && !self.tokenizer.is_eol() && self.tokenizer.peek_char() != '#' {
-                    return Err(format!(
-                        "{}: Expected end of line or trailing comment, got '{}'.",
-                        self.tokenizer.get_location(),
-                        self.tokenizer.peek_remainder_of_line()
-                    ));
+        Ok(())
+    }
+
+    fn parse_line(&mut self) -> Result<(), KeyLayoutParseError> {
+        let keyword_token = self.tokenizer.next_token(WHITESPACE);
+
+        match keyword_token.as_str() {
+            "key" => {
+                self.tokenizer.skip_delimiters(WHITESPACE);
+                let code_token = self.tokenizer.next_token(WHITESPACE);
+
+                if code_token == "usage" {
+                    self.tokenizer.skip_delimiters(WHITESPACE);
+                    let usage_token = self.tokenizer.next_token(WHITESPACE);
+                    self.parse_key(&usage_token, true)?;
+                } else {
+                    self.parse_key(&code_token, false)?;
                 }
             }
+            "axis" => {
+                self.tokenizer.skip_delimiters(WHITESPACE);
+                self.parse_axis()?;
+            }
+            "led" => {
+                self.tokenizer.skip_delimiters(WHITESPACE);
+                self.parse_led()?;
+            }
+            "sensor" => {
+                self.tokenizer.skip_delimiters(WHITESPACE);
+                self.parse_sensor()?;
+            }
+            // The kernel config can not be checked so the file is loaded whatever it requires.
+            // Usage codes are mapped by "key usage" lines above so a bare usage line is ignored.
+            "requires_kernel_config" | "usage" => return Ok(()),
+            _ => {
+                return Err(self.error(
+                    KeyLayoutParseErrorKind::UnknownKeyword,
+                    &keyword_token,
+                    format!("Expected keyword, got '{}'.", keyword_token),
+                ));
+            }
+        }
 
-            self.tokenizer.next_line();
+        self.tokenizer.skip_delimiters(WHITESPACE);
+
+        if !self.tokenizer.is_eol() && self.tokenizer.peek_char() != '#' {
+            let remainder = self.tokenizer.peek_remainder_of_line();
+
+            return Err(KeyLayoutParseError::at_position(
+                self.tokenizer,
+                KeyLayoutParseErrorKind::TrailingText,
+                &remainder,
+                format!(
+                    "Expected end of line or trailing comment, got '{}'.",
+                    remainder
+                ),
+            ));
         }
 
         Ok(())
     }
 
-    fn parse_key(&mut self, code_token: &str) -> Result<(), String> {
-        let scan_code = parse_int(code_token).ok_or_else(|| {
-            format!(
-                "{}: Expected key scan code number, got '{}'.",
-                self.tokenizer.get_location(),
-                code_token
+    /// Create an error for the token that was last read.
+    fn error(
+        &self,
+        kind: KeyLayoutParseErrorKind,
+        token: &str,
+        message: String,
+    ) -> KeyLayoutParseError {
+        KeyLayoutParseError::at_token(self.tokenizer, kind, token, message)
+    }
+
+    /// Record a problem with the token that was last read that does not stop the file
+    /// from loading.
+    fn warn(&mut self, kind: KeyLayoutParseErrorKind, token: &str, message: String) {
+        let warning = self.error(kind, token, message);
+        warn!("{}", warning);
+        self.diagnostics.push(warning);
+    }
+
+    fn parse_key(&mut self, code_token: &str, map_usage: bool) -> Result<(), KeyLayoutParseError> {
+        let code_name = if map_usage { "usage" } else { "scan code" };
+
+        let code = parse_int(code_token).ok_or_else(|| {
+            self.error(
+                KeyLayoutParseErrorKind::InvalidCode,
+                code_token,
+                format!("Expected key {} number, got '{}'.", code_name, code_token),
             )
         })?;
 
-        if scan_code < 0 {
-            return Err(format!(
-                "{} is not a valid key scan code. Negative numbers are not allowed.",
-                scan_code
+        if code < 0 {
+            return Err(self.error(
+                KeyLayoutParseErrorKind::InvalidCode,
+                code_token,
+                format!(
+                    "{} is not a valid key {}. Negative numbers are not allowed.",
+                    code, code_name
+                ),
             ));
         }
 
-        if self.map.keys_by_scan_code.contains_key(&(scan_code as u32)) {
-            return Err(format!(
-                "{}: Duplicate entry for key scan code '{}'.",
-                self.tokenizer.get_location(),
-                code_token
+        let code = code as u32;
+
+        let is_duplicate = if map_usage {
+            self.map.keys_by_usage_code.contains_key(&code)
+        } else {
+            self.map.keys_by_scan_code.contains_key(&code)
+        };
+
+        if is_duplicate {
+            return Err(self.error(
+                KeyLayoutParseErrorKind::DuplicateEntry,
+                code_token,
+                format!("Duplicate entry for key {} '{}'.", code_name, code_token),
             ));
         }
 
@@ -226,56 +503,209 @@ impl<'a> Parser<'a> {
         let key_code_token = self.tokenizer.next_token(WHITESPACE);
         let key_code = get_key_code_by_label(&key_code_token);
 
-        // Skip any remaining tokens on the line (flags, etc.)
+        if key_code.is_none() {
+            self.warn(
+                KeyLayoutParseErrorKind::UnknownKeyCode,
+                &key_code_token,
+                format!(
+                    "Ignoring key {} '{}' with unknown key code label '{}'.",
+                    code_name, code_token, key_code_token
+                ),
+            );
+        }
+
+        let mut flags: u32 = 0;
+
         loop {
             self.tokenizer.skip_delimiters(WHITESPACE);
             if self.tokenizer.is_eol() || self.tokenizer.peek_char() == '#' {
                 break;
             }
-            // Consume and skip the token (flags are ignored)
-            self.tokenizer.next_token(WHITESPACE);
+
+            let flag_token = self.tokenizer.next_token(WHITESPACE);
+
+            // Some OEMs add their own flags so skip the ones that are unknown rather than
+            // failing to load the whole file.
+            let Some(flag) = get_key_flag_by_label(&flag_token) else {
+                self.warn(
+                    KeyLayoutParseErrorKind::UnknownKeyFlag,
+                    &flag_token,
+                    format!("Ignoring unknown key flag '{}'.", flag_token),
+                );
+                continue;
+            };
+
+            if flags & flag != 0 {
+                return Err(self.error(
+                    KeyLayoutParseErrorKind::DuplicateKeyFlag,
+                    &flag_token,
+                    format!("Duplicate key flag '{}'.", flag_token),
+                ));
+            }
+
+            flags |= flag;
         }
 
         // Only insert if the key code is known
         if let Some(key_code) = key_code {
-            self.map.scan_codes.push(scan_code as u32);
-            self.map
-                .keys_by_scan_code
-                .insert(scan_code as u32, key_code);
-
-            // Only insert if this key_code doesn't already have a scan code.
-            // This ensures we keep the first (typically non-FUNCTION) scan code.
-            self.map
-                .scan_codes_by_key_code
-                .entry(key_code)
-                .or_insert(scan_code as u32);
+            let key = KeyLayoutKey { key_code, flags };
+
+            if map_usage {
+                self.map.keys_by_usage_code.insert(code, key);
+            } else {
+                self.map.scan_codes.push(code);
+                self.map.keys_by_scan_code.insert(code, key);
+
+                self.map
+                    .scan_codes_by_key_code
+                    .entry(key_code)
+                    .or_default()
+                    .push(code);
+            }
         }
 
         Ok(())
     }
 
-    fn parse_axis(&mut self) -> Result<(), String> {
+    fn parse_led(&mut self) -> Result<(), KeyLayoutParseError> {
+        let mut code_token = self.tokenizer.next_token(WHITESPACE);
+        let map_usage = code_token == "usage";
+
+        if map_usage {
+            self.tokenizer.skip_delimiters(WHITESPACE);
+            code_token = self.tokenizer.next_token(WHITESPACE);
+        }
+
+        let code_name = if map_usage { "usage" } else { "scan code" };
+
+        let code = parse_int(&code_token)
+            .filter(|code| *code >= 0)
+            .ok_or_else(|| {
+                self.error(
+                    KeyLayoutParseErrorKind::InvalidCode,
+                    &code_token,
+                    format!("Expected led {} number, got '{}'.", code_name, code_token),
+                )
+            })? as u32;
+
+        let is_duplicate = if map_usage {
+            self.map.leds_by_usage_code.contains_key(&code)
+        } else {
+            self.map.leds_by_scan_code.contains_key(&code)
+        };
+
+        if is_duplicate {
+            return Err(self.error(
+                KeyLayoutParseErrorKind::DuplicateEntry,
+                &code_token,
+                format!("Duplicate entry for led {} '{}'.", code_name, code_token),
+            ));
+        }
+
+        self.tokenizer.skip_delimiters(WHITESPACE);
+        let led_code_token = self.tokenizer.next_token(WHITESPACE);
+        let led_code = get_led_by_label(&led_code_token).ok_or_else(|| {
+            self.error(
+                KeyLayoutParseErrorKind::UnknownLed,
+                &led_code_token,
+                format!("Expected LED code label, got '{}'.", led_code_token),
+            )
+        })?;
+
+        if map_usage {
+            self.map.leds_by_usage_code.insert(code, led_code);
+        } else {
+            self.map.leds_by_scan_code.insert(code, led_code);
+        }
+
+        Ok(())
+    }
+
+    fn parse_sensor(&mut self) -> Result<(), KeyLayoutParseError> {
+        let code_token = self.tokenizer.next_token(WHITESPACE);
+        let abs_code = parse_int(&code_token)
+            .filter(|code| *code >= 0)
+            .ok_or_else(|| {
+                self.error(
+                    KeyLayoutParseErrorKind::InvalidCode,
+                    &code_token,
+                    format!("Expected sensor abs code number, got '{}'.", code_token),
+                )
+            })? as u32;
+
+        if self.map.sensors_by_abs_code.contains_key(&abs_code) {
+            return Err(self.error(
+                KeyLayoutParseErrorKind::DuplicateEntry,
+                &code_token,
+                format!("Duplicate entry for sensor abs code '{}'.", code_token),
+            ));
+        }
+
+        self.tokenizer.skip_delimiters(WHITESPACE);
+        let sensor_type_token = self.tokenizer.next_token(WHITESPACE);
+        let sensor_type = get_sensor_type_by_label(&sensor_type_token).ok_or_else(|| {
+            self.error(
+                KeyLayoutParseErrorKind::UnknownSensorType,
+                &sensor_type_token,
+                format!("Expected sensor code label, got '{}'.", sensor_type_token),
+            )
+        })?;
+
+        self.tokenizer.skip_delimiters(WHITESPACE);
+        let data_index_token = self.tokenizer.next_token(WHITESPACE);
+        let data_index = match data_index_token.as_str() {
+            "X" => 0,
+            "Y" => 1,
+            "Z" => 2,
+            _ => {
+                return Err(self.error(
+                    KeyLayoutParseErrorKind::InvalidSensorDataIndex,
+                    &data_index_token,
+                    format!(
+                        "Expected sensor data index label, got '{}'.",
+                        data_index_token
+                    ),
+                ));
+            }
+        };
+
+        self.map.sensors_by_abs_code.insert(
+            abs_code,
+            KeyLayoutSensorInfo {
+                sensor_type,
+                data_index,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn parse_axis(&mut self) -> Result<(), KeyLayoutParseError> {
         let scan_code_token = self.tokenizer.next_token(WHITESPACE);
         let scan_code = parse_int(&scan_code_token).ok_or_else(|| {
-            format!(
-                "{}: Expected axis scan code number, got '{}'.",
-                self.tokenizer.get_location(),
-                scan_code_token
+            self.error(
+                KeyLayoutParseErrorKind::InvalidCode,
+                &scan_code_token,
+                format!("Expected axis scan code number, got '{}'.", scan_code_token),
             )
         })?;
 
         if scan_code < 0 {
-            return Err(format!(
-                "{} is not a valid key scan code for an axis. Negative numbers are not allowed.",
-                scan_code
+            return Err(self.error(
+                KeyLayoutParseErrorKind::InvalidCode,
+                &scan_code_token,
+                format!(
+                    "{} is not a valid key scan code for an axis. Negative numbers are not allowed.",
+                    scan_code
+                ),
             ));
         }
 
         if self.map.axes.contains_key(&(scan_code as u32)) {
-            return Err(format!(
-                "{}: Duplicate entry for axis scan code '{}'.",
-                self.tokenizer.get_location(),
-                scan_code_token
+            return Err(self.error(
+                KeyLayoutParseErrorKind::DuplicateEntry,
+                &scan_code_token,
+                format!("Duplicate entry for axis scan code '{}'.", scan_code_token),
             ));
         }
 
@@ -296,10 +726,10 @@ impl<'a> Parser<'a> {
             let axis_token = self.tokenizer.next_token(WHITESPACE);
 
             axis = get_axis_by_label(&axis_token).ok_or_else(|| {
-                format!(
-                    "{}: Expected inverted axis label, got '{}'.",
-                    self.tokenizer.get_location(),
-                    axis_token
+                self.error(
+                    KeyLayoutParseErrorKind::UnknownAxis,
+                    &axis_token,
+                    format!("Expected inverted axis label, got '{}'.", axis_token),
                 )
             })?;
         } else if token == "split" {
@@ -308,10 +738,10 @@ impl<'a> Parser<'a> {
             self.tokenizer.skip_delimiters(WHITESPACE);
             let split_token = self.tokenizer.next_token(WHITESPACE);
             let split_value_raw = parse_int(&split_token).ok_or_else(|| {
-                format!(
-                    "{}: Expected split value, got '{}'.",
-                    self.tokenizer.get_location(),
-                    split_token
+                self.error(
+                    KeyLayoutParseErrorKind::InvalidValue,
+                    &split_token,
+                    format!("Expected split value, got '{}'.", split_token),
                 )
             })?;
             split_value = Some(split_value_raw);
@@ -319,29 +749,29 @@ impl<'a> Parser<'a> {
             self.tokenizer.skip_delimiters(WHITESPACE);
             let low_axis_token = self.tokenizer.next_token(WHITESPACE);
             axis = get_axis_by_label(&low_axis_token).ok_or_else(|| {
-                format!(
-                    "{}: Expected low axis label, got '{}'.",
-                    self.tokenizer.get_location(),
-                    low_axis_token
+                self.error(
+                    KeyLayoutParseErrorKind::UnknownAxis,
+                    &low_axis_token,
+                    format!("Expected low axis label, got '{}'.", low_axis_token),
                 )
             })?;
 
             self.tokenizer.skip_delimiters(WHITESPACE);
             let high_axis_token = self.tokenizer.next_token(WHITESPACE);
             let high_axis_raw = get_axis_by_label(&high_axis_token).ok_or_else(|| {
-                format!(
-                    "{}: Expected high axis label, got '{}'.",
-                    self.tokenizer.get_location(),
-                    high_axis_token
+                self.error(
+                    KeyLayoutParseErrorKind::UnknownAxis,
+                    &high_axis_token,
+                    format!("Expected high axis label, got '{}'.", high_axis_token),
                 )
             })?;
             high_axis = Some(high_axis_raw);
         } else {
             axis = get_axis_by_label(&token).ok_or_else(|| {
-                format!(
-                    "{}: Expected axis label, 'split' or 'invert', got '{}'.",
-                    self.tokenizer.get_location(),
-                    token
+                self.error(
+                    KeyLayoutParseErrorKind::UnknownAxis,
+                    &token,
+                    format!("Expected axis label, 'split' or 'invert', got '{}'.", token),
                 )
             })?;
         }
@@ -357,18 +787,18 @@ impl<'a> Parser<'a> {
                 self.tokenizer.skip_delimiters(WHITESPACE);
                 let flat_token = self.tokenizer.next_token(WHITESPACE);
                 let flat_override_raw = parse_int(&flat_token).ok_or_else(|| {
-                    format!(
-                        "{}: Expected flat value, got '{}'.",
-                        self.tokenizer.get_location(),
-                        flat_token
+                    self.error(
+                        KeyLayoutParseErrorKind::InvalidValue,
+                        &flat_token,
+                        format!("Expected flat value, got '{}'.", flat_token),
                     )
                 })?;
                 flat_override = Some(flat_override_raw);
             } else {
-                return Err(format!(
-                    "{}: Expected keyword 'flat', got '{}'.",
-                    self.tokenizer.get_location(),
-                    keyword_token
+                return Err(self.error(
+                    KeyLayoutParseErrorKind::UnknownKeyword,
+                    &keyword_token,
+                    format!("Expected keyword 'flat', got '{}'.", keyword_token),
                 ));
             }
         }
@@ -1,9 +1,14 @@
 use crate::android::keylayout::generic_key_layout::GENERIC_KEY_LAYOUT_CONTENTS;
+use crate::android::keylayout::input_device_configuration::InputDeviceConfiguration;
+use crate::android::keylayout::key_character_map::KeyCharacterMap;
 use crate::android::keylayout::key_layout_file_finder::{
     AndroidKeyLayoutFileFinder, KeyLayoutFileFinder,
 };
-use crate::android::keylayout::key_layout_map::KeyLayoutMap;
+use crate::android::keylayout::key_layout_file_watcher::KeyLayoutFileWatcher;
+use crate::android::keylayout::key_layout_map::{KeyLayoutAxisInfo, KeyLayoutKey, KeyLayoutMap};
+use crate::device_capabilities::DeviceCapabilities;
 use crate::evdev_device_info::EvdevDeviceInfo;
+use crate::evdev_error::EvdevError;
 use evdev::enums::{EventCode, EventType};
 use evdev::util::int_to_event_code;
 use libc::c_uint;
@@ -11,11 +16,14 @@ use log::{error, info};
 use std::collections::HashMap;
 use std::error::Error;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
 
 static KEY_LAYOUT_MANAGER: OnceLock<Arc<KeyLayoutMapManager>> = OnceLock::new();
 static GENERIC_KEY_LAYOUT_MAP: OnceLock<Arc<KeyLayoutMap>> = OnceLock::new();
 
+/// The name of the files that every device falls back to, without the extension.
+const GENERIC_FILE_NAME: &str = "Generic";
+
 /// Get the static generic KeyLayoutMap instance.
 /// This is lazily initialized from hardcoded key mappings based on AOSP Generic.kl.
 pub fn get_generic_key_layout_map() -> Arc<KeyLayoutMap> {
@@ -36,20 +44,35 @@ pub struct KeyLayoutMapManager {
     /// the key layout map could not be found or there was an error parsing,
     /// and it shouldn't be attempted again.
     pub key_layout_maps: Mutex<HashMap<EvdevDeviceInfo, Option<Arc<KeyLayoutMap>>>>,
+    /// KeyCharacterMap cache. If the value is None then no key character map could be
+    /// loaded for the device.
+    key_character_maps: Mutex<HashMap<EvdevDeviceInfo, Option<Arc<KeyCharacterMap>>>>,
+    /// Input device configuration cache. If the value is None then the device does not
+    /// have an input device configuration file or it could not be parsed.
+    input_device_configurations:
+        Mutex<HashMap<EvdevDeviceInfo, Option<Arc<InputDeviceConfiguration>>>>,
     /// File finder for locating key layout files
     file_finder: Arc<dyn KeyLayoutFileFinder>,
+    /// Watches the key layout directories so the cache can be invalidated.
+    file_watcher: KeyLayoutFileWatcher,
 }
 
 impl KeyLayoutMapManager {
+    /// Initialize the static instance with a custom file finder, such as one that also
+    /// searches the key layout files shipped with Key Mapper. This must be called before
+    /// the first call to `get()`, otherwise only the system files are searched.
+    pub fn init(file_finder: Arc<dyn KeyLayoutFileFinder>) {
+        KEY_LAYOUT_MANAGER
+            .set(Arc::new(Self::with_file_finder(file_finder)))
+            .unwrap_or_else(|_| error!("KeyLayoutMapManager already initialized"));
+    }
+
     pub fn get() -> Arc<Self> {
         Arc::clone(KEY_LAYOUT_MANAGER.get_or_init(|| Arc::new(Self::new())))
     }
 
     fn new() -> Self {
-        Self {
-            key_layout_maps: Mutex::new(HashMap::with_capacity(32)),
-            file_finder: Arc::new(AndroidKeyLayoutFileFinder),
-        }
+        Self::with_file_finder(Arc::new(AndroidKeyLayoutFileFinder::default()))
     }
 
     /// Create a new instance with a custom file finder.
@@ -57,7 +80,110 @@ impl KeyLayoutMapManager {
     pub fn with_file_finder(file_finder: Arc<dyn KeyLayoutFileFinder>) -> Self {
         Self {
             key_layout_maps: Mutex::new(HashMap::with_capacity(32)),
+            key_character_maps: Mutex::new(HashMap::new()),
+            input_device_configurations: Mutex::new(HashMap::new()),
             file_finder,
+            file_watcher: KeyLayoutFileWatcher::new(),
+        }
+    }
+
+    /// Start watching the key layout and input device configuration directories so that
+    /// files which are added, edited or removed are used without restarting.
+    pub fn start_watching(self: &Arc<Self>) -> Result<(), EvdevError> {
+        let manager: Weak<Self> = Arc::downgrade(self);
+
+        let mut directories = self.file_finder.key_layout_directories();
+
+        for directory in self.file_finder.input_device_configuration_directories() {
+            if !directories.contains(&directory) {
+                directories.push(directory);
+            }
+        }
+
+        self.file_watcher.start(&directories, move |paths| {
+            if let Some(manager) = manager.upgrade() {
+                manager.on_key_layout_files_changed(paths);
+            }
+        })
+    }
+
+    pub fn stop_watching(&self) {
+        self.file_watcher.stop();
+    }
+
+    /// Remove the cached key layout maps of the devices that could use one of the changed
+    /// files, so they are loaded again the next time they are needed. A device could use a
+    /// file if it has one of the names that are searched for the device. This includes
+    /// devices that use the generic fallback because a better file may have been added.
+    /// Changed input device configuration files also remove the cached configurations.
+    pub fn on_key_layout_files_changed(&self, paths: &[PathBuf]) {
+        let changed_names: Vec<&str> = paths
+            .iter()
+            .filter_map(|path| path.file_stem().and_then(|stem| stem.to_str()))
+            .collect();
+
+        let changed_configuration_names: Vec<&str> = paths
+            .iter()
+            .filter(|path| path.extension().is_some_and(|extension| extension == "idc"))
+            .filter_map(|path| path.file_stem().and_then(|stem| stem.to_str()))
+            .collect();
+
+        if !changed_configuration_names.is_empty() {
+            self.remove_input_device_configurations(&changed_configuration_names);
+        }
+
+        let mut key_layout_maps = self.key_layout_maps.lock().unwrap();
+
+        key_layout_maps.retain(|device_info, _| {
+            let configured_name = self
+                .get_input_device_configuration(device_info)
+                .and_then(|configuration| configuration.keyboard_layout().map(str::to_string));
+
+            let is_affected = changed_names.contains(&GENERIC_FILE_NAME)
+                || get_file_names_by_device(device_info, configured_name.as_deref())
+                    .iter()
+                    .any(|name| changed_names.contains(&name.as_str()));
+
+            if is_affected {
+                info!(
+                    "Reloading key layout map for device {} because {:?} changed",
+                    device_info.name, paths
+                );
+            }
+
+            !is_affected
+        });
+    }
+
+    /// Remove the cached input device configurations of the devices that could use one of
+    /// the changed files. Their key layout and key character maps are removed too because
+    /// the configuration can choose which files they are loaded from.
+    fn remove_input_device_configurations(&self, changed_names: &[&str]) {
+        let affected_devices: Vec<EvdevDeviceInfo> = self
+            .input_device_configurations
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|device_info| {
+                get_file_names_by_device(device_info, None)
+                    .iter()
+                    .any(|name| changed_names.contains(&name.as_str()))
+            })
+            .cloned()
+            .collect();
+
+        for device_info in affected_devices {
+            info!(
+                "Reloading input device configuration for device {} because {:?} changed",
+                device_info.name, changed_names
+            );
+
+            self.input_device_configurations
+                .lock()
+                .unwrap()
+                .remove(&device_info);
+            self.key_layout_maps.lock().unwrap().remove(&device_info);
+            self.key_character_maps.lock().unwrap().remove(&device_info);
         }
     }
 
@@ -81,6 +207,59 @@ impl KeyLayoutMapManager {
         Ok(get_generic_key_layout_map().map_key(scan_code))
     }
 
+    /// Map a key to an Android key code using the HID usage code that the device reported
+    /// for it, if any. The usage entries of a map take priority over its scan code entries,
+    /// and the device's map takes priority over the generic key layout.
+    pub fn map_key_with_usage(
+        &self,
+        device_info: &EvdevDeviceInfo,
+        scan_code: u32,
+        usage_code: Option<u32>,
+    ) -> Result<Option<u32>, Box<dyn Error>> {
+        let key = self.map_key_with_flags(device_info, scan_code, usage_code)?;
+        Ok(key.map(|key| key.key_code))
+    }
+
+    /// Like `map_key_with_usage` but also returns the flags of the key from the layout
+    /// file it was found in.
+    pub fn map_key_with_flags(
+        &self,
+        device_info: &EvdevDeviceInfo,
+        scan_code: u32,
+        usage_code: Option<u32>,
+    ) -> Result<Option<KeyLayoutKey>, Box<dyn Error>> {
+        let device_map = self.get_key_layout_map_lazy(device_info)?;
+
+        if let Some(map) = device_map {
+            if let Some(key) = map.map_key_with_flags(scan_code, usage_code) {
+                return Ok(Some(key));
+            }
+        }
+
+        // Fall back to generic key layout
+        Ok(get_generic_key_layout_map().map_key_with_flags(scan_code, usage_code))
+    }
+
+    /// Map a raw EV_ABS code to the Android axis it controls.
+    /// Returns the axis if it is found in the device's map,
+    /// falling back to the generic key layout if not found.
+    pub fn map_axis(
+        &self,
+        device_info: &EvdevDeviceInfo,
+        scan_code: u32,
+    ) -> Result<Option<KeyLayoutAxisInfo>, Box<dyn Error>> {
+        let device_map = self.get_key_layout_map_lazy(device_info)?;
+
+        if let Some(map) = device_map {
+            if let Some(axis_info) = map.map_axis(scan_code) {
+                return Ok(Some(axis_info));
+            }
+        }
+
+        // Fall back to generic key layout
+        Ok(get_generic_key_layout_map().map_axis(scan_code))
+    }
+
     /// Find the scan code for a given Android key code.
     /// Returns the scan code if found in the device's map,
     /// falling back to the generic key layout if not found.
@@ -101,6 +280,91 @@ impl KeyLayoutMapManager {
         Ok(get_generic_key_layout_map().find_scan_code_for_key(key_code))
     }
 
+    /// Find every scan code for a given Android key code with the key it maps to, in the
+    /// order they are in the device's map. Falls back to the generic key layout if the
+    /// device's map has none.
+    pub fn find_scan_codes_for_key(
+        &self,
+        device_info: &EvdevDeviceInfo,
+        key_code: u32,
+    ) -> Result<Vec<(u32, KeyLayoutKey)>, Box<dyn Error>> {
+        let device_map = self.get_key_layout_map_lazy(device_info)?;
+
+        if let Some(map) = device_map {
+            let scan_codes = map.find_scan_codes_for_key(key_code);
+
+            if !scan_codes.is_empty() {
+                return Ok(scan_codes);
+            }
+        }
+
+        Ok(get_generic_key_layout_map().find_scan_codes_for_key(key_code))
+    }
+
+    /// Find the scan code to input a given Android key code on a device. The first scan
+    /// code that the device supports is preferred so it looks like the device's own key,
+    /// otherwise the first scan code is used like `find_scan_code_for_key`.
+    pub fn find_supported_scan_code_for_key(
+        &self,
+        device_info: &EvdevDeviceInfo,
+        capabilities: &DeviceCapabilities,
+        key_code: u32,
+    ) -> Result<Option<u32>, Box<dyn Error>> {
+        let scan_codes: Vec<u32> = self
+            .find_scan_codes_for_key(device_info, key_code)?
+            .into_iter()
+            .map(|(scan_code, _)| scan_code)
+            .collect();
+
+        let supported_scan_code = scan_codes.iter().copied().find(|scan_code| {
+            let event_code = int_to_event_code(EventType::EV_KEY as c_uint, *scan_code as c_uint);
+            capabilities.has_event_code(&event_code)
+        });
+
+        Ok(supported_scan_code.or_else(|| scan_codes.first().copied()))
+    }
+
+    /// Find the EV_LED code that drives an Android LED, such as ALED_CAPS_LOCK.
+    /// Returns the code if found in the device's map,
+    /// falling back to the generic key layout if not found.
+    pub fn find_scan_code_for_led(
+        &self,
+        device_info: &EvdevDeviceInfo,
+        led_code: u32,
+    ) -> Result<Option<u32>, Box<dyn Error>> {
+        let device_map = self.get_key_layout_map_lazy(device_info)?;
+
+        if let Some(map) = device_map {
+            if let Some(scan_code) = map.find_scan_code_for_led(led_code) {
+                return Ok(Some(scan_code));
+            }
+        }
+
+        // Fall back to generic key layout
+        Ok(get_generic_key_layout_map().find_scan_code_for_led(led_code))
+    }
+
+    /// Find the EV_ABS code that reports a value of a motion sensor, such as the X value of
+    /// ASENSOR_TYPE_GYROSCOPE. Returns the code if found in the device's map,
+    /// falling back to the generic key layout if not found.
+    pub fn find_abs_code_for_sensor(
+        &self,
+        device_info: &EvdevDeviceInfo,
+        sensor_type: u32,
+        data_index: u32,
+    ) -> Result<Option<u32>, Box<dyn Error>> {
+        let device_map = self.get_key_layout_map_lazy(device_info)?;
+
+        if let Some(map) = device_map {
+            if let Some(abs_code) = map.find_abs_code_for_sensor(sensor_type, data_index) {
+                return Ok(Some(abs_code));
+            }
+        }
+
+        // Fall back to generic key layout
+        Ok(get_generic_key_layout_map().find_abs_code_for_sensor(sensor_type, data_index))
+    }
+
     pub fn preload_key_layout_map(
         &self,
         device_info: &EvdevDeviceInfo,
@@ -161,61 +425,104 @@ impl KeyLayoutMapManager {
     /// A list is returned so there are fallback key layout files if one can't be parsed
     /// for whatever reason.
     /// Tries multiple naming schemes based on vendor/product/version, then device name, then Generic.
-    /// It first tries searching the system for the file, and then does the search again
-    /// in the files shipped with Key Mapper.
+    /// For each name it first tries the files supplied by the user, then the system files,
+    /// and then the files shipped with Key Mapper. This means a device-specific file shipped
+    /// with Key Mapper is used before the system's Generic.kl.
     ///
     /// See https://source.android.com/docs/core/interaction/input/key-layout-files#location
+    ///
+    /// If the device's input device configuration file names a key layout with
+    /// `keyboard.layout` then that file is tried first, like Android's InputReader does.
     pub fn find_key_layout_files(&self, device_info: &EvdevDeviceInfo) -> Vec<PathBuf> {
-        let name = device_info.name.as_str();
-        let vendor = device_info.vendor;
-        let product = device_info.product;
-        let version = device_info.version;
-
-        let mut paths: Vec<PathBuf> = Vec::new();
-
-        // Try vendor/product/version path first
-        if vendor != 0 && product != 0 {
-            if version != 0 {
-                let version_name = format!(
-                    "Vendor_{:04x}_Product_{:04x}_Version_{:04x}",
-                    vendor, product, version
-                );
-                if let Some(path) = self
-                    .file_finder
-                    .find_system_key_layout_file_by_name(&version_name)
-                {
-                    paths.push(path);
-                }
-            }
+        let configured_name = self
+            .get_input_device_configuration(device_info)
+            .and_then(|configuration| configuration.keyboard_layout().map(str::to_string));
 
-            // Try vendor/product
-            let product_name = format!("Vendor_{:04x}_Product_{:04x}", vendor, product);
-            if let Some(path) = self
-                .file_finder
-                .find_system_key_layout_file_by_name(&product_name)
-            {
-                paths.push(path);
-            }
-        }
+        find_files_by_device(device_info, configured_name.as_deref(), true, |name| {
+            [
+                self.file_finder.find_user_key_layout_file_by_name(name),
+                self.file_finder.find_system_key_layout_file_by_name(name),
+                self.file_finder
+                    .find_key_mapper_key_layout_file_by_name(name),
+            ]
+            .into_iter()
+            .flatten()
+        })
+    }
+
+    /// Find all the possible key character map files to use for a device ordered by their
+    /// priority. This uses the same naming schemes as `find_key_layout_files`, starting with
+    /// the `keyboard.characterMap` of the device's input device configuration file.
+    ///
+    /// See https://source.android.com/docs/core/interaction/input/key-character-map-files#location
+    pub fn find_key_character_map_files(&self, device_info: &EvdevDeviceInfo) -> Vec<PathBuf> {
+        let configured_name = self
+            .get_input_device_configuration(device_info)
+            .and_then(|configuration| configuration.keyboard_character_map().map(str::to_string));
 
-        // Try device name (canonical)
-        let canonical_name = get_canonical_name(name);
-        if let Some(path) = self
-            .file_finder
-            .find_system_key_layout_file_by_name(&canonical_name)
-        {
-            paths.push(path);
+        find_files_by_device(device_info, configured_name.as_deref(), true, |name| {
+            self.file_finder
+                .find_system_key_character_map_file_by_name(name)
+        })
+    }
+
+    /// Get or load the key character map for a device. Returns None if no key character
+    /// map file could be found or parsed. The result is cached.
+    pub fn get_key_character_map(
+        &self,
+        device_info: &EvdevDeviceInfo,
+    ) -> Option<Arc<KeyCharacterMap>> {
+        let mut key_character_maps = self.key_character_maps.lock().unwrap();
+
+        if let Some(key_character_map) = key_character_maps.get(device_info) {
+            return key_character_map.clone();
         }
 
-        // Try system generic
-        if let Some(path) = self
-            .file_finder
-            .find_system_key_layout_file_by_name("Generic")
-        {
-            paths.push(path);
+        let key_character_map = self
+            .find_key_character_map_files(device_info)
+            .into_iter()
+            .find_map(|path| {
+                KeyCharacterMap::load_from_file(path)
+                    .inspect_err(|e| error!("Error parsing key character map: {}", e))
+                    .ok()
+            })
+            .map(Arc::new);
+
+        key_character_maps.insert(device_info.clone(), key_character_map.clone());
+        key_character_map
+    }
+
+    /// Get or load the input device configuration (.idc) file of a device. Returns None if
+    /// the device does not have one or it could not be parsed. Unlike key layout files there
+    /// is no Generic fallback because Android only uses a device's own configuration file.
+    /// The result is cached.
+    ///
+    /// See https://source.android.com/docs/core/interaction/input/input-device-configuration-files#location
+    pub fn get_input_device_configuration(
+        &self,
+        device_info: &EvdevDeviceInfo,
+    ) -> Option<Arc<InputDeviceConfiguration>> {
+        let mut configurations = self.input_device_configurations.lock().unwrap();
+
+        if let Some(configuration) = configurations.get(device_info) {
+            return configuration.clone();
         }
 
-        paths
+        let configuration = find_files_by_device(device_info, None, false, |name| {
+            self.file_finder
+                .find_system_input_device_configuration_file_by_name(name)
+        })
+        .into_iter()
+        .next()
+        .and_then(|path| {
+            InputDeviceConfiguration::load_from_file(path)
+                .inspect_err(|e| error!("Error parsing input device configuration: {}", e))
+                .ok()
+        })
+        .map(Arc::new);
+
+        configurations.insert(device_info.clone(), configuration.clone());
+        configuration
     }
 
     pub fn map_key_codes_to_event_codes(key_codes: &[u32]) -> Vec<EventCode> {
@@ -235,6 +542,86 @@ impl Default for KeyLayoutMapManager {
     }
 }
 
+/// Find the files of a device ordered by their priority. This tries the names from
+/// `get_file_names_by_device` in order and then Generic if include_generic is true.
+/// `find_files_by_name` returns the files with a name in the order they should be tried.
+fn find_files_by_device<I>(
+    device_info: &EvdevDeviceInfo,
+    configured_name: Option<&str>,
+    include_generic: bool,
+    find_files_by_name: impl Fn(&str) -> I,
+) -> Vec<PathBuf>
+where
+    I: IntoIterator<Item = PathBuf>,
+{
+    let mut paths: Vec<PathBuf> = Vec::new();
+
+    let mut names = get_file_names_by_device(device_info, configured_name);
+
+    if include_generic {
+        names.push(GENERIC_FILE_NAME.to_string());
+    }
+
+    for name in names {
+        let count = paths.len();
+        paths.extend(find_files_by_name(&name));
+
+        if Some(name.as_str()) == configured_name && paths.len() == count {
+            error!(
+                "Configuration for device '{}' requested file '{}' but it was not found.",
+                device_info.name, name
+            );
+        }
+    }
+
+    // The configured file may also be found by the device's identity.
+    let mut unique_paths: Vec<PathBuf> = Vec::with_capacity(paths.len());
+    for path in paths {
+        if !unique_paths.contains(&path) {
+            unique_paths.push(path);
+        }
+    }
+
+    unique_paths
+}
+
+/// The names of the files that are specific to a device, without the extension, ordered by
+/// their priority. This is the configured name, then vendor/product/version, then
+/// vendor/product, then device name.
+fn get_file_names_by_device(
+    device_info: &EvdevDeviceInfo,
+    configured_name: Option<&str>,
+) -> Vec<String> {
+    let vendor = device_info.vendor;
+    let product = device_info.product;
+    let version = device_info.version;
+
+    let mut names: Vec<String> = Vec::with_capacity(4);
+
+    // Try the file that the input device configuration asks for
+    if let Some(configured_name) = configured_name {
+        names.push(configured_name.to_string());
+    }
+
+    // Try vendor/product/version path first
+    if vendor != 0 && product != 0 {
+        if version != 0 {
+            names.push(format!(
+                "Vendor_{:04x}_Product_{:04x}_Version_{:04x}",
+                vendor, product, version
+            ));
+        }
+
+        // Try vendor/product
+        names.push(format!("Vendor_{:04x}_Product_{:04x}", vendor, product));
+    }
+
+    // Try device name (canonical)
+    names.push(get_canonical_name(&device_info.name));
+
+    names
+}
+
 /// Get canonical name with all invalid characters replaced by underscores
 fn get_canonical_name(name: &str) -> String {
     name.chars()
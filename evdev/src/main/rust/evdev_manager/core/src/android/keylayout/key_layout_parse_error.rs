@@ -0,0 +1,165 @@
+//! Diagnostics for problems found while parsing key layout files.
+//!
+//! Each diagnostic records where in the file the problem is so it can be shown to the user,
+//! such as when their custom key layout file is rejected.
+
+use crate::android::keylayout::tokenizer::Tokenizer;
+use std::fmt;
+use std::path::PathBuf;
+
+/// What is wrong with a key layout file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyLayoutParseErrorKind {
+    /// The file could not be read.
+    Io,
+    /// A line or axis option starts with an unknown keyword.
+    UnknownKeyword,
+    /// There is more text after the end of an entry.
+    TrailingText,
+    /// A scan code, usage code or abs code is not a non-negative number.
+    InvalidCode,
+    /// A code has already been mapped by an earlier entry.
+    DuplicateEntry,
+    /// A key code label is not known, so the key is not mapped.
+    UnknownKeyCode,
+    /// A key flag is not known, so it is ignored.
+    UnknownKeyFlag,
+    /// The same flag is given twice for a key.
+    DuplicateKeyFlag,
+    /// An axis label is not known.
+    UnknownAxis,
+    /// A split or flat value is not a number.
+    InvalidValue,
+    /// An LED code label is not known.
+    UnknownLed,
+    /// A sensor type label is not known.
+    UnknownSensorType,
+    /// A sensor data index is not X, Y or Z.
+    InvalidSensorDataIndex,
+}
+
+/// Whether a problem stops the file from being loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyLayoutParseSeverity {
+    /// The file is rejected.
+    Error,
+    /// The file is loaded but part of an entry is skipped.
+    Warning,
+}
+
+impl KeyLayoutParseErrorKind {
+    pub fn severity(&self) -> KeyLayoutParseSeverity {
+        match self {
+            // The key code tables can be older than the files on the device, and OEMs add
+            // their own flags, so these are not worth rejecting the whole file for.
+            KeyLayoutParseErrorKind::UnknownKeyCode | KeyLayoutParseErrorKind::UnknownKeyFlag => {
+                KeyLayoutParseSeverity::Warning
+            }
+            _ => KeyLayoutParseSeverity::Error,
+        }
+    }
+}
+
+/// A problem in a key layout file and where it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyLayoutParseError {
+    pub file_path: PathBuf,
+    /// The line of the problem, starting from 1. This is 0 if the file could not be read.
+    pub line: usize,
+    /// The column of the offending token, starting from 1. This is 0 if the file could not
+    /// be read.
+    pub column: usize,
+    pub kind: KeyLayoutParseErrorKind,
+    /// The text that caused the problem. This is empty if a token was missing.
+    pub token: String,
+    pub message: String,
+}
+
+impl KeyLayoutParseError {
+    /// An error at the token that the tokenizer last returned.
+    pub(crate) fn at_token(
+        tokenizer: &Tokenizer,
+        kind: KeyLayoutParseErrorKind,
+        token: &str,
+        message: String,
+    ) -> Self {
+        Self::at_column(
+            tokenizer,
+            tokenizer.get_token_column(),
+            kind,
+            token,
+            message,
+        )
+    }
+
+    /// An error at the current position of the tokenizer.
+    pub(crate) fn at_position(
+        tokenizer: &Tokenizer,
+        kind: KeyLayoutParseErrorKind,
+        token: &str,
+        message: String,
+    ) -> Self {
+        Self::at_column(tokenizer, tokenizer.get_column(), kind, token, message)
+    }
+
+    fn at_column(
+        tokenizer: &Tokenizer,
+        column: usize,
+        kind: KeyLayoutParseErrorKind,
+        token: &str,
+        message: String,
+    ) -> Self {
+        Self {
+            file_path: tokenizer.file_path().to_path_buf(),
+            line: tokenizer.get_line_number(),
+            column,
+            kind,
+            token: token.to_string(),
+            message,
+        }
+    }
+
+    pub(crate) fn io(file_path: PathBuf, message: String) -> Self {
+        Self {
+            file_path,
+            line: 0,
+            column: 0,
+            kind: KeyLayoutParseErrorKind::Io,
+            token: String::new(),
+            message,
+        }
+    }
+
+    pub fn severity(&self) -> KeyLayoutParseSeverity {
+        self.kind.severity()
+    }
+
+    pub fn is_warning(&self) -> bool {
+        self.severity() == KeyLayoutParseSeverity::Warning
+    }
+}
+
+/// Formats the error like "Vendor_1234_Product_5678.kl:3:9: Expected key scan code number,
+/// got 'abc'." The path is left out if the map was loaded from a string.
+impl fmt::Display for KeyLayoutParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut location: Vec<String> = Vec::new();
+
+        if !self.file_path.as_os_str().is_empty() {
+            location.push(self.file_path.display().to_string());
+        }
+
+        if self.line > 0 {
+            location.push(self.line.to_string());
+            location.push(self.column.to_string());
+        }
+
+        if location.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", location.join(":"), self.message)
+        }
+    }
+}
+
+impl std::error::Error for KeyLayoutParseError {}
@@ -1,6 +1,10 @@
 pub mod generic_key_layout;
+pub mod input_device_configuration;
 pub mod input_event_lookup;
+pub mod key_character_map;
 pub mod key_layout_file_finder;
+pub mod key_layout_file_watcher;
 pub mod key_layout_map;
 pub mod key_layout_map_manager;
+pub mod key_layout_parse_error;
 pub mod tokenizer;
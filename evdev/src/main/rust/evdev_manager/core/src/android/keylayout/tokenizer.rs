@@ -3,7 +3,7 @@
 //! This tokenizer tracks position in a buffer and provides methods for
 //! reading tokens, characters, and navigating through lines.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str;
 
 /// Simple tokenizer for parsing ASCII text files line by line.
@@ -12,6 +12,10 @@ pub struct Tokenizer {
     buffer: String,
     current: usize,
     line_number: usize,
+    /// The position in the buffer where the current line starts.
+    line_start: usize,
+    /// The column of the token that was last returned by `next_token`.
+    token_column: usize,
 }
 
 impl Tokenizer {
@@ -22,6 +26,8 @@ impl Tokenizer {
             buffer: contents.to_string(),
             current: 0,
             line_number: 1,
+            line_start: 0,
+            token_column: 1,
         }
     }
 
@@ -52,6 +58,27 @@ impl Tokenizer {
         )
     }
 
+    /// The path of the file that is being tokenized.
+    pub fn file_path(&self) -> &Path {
+        &self.file_path
+    }
+
+    /// The current line number, starting from 1.
+    pub fn get_line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// The column of the current position in the line, starting from 1.
+    pub fn get_column(&self) -> usize {
+        self.buffer[self.line_start..self.current].chars().count() + 1
+    }
+
+    /// The column where the token that was last returned by `next_token` starts. If the
+    /// token was empty then this is where it was expected.
+    pub fn get_token_column(&self) -> usize {
+        self.token_column
+    }
+
     /// Gets the character at the current position.
     /// Returns null character at end of file.
     pub fn peek_char(&self) -> char {
@@ -85,10 +112,11 @@ impl Tokenizer {
         }
 
         let ch = self.peek_char();
+        self.current += ch.len_utf8();
         if ch == '\n' {
             self.line_number += 1;
+            self.line_start = self.current;
         }
-        self.current += ch.len_utf8();
         ch
     }
 
@@ -98,6 +126,7 @@ impl Tokenizer {
     /// or is at the end of the line.
     pub fn next_token(&mut self, delimiters: &str) -> String {
         self.skip_delimiters(delimiters);
+        self.token_column = self.get_column();
 
         if self.is_eol() {
             return String::new();
@@ -1,2 +1,3 @@
 pub mod android_codes;
+pub mod axis_mapper;
 pub mod keylayout;
@@ -0,0 +1,21 @@
+/// Presses keys when an EV_ABS axis of a grabbed device moves far enough from its resting
+/// position, such as a hat switch, thumbstick or analog trigger. The keys are processed like
+/// keys of the device so key triggers and the callback work with analog controls.
+///
+/// The axis is scaled with its axis info from the key layout so a key is pressed at the same
+/// point whatever the range of the device is. Centered axes press `low_scan_code` when moved
+/// towards their minimum and `high_scan_code` when moved towards their maximum. Split axes
+/// press `low_scan_code` for the low axis and `high_scan_code` for the high axis. Other axes,
+/// such as triggers, only press `high_scan_code`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisKeyMapping {
+    /// The EV_ABS code of the axis.
+    pub abs_code: u32,
+    pub low_scan_code: Option<u32>,
+    pub high_scan_code: Option<u32>,
+    /// How far the axis must move from rest to press a key, between 0 and 1.
+    pub press_threshold: f32,
+    /// A pressed key is released once the axis moves back below this. It is lower than
+    /// `press_threshold` so a key does not bounce when the axis jitters around the threshold.
+    pub release_threshold: f32,
+}
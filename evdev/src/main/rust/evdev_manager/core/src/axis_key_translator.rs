@@ -0,0 +1,146 @@
+use crate::android::axis_mapper::map_abs_value;
+use crate::android::keylayout::key_layout_map::KeyLayoutAxisInfo;
+use crate::axis_key_mapping::AxisKeyMapping;
+use evdev::enums::{EventCode, EventType, EV_SYN};
+use evdev::util::int_to_event_code;
+use evdev::{AbsInfo, InputEvent, TimeVal};
+use libc::c_uint;
+use std::collections::HashMap;
+
+/// An axis key mapping together with what is needed to scale the values of its axis.
+#[derive(Debug, Clone)]
+pub struct AxisKeyTranslation {
+    pub mapping: AxisKeyMapping,
+    pub axis_info: KeyLayoutAxisInfo,
+    pub abs_info: AbsInfo,
+}
+
+#[derive(Debug)]
+struct AxisState {
+    translation: AxisKeyTranslation,
+    low_pressed: bool,
+    high_pressed: bool,
+    /// The time of the last event of the axis so keys released outside of an event have a
+    /// sensible time.
+    time: TimeVal,
+}
+
+/// Translates the axis events of a single grabbed device into key events.
+#[derive(Debug, Default)]
+pub struct AxisKeyTranslator {
+    axes: HashMap<u32, AxisState>,
+}
+
+impl AxisKeyTranslator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the axis key mappings. Returns the key events that release the keys that are
+    /// still pressed by the old mappings.
+    pub fn set_translations(&mut self, translations: Vec<AxisKeyTranslation>) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+
+        for state in self.axes.values() {
+            let mapping = &state.translation.mapping;
+
+            if state.low_pressed {
+                push_key(&mut events, state.time, mapping.low_scan_code, 0);
+            }
+
+            if state.high_pressed {
+                push_key(&mut events, state.time, mapping.high_scan_code, 0);
+            }
+        }
+
+        if let Some(last_event) = events.last() {
+            events.push(InputEvent::new(
+                &last_event.time,
+                &EventCode::EV_SYN(EV_SYN::SYN_REPORT),
+                0,
+            ));
+        }
+
+        self.axes = translations
+            .into_iter()
+            .map(|translation| {
+                let state = AxisState {
+                    translation,
+                    low_pressed: false,
+                    high_pressed: false,
+                    time: TimeVal::new(0, 0),
+                };
+
+                (state.translation.mapping.abs_code, state)
+            })
+            .collect();
+
+        events
+    }
+
+    /// Returns the key events that an event read from the device presses or releases. These
+    /// are in addition to the event itself.
+    pub fn on_event(&mut self, event: &InputEvent) -> Vec<InputEvent> {
+        let EventCode::EV_ABS(abs_code) = event.event_code else {
+            return Vec::new();
+        };
+
+        let Some(state) = self.axes.get_mut(&(abs_code as u32)) else {
+            return Vec::new();
+        };
+
+        state.time = event.time;
+
+        let translation = &state.translation;
+        let values = map_abs_value(&translation.axis_info, &translation.abs_info, event.value);
+
+        // How far the axis is from rest in the direction of each key.
+        let (low_value, high_value) = match values.as_slice() {
+            [low, high] => (low.value, high.value),
+            [value] => ((-value.value).max(0.0), value.value.max(0.0)),
+            _ => return Vec::new(),
+        };
+
+        let mapping = &translation.mapping;
+        let low_change = update_key(&mut state.low_pressed, low_value, mapping);
+        let high_change = update_key(&mut state.high_pressed, high_value, mapping);
+
+        let mut events = Vec::new();
+
+        // Release keys before pressing keys so moving a hat switch straight to the opposite
+        // direction never presses both.
+        for key_value in [0, 1] {
+            if low_change == Some(key_value) {
+                push_key(&mut events, event.time, mapping.low_scan_code, key_value);
+            }
+
+            if high_change == Some(key_value) {
+                push_key(&mut events, event.time, mapping.high_scan_code, key_value);
+            }
+        }
+
+        events
+    }
+}
+
+/// Returns the new key value if the key is pressed or released.
+fn update_key(pressed: &mut bool, value: f32, mapping: &AxisKeyMapping) -> Option<i32> {
+    let release_threshold = mapping.release_threshold.min(mapping.press_threshold);
+
+    if !*pressed && value >= mapping.press_threshold {
+        *pressed = true;
+        Some(1)
+    } else if *pressed && value < release_threshold {
+        *pressed = false;
+        Some(0)
+    } else {
+        None
+    }
+}
+
+fn push_key(events: &mut Vec<InputEvent>, time: TimeVal, scan_code: Option<u32>, value: i32) {
+    if let Some(scan_code) = scan_code {
+        let event_code = int_to_event_code(EventType::EV_KEY as c_uint, scan_code as c_uint);
+        events.push(InputEvent::new(&time, &event_code, value));
+    }
+}
@@ -0,0 +1,68 @@
+use evdev::enums::{EventCode, EV_KEY};
+
+use crate::device_capabilities::DeviceCapabilities;
+
+/// The letter keys that a device must report to be an alphabetic keyboard.
+const ALPHABETIC_KEYS: [EV_KEY; 26] = [
+    EV_KEY::KEY_A,
+    EV_KEY::KEY_B,
+    EV_KEY::KEY_C,
+    EV_KEY::KEY_D,
+    EV_KEY::KEY_E,
+    EV_KEY::KEY_F,
+    EV_KEY::KEY_G,
+    EV_KEY::KEY_H,
+    EV_KEY::KEY_I,
+    EV_KEY::KEY_J,
+    EV_KEY::KEY_K,
+    EV_KEY::KEY_L,
+    EV_KEY::KEY_M,
+    EV_KEY::KEY_N,
+    EV_KEY::KEY_O,
+    EV_KEY::KEY_P,
+    EV_KEY::KEY_Q,
+    EV_KEY::KEY_R,
+    EV_KEY::KEY_S,
+    EV_KEY::KEY_T,
+    EV_KEY::KEY_U,
+    EV_KEY::KEY_V,
+    EV_KEY::KEY_W,
+    EV_KEY::KEY_X,
+    EV_KEY::KEY_Y,
+    EV_KEY::KEY_Z,
+];
+
+/// A class of devices that is identified by the event codes they support.
+#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+pub enum DeviceCapabilityClass {
+    /// Devices that report every letter key.
+    AlphabeticKeyboard,
+    /// Devices that report BTN_SOUTH, which is the A button of a gamepad.
+    Gamepad,
+    /// Devices that report this event code.
+    EventCode(EventCode),
+}
+
+impl DeviceCapabilityClass {
+    pub fn matches(&self, capabilities: &DeviceCapabilities) -> bool {
+        match self {
+            Self::AlphabeticKeyboard => ALPHABETIC_KEYS
+                .iter()
+                .all(|key| capabilities.has_event_code(&EventCode::EV_KEY(*key))),
+            Self::Gamepad => capabilities.has_event_code(&EventCode::EV_KEY(EV_KEY::BTN_SOUTH)),
+            Self::EventCode(event_code) => capabilities.has_event_code(event_code),
+        }
+    }
+}
+
+/// Grab every device in a capability class, including devices that are plugged in later.
+#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+pub struct CapabilityGrabTarget {
+    pub class: DeviceCapabilityClass,
+    /// The extra event codes that should be enabled for the devices. This is so that the
+    /// uinput device can input events that the original device didn't support.
+    pub extra_event_codes: Vec<EventCode>,
+    /// Send the EV_ABS and EV_REL events of the devices to the callback instead of only
+    /// writing them to the uinput device.
+    pub forward_axes: bool,
+}
@@ -0,0 +1,8 @@
+use crate::capability_grab_target::DeviceCapabilityClass;
+
+#[derive(Debug)]
+pub struct CapabilityGrabTargetKeyCode {
+    pub class: DeviceCapabilityClass,
+    pub extra_key_codes: Vec<u32>,
+    pub forward_axes: bool,
+}
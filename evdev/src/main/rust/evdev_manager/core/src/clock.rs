@@ -0,0 +1,16 @@
+use std::time::Instant;
+
+/// Source of the current time for anything that makes timing decisions about input events.
+/// This allows dependency injection for testing purposes.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// Clock that uses the monotonic system time.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
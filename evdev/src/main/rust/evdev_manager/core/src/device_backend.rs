@@ -0,0 +1,251 @@
+use crate::device_capabilities::DeviceCapabilities;
+use crate::evdev_device_info::EvdevDeviceInfo;
+use crate::evemu_device::EvemuDevice;
+use crate::input_event_sink::InputEventSink;
+use crate::virtual_device::{create_uinput_device, VirtualDeviceDescription};
+use evdev::enums::EventCode;
+use evdev::{Device, DeviceWrapper, GrabMode, InputEvent, ReadFlag, ReadStatus, UInputDevice};
+use std::fs::{canonicalize, read_dir, OpenOptions};
+use std::io;
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+/// The directory that the kernel creates evdev device nodes in.
+pub const DEFAULT_INPUT_DEVICE_ROOT: &str = "/dev/input";
+
+/// The sysfs directory that has a subdirectory for each evdev device node.
+const SYSFS_INPUT_CLASS_DIR: &str = "/sys/class/input";
+
+/// Finds and opens the input devices that can be grabbed.
+/// This allows dependency injection for testing purposes.
+pub trait DeviceBackend: Send + Sync {
+    /// The directories to watch for devices being added and removed.
+    fn get_device_roots(&self) -> Vec<PathBuf>;
+
+    /// Get the paths to all the input devices. This includes the output devices that were
+    /// created for grabbed devices.
+    fn get_device_paths(&self) -> io::Result<Vec<PathBuf>>;
+
+    fn get_device_info(&self, path: &Path) -> io::Result<EvdevDeviceInfo>;
+
+    /// Get the event codes that the device supports.
+    fn get_device_capabilities(&self, path: &Path) -> io::Result<DeviceCapabilities>;
+
+    /// Open an input device so it can be grabbed and its events read.
+    fn open_device(&self, path: &Path) -> io::Result<Box<dyn InputDeviceSource>>;
+
+    /// Create a virtual device that is not tied to an input device.
+    fn create_virtual_device(
+        &self,
+        description: &VirtualDeviceDescription,
+    ) -> io::Result<Box<dyn OutputDeviceSink>>;
+}
+
+/// An input device that events are read from. The file descriptor is registered with the
+/// event loop so it must become readable when events are available.
+pub trait InputDeviceSource: AsRawFd + Send {
+    fn device_info(&self) -> EvdevDeviceInfo;
+
+    /// Enable an event code so the output device can input events that this device
+    /// doesn't support.
+    fn enable_event_code(&mut self, event_code: EventCode) -> io::Result<()>;
+
+    fn grab(&mut self, mode: GrabMode) -> io::Result<()>;
+
+    /// Read the next event. Returns None when there are no more events to read.
+    fn next_event(&mut self) -> Option<InputEvent>;
+
+    /// Create an output device with the same name, ids and capabilities as this device.
+    fn create_output_device(&self) -> io::Result<Box<dyn OutputDeviceSink>>;
+
+    /// Describe this device for an evemu-record file.
+    fn evemu_device(&self) -> EvemuDevice;
+}
+
+/// An output device that the events of a grabbed device are written to.
+pub trait OutputDeviceSink: InputEventSink + Send + Sync {
+    /// The path of this device in the input devices directory.
+    fn devnode(&self) -> Option<PathBuf>;
+}
+
+/// Backend for the real evdev devices in one or more directories of device nodes.
+pub struct EvdevDeviceBackend {
+    roots: Vec<PathBuf>,
+}
+
+impl EvdevDeviceBackend {
+    /// The roots can contain device nodes or symlinks to them, such as /dev/input/by-id.
+    /// A device that is in more than one root is only returned once.
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        Self { roots }
+    }
+}
+
+impl Default for EvdevDeviceBackend {
+    fn default() -> Self {
+        Self::new(vec![PathBuf::from(DEFAULT_INPUT_DEVICE_ROOT)])
+    }
+}
+
+impl DeviceBackend for EvdevDeviceBackend {
+    fn get_device_roots(&self) -> Vec<PathBuf> {
+        self.roots.clone()
+    }
+
+    fn get_device_paths(&self) -> io::Result<Vec<PathBuf>> {
+        let mut paths: Vec<PathBuf> = Vec::new();
+
+        for root in &self.roots {
+            let dir = match read_dir(root) {
+                Ok(dir) => dir,
+                // Alias directories only exist while a device is connected that has an alias.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    debug!("Input device root {:?} does not exist", root);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            for entry_result in dir {
+                let entry = match entry_result {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        debug!("Failed to read {:?} entry: {}", root, err);
+                        continue;
+                    }
+                };
+
+                // Resolve symlinks so that devices are identified by their device node. This
+                // is also the path of the output devices that are created.
+                let Ok(path) = canonicalize(entry.path()) else {
+                    continue;
+                };
+
+                if !path.is_dir() && !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+
+        Ok(paths)
+    }
+
+    fn get_device_info(&self, path: &Path) -> io::Result<EvdevDeviceInfo> {
+        Device::new_from_path(path).map(|device| get_device_info(&device, path))
+    }
+
+    fn get_device_capabilities(&self, path: &Path) -> io::Result<DeviceCapabilities> {
+        Device::new_from_path(path).map(|device| DeviceCapabilities::from_device(&device))
+    }
+
+    fn open_device(&self, path: &Path) -> io::Result<Box<dyn InputDeviceSource>> {
+        // Open device with O_NONBLOCK so that the loop reading events eventually returns
+        // due to an EAGAIN error
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)?;
+
+        let device = Device::new_from_file(file)?;
+
+        Ok(Box::new(EvdevInputDevice {
+            device,
+            path: path.to_path_buf(),
+            read_flag: ReadFlag::NORMAL,
+        }))
+    }
+
+    fn create_virtual_device(
+        &self,
+        description: &VirtualDeviceDescription,
+    ) -> io::Result<Box<dyn OutputDeviceSink>> {
+        let uinput = create_uinput_device(description)?;
+        Ok(Box::new(uinput))
+    }
+}
+
+/// An evdev device opened with libevdev.
+struct EvdevInputDevice {
+    device: Device,
+    path: PathBuf,
+    read_flag: ReadFlag,
+}
+
+impl AsRawFd for EvdevInputDevice {
+    fn as_raw_fd(&self) -> RawFd {
+        self.device.as_raw_fd()
+    }
+}
+
+impl InputDeviceSource for EvdevInputDevice {
+    fn device_info(&self) -> EvdevDeviceInfo {
+        get_device_info(&self.device, &self.path)
+    }
+
+    fn enable_event_code(&mut self, event_code: EventCode) -> io::Result<()> {
+        self.device.enable(event_code)
+    }
+
+    fn grab(&mut self, mode: GrabMode) -> io::Result<()> {
+        self.device.grab(mode)
+    }
+
+    fn next_event(&mut self) -> Option<InputEvent> {
+        loop {
+            match self.device.next_event(self.read_flag) {
+                Ok((ReadStatus::Success, input_event)) => {
+                    self.read_flag = ReadFlag::NORMAL;
+                    return Some(input_event);
+                }
+                Ok((ReadStatus::Sync, _event)) => {
+                    // Continue reading sync events
+                    self.read_flag = ReadFlag::NORMAL | ReadFlag::SYNC;
+                }
+                Err(_error) => {
+                    // Stop if it's EAGAIN (no more events) or any other error.
+                    // Do not log these errors because it is expected
+                    self.read_flag = ReadFlag::NORMAL;
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn create_output_device(&self) -> io::Result<Box<dyn OutputDeviceSink>> {
+        let uinput = UInputDevice::create_from_device(&self.device)?;
+        Ok(Box::new(uinput))
+    }
+
+    fn evemu_device(&self) -> EvemuDevice {
+        EvemuDevice::from_device(&self.device)
+    }
+}
+
+impl OutputDeviceSink for UInputDevice {
+    fn devnode(&self) -> Option<PathBuf> {
+        UInputDevice::devnode(self).map(PathBuf::from)
+    }
+}
+
+fn get_device_info(device: &Device, path: &Path) -> EvdevDeviceInfo {
+    EvdevDeviceInfo {
+        name: device.name().unwrap_or("").to_string(),
+        bus: device.bustype(),
+        vendor: device.vendor_id(),
+        product: device.product_id(),
+        version: device.version(),
+        phys: device.phys().unwrap_or("").to_string(),
+        uniq: device.uniq().unwrap_or("").to_string(),
+        sysfs_path: get_sysfs_path(path),
+    }
+}
+
+/// Get the sysfs directory of a device node. The node may be a symlink such as the ones
+/// in /dev/input/by-id so resolve it to get the name of the node.
+fn get_sysfs_path(path: &Path) -> Option<PathBuf> {
+    let node_path = canonicalize(path).ok()?;
+    let sysfs_path = Path::new(SYSFS_INPUT_CLASS_DIR).join(node_path.file_name()?);
+
+    sysfs_path.exists().then_some(sysfs_path)
+}
@@ -0,0 +1,111 @@
+use crate::evemu_device::EvemuDevice;
+use evdev::enums::{EventCode, EventType, EV_REP};
+use evdev::util::{event_code_to_int, int_to_event_code};
+use evdev::{AbsInfo, Device, DeviceWrapper};
+use libc::c_uint;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The key repeat settings of a device that supports EV_REP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRepeat {
+    /// The time in milliseconds before a held key starts repeating.
+    pub delay: i32,
+    /// The time in milliseconds between each repeat.
+    pub period: i32,
+}
+
+/// Everything that an input device can report.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceCapabilities {
+    /// The supported event codes grouped by their event type.
+    pub event_codes: BTreeMap<u32, BTreeSet<u32>>,
+    /// The axis info of each supported EV_ABS code.
+    pub abs_info: BTreeMap<u32, AbsInfo>,
+    /// The input properties, such as INPUT_PROP_DIRECT for touchscreens.
+    pub properties: BTreeSet<u32>,
+    pub key_repeat: Option<KeyRepeat>,
+}
+
+impl DeviceCapabilities {
+    pub fn new(event_codes: &[EventCode]) -> Self {
+        let mut capabilities = Self::default();
+
+        for event_code in event_codes {
+            capabilities.insert(event_code);
+        }
+
+        capabilities
+    }
+
+    /// Read the capabilities of an evdev device.
+    pub fn from_device(device: &Device) -> Self {
+        let evemu_device = EvemuDevice::from_device(device);
+
+        let key_repeat = if device.has_event_type(&EventType::EV_REP) {
+            let delay = device.event_value(&EventCode::EV_REP(EV_REP::REP_DELAY));
+            let period = device.event_value(&EventCode::EV_REP(EV_REP::REP_PERIOD));
+
+            delay
+                .zip(period)
+                .map(|(delay, period)| KeyRepeat { delay, period })
+        } else {
+            None
+        };
+
+        Self {
+            event_codes: evemu_device.event_codes,
+            abs_info: evemu_device.abs_info,
+            properties: evemu_device.properties,
+            key_repeat,
+        }
+    }
+
+    /// The capabilities described by an evemu device. An evemu device does not have the key
+    /// repeat settings.
+    pub fn from_evemu_device(evemu_device: EvemuDevice) -> Self {
+        Self {
+            event_codes: evemu_device.event_codes,
+            abs_info: evemu_device.abs_info,
+            properties: evemu_device.properties,
+            key_repeat: None,
+        }
+    }
+
+    pub fn insert(&mut self, event_code: &EventCode) {
+        let (event_type, code) = event_code_to_int(event_code);
+        self.event_codes.entry(event_type).or_default().insert(code);
+    }
+
+    pub fn has_event_code(&self, event_code: &EventCode) -> bool {
+        let (event_type, code) = event_code_to_int(event_code);
+
+        self.event_codes
+            .get(&event_type)
+            .is_some_and(|codes| codes.contains(&code))
+    }
+
+    /// The event types that the device supports at least one code of.
+    pub fn event_types(&self) -> Vec<u32> {
+        self.event_codes.keys().copied().collect()
+    }
+
+    /// All the supported event codes ordered by their type and code.
+    pub fn event_codes(&self) -> Vec<EventCode> {
+        self.event_codes
+            .iter()
+            .flat_map(|(event_type, codes)| {
+                codes
+                    .iter()
+                    .map(|code| int_to_event_code(*event_type as c_uint, *code as c_uint))
+            })
+            .collect()
+    }
+
+    /// The LEDs that the device has, such as LED_CAPSL.
+    pub fn leds(&self) -> Vec<u32> {
+        self.event_codes
+            .get(&(EventType::EV_LED as u32))
+            .map(|codes| codes.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,87 @@
+use regex::Regex;
+use std::hash::{Hash, Hasher};
+
+/// How the name of a device is matched when deciding whether to grab it.
+#[derive(Clone, Debug)]
+pub enum DeviceNameMatcher {
+    /// The name must be exactly the same.
+    Exact(String),
+    /// A pattern where `*` matches any number of characters and `?` matches one character.
+    /// This is useful for devices that change the suffix of their name across firmware
+    /// versions or reconnects.
+    Wildcard { pattern: String, regex: Regex },
+    /// A regular expression that must match the whole name.
+    Regex(Regex),
+}
+
+impl DeviceNameMatcher {
+    pub fn exact(name: &str) -> Self {
+        Self::Exact(name.to_string())
+    }
+
+    pub fn wildcard(pattern: &str) -> Self {
+        let mut regex_pattern = String::from("^");
+
+        for c in pattern.chars() {
+            match c {
+                '*' => regex_pattern.push_str(".*"),
+                '?' => regex_pattern.push('.'),
+                _ => regex_pattern.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+
+        regex_pattern.push('$');
+
+        Self::Wildcard {
+            pattern: pattern.to_string(),
+            // Every character that has a meaning in a regex is escaped.
+            regex: Regex::new(&regex_pattern).unwrap(),
+        }
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self, String> {
+        Regex::new(&format!("^(?:{})$", pattern))
+            .map(Self::Regex)
+            .map_err(|err| format!("Invalid device name regex '{}': {}", pattern, err))
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Exact(expected) => name == expected,
+            Self::Wildcard { regex, .. } | Self::Regex(regex) => regex.is_match(name),
+        }
+    }
+
+    /// The name or pattern that this matcher was created from.
+    pub fn pattern(&self) -> &str {
+        match self {
+            Self::Exact(name) => name,
+            Self::Wildcard { pattern, .. } => pattern,
+            // Strip the anchors that were added when it was created.
+            Self::Regex(regex) => &regex.as_str()[4..regex.as_str().len() - 2],
+        }
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            Self::Exact(_) => 0,
+            Self::Wildcard { .. } => 1,
+            Self::Regex(_) => 2,
+        }
+    }
+}
+
+impl PartialEq for DeviceNameMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind() == other.kind() && self.pattern() == other.pattern()
+    }
+}
+
+impl Eq for DeviceNameMatcher {}
+
+impl Hash for DeviceNameMatcher {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind().hash(state);
+        self.pattern().hash(state);
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub struct EvdevDeviceInfo {
     pub name: String,
@@ -5,4 +7,11 @@ pub struct EvdevDeviceInfo {
     pub vendor: u16,
     pub product: u16,
     pub version: u16,
+    /// The physical location of the device, such as the USB port it is plugged in to.
+    pub phys: String,
+    /// The unique identifier of the device, such as the MAC address of a Bluetooth device.
+    /// This is the only reliable way to tell apart identical devices and is often empty.
+    pub uniq: String,
+    /// The /sys/class/input/eventN directory of the device.
+    pub sysfs_path: Option<PathBuf>,
 }
@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     mpsc, Arc, Mutex, RwLock,
@@ -7,6 +7,7 @@ use std::sync::{
 use notify::{EventKind, RecommendedWatcher, Watcher};
 use tokio::task::JoinHandle;
 
+use crate::device_backend::DEFAULT_INPUT_DEVICE_ROOT;
 use crate::evdev_error::{EvdevError, EvdevErrorCode};
 use crate::runtime::get_runtime;
 
@@ -16,21 +17,24 @@ pub trait InotifyCallback: Send + Sync {
 }
 
 pub struct EvdevDevicesWatcher {
+    roots: Vec<PathBuf>,
     watcher: Arc<Mutex<Option<RecommendedWatcher>>>,
     inotify_handle: RwLock<Option<JoinHandle<()>>>,
     enabled: Arc<AtomicBool>,
 }
 
 impl EvdevDevicesWatcher {
-    pub fn new() -> Self {
+    /// Create a watcher for the directories that contain input devices.
+    pub fn new(roots: Vec<PathBuf>) -> Self {
         Self {
+            roots,
             watcher: Arc::new(Mutex::new(None)),
             inotify_handle: RwLock::new(None),
             enabled: Arc::new(AtomicBool::new(true)),
         }
     }
 
-    /// Start the thread to watch /dev/input for device changes
+    /// Start the thread to watch the input device roots for device changes
     pub fn start(&self, callback: Arc<dyn InotifyCallback>) -> Result<(), EvdevError> {
         let is_running = { self.inotify_handle.read().unwrap().is_some() };
 
@@ -47,12 +51,20 @@ impl EvdevDevicesWatcher {
             EvdevError::from_enum(EvdevErrorCode::IoError)
         })?;
 
-        watcher
-            .watch(Path::new("/dev/input"), notify::RecursiveMode::Recursive)
-            .map_err(|e| {
-                error!("Failed to watch /dev/input: {}", e);
-                EvdevError::from_enum(EvdevErrorCode::IoError)
-            })?;
+        for root in &self.roots {
+            // Alias directories only exist while a device is connected that has an alias.
+            if !root.exists() {
+                warn!("Not watching {:?} because it does not exist", root);
+                continue;
+            }
+
+            watcher
+                .watch(root, notify::RecursiveMode::Recursive)
+                .map_err(|e| {
+                    error!("Failed to watch {:?}: {}", root, e);
+                    EvdevError::from_enum(EvdevErrorCode::IoError)
+                })?;
+        }
 
         // Store the watcher in Arc
         {
@@ -91,7 +103,7 @@ impl EvdevDevicesWatcher {
         Ok(())
     }
 
-    /// Stop the thread watching /dev/input for device changes
+    /// Stop the thread watching the input device roots for device changes
     pub fn stop(&self) -> Result<(), EvdevError> {
         self.enabled.store(false, Ordering::Relaxed);
 
@@ -121,6 +133,6 @@ impl EvdevDevicesWatcher {
 
 impl Default for EvdevDevicesWatcher {
     fn default() -> Self {
-        Self::new()
+        Self::new(vec![PathBuf::from(DEFAULT_INPUT_DEVICE_ROOT)])
     }
 }
@@ -1,49 +1,86 @@
 use std::{
+    collections::BTreeMap,
     error::Error,
-    fs::read_dir,
-    io,
-    os::fd::AsRawFd,
-    path::PathBuf,
+    fs, io,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex, RwLock},
 };
 
-use bimap::BiHashMap;
-use evdev::{enums::EventCode, DeviceWrapper};
+use evdev::enums::EventCode;
 use mio::{unix::SourceFd, Interest, Registry, Token};
 use slab::Slab;
 
 use crate::{
+    capability_grab_target::CapabilityGrabTarget,
+    device_backend::{DeviceBackend, EvdevDeviceBackend, OutputDeviceSink},
+    device_capabilities::DeviceCapabilities,
     evdev_device_info::EvdevDeviceInfo,
     evdev_devices_watcher::{EvdevDevicesWatcher, InotifyCallback},
-    evdev_error::EvdevError,
+    evdev_error::{EvdevError, EvdevErrorCode},
+    evemu_device::EvemuDevice,
+    evemu_player::ReplayTarget,
     event_loop::EvdevCallback,
     grab_target::GrabTarget,
     grabbed_device::GrabbedDevice,
     grabbed_device_handle::GrabbedDeviceHandle,
+    input_event_sink::InputEventSink,
+    virtual_device::{VirtualDevice, VirtualDeviceDescription},
 };
 
+/// Grabs the devices that match the grab targets as they are added and removed.
+///
+/// The locks must always be taken in this order to avoid deadlocks: grabbed_devices,
+/// grab_targets, capability_grab_targets and then virtual_devices.
 pub struct EvdevGrabController {
     poll_registry: Arc<Registry>,
     callback: Arc<dyn EvdevCallback>,
+    backend: Arc<dyn DeviceBackend>,
     grab_targets: Mutex<Vec<GrabTarget>>,
+    /// Grab every device in these classes. These are re-evaluated whenever devices are
+    /// added or removed.
+    capability_grab_targets: Mutex<Vec<CapabilityGrabTarget>>,
     grabbed_devices: RwLock<Slab<GrabbedDevice>>,
+    /// Virtual devices that the caller created. Their IDs are separate from the IDs of the
+    /// grabbed devices.
+    virtual_devices: RwLock<Slab<VirtualDevice>>,
     devices_watcher: EvdevDevicesWatcher,
 }
 
 impl EvdevGrabController {
-    pub fn new(poll_registry: Arc<Registry>, callback: Arc<dyn EvdevCallback>) -> Self {
+    /// Create a grab controller for the evdev devices in the input device roots.
+    pub fn new(
+        poll_registry: Arc<Registry>,
+        callback: Arc<dyn EvdevCallback>,
+        input_device_roots: Vec<PathBuf>,
+    ) -> Self {
+        let backend = EvdevDeviceBackend::new(input_device_roots);
+        Self::with_backend(poll_registry, callback, Arc::new(backend))
+    }
+
+    /// Create a grab controller that finds and opens devices with a custom backend.
+    pub fn with_backend(
+        poll_registry: Arc<Registry>,
+        callback: Arc<dyn EvdevCallback>,
+        backend: Arc<dyn DeviceBackend>,
+    ) -> Self {
+        let devices_watcher = EvdevDevicesWatcher::new(backend.get_device_roots());
+
         Self {
             poll_registry,
             callback,
+            backend,
             grab_targets: Mutex::new(Vec::with_capacity(64)),
+            capability_grab_targets: Mutex::new(Vec::new()),
             grabbed_devices: RwLock::new(Slab::with_capacity(64)),
-            devices_watcher: EvdevDevicesWatcher::new(),
+            virtual_devices: RwLock::new(Slab::new()),
+            devices_watcher,
         }
     }
 
     pub fn set_grab_targets(&self, targets: Vec<GrabTarget>) -> Vec<GrabbedDeviceHandle> {
         debug!("Setting grab targets: {:?}", targets);
 
+        let mut grabbed_devices = self.grabbed_devices.write().unwrap();
         let mut grab_targets = self.grab_targets.lock().unwrap();
 
         grab_targets.clear();
@@ -52,28 +89,65 @@ impl EvdevGrabController {
             grab_targets.push(target);
         }
 
-        let mut grabbed_devices = self.grabbed_devices.write().unwrap();
-        let handles = self.invalidate(grab_targets.as_ref(), &mut grabbed_devices);
+        let capability_grab_targets = self.capability_grab_targets.lock().unwrap();
+        let handles = self.invalidate(
+            grab_targets.as_ref(),
+            capability_grab_targets.as_ref(),
+            &mut grabbed_devices,
+        );
 
         handles
     }
 
+    /// Grab every device that is in one of the capability classes. A device that matches a
+    /// grab target from [Self::set_grab_targets] is grabbed with the extra event codes of
+    /// that target instead.
+    pub fn set_capability_grab_targets(
+        &self,
+        targets: Vec<CapabilityGrabTarget>,
+    ) -> Vec<GrabbedDeviceHandle> {
+        debug!("Setting capability grab targets: {:?}", targets);
+
+        let mut grabbed_devices = self.grabbed_devices.write().unwrap();
+        let grab_targets = self.grab_targets.lock().unwrap();
+        let mut capability_grab_targets = self.capability_grab_targets.lock().unwrap();
+
+        *capability_grab_targets = targets;
+
+        self.invalidate(
+            grab_targets.as_ref(),
+            capability_grab_targets.as_ref(),
+            &mut grabbed_devices,
+        )
+    }
+
     fn invalidate(
         &self,
         grab_targets: &[GrabTarget],
+        capability_grab_targets: &[CapabilityGrabTarget],
         grabbed_devices: &mut Slab<GrabbedDevice>,
     ) -> Vec<GrabbedDeviceHandle> {
         // Disable inotify event processing during invalidate to avoid race conditions
         self.devices_watcher.disable();
 
-        let real_device_paths =
-            Self::get_real_device_paths(grabbed_devices).expect("Unable to evdev device paths");
-        let device_info_path_map = Self::build_device_info_path_map(&real_device_paths);
+        let real_device_paths = self
+            .get_real_device_paths(grabbed_devices)
+            .expect("Unable to evdev device paths");
+        let path_device_info_map = self.build_path_device_info_map(&real_device_paths);
+
+        // Only read the capabilities when they are needed because it opens every device.
+        let path_capabilities_map = if capability_grab_targets.is_empty() {
+            BTreeMap::new()
+        } else {
+            self.build_path_capabilities_map(&real_device_paths)
+        };
 
         let device_keys_to_ungrab = Self::get_devices_to_ungrab(
             grab_targets,
+            capability_grab_targets,
             grabbed_devices,
-            device_info_path_map.clone(),
+            &path_device_info_map,
+            &path_capabilities_map,
         );
 
         // Ungrab devices that are no longer requested
@@ -82,18 +156,29 @@ impl EvdevGrabController {
             self.ungrab_device(grabbed_device);
         }
 
-        let devices_to_grab =
-            Self::get_targets_to_grab(grab_targets, grabbed_devices, device_info_path_map);
+        let devices_to_grab = Self::get_targets_to_grab(
+            grab_targets,
+            capability_grab_targets,
+            grabbed_devices,
+            &path_device_info_map,
+            &path_capabilities_map,
+        );
 
-        for (path, extra_event_codes) in devices_to_grab {
-            self.try_grab_target(&path, &extra_event_codes, grabbed_devices)
+        for (path, extra_event_codes, forward_axes) in devices_to_grab {
+            self.try_grab_target(&path, &extra_event_codes, forward_axes, grabbed_devices)
                 .inspect_err(|err| error!("Failed to grab device {:?}: {:?}", path, err))
                 .ok();
         }
 
         let grabbed_device_handles: Vec<GrabbedDeviceHandle> = grabbed_devices
             .iter()
-            .map(|(key, device)| GrabbedDeviceHandle::new(key, device.device_info.clone()))
+            .map(|(key, device)| {
+                GrabbedDeviceHandle::new(
+                    key,
+                    device.device_info.clone(),
+                    device.device_path.clone(),
+                )
+            })
             .collect();
 
         debug!("Grabbed devices: {:?}", grabbed_device_handles);
@@ -117,58 +202,128 @@ impl EvdevGrabController {
         grabbed_devices.get(device_id).map(f)
     }
 
+    /// Access every grabbed device with its ID through a closure.
+    pub fn for_each_grabbed_device<F>(&self, mut f: F)
+    where
+        F: FnMut(usize, &GrabbedDevice),
+    {
+        let grabbed_devices = self.grabbed_devices.read().unwrap();
+
+        for (device_id, device) in grabbed_devices.iter() {
+            f(device_id, device);
+        }
+    }
+
+    /// Create a virtual device and return its ID. The device is never grabbed or returned
+    /// as a real device.
+    pub fn create_virtual_device(
+        &self,
+        description: VirtualDeviceDescription,
+    ) -> Result<usize, EvdevError> {
+        // Hold the lock while creating the device so the device is not mistaken for a new
+        // real device when its device node appears.
+        let mut virtual_devices = self.virtual_devices.write().unwrap();
+        let output = self.backend.create_virtual_device(&description)?;
+
+        Ok(virtual_devices.insert(VirtualDevice::new(description, output)))
+    }
+
+    /// Destroy a virtual device. Returns false if there is no virtual device with the ID.
+    pub fn destroy_virtual_device(&self, virtual_device_id: usize) -> bool {
+        self.virtual_devices
+            .write()
+            .unwrap()
+            .try_remove(virtual_device_id)
+            .is_some()
+    }
+
+    /// Access a virtual device by ID through a closure.
+    /// Returns None if the device is not found, otherwise returns the result of the closure.
+    pub fn with_virtual_device<F, R>(&self, virtual_device_id: usize, f: F) -> Option<R>
+    where
+        F: FnOnce(&VirtualDevice) -> R,
+    {
+        let virtual_devices = self.virtual_devices.read().unwrap();
+        virtual_devices.get(virtual_device_id).map(f)
+    }
+
+    /// Create the device that a recording of the evemu device is replayed to. A new device
+    /// is not a virtual device so it is grabbed like a real device.
+    pub fn create_replay_sink(
+        self: &Arc<Self>,
+        target: ReplayTarget,
+        evemu_device: &EvemuDevice,
+    ) -> Result<Box<dyn OutputDeviceSink>, EvdevError> {
+        match target {
+            ReplayTarget::NewDevice => {
+                let description = VirtualDeviceDescription::from_evemu_device(evemu_device.clone());
+                Ok(self.backend.create_virtual_device(&description)?)
+            }
+            ReplayTarget::GrabbedDevice(device_id) => {
+                if self.with_grabbed_device(device_id, |_| ()).is_none() {
+                    return Err(EvdevError::from_enum(EvdevErrorCode::NoSuchDevice));
+                }
+
+                Ok(Box::new(GrabbedDeviceSink {
+                    grab_controller: self.clone(),
+                    device_id,
+                }))
+            }
+        }
+    }
+
     /// Get devices that should be ungrabed based on current grab targets and device state.
     /// This function is public for testing purposes.
     pub fn get_devices_to_ungrab(
         grab_targets: &[GrabTarget],
+        capability_grab_targets: &[CapabilityGrabTarget],
         grabbed_devices: &Slab<GrabbedDevice>,
-        device_info_path_map: BiHashMap<EvdevDeviceInfo, PathBuf>,
+        path_device_info_map: &BTreeMap<PathBuf, EvdevDeviceInfo>,
+        path_capabilities_map: &BTreeMap<PathBuf, DeviceCapabilities>,
     ) -> Vec<usize> {
         let mut keys_to_remove: Vec<usize> = Vec::new();
 
         for (key, grabbed_device) in grabbed_devices.iter() {
-            let is_connected: bool =
-                device_info_path_map.contains_left(&grabbed_device.device_info);
-
-            if !is_connected {
-                keys_to_remove.push(key);
-                continue;
-            }
-
-            let matching_grab_target: Option<&GrabTarget> = grab_targets
-                .iter()
-                .find(|target| target.matches_device_info(&grabbed_device.device_info));
+            let current_device_at_path: Option<&EvdevDeviceInfo> =
+                path_device_info_map.get(&grabbed_device.device_path);
 
-            match matching_grab_target {
-                // Ungrab if the device should be grabbed with different event codes.
-                Some(target) => {
-                    if target.extra_event_codes != grabbed_device.extra_event_codes {
+            match current_device_at_path {
+                // If the device path cached in the grabbed device no longer points
+                // to the same device
+                Some(real_device_info) => {
+                    if grabbed_device.device_info != *real_device_info {
                         keys_to_remove.push(key);
                         continue;
                     }
                 }
 
-                // Ungrab the device if it is no longer targeted to grab
+                // The path of the grabbed device no longer exists
                 None => {
                     keys_to_remove.push(key);
                     continue;
                 }
             }
 
-            let current_device_at_path: Option<&EvdevDeviceInfo> =
-                device_info_path_map.get_by_right(&grabbed_device.device_path);
-
-            match current_device_at_path {
-                // If the device path cached in the grabbed device no longer points
-                // to the same device
-                Some(real_device_info) => {
-                    if grabbed_device.device_info != *real_device_info {
+            let grab_settings = Self::find_grab_settings(
+                grab_targets,
+                capability_grab_targets,
+                &grabbed_device.device_info,
+                path_capabilities_map.get(&grabbed_device.device_path),
+            );
+
+            match grab_settings {
+                // Ungrab if the device should be grabbed with different event codes or
+                // axis forwarding.
+                Some((extra_event_codes, forward_axes)) => {
+                    if *extra_event_codes != grabbed_device.extra_event_codes
+                        || forward_axes != grabbed_device.axis_forwarding.is_some()
+                    {
                         keys_to_remove.push(key);
                         continue;
                     }
                 }
 
-                // The path of the grabbed device no longer exists
+                // Ungrab the device if it is no longer targeted to grab
                 None => {
                     keys_to_remove.push(key);
                     continue;
@@ -180,7 +335,7 @@ impl EvdevGrabController {
     }
 
     fn ungrab_device(&self, device: GrabbedDevice) {
-        let fd = device.evdev.lock().unwrap().as_raw_fd();
+        let fd = device.source.lock().unwrap().as_raw_fd();
 
         let mut source_fd = SourceFd(&fd);
         self.poll_registry
@@ -194,51 +349,81 @@ impl EvdevGrabController {
             .ok();
     }
 
-    /// Get targets that should be grabbed based on current grab targets and device state.
+    /// Get the paths of the devices that should be grabbed based on current grab targets and
+    /// device state. Every connected device that matches a target is grabbed, so identical
+    /// devices are each grabbed at their own path.
     /// This function is public for testing purposes.
     pub fn get_targets_to_grab(
         grab_targets: &[GrabTarget],
+        capability_grab_targets: &[CapabilityGrabTarget],
         grabbed_devices: &Slab<GrabbedDevice>,
-        device_info_path_map: BiHashMap<EvdevDeviceInfo, PathBuf>,
-    ) -> Vec<(PathBuf, Vec<EventCode>)> {
-        let mut targets_to_grab: Vec<(PathBuf, Vec<EventCode>)> = Vec::new();
+        path_device_info_map: &BTreeMap<PathBuf, EvdevDeviceInfo>,
+        path_capabilities_map: &BTreeMap<PathBuf, DeviceCapabilities>,
+    ) -> Vec<(PathBuf, Vec<EventCode>, bool)> {
+        let mut targets_to_grab: Vec<(PathBuf, Vec<EventCode>, bool)> = Vec::new();
 
-        for target in grab_targets {
+        for (path, device_info) in path_device_info_map {
             let already_grabbed = grabbed_devices
                 .iter()
-                .any(|(_, device)| target.matches_device_info(&device.device_info));
+                .any(|(_, device)| device.device_path == *path);
 
             if already_grabbed {
                 continue;
             }
 
-            let device_info = device_info_path_map
-                .left_values()
-                .find(|device_info| target.matches_device_info(device_info));
-
-            match device_info {
-                // Target device not connected
-                None => continue,
-                // Device is connected
-                Some(device_info) => {
-                    let path = device_info_path_map.get_by_left(device_info).unwrap();
+            let grab_settings = Self::find_grab_settings(
+                grab_targets,
+                capability_grab_targets,
+                device_info,
+                path_capabilities_map.get(path),
+            );
 
-                    targets_to_grab.push((path.clone(), target.extra_event_codes.clone()));
-                }
+            if let Some((extra_event_codes, forward_axes)) = grab_settings {
+                targets_to_grab.push((path.clone(), extra_event_codes.clone(), forward_axes));
             }
         }
 
         targets_to_grab
     }
 
+    /// Get the extra event codes that a device should be grabbed with and whether its axis
+    /// events are forwarded, or None if it should not be grabbed. Grab targets for a specific
+    /// device take precedence over capability classes.
+    fn find_grab_settings<'a>(
+        grab_targets: &'a [GrabTarget],
+        capability_grab_targets: &'a [CapabilityGrabTarget],
+        device_info: &EvdevDeviceInfo,
+        capabilities: Option<&DeviceCapabilities>,
+    ) -> Option<(&'a Vec<EventCode>, bool)> {
+        if let Some(target) = grab_targets
+            .iter()
+            .find(|target| target.matches_device_info(device_info))
+        {
+            return Some((&target.extra_event_codes, target.forward_axes));
+        }
+
+        let capabilities = capabilities?;
+
+        capability_grab_targets
+            .iter()
+            .find(|target| target.class.matches(capabilities))
+            .map(|target| (&target.extra_event_codes, target.forward_axes))
+    }
+
     fn try_grab_target(
         &self,
-        device_path: &PathBuf,
+        device_path: &Path,
         extra_event_codes: &[EventCode],
+        forward_axes: bool,
         grabbed_devices: &mut Slab<GrabbedDevice>,
     ) -> Result<usize, Box<dyn Error>> {
-        let device = GrabbedDevice::new(device_path, extra_event_codes)?;
-        let fd = device.evdev.lock().unwrap().as_raw_fd();
+        let device = GrabbedDevice::new(
+            self.backend.as_ref(),
+            device_path,
+            extra_event_codes,
+            forward_axes,
+        )?;
+        let fd = device.source.lock().unwrap().as_raw_fd();
         let key = grabbed_devices.insert(device);
 
         let mut source_fd = SourceFd(&fd);
@@ -259,8 +444,8 @@ impl EvdevGrabController {
 
         let mut list: Vec<EvdevDeviceInfo> = Vec::new();
 
-        for path in Self::get_real_device_paths(&grabbed_devices)? {
-            if let Ok(info) = Self::get_device_info(&path) {
+        for path in self.get_real_device_paths(&grabbed_devices)? {
+            if let Ok(info) = self.backend.get_device_info(&path) {
                 list.push(info);
             }
         }
@@ -268,70 +453,89 @@ impl EvdevGrabController {
         Ok(list)
     }
 
+    /// Read everything that a connected device can report. The device does not need to be
+    /// grabbed. The path can be a symlink to the device node, such as the ones in
+    /// /dev/input/by-id. Only real devices can be queried so the output devices of grabbed
+    /// devices and paths that are not input devices return NoSuchDevice.
+    pub fn get_device_capabilities(&self, path: &Path) -> Result<DeviceCapabilities, EvdevError> {
+        // The real device paths are resolved so resolve this one too. A path that does not
+        // exist is not a real device either, which is checked below.
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let grabbed_devices = self.grabbed_devices.read().unwrap();
+
+        if !self
+            .get_real_device_paths(&grabbed_devices)?
+            .contains(&path)
+        {
+            return Err(EvdevError::from_enum(EvdevErrorCode::NoSuchDevice));
+        }
+
+        Ok(self.backend.get_device_capabilities(&path)?)
+    }
+
     /// Get the paths to all the real (non uinput) connected devices.
     fn get_real_device_paths(
+        &self,
         grabbed_devices: &Slab<GrabbedDevice>,
     ) -> Result<Vec<PathBuf>, EvdevError> {
-        let uinput_paths: Vec<PathBuf> = grabbed_devices
-            .iter()
-            .filter_map(|(_, device)| device.uinput.devnode().map(|node| PathBuf::from(node)))
+        let uinput_paths: Vec<PathBuf> = self.get_uinput_paths(grabbed_devices);
+
+        let paths = self
+            .backend
+            .get_device_paths()?
+            .into_iter()
+            // Do not return paths to uinput devices that were created.
+            .filter(|path| !uinput_paths.contains(path))
             .collect();
 
-        let mut paths: Vec<PathBuf> = Vec::new();
+        Ok(paths)
+    }
+
+    /// Get the paths of the output devices of grabbed devices and the virtual devices.
+    fn get_uinput_paths(&self, grabbed_devices: &Slab<GrabbedDevice>) -> Vec<PathBuf> {
+        let virtual_devices = self.virtual_devices.read().unwrap();
 
-        let dir = read_dir("/dev/input")?;
+        grabbed_devices
+            .iter()
+            .map(|(_, device)| &device.output)
+            .chain(virtual_devices.iter().map(|(_, device)| &device.output))
+            .filter_map(|output| output.devnode())
+            .collect()
+    }
 
-        for entry_result in dir {
-            match entry_result {
-                Ok(entry) => {
-                    let path = entry.path();
-                    // Do not return paths to uinput devices that were created.
-                    if uinput_paths.contains(&path) {
-                        continue;
-                    }
+    fn build_path_device_info_map(&self, paths: &[PathBuf]) -> BTreeMap<PathBuf, EvdevDeviceInfo> {
+        let mut map: BTreeMap<PathBuf, EvdevDeviceInfo> = BTreeMap::new();
 
-                    paths.push(path);
-                }
-                Err(_) => {
-                    debug!(
-                        "Failed to read /dev/input entry: {}",
-                        entry_result.unwrap_err()
-                    );
-                }
+        for path in paths {
+            if let Ok(info) = self.backend.get_device_info(path) {
+                map.insert(path.clone(), info);
             }
         }
 
-        Ok(paths)
+        map
     }
 
-    fn build_device_info_path_map(paths: &[PathBuf]) -> BiHashMap<EvdevDeviceInfo, PathBuf> {
-        let mut map: BiHashMap<EvdevDeviceInfo, PathBuf> = BiHashMap::new();
+    fn build_path_capabilities_map(
+        &self,
+        paths: &[PathBuf],
+    ) -> BTreeMap<PathBuf, DeviceCapabilities> {
+        let mut map: BTreeMap<PathBuf, DeviceCapabilities> = BTreeMap::new();
 
         for path in paths {
-            if let Ok(info) = Self::get_device_info(path) {
-                map.insert(info, path.clone());
+            if let Ok(capabilities) = self.backend.get_device_capabilities(path) {
+                map.insert(path.clone(), capabilities);
             }
         }
 
         map
     }
 
-    fn get_device_info(path: &PathBuf) -> Result<EvdevDeviceInfo, io::Error> {
-        evdev::Device::new_from_path(path).map(|device| EvdevDeviceInfo {
-            name: device.name().unwrap_or("").to_string(),
-            bus: device.bustype(),
-            vendor: device.vendor_id(),
-            product: device.product_id(),
-            version: device.version(),
-        })
-    }
-
-    /// Start watching /dev/input for device changes
+    /// Start watching the input device roots for device changes
     pub fn start_watching(self: &Arc<Self>) -> Result<(), EvdevError> {
         self.devices_watcher.start(self.clone())
     }
 
-    /// Stop watching /dev/input for device changes
+    /// Stop watching the input device roots for device changes
     pub fn stop_watching(&self) -> Result<(), EvdevError> {
         self.devices_watcher.stop()
     }
@@ -339,25 +543,29 @@ impl EvdevGrabController {
 
 impl InotifyCallback for EvdevGrabController {
     fn on_inotify_dev_input(&self, paths: &[PathBuf]) {
-        let mut grabbed_devices = self.grabbed_devices.write().unwrap();
-        let is_uinput_device =
-            grabbed_devices
+        {
+            let mut grabbed_devices = self.grabbed_devices.write().unwrap();
+            let is_uinput_device = self
+                .get_uinput_paths(&grabbed_devices)
                 .iter()
-                .any(|(_, device)| match &device.uinput.devnode() {
-                    None => false,
-                    Some(dev_node) => paths.contains(&dev_node.into()),
-                });
+                .any(|dev_node| paths.contains(dev_node));
 
-        if is_uinput_device {
-            return;
-        }
+            if is_uinput_device {
+                return;
+            }
 
-        info!("inotify /dev/input event received");
-        let grab_targets = self.grab_targets.lock().unwrap();
-        self.invalidate(grab_targets.as_ref(), &mut grabbed_devices);
+            info!("inotify /dev/input event received");
+            let grab_targets = self.grab_targets.lock().unwrap();
+            let capability_grab_targets = self.capability_grab_targets.lock().unwrap();
+            self.invalidate(
+                grab_targets.as_ref(),
+                capability_grab_targets.as_ref(),
+                &mut grabbed_devices,
+            );
+        }
 
-        // Notify callback about device list changes
-        drop(grabbed_devices); // Release the write lock before calling get_real_devices
+        // Notify callback about device list changes. All the locks are released first
+        // because get_real_devices locks the grabbed devices again.
         match self.get_real_devices() {
             Ok(devices) => {
                 self.callback.on_evdev_devices_changed(devices);
@@ -368,3 +576,27 @@ impl InotifyCallback for EvdevGrabController {
         }
     }
 }
+
+/// Writes to the output device of a grabbed device for as long as it is grabbed.
+struct GrabbedDeviceSink {
+    grab_controller: Arc<EvdevGrabController>,
+    device_id: usize,
+}
+
+impl InputEventSink for GrabbedDeviceSink {
+    fn write_event(&self, event_type: u32, code: u32, value: i32) -> io::Result<()> {
+        self.grab_controller
+            .with_grabbed_device(self.device_id, |device| {
+                device.output.write_event(event_type, code, value)
+            })
+            .unwrap_or_else(|| Err(io::Error::from_raw_os_error(libc::ENODEV)))
+    }
+}
+
+impl OutputDeviceSink for GrabbedDeviceSink {
+    fn devnode(&self) -> Option<PathBuf> {
+        self.grab_controller
+            .with_grabbed_device(self.device_id, |device| device.output.devnode())
+            .flatten()
+    }
+}
@@ -0,0 +1,155 @@
+use evdev::enums::{int_to_input_prop, EventCode, EventType, InputProp};
+use evdev::util::int_to_event_code;
+use evdev::{AbsInfo, Device, DeviceWrapper};
+use libc::c_uint;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::io::Write;
+
+/// The highest code of each event type that is written to the evemu bit masks. These match
+/// the kernel's *_MAX constants so the file can be read by evemu on any device.
+const EVEMU_TYPE_MAX_CODES: [(u32, u32); 11] = [
+    (EventType::EV_SYN as u32, EventType::EV_MAX as u32),
+    (EventType::EV_KEY as u32, 0x2ff),
+    (EventType::EV_REL as u32, 0x0f),
+    (EventType::EV_ABS as u32, 0x3f),
+    (EventType::EV_MSC as u32, 0x07),
+    (EventType::EV_SW as u32, 0x10),
+    (EventType::EV_LED as u32, 0x0f),
+    (EventType::EV_SND as u32, 0x07),
+    (EventType::EV_REP as u32, 0x01),
+    (EventType::EV_FF as u32, 0x7f),
+    (EventType::EV_FF_STATUS as u32, 0x01),
+];
+
+const INPUT_PROP_MAX: u32 = InputProp::INPUT_PROP_MAX as u32;
+
+/// The description of an input device in the evemu-record text format.
+#[derive(Debug, Clone)]
+pub struct EvemuDevice {
+    pub name: String,
+    pub bus: u16,
+    pub vendor: u16,
+    pub product: u16,
+    pub version: u16,
+    pub properties: BTreeSet<u32>,
+    /// The supported event codes grouped by their event type. EV_SYN is implied.
+    pub event_codes: BTreeMap<u32, BTreeSet<u32>>,
+    /// The axis info of each supported EV_ABS code.
+    pub abs_info: BTreeMap<u32, AbsInfo>,
+}
+
+impl EvemuDevice {
+    /// Read the description of an evdev device.
+    pub fn from_device(device: &Device) -> Self {
+        let properties = (0..=INPUT_PROP_MAX)
+            .filter(|prop| int_to_input_prop(*prop).is_some_and(|prop| device.has(prop)))
+            .collect();
+
+        let mut event_codes: BTreeMap<u32, BTreeSet<u32>> = BTreeMap::new();
+        let mut abs_info = BTreeMap::new();
+
+        for (event_type, max_code) in EVEMU_TYPE_MAX_CODES {
+            if event_type == EventType::EV_SYN as u32 {
+                continue;
+            }
+
+            for code in 0..=max_code {
+                let event_code = int_to_event_code(event_type as c_uint, code as c_uint);
+
+                if !device.has(event_code) {
+                    continue;
+                }
+
+                event_codes.entry(event_type).or_default().insert(code);
+
+                if let EventCode::EV_ABS(_) = event_code {
+                    if let Some(info) = device.abs_info(&event_code) {
+                        abs_info.insert(code, info);
+                    }
+                }
+            }
+        }
+
+        Self {
+            name: device.name().unwrap_or("").to_string(),
+            bus: device.bustype(),
+            vendor: device.vendor_id(),
+            product: device.product_id(),
+            version: device.version(),
+            properties,
+            event_codes,
+            abs_info,
+        }
+    }
+
+    /// Write the device description lines of an evemu-record file.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "# EVEMU 1.3")?;
+        writeln!(writer, "# Input device name: \"{}\"", self.name)?;
+        writeln!(
+            writer,
+            "# Input device ID: bus {:#x} vendor {:#x} product {:#x} version {:#x}",
+            self.bus, self.vendor, self.product, self.version
+        )?;
+        writeln!(writer, "N: {}", self.name)?;
+        writeln!(
+            writer,
+            "I: {:04x} {:04x} {:04x} {:04x}",
+            self.bus, self.vendor, self.product, self.version
+        )?;
+
+        for chunk in to_bit_mask(&self.properties, INPUT_PROP_MAX).chunks(8) {
+            writeln!(writer, "P: {}", format_bytes(chunk))?;
+        }
+
+        let event_types: BTreeSet<u32> = std::iter::once(EventType::EV_SYN as u32)
+            .chain(self.event_codes.keys().copied())
+            .collect();
+
+        for (event_type, max_code) in EVEMU_TYPE_MAX_CODES {
+            let mask = if event_type == EventType::EV_SYN as u32 {
+                to_bit_mask(&event_types, max_code)
+            } else {
+                let empty = BTreeSet::new();
+                let codes = self.event_codes.get(&event_type).unwrap_or(&empty);
+                to_bit_mask(codes, max_code)
+            };
+
+            for chunk in mask.chunks(8) {
+                writeln!(writer, "B: {:02x} {}", event_type, format_bytes(chunk))?;
+            }
+        }
+
+        for (code, info) in &self.abs_info {
+            writeln!(
+                writer,
+                "A: {:02x} {} {} {} {} {}",
+                code, info.minimum, info.maximum, info.fuzz, info.flat, info.resolution
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert the set bits to bytes. The length is padded to a multiple of 8 because evemu
+/// writes 8 bytes on each line.
+fn to_bit_mask(bits: &BTreeSet<u32>, max_bit: u32) -> Vec<u8> {
+    let byte_count = (max_bit as usize / 8 + 1).div_ceil(8) * 8;
+    let mut mask = vec![0u8; byte_count];
+
+    for bit in bits.iter().filter(|bit| **bit <= max_bit) {
+        mask[*bit as usize / 8] |= 1 << (bit % 8);
+    }
+
+    mask
+}
+
+fn format_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
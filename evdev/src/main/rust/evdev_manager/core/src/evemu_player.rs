@@ -0,0 +1,49 @@
+use crate::evemu_device::EvemuDevice;
+use crate::evemu_recording::EvemuEvent;
+use crate::input_event_sink::InputEventSink;
+use crate::virtual_device;
+use crate::virtual_device::VirtualDeviceDescription;
+use evdev::UInputDevice;
+use std::io;
+use std::io::ErrorKind;
+
+/// Where a recording is replayed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayTarget {
+    /// Create a uinput device that matches the recorded device. It is grabbed like any other
+    /// device so the events go through the grab and remap pipeline.
+    NewDevice,
+    /// Write the events to the uinput device of the grabbed device with this ID.
+    GrabbedDevice(usize),
+}
+
+/// Create a uinput device with the same name, ids and capabilities as a recorded device.
+pub fn create_uinput_device(device: &EvemuDevice) -> io::Result<UInputDevice> {
+    virtual_device::create_uinput_device(&VirtualDeviceDescription::from_evemu_device(
+        device.clone(),
+    ))
+}
+
+/// Write the events to the sink with their original relative timing. The times are divided
+/// by the speed so 2.0 replays twice as fast.
+pub async fn replay_events<S: InputEventSink + ?Sized>(
+    events: &[EvemuEvent],
+    sink: &S,
+    speed: f64,
+) -> io::Result<()> {
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Replay speed must be positive, got {}", speed),
+        ));
+    }
+
+    let start = tokio::time::Instant::now();
+
+    for event in events {
+        tokio::time::sleep_until(start + event.time.div_f64(speed)).await;
+        sink.write_event(event.event_type, event.code, event.value)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use crate::evemu_device::EvemuDevice;
+use evdev::util::event_code_to_int;
+use evdev::{InputEvent, TimeVal};
+use std::io;
+use std::io::Write;
+
+/// Records the events of a device in the evemu-record text format so they can be replayed
+/// with evemu-play.
+pub struct EvemuRecorder<W: Write> {
+    writer: W,
+    /// The time of the first recorded event. Event times are written relative to this.
+    start_time: Option<TimeVal>,
+}
+
+impl<W: Write> EvemuRecorder<W> {
+    /// Start a recording by writing the device description.
+    pub fn new(mut writer: W, device: &EvemuDevice) -> io::Result<Self> {
+        device.write(&mut writer)?;
+
+        Ok(Self {
+            writer,
+            start_time: None,
+        })
+    }
+
+    pub fn write_event(&mut self, event: &InputEvent) -> io::Result<()> {
+        let start_time = *self.start_time.get_or_insert(event.time);
+        let micros = (to_micros(&event.time) - to_micros(&start_time)).max(0);
+        let (event_type, code) = event_code_to_int(&event.event_code);
+
+        writeln!(
+            self.writer,
+            "E: {}.{:06} {:04x} {:04x} {:04}",
+            micros / 1_000_000,
+            micros % 1_000_000,
+            event_type,
+            code,
+            event.value
+        )
+    }
+
+    /// Flush the recording and return the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// The casts are needed on 32-bit targets where the fields are 32-bit.
+#[allow(clippy::unnecessary_cast)]
+fn to_micros(time: &TimeVal) -> i64 {
+    time.tv_sec as i64 * 1_000_000 + time.tv_usec as i64
+}
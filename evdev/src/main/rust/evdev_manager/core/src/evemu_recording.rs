@@ -0,0 +1,227 @@
+//! Parser for recordings in the evemu-record text format.
+//!
+//! The format is documented at https://gitlab.freedesktop.org/libevdev/evemu
+
+use crate::evemu_device::EvemuDevice;
+use evdev::enums::{int_to_event_type, EventType};
+use evdev::AbsInfo;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::time::Duration;
+
+/// A device description and the events that were recorded from it.
+#[derive(Debug, Clone)]
+pub struct EvemuRecording {
+    pub device: EvemuDevice,
+    pub events: Vec<EvemuEvent>,
+}
+
+/// An event in a recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvemuEvent {
+    /// The time since the start of the recording.
+    pub time: Duration,
+    pub event_type: u32,
+    pub code: u32,
+    pub value: i32,
+}
+
+impl EvemuRecording {
+    pub fn load_from_file(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut device = EvemuDevice {
+            name: String::new(),
+            bus: 0,
+            vendor: 0,
+            product: 0,
+            version: 0,
+            properties: BTreeSet::new(),
+            event_codes: BTreeMap::new(),
+            abs_info: BTreeMap::new(),
+        };
+
+        let mut property_mask: Vec<u8> = Vec::new();
+        let mut type_masks: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+        let mut events = Vec::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            let line_number = index + 1;
+
+            let Some((prefix, rest)) = line.split_once(':') else {
+                if line.trim().is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                return Err(format!(
+                    "line {}: Expected 'X: ...', got '{}'.",
+                    line_number, line
+                ));
+            };
+
+            // Everything after a '#' is a comment, except in the device name.
+            let values: Vec<&str> = rest
+                .split('#')
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect();
+
+            let result = match prefix {
+                _ if prefix.starts_with('#') => Ok(()),
+                "N" => {
+                    device.name = rest.trim().to_string();
+                    Ok(())
+                }
+                "I" => parse_id(&values, &mut device),
+                "P" => parse_hex_bytes(&values).map(|bytes| property_mask.extend(bytes)),
+                "B" => parse_type_mask(&values, &mut type_masks),
+                "A" => parse_abs_info(&values, &mut device.abs_info),
+                "E" => parse_event(&values).map(|event| events.push(event)),
+                // LED and switch states are not needed for replaying.
+                "L" | "S" => Ok(()),
+                _ => Err(format!("Unknown line type '{}'.", prefix)),
+            };
+
+            result.map_err(|err| format!("line {}: {}", line_number, err))?;
+        }
+
+        device.properties = from_bit_mask(&property_mask);
+        device.event_codes = type_masks
+            .into_iter()
+            .filter(|(event_type, _)| *event_type != EventType::EV_SYN as u32)
+            .map(|(event_type, mask)| (event_type, from_bit_mask(&mask)))
+            .filter(|(_, codes)| !codes.is_empty())
+            .collect();
+
+        Ok(Self { device, events })
+    }
+}
+
+fn parse_id(values: &[&str], device: &mut EvemuDevice) -> Result<(), String> {
+    let [bus, vendor, product, version] = values else {
+        return Err(format!("Expected 4 ids, got {}.", values.len()));
+    };
+
+    device.bus = parse_hex_u16(bus)?;
+    device.vendor = parse_hex_u16(vendor)?;
+    device.product = parse_hex_u16(product)?;
+    device.version = parse_hex_u16(version)?;
+    Ok(())
+}
+
+fn parse_type_mask(values: &[&str], type_masks: &mut BTreeMap<u32, Vec<u8>>) -> Result<(), String> {
+    let Some((event_type, bytes)) = values.split_first() else {
+        return Err("Expected an event type.".to_string());
+    };
+
+    let event_type = parse_event_type(event_type)?;
+    let bytes = parse_hex_bytes(bytes)?;
+    type_masks.entry(event_type).or_default().extend(bytes);
+    Ok(())
+}
+
+fn parse_abs_info(values: &[&str], abs_info: &mut BTreeMap<u32, AbsInfo>) -> Result<(), String> {
+    // Files from before evemu 1.1 do not have a resolution.
+    let (code, numbers) = match values {
+        [code, numbers @ ..] if numbers.len() == 4 || numbers.len() == 5 => (code, numbers),
+        _ => return Err(format!("Expected 5 or 6 values, got {}.", values.len())),
+    };
+
+    let numbers: Vec<i32> = numbers
+        .iter()
+        .map(|number| parse_i32(number))
+        .collect::<Result<_, _>>()?;
+
+    abs_info.insert(
+        parse_hex_u32(code)?,
+        AbsInfo {
+            value: 0,
+            minimum: numbers[0],
+            maximum: numbers[1],
+            fuzz: numbers[2],
+            flat: numbers[3],
+            resolution: numbers.get(4).copied().unwrap_or(0),
+        },
+    );
+
+    Ok(())
+}
+
+fn parse_event(values: &[&str]) -> Result<EvemuEvent, String> {
+    let [time, event_type, code, value] = values else {
+        return Err(format!("Expected 4 values, got {}.", values.len()));
+    };
+
+    let (seconds, micros) = time.split_once('.').ok_or_else(|| {
+        format!(
+            "Expected a time in the format 'seconds.micros', got '{}'.",
+            time
+        )
+    })?;
+
+    let seconds: u64 = seconds
+        .parse()
+        .map_err(|_| format!("Expected seconds, got '{}'.", seconds))?;
+    let micros: u32 = micros
+        .parse()
+        .map_err(|_| format!("Expected microseconds, got '{}'.", micros))?;
+
+    Ok(EvemuEvent {
+        time: Duration::from_secs(seconds) + Duration::from_micros(micros as u64),
+        event_type: parse_event_type(event_type)?,
+        code: parse_hex_u32(code)?,
+        value: parse_i32(value)?,
+    })
+}
+
+fn parse_hex_bytes(values: &[&str]) -> Result<Vec<u8>, String> {
+    values
+        .iter()
+        .map(|value| {
+            u8::from_str_radix(value, 16)
+                .map_err(|_| format!("Expected a hex byte, got '{}'.", value))
+        })
+        .collect()
+}
+
+fn parse_hex_u16(value: &str) -> Result<u16, String> {
+    u16::from_str_radix(value, 16).map_err(|_| format!("Expected a hex number, got '{}'.", value))
+}
+
+fn parse_hex_u32(value: &str) -> Result<u32, String> {
+    u32::from_str_radix(value, 16).map_err(|_| format!("Expected a hex number, got '{}'.", value))
+}
+
+/// Parse an event type. Unknown types are rejected because evdev can not create devices with
+/// them or write their events.
+fn parse_event_type(value: &str) -> Result<u32, String> {
+    let event_type = parse_hex_u32(value)?;
+
+    match int_to_event_type(event_type) {
+        Some(_) => Ok(event_type),
+        None => Err(format!("Unknown event type '{}'.", value)),
+    }
+}
+
+fn parse_i32(value: &str) -> Result<i32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Expected a number, got '{}'.", value))
+}
+
+fn from_bit_mask(mask: &[u8]) -> BTreeSet<u32> {
+    mask.iter()
+        .enumerate()
+        .flat_map(|(index, byte)| {
+            (0..8)
+                .filter(move |bit| byte & (1 << bit) != 0)
+                .map(move |bit| (index * 8 + bit) as u32)
+        })
+        .collect()
+}
@@ -1,15 +1,29 @@
 use crate::android::keylayout::key_layout_map_manager::KeyLayoutMapManager;
+use crate::axis_key_mapping::AxisKeyMapping;
+use crate::axis_key_translator::AxisKeyTranslation;
+use crate::capability_grab_target::CapabilityGrabTarget;
+use crate::capability_grab_target_key_code::CapabilityGrabTargetKeyCode;
+use crate::device_capabilities::DeviceCapabilities;
 use crate::evdev_device_info::EvdevDeviceInfo;
 use crate::evdev_error::{EvdevError, EvdevErrorCode};
 use crate::evdev_grab_controller::EvdevGrabController;
+use crate::evemu_player::{replay_events, ReplayTarget};
+use crate::evemu_recording::EvemuRecording;
 use crate::grab_target::GrabTarget;
 use crate::grab_target_key_code::GrabTargetKeyCode;
-use crate::grabbed_device::GrabbedDevice;
+use crate::grabbed_device::{AxisForwarding, GrabbedDevice};
 use crate::grabbed_device_handle::GrabbedDeviceHandle;
+use crate::input_event_sink::InputEventSink;
+use crate::key_behaviour::KeyBehaviour;
+use crate::key_behaviour_resolver::KeyBehaviourOutput;
+use crate::key_remap_table::{KeyRemapTable, KeyRemapper, RemapResult};
+use crate::key_trigger::KeyTrigger;
+use crate::key_trigger_detector::KeyTriggerOutput;
 use crate::runtime::get_runtime;
+use crate::virtual_device::VirtualDeviceDescription;
 use evdev::enums::{EventCode, EventType, EV_SYN};
 use evdev::util::event_code_to_int;
-use evdev::{InputEvent, ReadFlag, ReadStatus};
+use evdev::{AbsInfo, InputEvent};
 use libc::c_uint;
 use log::Level;
 use mio::event::Event;
@@ -18,8 +32,10 @@ use std::any::Any;
 use std::error::Error;
 use std::io;
 use std::io::ErrorKind;
+use std::iter;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, OnceLock, RwLock};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
 use std::time::{Duration, Instant};
 use std::{fmt, usize};
 use tokio::task::JoinHandle;
@@ -28,14 +44,49 @@ use tokio::task::JoinHandle;
 pub trait EvdevCallback: Send + Sync {
     /// Called when an input event is received from a grabbed device.
     /// Returns true if the callback consumed the event, false to pass through.
-    /// Parameters: device_id (slab key), device_identifier, event
+    /// Parameters: device_id (slab key), device_identifier, event, and the HID usage code
+    /// that the device reported for the key with EV_MSC/MSC_SCAN if any.
     fn on_evdev_event(
         &self,
         device_id: usize,
         device_identifier: &EvdevDeviceInfo,
         event: &InputEvent,
+        usage_code: Option<u32>,
     ) -> bool;
 
+    /// Called with every key event read from a grabbed device before it is used by key
+    /// triggers, key behaviours or the remap table, because they can hide it from
+    /// `on_evdev_event`. This is for safety features, such as the power button emergency
+    /// stop, which must not be switched off by them. The event can not be consumed.
+    fn on_evdev_key_read(
+        &self,
+        device_id: usize,
+        device_identifier: &EvdevDeviceInfo,
+        event: &InputEvent,
+        usage_code: Option<u32>,
+    );
+
+    /// Called when an EV_ABS or EV_REL event is received from a grabbed device whose grab
+    /// target forwards axes. abs_info is the range of the axis for EV_ABS events.
+    /// Returns true if the callback consumed the event, false to pass through.
+    fn on_evdev_axis_event(
+        &self,
+        device_id: usize,
+        device_identifier: &EvdevDeviceInfo,
+        event: &InputEvent,
+        abs_info: Option<&AbsInfo>,
+    ) -> bool;
+
+    /// Called when a chord or sequence trigger registered for a grabbed device fires.
+    /// The key events that made up the trigger are not sent to `on_evdev_event`, only to
+    /// `on_evdev_key_read`.
+    fn on_key_trigger_fired(
+        &self,
+        device_id: usize,
+        device_identifier: &EvdevDeviceInfo,
+        trigger_id: u32,
+    );
+
     /// Called when the list of grabbed devices changes.
     /// Parameters: grabbed_devices list with their assigned IDs
     fn on_grabbed_devices_changed(&self, grabbed_devices: Vec<GrabbedDeviceHandle>);
@@ -56,6 +107,7 @@ pub struct EventLoopManager {
     waker: Waker,
     callback: Arc<dyn EvdevCallback>,
     grab_controller: Arc<EvdevGrabController>,
+    replay_handle: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl fmt::Debug for EventLoopManager {
@@ -68,16 +120,18 @@ impl fmt::Debug for EventLoopManager {
             .field("waker", &"<Waker>")
             .field("callback", &"<EvdevCallback>")
             .field("grab_controller", &"<EvdevGrabController>")
+            .field("replay_handle", &"<JoinHandle>")
             .finish()
     }
 }
 
 impl EventLoopManager {
-    /// Initialize the EventLoopManager with a callback. Must be called once before `get()`.
-    /// Panics if called more than once.
-    pub fn init(callback: Arc<dyn EvdevCallback>) {
+    /// Initialize the EventLoopManager with a callback and the directories that contain the
+    /// input devices, which is usually just `DEFAULT_INPUT_DEVICE_ROOT`. Must be called once
+    /// before `get()`. Panics if called more than once.
+    pub fn init(callback: Arc<dyn EvdevCallback>, input_device_roots: Vec<PathBuf>) {
         EVENT_LOOP_MANAGER
-            .set(Self::new(callback))
+            .set(Self::new(callback, input_device_roots))
             .expect("EventLoopManager already initialized");
     }
 
@@ -88,14 +142,15 @@ impl EventLoopManager {
             .expect("EventLoopManager not initialized. Call init() first.")
     }
 
-    fn new(callback: Arc<dyn EvdevCallback>) -> Self {
+    fn new(callback: Arc<dyn EvdevCallback>, input_device_roots: Vec<PathBuf>) -> Self {
         let poll = Poll::new().unwrap();
         let registry = poll.registry().try_clone().unwrap();
         let waker = Waker::new(&registry, WAKER_TOKEN).unwrap();
         let poll_lock = Arc::new(RwLock::new(poll));
 
         let registry_arc = Arc::new(registry);
-        let grab_controller = EvdevGrabController::new(registry_arc.clone(), callback.clone());
+        let grab_controller =
+            EvdevGrabController::new(registry_arc.clone(), callback.clone(), input_device_roots);
 
         Self {
             stop_flag: Arc::new(AtomicBool::new(false)),
@@ -104,6 +159,7 @@ impl EventLoopManager {
             waker,
             callback,
             grab_controller: Arc::new(grab_controller),
+            replay_handle: Mutex::new(None),
         }
     }
 
@@ -183,14 +239,39 @@ impl EventLoopManager {
     }
 
     /// Set the list of grabbed devices. This will ungrab any devices that are no longer in the list
-    /// and grab any new devices. Devices are matched by DeviceIdentifier (name, bus, vendor, product).
+    /// and grab any new devices. Devices are matched with `GrabTarget::matches_device_info`.
     /// Returns: A list of (device_id, DeviceIdentifier) for all successfully grabbed devices.
     pub fn set_grab_targets(&self, targets: Vec<GrabTargetKeyCode>) -> Vec<GrabbedDeviceHandle> {
         let internal_grab_targets = targets.iter().map(Self::convert_grab_target).collect();
 
         let handles = self.grab_controller.set_grab_targets(internal_grab_targets);
+        Self::preload_key_layout_maps(&handles);
 
-        for handle in handles.clone() {
+        handles
+    }
+
+    /// Set the classes of devices to grab, such as every gamepad. Devices in these classes
+    /// are also grabbed when they are plugged in later.
+    /// Returns: A list of (device_id, DeviceIdentifier) for all successfully grabbed devices.
+    pub fn set_capability_grab_targets(
+        &self,
+        targets: Vec<CapabilityGrabTargetKeyCode>,
+    ) -> Vec<GrabbedDeviceHandle> {
+        let internal_grab_targets = targets
+            .iter()
+            .map(Self::convert_capability_grab_target)
+            .collect();
+
+        let handles = self
+            .grab_controller
+            .set_capability_grab_targets(internal_grab_targets);
+        Self::preload_key_layout_maps(&handles);
+
+        handles
+    }
+
+    fn preload_key_layout_maps(handles: &[GrabbedDeviceHandle]) {
+        for handle in handles {
             KeyLayoutMapManager::get()
                 .preload_key_layout_map(&handle.device_info)
                 .inspect_err(|err| {
@@ -201,14 +282,17 @@ impl EventLoopManager {
                 })
                 .ok();
         }
-
-        handles
     }
 
     pub fn get_real_devices(&self) -> Result<Vec<EvdevDeviceInfo>, EvdevError> {
         self.grab_controller.get_real_devices()
     }
 
+    /// Get the full capability set of the device at the path, whether or not it is grabbed.
+    pub fn get_device_capabilities(&self, path: &Path) -> Result<DeviceCapabilities, EvdevError> {
+        self.grab_controller.get_device_capabilities(path)
+    }
+
     /// Write an event to a grabbed device's uinput.
     /// The device_id is the slab key returned by set_grabbed_devices(), enabling O(1) lookup.
     pub fn write_event(
@@ -228,65 +312,343 @@ impl EventLoopManager {
         self.grab_controller
             .with_grabbed_device(device_id, |device| {
                 device
-                    .uinput
+                    .output
                     .write_event(event_type, code, value)
                     .map_err(EvdevError::from)
             })
             .ok_or(EvdevError::from_enum(EvdevErrorCode::NoSuchDevice))?
     }
 
+    /// Replace the scan code remap table of a grabbed device. The table is applied in the
+    /// event loop before the event is sent to the callback. The destination scan codes must be
+    /// supported by the uinput device so request them with `GrabTarget::extra_event_codes`.
+    pub fn set_remap_table(
+        &self,
+        device_id: usize,
+        remap_table: KeyRemapTable,
+    ) -> Result<(), EvdevError> {
+        debug!(
+            "Set remap table: device_id={} remap_table={:?}",
+            device_id, remap_table
+        );
+
+        self.grab_controller
+            .with_grabbed_device(device_id, |device| {
+                device
+                    .set_remap_table(remap_table)
+                    .map_err(EvdevError::from)
+            })
+            .ok_or(EvdevError::from_enum(EvdevErrorCode::NoSuchDevice))?
+    }
+
+    /// Replace the chord and sequence triggers that are detected for a grabbed device.
+    pub fn set_key_triggers(
+        &self,
+        device_id: usize,
+        triggers: Vec<KeyTrigger>,
+    ) -> Result<(), EvdevError> {
+        debug!(
+            "Set key triggers: device_id={} triggers={:?}",
+            device_id, triggers
+        );
+
+        self.grab_controller
+            .with_grabbed_device(device_id, |device| {
+                let outputs = device
+                    .key_trigger_detector
+                    .lock()
+                    .unwrap()
+                    .set_triggers(triggers);
+
+                process_key_trigger_outputs(self.callback.as_ref(), device_id, device, outputs);
+            })
+            .ok_or(EvdevError::from_enum(EvdevErrorCode::NoSuchDevice))
+    }
+
+    /// Replace the tap/hold, long press and double tap behaviours of a grabbed device's keys.
+    /// The scan codes that the keys resolve to must be supported by the uinput device so
+    /// request them with `GrabTarget::extra_event_codes`.
+    pub fn set_key_behaviours(
+        &self,
+        device_id: usize,
+        behaviours: Vec<KeyBehaviour>,
+    ) -> Result<(), EvdevError> {
+        debug!(
+            "Set key behaviours: device_id={} behaviours={:?}",
+            device_id, behaviours
+        );
+
+        self.grab_controller
+            .with_grabbed_device(device_id, |device| {
+                let outputs = device
+                    .key_behaviour_resolver
+                    .lock()
+                    .unwrap()
+                    .set_behaviours(behaviours);
+
+                process_key_behaviour_outputs(self.callback.as_ref(), device_id, device, outputs);
+            })
+            .ok_or(EvdevError::from_enum(EvdevErrorCode::NoSuchDevice))
+    }
+
+    /// Replace the axes of a grabbed device that press keys. The key events are processed
+    /// like the device's own keys so they can be key triggers and are sent to the callback.
+    /// Returns an error and keeps the previous mappings if a mapping is for an axis that is
+    /// not in the device's key layout or that the device does not have.
+    pub fn set_axis_key_mappings(
+        &self,
+        device_id: usize,
+        mappings: Vec<AxisKeyMapping>,
+    ) -> Result<(), EvdevError> {
+        debug!(
+            "Set axis key mappings: device_id={} mappings={:?}",
+            device_id, mappings
+        );
+
+        self.grab_controller
+            .with_grabbed_device(device_id, |device| {
+                let abs_info = device.source.lock().unwrap().evemu_device().abs_info;
+                let key_layout_map_manager = KeyLayoutMapManager::get();
+
+                let translations = mappings
+                    .into_iter()
+                    .map(|mapping| {
+                        let axis_info = key_layout_map_manager
+                            .map_axis(&device.device_info, mapping.abs_code)
+                            .inspect_err(|err| {
+                                error!(
+                                    "Failed to map axis {} for device {:?}: {}",
+                                    mapping.abs_code, device.device_info, err
+                                )
+                            })
+                            .ok()
+                            .flatten();
+
+                        let Some((axis_info, abs_info)) =
+                            axis_info.zip(abs_info.get(&mapping.abs_code).copied())
+                        else {
+                            error!(
+                                "Rejecting axis key mappings because axis {} of device {:?} is unknown",
+                                mapping.abs_code, device.device_info
+                            );
+                            return Err(EvdevError::from_enum(EvdevErrorCode::InvalidArgument));
+                        };
+
+                        Ok(AxisKeyTranslation {
+                            mapping,
+                            axis_info,
+                            abs_info,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, EvdevError>>()?;
+
+                let events = device
+                    .axis_key_translator
+                    .lock()
+                    .unwrap()
+                    .set_translations(translations);
+
+                for event in events {
+                    detect_key_triggers(self.callback.as_ref(), device_id, device, &event);
+                }
+
+                Ok(())
+            })
+            .ok_or(EvdevError::from_enum(EvdevErrorCode::NoSuchDevice))?
+    }
+
+    /// Start recording the events read from a grabbed device to an evemu-record file at
+    /// the given path. The events are recorded before any remapping or trigger detection.
+    pub fn start_recording(&self, device_id: usize, path: &Path) -> Result<(), EvdevError> {
+        info!(
+            "Start recording evdev events: device_id={} path={:?}",
+            device_id, path
+        );
+
+        self.grab_controller
+            .with_grabbed_device(device_id, |device| device.start_recording(path))
+            .ok_or(EvdevError::from_enum(EvdevErrorCode::NoSuchDevice))?
+    }
+
+    /// Stop recording the events of a grabbed device. Returns false if it was not being recorded.
+    pub fn stop_recording(&self, device_id: usize) -> Result<bool, EvdevError> {
+        info!("Stop recording evdev events: device_id={}", device_id);
+
+        self.grab_controller
+            .with_grabbed_device(device_id, |device| device.stop_recording())
+            .ok_or(EvdevError::from_enum(EvdevErrorCode::NoSuchDevice))?
+    }
+
+    /// Replay an evemu-record file on the tokio runtime with the original relative timing.
+    /// This stops any replay that is in progress.
+    pub fn start_replay(
+        &self,
+        path: &Path,
+        target: ReplayTarget,
+        speed: f64,
+    ) -> Result<(), EvdevError> {
+        info!(
+            "Start replaying evdev events: path={:?} target={:?} speed={}",
+            path, target, speed
+        );
+
+        let recording = EvemuRecording::load_from_file(path).map_err(|err| {
+            error!("Failed to load recording: {}", err);
+            EvdevError::from_enum(EvdevErrorCode::InvalidArgument)
+        })?;
+
+        let sink = self
+            .grab_controller
+            .create_replay_sink(target, &recording.device)?;
+
+        let handle = get_runtime().spawn(async move {
+            match replay_events(&recording.events, sink.as_ref(), speed).await {
+                Ok(()) => info!("Finished replaying evdev events"),
+                Err(err) => error!("Failed to replay evdev events: {}", err),
+            }
+        });
+
+        if let Some(previous) = self.replay_handle.lock().unwrap().replace(handle) {
+            previous.abort();
+        }
+
+        Ok(())
+    }
+
+    /// Stop the replay that is in progress. A uinput device that was created for it is
+    /// destroyed.
+    pub fn stop_replay(&self) {
+        if let Some(handle) = self.replay_handle.lock().unwrap().take() {
+            info!("Stop replaying evdev events");
+            handle.abort();
+        }
+    }
+
+    /// Write an Android key code to the output device of a grabbed device. If several scan
+    /// codes map to the key code then one that the output device has is used, including the
+    /// extra event codes.
     pub fn write_key_code_event(
         &self,
         device_id: usize,
         key_code: u32,
         value: i32,
     ) -> Result<(), Box<dyn Error>> {
-        let result = self
-            .grab_controller
+        self.grab_controller
             .with_grabbed_device(device_id, |device| {
-                let scan_code_result = KeyLayoutMapManager::get()
-                    .find_scan_code_for_key(&device.device_info, key_code);
-
-                match scan_code_result {
-                    Err(e) => Err(e.into()),
-                    Ok(None) => {
-                        error!("Failed to find scan code for key: {}", key_code);
-                        Err(Box::new(EvdevError::new(-libc::ENODATA)) as Box<dyn Error>)
-                    }
-                    Ok(Some(code)) => {
-                        if log_enabled!(Level::Debug) {
-                            debug!(
-                                "Write key code evdev event: key_code={} value={}",
-                                key_code, value
-                            );
-                        }
+                write_key_code(
+                    device.output.as_ref(),
+                    &device.device_info,
+                    &device.output_capabilities(),
+                    key_code,
+                    value,
+                )
+            })
+            .unwrap_or_else(|| {
+                Err(Box::new(EvdevError::from_enum(
+                    EvdevErrorCode::NoSuchDevice,
+                )))
+            })
+    }
 
-                        device
-                            .uinput
-                            .write_event(EventType::EV_KEY as c_uint, code, value)
-                            .map_err(|err| Box::new(err) as Box<dyn Error>)?;
-
-                        // Send SYN_REPORT
-                        device
-                            .uinput
-                            .write_event(
-                                EventType::EV_SYN as c_uint,
-                                EV_SYN::SYN_REPORT as c_uint,
-                                0,
-                            )
-                            .map_err(|err| Box::new(err) as Box<dyn Error>)
-                    }
-                }
-            });
+    /// Create a virtual device that events can be written to when no device is grabbed.
+    /// Returns the ID of the virtual device, which is separate from the grabbed device IDs.
+    pub fn create_virtual_device(
+        &self,
+        description: VirtualDeviceDescription,
+    ) -> Result<usize, EvdevError> {
+        info!(
+            "Create virtual device: name={:?} bus={:#x} vendor={:#x} product={:#x} version={:#x}",
+            description.name,
+            description.bus,
+            description.vendor,
+            description.product,
+            description.version
+        );
+
+        let device_info = description.device_info();
+        let virtual_device_id = self.grab_controller.create_virtual_device(description)?;
+
+        KeyLayoutMapManager::get()
+            .preload_key_layout_map(&device_info)
+            .inspect_err(|err| {
+                error!(
+                    "Failed to preload key layout map for virtual device {:?}: {}",
+                    device_info, err
+                );
+            })
+            .ok();
 
-        match result {
-            Some(inner_result) => inner_result,
-            None => Err(Box::new(EvdevError::from_enum(
-                EvdevErrorCode::NoSuchDevice,
-            ))),
+        Ok(virtual_device_id)
+    }
+
+    /// Destroy a virtual device so its device node is removed.
+    pub fn destroy_virtual_device(&self, virtual_device_id: usize) -> Result<(), EvdevError> {
+        info!(
+            "Destroy virtual device: virtual_device_id={}",
+            virtual_device_id
+        );
+
+        if self
+            .grab_controller
+            .destroy_virtual_device(virtual_device_id)
+        {
+            Ok(())
+        } else {
+            Err(EvdevError::from_enum(EvdevErrorCode::NoSuchDevice))
         }
     }
 
+    /// Write an event to a virtual device. The caller is responsible for terminating any
+    /// event sequence with an EV_SYN/SYN_REPORT event.
+    pub fn write_virtual_device_event(
+        &self,
+        virtual_device_id: usize,
+        event_type: u32,
+        code: u32,
+        value: i32,
+    ) -> Result<(), EvdevError> {
+        if log_enabled!(Level::Debug) {
+            debug!(
+                "Write virtual device event: virtual_device_id={} event_type={} code={} value={}",
+                virtual_device_id, event_type, code, value
+            );
+        }
+
+        self.grab_controller
+            .with_virtual_device(virtual_device_id, |device| {
+                device
+                    .output
+                    .write_event(event_type, code, value)
+                    .map_err(EvdevError::from)
+            })
+            .ok_or(EvdevError::from_enum(EvdevErrorCode::NoSuchDevice))?
+    }
+
+    /// Write an Android key code to a virtual device. The scan code is found with the key
+    /// layout file of the virtual device, or Generic.kl if it has none.
+    pub fn write_virtual_device_key_code_event(
+        &self,
+        virtual_device_id: usize,
+        key_code: u32,
+        value: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        self.grab_controller
+            .with_virtual_device(virtual_device_id, |device| {
+                write_key_code(
+                    device.output.as_ref(),
+                    &device.device_info,
+                    &device.description.capabilities,
+                    key_code,
+                    value,
+                )
+            })
+            .unwrap_or_else(|| {
+                Err(Box::new(EvdevError::from_enum(
+                    EvdevErrorCode::NoSuchDevice,
+                )))
+            })
+    }
+
     fn convert_grab_target(target: &GrabTargetKeyCode) -> GrabTarget {
         let event_codes =
             KeyLayoutMapManager::map_key_codes_to_event_codes(&target.extra_key_codes);
@@ -296,9 +658,57 @@ impl EventLoopManager {
             bus: target.bus,
             vendor: target.vendor,
             product: target.product,
+            version: target.version,
+            phys_prefix: target.phys_prefix.clone(),
+            uniq: target.uniq.clone(),
             extra_event_codes: event_codes,
+            forward_axes: target.forward_axes,
         }
     }
+
+    fn convert_capability_grab_target(
+        target: &CapabilityGrabTargetKeyCode,
+    ) -> CapabilityGrabTarget {
+        let event_codes =
+            KeyLayoutMapManager::map_key_codes_to_event_codes(&target.extra_key_codes);
+
+        CapabilityGrabTarget {
+            class: target.class.clone(),
+            extra_event_codes: event_codes,
+            forward_axes: target.forward_axes,
+        }
+    }
+}
+
+/// Write the scan code of an Android key code followed by a SYN_REPORT. If several scan
+/// codes map to the key code then one that the device supports is used.
+fn write_key_code(
+    output: &dyn InputEventSink,
+    device_info: &EvdevDeviceInfo,
+    capabilities: &DeviceCapabilities,
+    key_code: u32,
+    value: i32,
+) -> Result<(), Box<dyn Error>> {
+    let scan_code = KeyLayoutMapManager::get()
+        .find_supported_scan_code_for_key(device_info, capabilities, key_code)?
+        .ok_or_else(|| {
+            error!("Failed to find scan code for key: {}", key_code);
+            Box::new(EvdevError::new(-libc::ENODATA)) as Box<dyn Error>
+        })?;
+
+    if log_enabled!(Level::Debug) {
+        debug!(
+            "Write key code evdev event: key_code={} value={}",
+            key_code, value
+        );
+    }
+
+    output.write_event(EventType::EV_KEY as c_uint, scan_code, value)?;
+
+    // Send SYN_REPORT
+    output.write_event(EventType::EV_SYN as c_uint, EV_SYN::SYN_REPORT as c_uint, 0)?;
+
+    Ok(())
 }
 
 struct EventLoopThread {
@@ -332,7 +742,13 @@ impl EventLoopThread {
         'main: loop {
             let mut poll = self.poll.write().unwrap();
 
-            match poll.poll(&mut events, None) {
+            // Wake up when held back key events must be released or key behaviours decided
+            // even if no events arrive.
+            let timeout = self
+                .get_next_deadline()
+                .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+            match poll.poll(&mut events, timeout) {
                 Ok(_) => {
                     for event in events.iter() {
                         // Break out of the loop if the stop flag is set.
@@ -343,6 +759,8 @@ impl EventLoopThread {
 
                         self.on_poll_event(event);
                     }
+
+                    self.on_timeout();
                 }
                 Err(e) if e.kind() == ErrorKind::Interrupted => {
                     // Interrupted, continue polling
@@ -364,60 +782,235 @@ impl EventLoopThread {
 
         self.grab_controller
             .with_grabbed_device(slab_key, |device| {
-                let evdev = device.evdev.lock().unwrap();
-                let mut flags: ReadFlag = ReadFlag::NORMAL;
-
-                loop {
-                    match evdev.next_event(flags) {
-                        Ok((ReadStatus::Success, input_event)) => {
-                            flags = ReadFlag::NORMAL;
-                            if log_enabled!(Level::Debug) {
-                                debug!("Evdev event: {:?}", input_event);
-                            }
-                            self.process_event(slab_key, &input_event, device);
-                        }
-                        Ok((ReadStatus::Sync, _event)) => {
-                            // Continue reading sync events
-                            flags = ReadFlag::NORMAL | ReadFlag::SYNC;
-                        }
-                        Err(_error) => {
-                            // Break if it's EAGAIN (no more events) or any other error.
-                            // Do not log these errors because it is expected
-                            break;
-                        }
+                let mut source = device.source.lock().unwrap();
+
+                while let Some(input_event) = source.next_event() {
+                    if log_enabled!(Level::Debug) {
+                        debug!("Evdev event: {:?}", input_event);
                     }
+                    device.record_event(&input_event);
+                    process_device_event(self.callback.as_ref(), slab_key, device, &input_event);
                 }
             });
     }
 
-    fn process_event(&self, device_id: usize, event: &InputEvent, grabbed_device: &GrabbedDevice) {
-        // Key Mapper only cares about key events. Do not send other events so latency
-        // isn't introduced with the IPC.
-        let consumed = match event.event_code {
-            // See #2030. Some devices send unknown scan codes so still send them
-            // to Key Mapper.
-            EventCode::EV_KEY(_)
-            | EventCode::EV_UNK {
-                event_type: 1,
-                event_code: _,
-            } => self
-                .callback
-                .on_evdev_event(device_id, &grabbed_device.device_info, event),
-            _ => false,
-        };
-
-        if !consumed {
-            let (event_type, event_code) = event_code_to_int(&event.event_code);
-            grabbed_device
-                .uinput
-                .write_event(event_type, event_code, event.value)
-                .inspect_err(|e| {
-                    error!(
-                        "Failed to passthrough event to {:?}. Event: {:?}. Error: {:?}",
-                        grabbed_device.device_path, event, e
-                    )
-                })
-                .ok();
+    fn get_next_deadline(&self) -> Option<Instant> {
+        let mut next_deadline: Option<Instant> = None;
+
+        self.grab_controller.for_each_grabbed_device(|_, device| {
+            let deadlines = [
+                device.key_trigger_detector.lock().unwrap().next_deadline(),
+                device
+                    .key_behaviour_resolver
+                    .lock()
+                    .unwrap()
+                    .next_deadline(),
+            ];
+
+            next_deadline = deadlines.into_iter().chain([next_deadline]).flatten().min();
+        });
+
+        next_deadline
+    }
+
+    fn on_timeout(&self) {
+        self.grab_controller
+            .for_each_grabbed_device(|device_id, device| {
+                let outputs = device.key_trigger_detector.lock().unwrap().on_timeout();
+                process_key_trigger_outputs(self.callback.as_ref(), device_id, device, outputs);
+
+                let outputs = device.key_behaviour_resolver.lock().unwrap().on_timeout();
+                process_key_behaviour_outputs(self.callback.as_ref(), device_id, device, outputs);
+            });
+    }
+}
+
+/// Process an event read from a grabbed device. Key events are first sent to
+/// `EvdevCallback::on_evdev_key_read` and then go through the key triggers, key behaviours
+/// and remap table. This function is public for testing purposes.
+pub fn process_device_event(
+    callback: &dyn EvdevCallback,
+    device_id: usize,
+    grabbed_device: &GrabbedDevice,
+    event: &InputEvent,
+) {
+    grabbed_device
+        .hid_usage_tracker
+        .lock()
+        .unwrap()
+        .on_event(event);
+
+    if let EventCode::EV_KEY(_) = event.event_code {
+        let (_, scan_code) = event_code_to_int(&event.event_code);
+        let usage_code = grabbed_device
+            .hid_usage_tracker
+            .lock()
+            .unwrap()
+            .usage_code(scan_code);
+
+        callback.on_evdev_key_read(device_id, &grabbed_device.device_info, event, usage_code);
+    }
+
+    let key_events = grabbed_device
+        .axis_key_translator
+        .lock()
+        .unwrap()
+        .on_event(event);
+
+    for event in iter::once(event).chain(&key_events) {
+        detect_key_triggers(callback, device_id, grabbed_device, event);
+    }
+}
+
+fn detect_key_triggers(
+    callback: &dyn EvdevCallback,
+    device_id: usize,
+    grabbed_device: &GrabbedDevice,
+    event: &InputEvent,
+) {
+    let outputs = grabbed_device
+        .key_trigger_detector
+        .lock()
+        .unwrap()
+        .on_event(event);
+
+    process_key_trigger_outputs(callback, device_id, grabbed_device, outputs);
+}
+
+fn process_key_trigger_outputs(
+    callback: &dyn EvdevCallback,
+    device_id: usize,
+    grabbed_device: &GrabbedDevice,
+    outputs: Vec<KeyTriggerOutput>,
+) {
+    for output in outputs {
+        match output {
+            KeyTriggerOutput::Forward(event) => {
+                let outputs = grabbed_device
+                    .key_behaviour_resolver
+                    .lock()
+                    .unwrap()
+                    .on_event(&event);
+
+                process_key_behaviour_outputs(callback, device_id, grabbed_device, outputs);
+            }
+            KeyTriggerOutput::Fired(trigger_id) => {
+                callback.on_key_trigger_fired(device_id, &grabbed_device.device_info, trigger_id)
+            }
+        }
+    }
+}
+
+fn process_key_behaviour_outputs(
+    callback: &dyn EvdevCallback,
+    device_id: usize,
+    grabbed_device: &GrabbedDevice,
+    outputs: Vec<KeyBehaviourOutput>,
+) {
+    if outputs.is_empty() {
+        return;
+    }
+
+    for output in outputs {
+        match output {
+            KeyBehaviourOutput::Forward(event) => {
+                let (_, scan_code) = event_code_to_int(&event.event_code);
+                let usage_code = grabbed_device
+                    .hid_usage_tracker
+                    .lock()
+                    .unwrap()
+                    .usage_code(scan_code);
+
+                process_grabbed_device_event(
+                    callback,
+                    device_id,
+                    &grabbed_device.device_info,
+                    &grabbed_device.key_remapper,
+                    grabbed_device.axis_forwarding.as_ref(),
+                    grabbed_device.output.as_ref(),
+                    &event,
+                    usage_code,
+                )
+            }
+            KeyBehaviourOutput::Emit(event) => write_output_event(
+                grabbed_device.output.as_ref(),
+                &grabbed_device.device_info,
+                &event,
+            ),
         }
     }
 }
+
+/// Decide what happens to an event read from a grabbed device. Remapped and dropped keys
+/// are handled here, everything else is either sent to the callback or written to the output.
+/// usage_code is the HID usage that the device reported for the key, if any.
+/// This function is public for testing purposes.
+#[allow(clippy::too_many_arguments)]
+pub fn process_grabbed_device_event(
+    callback: &dyn EvdevCallback,
+    device_id: usize,
+    device_info: &EvdevDeviceInfo,
+    key_remapper: &Mutex<KeyRemapper>,
+    axis_forwarding: Option<&AxisForwarding>,
+    output: &dyn InputEventSink,
+    event: &InputEvent,
+    usage_code: Option<u32>,
+) {
+    let remap_result = key_remapper.lock().unwrap().apply(event);
+
+    let passthrough_event = match remap_result {
+        RemapResult::Dropped => return,
+        RemapResult::Remapped(remapped_event) => remapped_event,
+        RemapResult::Unmapped => {
+            // Key Mapper only cares about key events, and axis events if the grab target
+            // asked for them. Do not send other events so latency isn't introduced with
+            // the IPC.
+            let consumed = match event.event_code {
+                // See #2030. Some devices send unknown scan codes so still send them
+                // to Key Mapper.
+                EventCode::EV_KEY(_)
+                | EventCode::EV_UNK {
+                    event_type: 1,
+                    event_code: _,
+                } => callback.on_evdev_event(device_id, device_info, event, usage_code),
+                EventCode::EV_ABS(_) | EventCode::EV_REL(_) => match axis_forwarding {
+                    Some(axis_forwarding) => {
+                        let abs_info = match event.event_code {
+                            EventCode::EV_ABS(code) => axis_forwarding.abs_info.get(&(code as u32)),
+                            _ => None,
+                        };
+
+                        callback.on_evdev_axis_event(device_id, device_info, event, abs_info)
+                    }
+                    None => false,
+                },
+                _ => false,
+            };
+
+            if consumed {
+                return;
+            }
+
+            event.clone()
+        }
+    };
+
+    write_output_event(output, device_info, &passthrough_event);
+}
+
+fn write_output_event(
+    output: &dyn InputEventSink,
+    device_info: &EvdevDeviceInfo,
+    event: &InputEvent,
+) {
+    let (event_type, event_code) = event_code_to_int(&event.event_code);
+    output
+        .write_event(event_type, event_code, event.value)
+        .inspect_err(|e| {
+            error!(
+                "Failed to passthrough event to {:?}. Event: {:?}. Error: {:?}",
+                device_info, event, e
+            )
+        })
+        .ok();
+}
@@ -1,25 +1,46 @@
 use evdev::enums::EventCode;
 
+use crate::device_name_matcher::DeviceNameMatcher;
 use crate::evdev_device_info::EvdevDeviceInfo;
 
 /// The information required to grab a device.
 ///
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub struct GrabTarget {
-    pub name: String,
+    pub name: DeviceNameMatcher,
     pub bus: u16,
     pub vendor: u16,
     pub product: u16,
+    /// Only grab devices with this version if it is set.
+    pub version: Option<u16>,
+    /// Only grab devices whose physical location starts with this if it is set.
+    pub phys_prefix: Option<String>,
+    /// Only grab the device with this unique identifier if it is set.
+    pub uniq: Option<String>,
     /// The extra event codes that should be enabled for the device. This is so that the
     /// uinput device can input events that the original device didn't support.
     pub extra_event_codes: Vec<EventCode>,
+    /// Send the EV_ABS and EV_REL events of the device to the callback instead of only
+    /// writing them to the uinput device.
+    pub forward_axes: bool,
 }
 
 impl GrabTarget {
     pub fn matches_device_info(&self, device_info: &EvdevDeviceInfo) -> bool {
-        device_info.name == self.name
+        self.name.matches(&device_info.name)
             && device_info.bus == self.bus
             && device_info.vendor == self.vendor
             && device_info.product == self.product
+            && self
+                .version
+                .is_none_or(|version| device_info.version == version)
+            && self
+                .phys_prefix
+                .as_ref()
+                .is_none_or(|prefix| device_info.phys.starts_with(prefix.as_str()))
+            && self
+                .uniq
+                .as_ref()
+                .is_none_or(|uniq| device_info.uniq == *uniq)
     }
 }
@@ -1,8 +1,14 @@
+use crate::device_name_matcher::DeviceNameMatcher;
+
 #[derive(Debug)]
 pub struct GrabTargetKeyCode {
-    pub name: String,
+    pub name: DeviceNameMatcher,
     pub bus: u16,
     pub vendor: u16,
     pub product: u16,
+    pub version: Option<u16>,
+    pub phys_prefix: Option<String>,
+    pub uniq: Option<String>,
     pub extra_key_codes: Vec<u32>,
+    pub forward_axes: bool,
 }
@@ -1,73 +1,196 @@
+use crate::axis_key_translator::AxisKeyTranslator;
+use crate::clock::SystemClock;
+use crate::device_backend::{DeviceBackend, InputDeviceSource, OutputDeviceSink};
+use crate::device_capabilities::DeviceCapabilities;
 use crate::evdev_device_info::EvdevDeviceInfo;
 use crate::evdev_error::EvdevError;
-use evdev::enums::EventCode;
-use evdev::{Device, DeviceWrapper, GrabMode, UInputDevice};
-use std::fs::OpenOptions;
-use std::os::unix::fs::OpenOptionsExt;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use crate::evemu_device::EvemuDevice;
+use crate::evemu_recorder::EvemuRecorder;
+use crate::hid_usage_tracker::HidUsageTracker;
+use crate::key_behaviour_resolver::KeyBehaviourResolver;
+use crate::key_remap_table::{KeyRemapTable, KeyRemapper};
+use crate::key_trigger_detector::KeyTriggerDetector;
+use evdev::enums::{EventCode, EventType, EV_SYN};
+use evdev::{AbsInfo, GrabMode, InputEvent};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 /// Device context containing all information about a grabbed evdev device
 pub struct GrabbedDevice {
     pub device_path: PathBuf,
     pub device_info: EvdevDeviceInfo,
-    /// The libevdev Device can not be shared safely across threads so wrap it in a mutex.
-    pub evdev: Mutex<Device>,
-    pub uinput: UInputDevice,
-    /// The extra event codes that were enabled for the uinput device. This is so that the
-    /// uinput device can input events that the original device didn't support.
+    /// The device that events are read from. This can not be shared safely across threads
+    /// so wrap it in a mutex.
+    pub source: Mutex<Box<dyn InputDeviceSource>>,
+    /// The uinput device that events are passed through to.
+    pub output: Box<dyn OutputDeviceSink>,
+    /// What the device reports by itself, without the extra event codes.
+    pub capabilities: DeviceCapabilities,
+    /// The extra event codes that were enabled for the output device. This is so that the
+    /// output device can input events that the original device didn't support.
     pub extra_event_codes: Vec<EventCode>,
+    /// Set if the EV_ABS and EV_REL events of this device are sent to the callback.
+    pub axis_forwarding: Option<AxisForwarding>,
+    /// Scan code remaps that are applied in the event loop. The table is replaced as a whole
+    /// so the event loop never sees a partially updated table.
+    pub key_remapper: Mutex<KeyRemapper>,
+    /// Detects the chord and sequence triggers registered for this device.
+    pub key_trigger_detector: Mutex<KeyTriggerDetector>,
+    /// Resolves the tap/hold, long press and double tap behaviours of this device's keys.
+    pub key_behaviour_resolver: Mutex<KeyBehaviourResolver>,
+    /// Presses keys when the axes of this device move past a threshold.
+    pub axis_key_translator: Mutex<AxisKeyTranslator>,
+    /// Pairs the MSC_SCAN events of this device with its keys.
+    pub hid_usage_tracker: Mutex<HidUsageTracker>,
+    /// Records the events read from this device if a recording was started.
+    recorder: Mutex<Option<EvemuRecorder<BufWriter<File>>>>,
+}
+
+/// What is needed to convert the axis events of a device to Android axis values.
+#[derive(Debug, Clone, Default)]
+pub struct AxisForwarding {
+    /// The range of each EV_ABS axis of the device.
+    pub abs_info: BTreeMap<u32, AbsInfo>,
 }
 
 impl GrabbedDevice {
-    /// Create a grabbed device that also enables the given EventCodes in the uinput device.
-    pub fn new(device_path: &PathBuf, extra_events: &[EventCode]) -> Result<Self, EvdevError> {
-        let mut evdev = Self::open_evdev_device(device_path)?;
+    /// Create a grabbed device that also enables the given EventCodes in the output device.
+    /// If forward_axes is true then its axis events are sent to the callback like key events.
+    pub fn new(
+        backend: &dyn DeviceBackend,
+        device_path: &Path,
+        extra_events: &[EventCode],
+        forward_axes: bool,
+    ) -> Result<Self, EvdevError> {
+        let mut source = backend.open_device(device_path)?;
+        let capabilities = DeviceCapabilities::from_evemu_device(source.evemu_device());
 
         for event in extra_events {
-            evdev.enable(*event)?;
+            source.enable_event_code(*event)?;
         }
 
-        evdev.grab(GrabMode::Grab).map_err(EvdevError::from)?;
-        let uinput = UInputDevice::create_from_device(&evdev).map_err(EvdevError::from)?;
+        source.grab(GrabMode::Grab).map_err(EvdevError::from)?;
+        let output = source.create_output_device().map_err(EvdevError::from)?;
+        let device_info = source.device_info();
 
-        let device_info = EvdevDeviceInfo {
-            name: evdev.name().unwrap_or("").to_string(),
-            bus: evdev.bustype(),
-            vendor: evdev.vendor_id(),
-            product: evdev.product_id(),
-            version: evdev.version(),
-        };
+        let axis_forwarding = forward_axes.then(|| AxisForwarding {
+            abs_info: source.evemu_device().abs_info,
+        });
 
         Ok(Self {
-            device_path: device_path.clone(),
+            device_path: device_path.to_path_buf(),
             device_info,
-            evdev: Mutex::new(evdev),
-            uinput,
+            source: Mutex::new(source),
+            output,
+            capabilities,
             extra_event_codes: extra_events.into(),
+            axis_forwarding,
+            key_remapper: Mutex::new(KeyRemapper::default()),
+            key_trigger_detector: Mutex::new(KeyTriggerDetector::new(Arc::new(SystemClock))),
+            key_behaviour_resolver: Mutex::new(KeyBehaviourResolver::new(Arc::new(SystemClock))),
+            axis_key_translator: Mutex::new(AxisKeyTranslator::new()),
+            hid_usage_tracker: Mutex::new(HidUsageTracker::new()),
+            recorder: Mutex::new(None),
         })
     }
 
-    fn open_evdev_device(device_path: &PathBuf) -> Result<Device, EvdevError> {
-        // Open device with O_NONBLOCK so that the loop reading events eventually returns
-        // due to an EAGAIN error
+    /// What the output device has, which is the device's own capabilities and the extra
+    /// event codes.
+    pub fn output_capabilities(&self) -> DeviceCapabilities {
+        let mut capabilities = self.capabilities.clone();
+
+        for event_code in &self.extra_event_codes {
+            capabilities.insert(event_code);
+        }
+
+        capabilities
+    }
 
-        let file = OpenOptions::new()
-            .read(true)
-            .custom_flags(libc::O_NONBLOCK)
-            .open(device_path)
-            .map_err(EvdevError::from)?;
+    /// Atomically replace the remap table for this device. Remapped keys that are held down
+    /// and are remapped differently by the new table are released so they don't get stuck.
+    pub fn set_remap_table(&self, remap_table: KeyRemapTable) -> io::Result<()> {
+        let released = self.key_remapper.lock().unwrap().set_table(remap_table);
+
+        if released.is_empty() {
+            return Ok(());
+        }
+
+        for scan_code in released {
+            self.output
+                .write_event(EventType::EV_KEY as u32, scan_code, 0)?;
+        }
+
+        self.output
+            .write_event(EventType::EV_SYN as u32, EV_SYN::SYN_REPORT as u32, 0)
+    }
 
-        let evdev = Device::new_from_file(file).map_err(EvdevError::from)?;
-        Ok(evdev)
+    /// Start recording the events read from this device to an evemu-record file. This
+    /// replaces any recording that is in progress.
+    ///
+    /// The recording describes what the device reports by itself so that replaying it does
+    /// not create a device with the extra event codes.
+    pub fn start_recording(&self, path: &Path) -> Result<(), EvdevError> {
+        let evemu_device = EvemuDevice {
+            name: self.device_info.name.clone(),
+            bus: self.device_info.bus,
+            vendor: self.device_info.vendor,
+            product: self.device_info.product,
+            version: self.device_info.version,
+            properties: self.capabilities.properties.clone(),
+            event_codes: self.capabilities.event_codes.clone(),
+            abs_info: self.capabilities.abs_info.clone(),
+        };
+        let file = File::create(path).map_err(EvdevError::from)?;
+        let recorder =
+            EvemuRecorder::new(BufWriter::new(file), &evemu_device).map_err(EvdevError::from)?;
+
+        let previous = self.recorder.lock().unwrap().replace(recorder);
+
+        if let Some(previous) = previous {
+            previous.finish().map_err(EvdevError::from)?;
+        }
+
+        Ok(())
+    }
+
+    /// Stop recording and flush the file. Returns false if nothing was being recorded.
+    pub fn stop_recording(&self) -> Result<bool, EvdevError> {
+        let recorder = self.recorder.lock().unwrap().take();
+
+        match recorder {
+            None => Ok(false),
+            Some(recorder) => {
+                recorder.finish().map_err(EvdevError::from)?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Write an event read from this device to the recording, if one is in progress.
+    pub fn record_event(&self, event: &InputEvent) {
+        if let Some(recorder) = self.recorder.lock().unwrap().as_mut() {
+            recorder
+                .write_event(event)
+                .inspect_err(|err| {
+                    error!(
+                        "Failed to record event for device {:?}: {}",
+                        self.device_info, err
+                    )
+                })
+                .ok();
+        }
     }
 }
 
 impl Drop for GrabbedDevice {
     fn drop(&mut self) {
-        let mut evdev = self.evdev.lock().unwrap();
+        let mut source = self.source.lock().unwrap();
         // Ungrab the device
-        evdev
+        source
             .grab(GrabMode::Ungrab)
             .inspect_err(|err| error!("Failed to ungrab device {:?}; {}", self.device_info, err))
             .ok();
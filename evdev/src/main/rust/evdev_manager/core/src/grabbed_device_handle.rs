@@ -1,14 +1,21 @@
 use crate::evdev_device_info::EvdevDeviceInfo;
+use std::path::PathBuf;
 
 /// Handle to a grabbed device with its assigned ID for O(1) lookup
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GrabbedDeviceHandle {
     pub id: usize,
     pub device_info: EvdevDeviceInfo,
+    /// The path of the device node. This tells apart devices that have identical info.
+    pub device_path: PathBuf,
 }
 
 impl GrabbedDeviceHandle {
-    pub fn new(id: usize, device_info: EvdevDeviceInfo) -> Self {
-        Self { id, device_info }
+    pub fn new(id: usize, device_info: EvdevDeviceInfo, device_path: PathBuf) -> Self {
+        Self {
+            id,
+            device_info,
+            device_path,
+        }
     }
 }
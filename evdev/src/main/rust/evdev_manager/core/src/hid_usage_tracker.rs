@@ -0,0 +1,46 @@
+use evdev::enums::{EventCode, EV_MSC, EV_SYN};
+use evdev::util::event_code_to_int;
+use evdev::InputEvent;
+use std::collections::HashMap;
+
+/// Remembers the HID usage code of each key of a device. Devices report the usage of a key
+/// with an EV_MSC/MSC_SCAN event just before the EV_KEY event, like Android's
+/// HidUsageAccumulator expects.
+#[derive(Debug, Default)]
+pub struct HidUsageTracker {
+    /// The usage of the MSC_SCAN event in the current report, if any.
+    pending_usage_code: Option<u32>,
+    usage_codes_by_scan_code: HashMap<u32, u32>,
+}
+
+impl HidUsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_event(&mut self, event: &InputEvent) {
+        match event.event_code {
+            EventCode::EV_MSC(EV_MSC::MSC_SCAN) => {
+                self.pending_usage_code = Some(event.value as u32);
+            }
+            EventCode::EV_KEY(_)
+            | EventCode::EV_UNK {
+                event_type: 1,
+                event_code: _,
+            } => {
+                if let Some(usage_code) = self.pending_usage_code.take() {
+                    let (_, scan_code) = event_code_to_int(&event.event_code);
+                    self.usage_codes_by_scan_code.insert(scan_code, usage_code);
+                }
+            }
+            EventCode::EV_SYN(EV_SYN::SYN_REPORT) => self.pending_usage_code = None,
+            _ => {}
+        }
+    }
+
+    /// The usage code that was last reported for a key. This is kept after the key is
+    /// released so events that are delayed by key behaviours still have their usage.
+    pub fn usage_code(&self, scan_code: u32) -> Option<u32> {
+        self.usage_codes_by_scan_code.get(&scan_code).copied()
+    }
+}
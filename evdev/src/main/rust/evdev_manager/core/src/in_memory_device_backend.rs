@@ -0,0 +1,501 @@
+//! A device backend that keeps its devices in memory so the grab controller can be tested
+//! without kernel devices.
+
+use crate::device_backend::{
+    DeviceBackend, InputDeviceSource, OutputDeviceSink, DEFAULT_INPUT_DEVICE_ROOT,
+};
+use crate::device_capabilities::DeviceCapabilities;
+use crate::evdev_device_info::EvdevDeviceInfo;
+use crate::evemu_device::EvemuDevice;
+use crate::input_event_sink::InputEventSink;
+use crate::virtual_device::VirtualDeviceDescription;
+use evdev::enums::EventCode;
+use evdev::util::event_code_to_int;
+use evdev::{GrabMode, InputEvent, TimeVal};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::io;
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Output and virtual devices are given paths from this event number so they do not clash
+/// with the devices that tests add.
+const FIRST_OUTPUT_DEVICE_NUMBER: usize = 1000;
+
+/// Operations that can be made to fail for a device.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InMemoryDeviceFailures {
+    pub open: bool,
+    pub grab: bool,
+    pub create_output: bool,
+}
+
+struct DeviceState {
+    info: EvdevDeviceInfo,
+    capabilities: DeviceCapabilities,
+    failures: InMemoryDeviceFailures,
+    grabbed: bool,
+    /// Incremented each time the device is opened so a source can tell whether it still
+    /// refers to the device at its path.
+    generation: usize,
+    enabled_event_codes: Vec<EventCode>,
+    pending_events: VecDeque<InputEvent>,
+    /// Wakes up the source that was opened last when events are pushed.
+    wake_stream: Option<UnixStream>,
+    /// The path of the output device that was created for this device.
+    output_path: Option<PathBuf>,
+    /// The events written to this device if it is an output device.
+    written_events: Vec<(u32, u32, i32)>,
+}
+
+impl DeviceState {
+    fn new(info: EvdevDeviceInfo) -> Self {
+        Self {
+            info,
+            capabilities: DeviceCapabilities::default(),
+            failures: InMemoryDeviceFailures::default(),
+            grabbed: false,
+            generation: 0,
+            enabled_event_codes: Vec::new(),
+            pending_events: VecDeque::new(),
+            wake_stream: None,
+            output_path: None,
+            written_events: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct BackendState {
+    devices: BTreeMap<PathBuf, DeviceState>,
+    next_output_number: usize,
+    /// Creating virtual devices fails while this is set.
+    fail_virtual_devices: bool,
+}
+
+impl BackendState {
+    /// Add an output or virtual device like uinput adds a device to /dev/input.
+    fn add_output_device(
+        &mut self,
+        directory: &Path,
+        info: EvdevDeviceInfo,
+        capabilities: DeviceCapabilities,
+    ) -> PathBuf {
+        let number = FIRST_OUTPUT_DEVICE_NUMBER + self.next_output_number;
+        self.next_output_number += 1;
+
+        let path = directory.join(format!("event{}", number));
+
+        let mut device = DeviceState::new(info);
+        device.capabilities = capabilities;
+        self.devices.insert(path.clone(), device);
+
+        path
+    }
+}
+
+/// A device backend with scripted devices. Devices can be added and removed to simulate
+/// hotplugging, events can be pushed to them and opening, grabbing and creating output
+/// devices can be made to fail.
+///
+/// Output devices are added to the backend like the kernel adds uinput devices to
+/// /dev/input and they are removed when they are dropped.
+#[derive(Clone, Default)]
+pub struct InMemoryDeviceBackend {
+    state: Arc<Mutex<BackendState>>,
+}
+
+impl InMemoryDeviceBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a device at the path. This replaces any device that is already at the path.
+    pub fn add_device(&self, path: impl Into<PathBuf>, info: EvdevDeviceInfo) {
+        let mut state = self.state.lock().unwrap();
+        state.devices.insert(path.into(), DeviceState::new(info));
+    }
+
+    pub fn remove_device(&self, path: &Path) {
+        self.state.lock().unwrap().devices.remove(path);
+    }
+
+    /// Set the event codes that the device supports. Devices support none by default.
+    pub fn set_event_codes(&self, path: &Path, event_codes: &[EventCode]) {
+        self.set_capabilities(path, DeviceCapabilities::new(event_codes));
+    }
+
+    pub fn set_capabilities(&self, path: &Path, capabilities: DeviceCapabilities) {
+        if let Some(device) = self.state.lock().unwrap().devices.get_mut(path) {
+            device.capabilities = capabilities;
+        }
+    }
+
+    pub fn set_failures(&self, path: &Path, failures: InMemoryDeviceFailures) {
+        if let Some(device) = self.state.lock().unwrap().devices.get_mut(path) {
+            device.failures = failures;
+        }
+    }
+
+    /// Queue an event to be read from the device. The file descriptor of the source that
+    /// was opened last becomes readable.
+    pub fn push_event(&self, path: &Path, event_code: EventCode, value: i32) {
+        let mut state = self.state.lock().unwrap();
+
+        let Some(device) = state.devices.get_mut(path) else {
+            return;
+        };
+
+        device
+            .pending_events
+            .push_back(InputEvent::new(&TimeVal::new(0, 0), &event_code, value));
+
+        if let Some(stream) = &mut device.wake_stream {
+            stream.write_all(&[0]).ok();
+        }
+    }
+
+    pub fn has_device(&self, path: &Path) -> bool {
+        self.state.lock().unwrap().devices.contains_key(path)
+    }
+
+    pub fn is_grabbed(&self, path: &Path) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .devices
+            .get(path)
+            .is_some_and(|device| device.grabbed)
+    }
+
+    /// The event codes that were enabled on the device in addition to its own.
+    pub fn enabled_event_codes(&self, path: &Path) -> Vec<EventCode> {
+        self.state
+            .lock()
+            .unwrap()
+            .devices
+            .get(path)
+            .map(|device| device.enabled_event_codes.clone())
+            .unwrap_or_default()
+    }
+
+    /// The path of the output device that was created for the device at the path.
+    pub fn output_device_path(&self, path: &Path) -> Option<PathBuf> {
+        let state = self.state.lock().unwrap();
+        let output_path = state.devices.get(path)?.output_path.clone()?;
+
+        // The output device is removed when it is dropped.
+        state
+            .devices
+            .contains_key(&output_path)
+            .then_some(output_path)
+    }
+
+    /// Make creating virtual devices fail, like when /dev/uinput can not be opened.
+    pub fn set_virtual_device_failure(&self, fail: bool) {
+        self.state.lock().unwrap().fail_virtual_devices = fail;
+    }
+
+    /// The events written to the output or virtual device at the path.
+    pub fn written_events(&self, path: &Path) -> Vec<(u32, u32, i32)> {
+        self.state
+            .lock()
+            .unwrap()
+            .devices
+            .get(path)
+            .map(|device| device.written_events.clone())
+            .unwrap_or_default()
+    }
+
+    /// The events written to the output device of the device at the path.
+    pub fn output_events(&self, path: &Path) -> Vec<(u32, u32, i32)> {
+        let state = self.state.lock().unwrap();
+
+        state
+            .devices
+            .get(path)
+            .and_then(|device| device.output_path.as_ref())
+            .and_then(|output_path| state.devices.get(output_path))
+            .map(|output| output.written_events.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl DeviceBackend for InMemoryDeviceBackend {
+    /// There are no directories to watch. Tests notify the grab controller of hotplugs.
+    fn get_device_roots(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    fn get_device_paths(&self) -> io::Result<Vec<PathBuf>> {
+        Ok(self.state.lock().unwrap().devices.keys().cloned().collect())
+    }
+
+    fn get_device_info(&self, path: &Path) -> io::Result<EvdevDeviceInfo> {
+        self.state
+            .lock()
+            .unwrap()
+            .devices
+            .get(path)
+            .map(|device| device.info.clone())
+            .ok_or_else(|| io::Error::from(ErrorKind::NotFound))
+    }
+
+    fn get_device_capabilities(&self, path: &Path) -> io::Result<DeviceCapabilities> {
+        self.state
+            .lock()
+            .unwrap()
+            .devices
+            .get(path)
+            .map(|device| device.capabilities.clone())
+            .ok_or_else(|| io::Error::from(ErrorKind::NotFound))
+    }
+
+    fn open_device(&self, path: &Path) -> io::Result<Box<dyn InputDeviceSource>> {
+        let mut state = self.state.lock().unwrap();
+
+        let device = state
+            .devices
+            .get_mut(path)
+            .ok_or_else(|| io::Error::from(ErrorKind::NotFound))?;
+
+        if device.failures.open {
+            return Err(io::Error::from_raw_os_error(libc::EACCES));
+        }
+
+        let (wake_stream, read_stream) = UnixStream::pair()?;
+        read_stream.set_nonblocking(true)?;
+
+        // Events that were pushed before the device was opened are readable straight away.
+        if !device.pending_events.is_empty() {
+            (&wake_stream).write_all(&[0])?;
+        }
+
+        device.generation += 1;
+        device.enabled_event_codes.clear();
+        device.wake_stream = Some(wake_stream);
+
+        Ok(Box::new(InMemoryInputDevice {
+            backend: self.clone(),
+            path: path.to_path_buf(),
+            generation: device.generation,
+            info: device.info.clone(),
+            enabled_event_codes: Vec::new(),
+            grabbed: false,
+            read_stream,
+        }))
+    }
+
+    /// Virtual devices are added to /dev/input like the output devices.
+    fn create_virtual_device(
+        &self,
+        description: &VirtualDeviceDescription,
+    ) -> io::Result<Box<dyn OutputDeviceSink>> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.fail_virtual_devices {
+            return Err(io::Error::from_raw_os_error(libc::EACCES));
+        }
+
+        let path = state.add_output_device(
+            Path::new(DEFAULT_INPUT_DEVICE_ROOT),
+            description.device_info(),
+            description.capabilities.clone(),
+        );
+
+        Ok(Box::new(InMemoryOutputDevice {
+            backend: self.clone(),
+            path,
+        }))
+    }
+}
+
+/// A device opened from the in-memory backend.
+struct InMemoryInputDevice {
+    backend: InMemoryDeviceBackend,
+    path: PathBuf,
+    generation: usize,
+    info: EvdevDeviceInfo,
+    enabled_event_codes: Vec<EventCode>,
+    grabbed: bool,
+    read_stream: UnixStream,
+}
+
+impl InMemoryInputDevice {
+    /// Access the state of the device if it is still the device that was opened.
+    fn with_device_state<R>(&self, f: impl FnOnce(&mut DeviceState) -> R) -> Option<R> {
+        let mut state = self.backend.state.lock().unwrap();
+
+        state
+            .devices
+            .get_mut(&self.path)
+            .filter(|device| device.generation == self.generation)
+            .map(f)
+    }
+}
+
+impl AsRawFd for InMemoryInputDevice {
+    fn as_raw_fd(&self) -> RawFd {
+        self.read_stream.as_raw_fd()
+    }
+}
+
+impl InputDeviceSource for InMemoryInputDevice {
+    fn device_info(&self) -> EvdevDeviceInfo {
+        self.info.clone()
+    }
+
+    fn enable_event_code(&mut self, event_code: EventCode) -> io::Result<()> {
+        self.enabled_event_codes.push(event_code);
+
+        self.with_device_state(|device| device.enabled_event_codes.push(event_code))
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENODEV))
+    }
+
+    fn grab(&mut self, mode: GrabMode) -> io::Result<()> {
+        let grab = matches!(mode, GrabMode::Grab);
+        let already_grabbed = self.grabbed;
+
+        let result = self
+            .with_device_state(|device| {
+                if grab && device.failures.grab {
+                    return Err(io::Error::from_raw_os_error(libc::EBUSY));
+                }
+
+                // Like the kernel, only one client can grab a device at a time.
+                if grab && device.grabbed && !already_grabbed {
+                    return Err(io::Error::from_raw_os_error(libc::EBUSY));
+                }
+
+                device.grabbed = grab;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err(io::Error::from_raw_os_error(libc::ENODEV)));
+
+        if result.is_ok() {
+            self.grabbed = grab;
+        }
+
+        result
+    }
+
+    fn next_event(&mut self) -> Option<InputEvent> {
+        let event = self
+            .with_device_state(|device| device.pending_events.pop_front())
+            .flatten();
+
+        if event.is_none() {
+            // Drain the wake up bytes so the file descriptor is no longer readable.
+            let mut buffer = [0u8; 64];
+            while matches!(self.read_stream.read(&mut buffer), Ok(count) if count > 0) {}
+        }
+
+        event
+    }
+
+    fn create_output_device(&self) -> io::Result<Box<dyn OutputDeviceSink>> {
+        let mut state = self.backend.state.lock().unwrap();
+
+        let (failures, mut capabilities) = state
+            .devices
+            .get(&self.path)
+            .filter(|device| device.generation == self.generation)
+            .map(|device| (device.failures, device.capabilities.clone()))
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENODEV))?;
+
+        if failures.create_output {
+            return Err(io::Error::from_raw_os_error(libc::EACCES));
+        }
+
+        // Like uinput devices, the output device supports the event codes of the device
+        // and the ones that were enabled.
+        for event_code in &self.enabled_event_codes {
+            capabilities.insert(event_code);
+        }
+
+        let directory = self.path.parent().unwrap_or(Path::new(""));
+        let output_path = state.add_output_device(directory, self.info.clone(), capabilities);
+
+        if let Some(device) = state.devices.get_mut(&self.path) {
+            device.output_path = Some(output_path.clone());
+        }
+
+        Ok(Box::new(InMemoryOutputDevice {
+            backend: self.backend.clone(),
+            path: output_path,
+        }))
+    }
+
+    fn evemu_device(&self) -> EvemuDevice {
+        // The device reports its own event codes and the ones that were enabled.
+        let mut event_codes: BTreeMap<u32, BTreeSet<u32>> = self
+            .backend
+            .get_device_capabilities(&self.path)
+            .map(|capabilities| capabilities.event_codes)
+            .unwrap_or_default();
+
+        for event_code in &self.enabled_event_codes {
+            let (event_type, code) = event_code_to_int(event_code);
+            event_codes.entry(event_type).or_default().insert(code);
+        }
+
+        EvemuDevice {
+            name: self.info.name.clone(),
+            bus: self.info.bus,
+            vendor: self.info.vendor,
+            product: self.info.product,
+            version: self.info.version,
+            properties: BTreeSet::new(),
+            event_codes,
+            abs_info: BTreeMap::new(),
+        }
+    }
+}
+
+impl Drop for InMemoryInputDevice {
+    fn drop(&mut self) {
+        // Closing a device releases its grab.
+        if self.grabbed {
+            self.with_device_state(|device| device.grabbed = false);
+        }
+    }
+}
+
+/// An output device in the in-memory backend. It records the events written to it.
+struct InMemoryOutputDevice {
+    backend: InMemoryDeviceBackend,
+    path: PathBuf,
+}
+
+impl InputEventSink for InMemoryOutputDevice {
+    fn write_event(&self, event_type: u32, code: u32, value: i32) -> io::Result<()> {
+        let mut state = self.backend.state.lock().unwrap();
+
+        let device = state
+            .devices
+            .get_mut(&self.path)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENODEV))?;
+
+        device.written_events.push((event_type, code, value));
+        Ok(())
+    }
+}
+
+impl OutputDeviceSink for InMemoryOutputDevice {
+    fn devnode(&self) -> Option<PathBuf> {
+        Some(self.path.clone())
+    }
+}
+
+impl Drop for InMemoryOutputDevice {
+    fn drop(&mut self) {
+        self.backend
+            .state
+            .lock()
+            .unwrap()
+            .devices
+            .remove(&self.path);
+    }
+}
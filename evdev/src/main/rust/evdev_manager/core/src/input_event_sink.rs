@@ -0,0 +1,16 @@
+use evdev::UInputDevice;
+use std::io;
+
+/// Destination for events that are written on behalf of a grabbed device.
+/// This allows dependency injection for testing purposes.
+pub trait InputEventSink {
+    /// Write an event. The caller is responsible for terminating any event sequence with
+    /// an EV_SYN/SYN_REPORT event.
+    fn write_event(&self, event_type: u32, code: u32, value: i32) -> io::Result<()>;
+}
+
+impl InputEventSink for UInputDevice {
+    fn write_event(&self, event_type: u32, code: u32, value: i32) -> io::Result<()> {
+        UInputDevice::write_event(self, event_type, code, value)
+    }
+}
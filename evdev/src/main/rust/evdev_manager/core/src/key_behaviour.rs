@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+/// Changes what a key on a grabbed device inputs depending on how it is pressed. This is
+/// resolved in the event loop so the timing does not depend on the latency of the callback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBehaviour {
+    /// The scan code of the physical key.
+    pub scan_code: u32,
+    pub kind: KeyBehaviourKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyBehaviourKind {
+    /// A dual-role key. Tapping it inputs `tap_scan_code`. Holding it past the timeout, or
+    /// pressing another key while it is down, presses `hold_scan_code` until it is released.
+    TapHold {
+        tap_scan_code: u32,
+        hold_scan_code: u32,
+        hold_timeout: Duration,
+    },
+    /// Like `TapHold` except that pressing other keys does not decide the behaviour. Only
+    /// holding the key past the timeout presses `long_press_scan_code`.
+    LongPress {
+        short_press_scan_code: u32,
+        long_press_scan_code: u32,
+        long_press_timeout: Duration,
+    },
+    /// Pressing the key a second time within the timeout of releasing it presses
+    /// `double_tap_scan_code` instead of inputting `single_tap_scan_code` twice.
+    DoubleTap {
+        single_tap_scan_code: u32,
+        double_tap_scan_code: u32,
+        double_tap_timeout: Duration,
+    },
+}
+
+impl KeyBehaviourKind {
+    /// How long the key can wait before its behaviour is decided.
+    pub fn timeout(&self) -> Duration {
+        match self {
+            KeyBehaviourKind::TapHold { hold_timeout, .. } => *hold_timeout,
+            KeyBehaviourKind::LongPress {
+                long_press_timeout, ..
+            } => *long_press_timeout,
+            KeyBehaviourKind::DoubleTap {
+                double_tap_timeout, ..
+            } => *double_tap_timeout,
+        }
+    }
+
+    /// The scan code that is input when the key is pressed once without holding it.
+    pub fn tap_scan_code(&self) -> u32 {
+        match self {
+            KeyBehaviourKind::TapHold { tap_scan_code, .. } => *tap_scan_code,
+            KeyBehaviourKind::LongPress {
+                short_press_scan_code,
+                ..
+            } => *short_press_scan_code,
+            KeyBehaviourKind::DoubleTap {
+                single_tap_scan_code,
+                ..
+            } => *single_tap_scan_code,
+        }
+    }
+}
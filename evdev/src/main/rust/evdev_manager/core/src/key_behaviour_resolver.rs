@@ -0,0 +1,320 @@
+use crate::clock::Clock;
+use crate::key_behaviour::{KeyBehaviour, KeyBehaviourKind};
+use evdev::enums::{EventCode, EventType, EV_SYN};
+use evdev::util::{event_code_to_int, int_to_event_code};
+use evdev::{InputEvent, TimeVal};
+use libc::c_uint;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// What the event loop should do after the resolver has seen an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyBehaviourOutput {
+    /// Process the event as normal.
+    Forward(InputEvent),
+    /// Write the event straight to the output device. These are the keys that a key
+    /// behaviour resolved to. The physical key was already sent to
+    /// `EvdevCallback::on_evdev_key_read` when it was read.
+    Emit(InputEvent),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyState {
+    /// The key is down and it is not known yet how it is being pressed.
+    Pressed { since: Instant, time: TimeVal },
+    /// A double tap key was released once and may be pressed again.
+    Tapped { since: Instant, time: TimeVal },
+    /// The behaviour is decided and this scan code is pressed until the key is released.
+    Resolved { scan_code: u32 },
+}
+
+/// Resolves the key behaviours of a single grabbed device into the keys they input.
+pub struct KeyBehaviourResolver {
+    clock: Arc<dyn Clock>,
+    behaviours: HashMap<u32, KeyBehaviourKind>,
+    /// The keys with a behaviour that are in progress, in the order they were pressed.
+    states: Vec<(u32, KeyState)>,
+}
+
+impl KeyBehaviourResolver {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            behaviours: HashMap::new(),
+            states: Vec::new(),
+        }
+    }
+
+    /// Replace the key behaviours. Keys that are still being decided are resolved as a tap.
+    /// Keys that are already resolved stay pressed until they are released.
+    pub fn set_behaviours(&mut self, behaviours: Vec<KeyBehaviour>) -> Vec<KeyBehaviourOutput> {
+        let mut outputs = Vec::new();
+
+        for (scan_code, state) in std::mem::take(&mut self.states) {
+            let tap_scan_code = self
+                .behaviours
+                .get(&scan_code)
+                .map(|kind| kind.tap_scan_code());
+
+            match (state, tap_scan_code) {
+                (KeyState::Pressed { time, .. }, Some(tap_scan_code)) => {
+                    emit_key(&mut outputs, time, tap_scan_code, 1);
+                    self.states.push((
+                        scan_code,
+                        KeyState::Resolved {
+                            scan_code: tap_scan_code,
+                        },
+                    ));
+                }
+                (KeyState::Tapped { time, .. }, Some(tap_scan_code)) => {
+                    emit_tap(&mut outputs, time, tap_scan_code);
+                }
+                (KeyState::Resolved { .. }, _) => self.states.push((scan_code, state)),
+                (_, None) => {}
+            }
+        }
+
+        self.behaviours = behaviours
+            .into_iter()
+            .map(|behaviour| (behaviour.scan_code, behaviour.kind))
+            .collect();
+
+        outputs
+    }
+
+    /// The time at which `on_timeout` must be called to decide a key behaviour.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.states
+            .iter()
+            .filter_map(|(scan_code, state)| self.get_deadline(*scan_code, state))
+            .min()
+    }
+
+    /// Decide the behaviour of keys that have been waiting longer than their timeout.
+    pub fn on_timeout(&mut self) -> Vec<KeyBehaviourOutput> {
+        let mut outputs = Vec::new();
+        self.resolve_expired(self.clock.now(), &mut outputs);
+        outputs
+    }
+
+    pub fn on_event(&mut self, event: &InputEvent) -> Vec<KeyBehaviourOutput> {
+        if self.behaviours.is_empty() && self.states.is_empty() {
+            return vec![KeyBehaviourOutput::Forward(event.clone())];
+        }
+
+        let (event_type, scan_code) = event_code_to_int(&event.event_code);
+
+        if event_type != EventType::EV_KEY as c_uint {
+            return vec![KeyBehaviourOutput::Forward(event.clone())];
+        }
+
+        let now = self.clock.now();
+        let mut outputs = Vec::new();
+        self.resolve_expired(now, &mut outputs);
+
+        if event.value == 1 {
+            self.on_other_key_pressed(scan_code, &mut outputs);
+        }
+
+        if let Some(index) = self.find_state(scan_code) {
+            self.on_key_with_state(index, event, now, &mut outputs);
+        } else if event.value == 1 && self.behaviours.contains_key(&scan_code) {
+            self.states.push((
+                scan_code,
+                KeyState::Pressed {
+                    since: now,
+                    time: event.time,
+                },
+            ));
+        } else {
+            outputs.push(KeyBehaviourOutput::Forward(event.clone()));
+        }
+
+        outputs
+    }
+
+    fn on_key_with_state(
+        &mut self,
+        index: usize,
+        event: &InputEvent,
+        now: Instant,
+        outputs: &mut Vec<KeyBehaviourOutput>,
+    ) {
+        let (scan_code, state) = self.states[index];
+        let kind = self.behaviours.get(&scan_code);
+
+        match (state, event.value) {
+            (KeyState::Resolved { scan_code }, 0) => {
+                emit_key(outputs, event.time, scan_code, 0);
+                self.states.remove(index);
+            }
+            (KeyState::Resolved { scan_code }, 2) => {
+                emit_key(outputs, event.time, scan_code, 2);
+            }
+            (KeyState::Pressed { time, .. }, 0) => match kind {
+                Some(KeyBehaviourKind::DoubleTap { .. }) => {
+                    self.states[index].1 = KeyState::Tapped { since: now, time };
+                }
+                Some(kind) => {
+                    emit_tap(outputs, event.time, kind.tap_scan_code());
+                    self.states.remove(index);
+                }
+                None => {
+                    self.states.remove(index);
+                }
+            },
+            (KeyState::Tapped { .. }, 1) => {
+                if let Some(KeyBehaviourKind::DoubleTap {
+                    double_tap_scan_code,
+                    ..
+                }) = kind
+                {
+                    emit_key(outputs, event.time, *double_tap_scan_code, 1);
+                    self.states[index].1 = KeyState::Resolved {
+                        scan_code: *double_tap_scan_code,
+                    };
+                }
+            }
+            // Repeats are consumed while the behaviour is being decided.
+            _ => {}
+        }
+    }
+
+    /// Pressing another key decides dual-role keys that are down and double tap keys that
+    /// are waiting for a second tap so that the keys are input in the order they were typed.
+    fn on_other_key_pressed(
+        &mut self,
+        pressed_scan_code: u32,
+        outputs: &mut Vec<KeyBehaviourOutput>,
+    ) {
+        let mut index = 0;
+
+        while index < self.states.len() {
+            let (scan_code, state) = self.states[index];
+
+            if scan_code == pressed_scan_code {
+                index += 1;
+                continue;
+            }
+
+            match (state, self.behaviours.get(&scan_code)) {
+                (
+                    KeyState::Pressed { time, .. },
+                    Some(KeyBehaviourKind::TapHold { hold_scan_code, .. }),
+                ) => {
+                    emit_key(outputs, time, *hold_scan_code, 1);
+                    self.states[index].1 = KeyState::Resolved {
+                        scan_code: *hold_scan_code,
+                    };
+                }
+                (
+                    KeyState::Pressed { time, .. },
+                    Some(KeyBehaviourKind::DoubleTap {
+                        single_tap_scan_code,
+                        ..
+                    }),
+                ) => {
+                    emit_key(outputs, time, *single_tap_scan_code, 1);
+                    self.states[index].1 = KeyState::Resolved {
+                        scan_code: *single_tap_scan_code,
+                    };
+                }
+                (KeyState::Tapped { time, .. }, Some(kind)) => {
+                    emit_tap(outputs, time, kind.tap_scan_code());
+                    self.states.remove(index);
+                    continue;
+                }
+                _ => {}
+            }
+
+            index += 1;
+        }
+    }
+
+    fn resolve_expired(&mut self, now: Instant, outputs: &mut Vec<KeyBehaviourOutput>) {
+        let mut index = 0;
+
+        while index < self.states.len() {
+            let (scan_code, state) = self.states[index];
+
+            let expired = self
+                .get_deadline(scan_code, &state)
+                .is_some_and(|deadline| now >= deadline);
+
+            if !expired {
+                index += 1;
+                continue;
+            }
+
+            let Some(kind) = self.behaviours.get(&scan_code) else {
+                index += 1;
+                continue;
+            };
+
+            match state {
+                KeyState::Pressed { time, .. } => {
+                    let held_scan_code = match kind {
+                        KeyBehaviourKind::TapHold { hold_scan_code, .. } => *hold_scan_code,
+                        KeyBehaviourKind::LongPress {
+                            long_press_scan_code,
+                            ..
+                        } => *long_press_scan_code,
+                        KeyBehaviourKind::DoubleTap {
+                            single_tap_scan_code,
+                            ..
+                        } => *single_tap_scan_code,
+                    };
+
+                    emit_key(outputs, time, held_scan_code, 1);
+                    self.states[index].1 = KeyState::Resolved {
+                        scan_code: held_scan_code,
+                    };
+                }
+                KeyState::Tapped { time, .. } => {
+                    emit_tap(outputs, time, kind.tap_scan_code());
+                    self.states.remove(index);
+                    continue;
+                }
+                KeyState::Resolved { .. } => {}
+            }
+
+            index += 1;
+        }
+    }
+
+    fn get_deadline(&self, scan_code: u32, state: &KeyState) -> Option<Instant> {
+        let since = match state {
+            KeyState::Pressed { since, .. } | KeyState::Tapped { since, .. } => *since,
+            KeyState::Resolved { .. } => return None,
+        };
+
+        let timeout = self.behaviours.get(&scan_code)?.timeout();
+        Some(since + timeout)
+    }
+
+    fn find_state(&self, scan_code: u32) -> Option<usize> {
+        self.states.iter().position(|(code, _)| *code == scan_code)
+    }
+}
+
+/// Emit a key event followed by a SYN_REPORT so it is input straight away.
+fn emit_key(outputs: &mut Vec<KeyBehaviourOutput>, time: TimeVal, scan_code: u32, value: i32) {
+    let event_code = int_to_event_code(EventType::EV_KEY as c_uint, scan_code);
+
+    outputs.push(KeyBehaviourOutput::Emit(InputEvent::new(
+        &time,
+        &event_code,
+        value,
+    )));
+    outputs.push(KeyBehaviourOutput::Emit(InputEvent::new(
+        &time,
+        &EventCode::EV_SYN(EV_SYN::SYN_REPORT),
+        0,
+    )));
+}
+
+fn emit_tap(outputs: &mut Vec<KeyBehaviourOutput>, time: TimeVal, scan_code: u32) {
+    emit_key(outputs, time, scan_code, 1);
+    emit_key(outputs, time, scan_code, 0);
+}
@@ -0,0 +1,148 @@
+use evdev::enums::EventType;
+use evdev::util::{event_code_to_int, int_to_event_code};
+use evdev::InputEvent;
+use libc::c_uint;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// What to do with a key event that has a scan code in the remap table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanCodeRemap {
+    /// Replace the scan code with this scan code.
+    Remap(u32),
+    /// Do not pass the event through at all.
+    Drop,
+}
+
+/// The result of applying a remap table to an input event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemapResult {
+    /// The event is not in the table so it must be handled normally.
+    Unmapped,
+    /// The event was remapped and should be written straight to the uinput device.
+    Remapped(InputEvent),
+    /// The event should be discarded.
+    Dropped,
+}
+
+/// A table of scan code remaps for a single grabbed device that is applied in the event loop
+/// so that simple 1:1 remaps don't need a round trip through JNI.
+///
+/// This object is immutable after it has been created. Replace the whole table to change it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyRemapTable {
+    remaps: HashMap<u32, ScanCodeRemap>,
+}
+
+impl KeyRemapTable {
+    pub fn new(remaps: HashMap<u32, ScanCodeRemap>) -> Self {
+        Self { remaps }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaps.is_empty()
+    }
+
+    /// Get the remap for a key scan code, if there is one.
+    pub fn get(&self, scan_code: u32) -> Option<ScanCodeRemap> {
+        self.remaps.get(&scan_code).copied()
+    }
+
+    /// Apply the table to an event. Only EV_KEY events are remapped, which also includes
+    /// EV_KEY events with scan codes unknown to libevdev.
+    pub fn apply(&self, event: &InputEvent) -> RemapResult {
+        if self.remaps.is_empty() {
+            return RemapResult::Unmapped;
+        }
+
+        match key_scan_code(event) {
+            None => RemapResult::Unmapped,
+            Some(scan_code) => apply_remap(event, self.get(scan_code)),
+        }
+    }
+}
+
+/// Applies the remap table of a device and remembers how each key that is down was
+/// remapped when it was pressed. This means a key is always released with the scan code it
+/// was pressed with, even if the table changes while it is held.
+#[derive(Debug, Default)]
+pub struct KeyRemapper {
+    table: Arc<KeyRemapTable>,
+    /// The remap that each key was pressed with. None means the key was not in the table.
+    keys_down: HashMap<u32, Option<ScanCodeRemap>>,
+}
+
+impl KeyRemapper {
+    pub fn new(table: KeyRemapTable) -> Self {
+        Self {
+            table: Arc::new(table),
+            keys_down: HashMap::new(),
+        }
+    }
+
+    /// Replace the table. Returns the remapped scan codes that are down and must be
+    /// released because their key is now remapped differently. The rest of the events of
+    /// those keys are dropped until they are pressed again.
+    pub fn set_table(&mut self, table: KeyRemapTable) -> Vec<u32> {
+        let mut released = Vec::new();
+
+        for (scan_code, remap) in self.keys_down.iter_mut() {
+            if let Some(ScanCodeRemap::Remap(new_scan_code)) = *remap {
+                if table.get(*scan_code) != *remap {
+                    released.push(new_scan_code);
+                    *remap = Some(ScanCodeRemap::Drop);
+                }
+            }
+        }
+
+        self.table = Arc::new(table);
+        released.sort_unstable();
+        released
+    }
+
+    /// Apply the table to an event. Repeats and releases of a key are remapped the same way
+    /// as its press.
+    pub fn apply(&mut self, event: &InputEvent) -> RemapResult {
+        let Some(scan_code) = key_scan_code(event) else {
+            return RemapResult::Unmapped;
+        };
+
+        let remap = match event.value {
+            0 => self
+                .keys_down
+                .remove(&scan_code)
+                .unwrap_or_else(|| self.table.get(scan_code)),
+            1 => {
+                let remap = self.table.get(scan_code);
+                self.keys_down.insert(scan_code, remap);
+                remap
+            }
+            _ => self
+                .keys_down
+                .get(&scan_code)
+                .copied()
+                .unwrap_or_else(|| self.table.get(scan_code)),
+        };
+
+        apply_remap(event, remap)
+    }
+}
+
+/// The scan code of an EV_KEY event, which also includes EV_KEY events with scan codes
+/// unknown to libevdev.
+fn key_scan_code(event: &InputEvent) -> Option<u32> {
+    let (event_type, scan_code) = event_code_to_int(&event.event_code);
+    (event_type == EventType::EV_KEY as c_uint).then_some(scan_code)
+}
+
+fn apply_remap(event: &InputEvent, remap: Option<ScanCodeRemap>) -> RemapResult {
+    match remap {
+        None => RemapResult::Unmapped,
+        Some(ScanCodeRemap::Drop) => RemapResult::Dropped,
+        Some(ScanCodeRemap::Remap(new_scan_code)) => RemapResult::Remapped(InputEvent::new(
+            &event.time,
+            &int_to_event_code(EventType::EV_KEY as c_uint, new_scan_code),
+            event.value,
+        )),
+    }
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+/// A combination of keys on a grabbed device that is detected in the event loop and
+/// reported to the callback as a single trigger instead of the individual key events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyTrigger {
+    /// The ID that is reported to the callback when the trigger fires.
+    pub id: u32,
+    pub kind: KeyTriggerKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyTriggerKind {
+    /// All the keys must be pressed down within the window, in any order.
+    ///
+    /// The key down events are held back until the chord completes or can no longer complete.
+    /// If the chord fires then the key events are never forwarded.
+    Chord {
+        scan_codes: Vec<u32>,
+        window: Duration,
+    },
+    /// The keys must be pressed in order and each press must happen within the timeout
+    /// of the previous one.
+    ///
+    /// Only the press of the last key is consumed. The other keys are forwarded as normal
+    /// because holding them back would delay typing.
+    Sequence {
+        scan_codes: Vec<u32>,
+        timeout: Duration,
+    },
+}
+
+impl KeyTrigger {
+    pub fn scan_codes(&self) -> &[u32] {
+        match &self.kind {
+            KeyTriggerKind::Chord { scan_codes, .. } => scan_codes,
+            KeyTriggerKind::Sequence { scan_codes, .. } => scan_codes,
+        }
+    }
+}
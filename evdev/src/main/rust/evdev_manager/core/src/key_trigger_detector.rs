@@ -0,0 +1,328 @@
+use crate::clock::Clock;
+use crate::key_trigger::{KeyTrigger, KeyTriggerKind};
+use evdev::enums::{EventCode, EventType, EV_SYN};
+use evdev::util::event_code_to_int;
+use evdev::InputEvent;
+use libc::c_uint;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What the event loop should do after the detector has seen an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyTriggerOutput {
+    /// Process the event as normal.
+    Forward(InputEvent),
+    /// The trigger with this ID fired.
+    Fired(u32),
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct SequenceProgress {
+    /// How many keys of the sequence have been pressed so far.
+    matched: usize,
+    last_press_time: Option<Instant>,
+}
+
+/// Detects chord and sequence triggers in the key events of a single grabbed device.
+pub struct KeyTriggerDetector {
+    clock: Arc<dyn Clock>,
+    triggers: Vec<KeyTrigger>,
+    /// Key events that are held back because they may be part of a chord.
+    held_events: Vec<InputEvent>,
+    /// When the first held key was pressed.
+    held_since: Option<Instant>,
+    /// Keys that completed a trigger. Their remaining events are consumed until
+    /// they are released.
+    consumed_keys: HashSet<u32>,
+    /// The progress through each trigger. Only used for sequence triggers.
+    sequence_progress: Vec<SequenceProgress>,
+    /// The prefix function of each trigger's scan codes. Only used for sequence triggers.
+    sequence_prefixes: Vec<Vec<usize>>,
+}
+
+impl KeyTriggerDetector {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            triggers: Vec::new(),
+            held_events: Vec::new(),
+            held_since: None,
+            consumed_keys: HashSet::new(),
+            sequence_progress: Vec::new(),
+            sequence_prefixes: Vec::new(),
+        }
+    }
+
+    pub fn triggers(&self) -> &[KeyTrigger] {
+        &self.triggers
+    }
+
+    /// Replace the triggers. Any held back events are returned so they are not lost.
+    pub fn set_triggers(&mut self, triggers: Vec<KeyTrigger>) -> Vec<KeyTriggerOutput> {
+        let mut outputs = Vec::new();
+        self.release_held_events(&mut outputs);
+
+        self.sequence_progress = vec![SequenceProgress::default(); triggers.len()];
+        self.sequence_prefixes = triggers
+            .iter()
+            .map(|trigger| prefix_function(trigger.scan_codes()))
+            .collect();
+        self.triggers = triggers;
+
+        outputs
+    }
+
+    /// The time at which `on_timeout` must be called to release held back events.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let held_since = self.held_since?;
+        let window = self.held_chord_window()?;
+        Some(held_since + window)
+    }
+
+    /// Release held back events if the chords they are part of can no longer complete.
+    pub fn on_timeout(&mut self) -> Vec<KeyTriggerOutput> {
+        let mut outputs = Vec::new();
+        self.release_expired_events(self.clock.now(), &mut outputs);
+        outputs
+    }
+
+    pub fn on_event(&mut self, event: &InputEvent) -> Vec<KeyTriggerOutput> {
+        if self.triggers.is_empty() {
+            return vec![KeyTriggerOutput::Forward(event.clone())];
+        }
+
+        let (event_type, scan_code) = event_code_to_int(&event.event_code);
+
+        if event_type != EventType::EV_KEY as c_uint {
+            return vec![KeyTriggerOutput::Forward(event.clone())];
+        }
+
+        let now = self.clock.now();
+        let mut outputs = Vec::new();
+        self.release_expired_events(now, &mut outputs);
+
+        if self.consumed_keys.contains(&scan_code) {
+            if event.value == 0 {
+                self.consumed_keys.remove(&scan_code);
+            }
+
+            return outputs;
+        }
+
+        if event.value != 1 {
+            // A held key was released or is repeating so its chord did not complete in time.
+            if self.is_held(scan_code) {
+                self.release_held_events(&mut outputs);
+            }
+
+            outputs.push(KeyTriggerOutput::Forward(event.clone()));
+            return outputs;
+        }
+
+        if let Some(trigger_id) = self.update_sequences(scan_code, now) {
+            self.release_held_events(&mut outputs);
+            self.consumed_keys.insert(scan_code);
+            outputs.push(KeyTriggerOutput::Fired(trigger_id));
+            return outputs;
+        }
+
+        if !self.can_complete_chord_with(scan_code) {
+            self.release_held_events(&mut outputs);
+        }
+
+        if !self.is_chord_key(scan_code) {
+            outputs.push(KeyTriggerOutput::Forward(event.clone()));
+            return outputs;
+        }
+
+        self.held_events.push(event.clone());
+        self.held_since.get_or_insert(now);
+
+        if let Some(trigger_id) = self.find_completed_chord() {
+            self.fire_chord(trigger_id, &mut outputs);
+        }
+
+        outputs
+    }
+
+    fn update_sequences(&mut self, scan_code: u32, now: Instant) -> Option<u32> {
+        let mut completed: Option<u32> = None;
+
+        for ((trigger, progress), prefixes) in self
+            .triggers
+            .iter()
+            .zip(self.sequence_progress.iter_mut())
+            .zip(self.sequence_prefixes.iter())
+        {
+            let KeyTriggerKind::Sequence {
+                scan_codes,
+                timeout,
+            } = &trigger.kind
+            else {
+                continue;
+            };
+
+            if scan_codes.is_empty() {
+                continue;
+            }
+
+            let timed_out = progress
+                .last_press_time
+                .is_some_and(|last_press_time| now.duration_since(last_press_time) > *timeout);
+
+            if timed_out {
+                *progress = SequenceProgress::default();
+            }
+
+            // On a mismatch fall back to the longest pressed suffix that is also a prefix of
+            // the sequence, so pressing A, A, A, B still completes A, A, B.
+            let mut matched = progress.matched;
+
+            while matched > 0 && scan_codes[matched] != scan_code {
+                matched = prefixes[matched - 1];
+            }
+
+            if scan_codes[matched] == scan_code {
+                progress.matched = matched + 1;
+                progress.last_press_time = Some(now);
+            } else {
+                *progress = SequenceProgress::default();
+            }
+
+            if progress.matched == scan_codes.len() && completed.is_none() {
+                completed = Some(trigger.id);
+            }
+        }
+
+        if completed.is_some() {
+            self.sequence_progress.fill(SequenceProgress::default());
+        }
+
+        completed
+    }
+
+    fn fire_chord(&mut self, trigger_id: u32, outputs: &mut Vec<KeyTriggerOutput>) {
+        let chord_scan_codes: Vec<u32> = self
+            .triggers
+            .iter()
+            .find(|trigger| trigger.id == trigger_id)
+            .map(|trigger| trigger.scan_codes().to_vec())
+            .unwrap_or_default();
+
+        // Held keys that are not part of the chord that fired are still pressed by the user.
+        self.held_events
+            .retain(|event| !chord_scan_codes.contains(&get_scan_code(event)));
+        self.release_held_events(outputs);
+
+        self.consumed_keys.extend(chord_scan_codes);
+        outputs.push(KeyTriggerOutput::Fired(trigger_id));
+    }
+
+    fn release_expired_events(&mut self, now: Instant, outputs: &mut Vec<KeyTriggerOutput>) {
+        if let Some(deadline) = self.next_deadline() {
+            if now >= deadline {
+                self.release_held_events(outputs);
+            }
+        }
+    }
+
+    /// Forward the held back events in the order they were received.
+    fn release_held_events(&mut self, outputs: &mut Vec<KeyTriggerOutput>) {
+        self.held_since = None;
+
+        let Some(last_event) = self.held_events.last() else {
+            return;
+        };
+
+        // The SYN_REPORT that followed the held events has already been forwarded so another
+        // one is needed for the released events to be seen.
+        let syn_report =
+            InputEvent::new(&last_event.time, &EventCode::EV_SYN(EV_SYN::SYN_REPORT), 0);
+
+        outputs.extend(self.held_events.drain(..).map(KeyTriggerOutput::Forward));
+        outputs.push(KeyTriggerOutput::Forward(syn_report));
+    }
+
+    fn held_scan_codes(&self) -> impl Iterator<Item = u32> + '_ {
+        self.held_events.iter().map(get_scan_code)
+    }
+
+    fn is_held(&self, scan_code: u32) -> bool {
+        self.held_scan_codes().any(|held| held == scan_code)
+    }
+
+    fn is_chord_key(&self, scan_code: u32) -> bool {
+        self.chords()
+            .any(|(_, scan_codes, _)| scan_codes.contains(&scan_code))
+    }
+
+    /// Whether a chord contains all the held keys and the given key.
+    fn can_complete_chord_with(&self, scan_code: u32) -> bool {
+        self.chords().any(|(_, scan_codes, _)| {
+            scan_codes.contains(&scan_code)
+                && self
+                    .held_scan_codes()
+                    .all(|held| scan_codes.contains(&held))
+        })
+    }
+
+    /// The completed chord with the most keys so that a chord is not shadowed by a
+    /// smaller chord that it contains.
+    fn find_completed_chord(&self) -> Option<u32> {
+        self.chords()
+            .filter(|(_, scan_codes, _)| {
+                !scan_codes.is_empty()
+                    && scan_codes.iter().all(|scan_code| self.is_held(*scan_code))
+            })
+            .max_by_key(|(_, scan_codes, _)| scan_codes.len())
+            .map(|(id, _, _)| id)
+    }
+
+    /// The longest window of the chords that the held keys could still complete.
+    fn held_chord_window(&self) -> Option<Duration> {
+        self.chords()
+            .filter(|(_, scan_codes, _)| {
+                self.held_scan_codes()
+                    .all(|held| scan_codes.contains(&held))
+            })
+            .map(|(_, _, window)| window)
+            .max()
+    }
+
+    fn chords(&self) -> impl Iterator<Item = (u32, &[u32], Duration)> + '_ {
+        self.triggers
+            .iter()
+            .filter_map(|trigger| match &trigger.kind {
+                KeyTriggerKind::Chord { scan_codes, window } => {
+                    Some((trigger.id, scan_codes.as_slice(), *window))
+                }
+                KeyTriggerKind::Sequence { .. } => None,
+            })
+    }
+}
+
+/// The length of the longest proper prefix of `scan_codes[..=i]` that is also a suffix of it,
+/// for each i. This is the failure table of the Knuth-Morris-Pratt algorithm.
+fn prefix_function(scan_codes: &[u32]) -> Vec<usize> {
+    let mut prefixes = vec![0; scan_codes.len()];
+    let mut length = 0;
+
+    for i in 1..scan_codes.len() {
+        while length > 0 && scan_codes[i] != scan_codes[length] {
+            length = prefixes[length - 1];
+        }
+
+        if scan_codes[i] == scan_codes[length] {
+            length += 1;
+        }
+
+        prefixes[i] = length;
+    }
+
+    prefixes
+}
+
+fn get_scan_code(event: &InputEvent) -> u32 {
+    event_code_to_int(&event.event_code).1
+}
@@ -1,13 +1,35 @@
 #[macro_use]
 extern crate log;
 pub mod android;
+pub mod axis_key_mapping;
+pub mod axis_key_translator;
+pub mod capability_grab_target;
+pub mod capability_grab_target_key_code;
+pub mod clock;
+pub mod device_backend;
+pub mod device_capabilities;
+pub mod device_name_matcher;
 pub mod evdev_device_info;
 pub mod evdev_devices_watcher;
 pub mod evdev_error;
 pub mod evdev_grab_controller;
+pub mod evemu_device;
+pub mod evemu_player;
+pub mod evemu_recorder;
+pub mod evemu_recording;
 pub mod event_loop;
 pub mod grab_target;
 pub mod grab_target_key_code;
 pub mod grabbed_device;
 pub mod grabbed_device_handle;
+pub mod hid_usage_tracker;
+#[cfg(any(test, feature = "test-util"))]
+pub mod in_memory_device_backend;
+pub mod input_event_sink;
+pub mod key_behaviour;
+pub mod key_behaviour_resolver;
+pub mod key_remap_table;
+pub mod key_trigger;
+pub mod key_trigger_detector;
 pub mod runtime;
+pub mod virtual_device;
@@ -0,0 +1,115 @@
+use crate::device_backend::OutputDeviceSink;
+use crate::device_capabilities::DeviceCapabilities;
+use crate::evdev_device_info::EvdevDeviceInfo;
+use crate::evemu_device::EvemuDevice;
+use evdev::enums::{int_to_input_prop, EventCode};
+use evdev::util::int_to_event_code;
+use evdev::{DeviceWrapper, EnableCodeData, UInputDevice, UninitDevice};
+use libc::c_uint;
+use std::io;
+use std::io::ErrorKind;
+
+/// The name, ids and capabilities of a virtual input device, such as a keyboard that keys
+/// are injected with when no device is grabbed.
+#[derive(Debug, Clone)]
+pub struct VirtualDeviceDescription {
+    pub name: String,
+    pub bus: u16,
+    pub vendor: u16,
+    pub product: u16,
+    pub version: u16,
+    /// The event codes, axes and properties of the device. uinput does not allow the key
+    /// repeat settings to be set so they are ignored.
+    pub capabilities: DeviceCapabilities,
+}
+
+impl VirtualDeviceDescription {
+    pub fn from_evemu_device(evemu_device: EvemuDevice) -> Self {
+        Self {
+            name: evemu_device.name.clone(),
+            bus: evemu_device.bus,
+            vendor: evemu_device.vendor,
+            product: evemu_device.product,
+            version: evemu_device.version,
+            capabilities: DeviceCapabilities::from_evemu_device(evemu_device),
+        }
+    }
+
+    /// The info that the device will have once it is created. This is used to find its
+    /// key layout file.
+    pub fn device_info(&self) -> EvdevDeviceInfo {
+        EvdevDeviceInfo {
+            name: self.name.clone(),
+            bus: self.bus,
+            vendor: self.vendor,
+            product: self.product,
+            version: self.version,
+            phys: String::new(),
+            uniq: String::new(),
+            sysfs_path: None,
+        }
+    }
+}
+
+/// A virtual input device that is not tied to a grabbed device. It is destroyed when it is
+/// dropped.
+pub struct VirtualDevice {
+    pub description: VirtualDeviceDescription,
+    pub device_info: EvdevDeviceInfo,
+    pub output: Box<dyn OutputDeviceSink>,
+}
+
+impl VirtualDevice {
+    pub fn new(description: VirtualDeviceDescription, output: Box<dyn OutputDeviceSink>) -> Self {
+        let device_info = description.device_info();
+
+        Self {
+            description,
+            device_info,
+            output,
+        }
+    }
+}
+
+/// Create a uinput device from a description of the device.
+pub fn create_uinput_device(description: &VirtualDeviceDescription) -> io::Result<UInputDevice> {
+    let evdev = UninitDevice::new()
+        .ok_or_else(|| io::Error::new(ErrorKind::OutOfMemory, "Failed to create libevdev"))?;
+
+    evdev.set_name(&description.name);
+    evdev.set_bustype(description.bus);
+    evdev.set_vendor_id(description.vendor);
+    evdev.set_product_id(description.product);
+    evdev.set_version(description.version);
+
+    let capabilities = &description.capabilities;
+
+    for prop in capabilities
+        .properties
+        .iter()
+        .filter_map(|prop| int_to_input_prop(*prop))
+    {
+        evdev.enable(prop)?;
+    }
+
+    for (event_type, codes) in &capabilities.event_codes {
+        for code in codes {
+            let event_code = int_to_event_code(*event_type as c_uint, *code as c_uint);
+
+            let data = match event_code {
+                EventCode::EV_ABS(_) => capabilities
+                    .abs_info
+                    .get(code)
+                    .copied()
+                    .map(EnableCodeData::AbsInfo),
+                // uinput can not set the key repeat settings.
+                EventCode::EV_REP(_) => continue,
+                _ => None,
+            };
+
+            evdev.enable_event_code(&event_code, data)?;
+        }
+    }
+
+    UInputDevice::create_from_device(&evdev)
+}
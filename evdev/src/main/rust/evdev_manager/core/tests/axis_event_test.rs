@@ -0,0 +1,271 @@
+//! Tests for converting axis events to Android axis values and forwarding them to the callback.
+mod common;
+
+use common::{FakeCallback, FakeSink};
+use evdev::enums::{EventCode, EV_ABS, EV_KEY, EV_REL};
+use evdev::{AbsInfo, InputEvent, TimeVal};
+use evdev_manager_core::android::android_codes::{
+    AMOTION_EVENT_AXIS_BRAKE, AMOTION_EVENT_AXIS_GAS, AMOTION_EVENT_AXIS_HSCROLL,
+    AMOTION_EVENT_AXIS_LTRIGGER, AMOTION_EVENT_AXIS_RELATIVE_X, AMOTION_EVENT_AXIS_VSCROLL,
+    AMOTION_EVENT_AXIS_X,
+};
+use evdev_manager_core::android::axis_mapper::{map_axis_event, AndroidAxisValue};
+use evdev_manager_core::android::keylayout::key_layout_map::{
+    KeyLayoutAxisInfo, KeyLayoutAxisMode,
+};
+use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
+use evdev_manager_core::event_loop::process_grabbed_device_event;
+use evdev_manager_core::grabbed_device::AxisForwarding;
+use evdev_manager_core::key_remap_table::KeyRemapper;
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+fn abs_info(minimum: i32, maximum: i32, flat: i32) -> AbsInfo {
+    AbsInfo {
+        value: 0,
+        minimum,
+        maximum,
+        fuzz: 0,
+        flat,
+        resolution: 0,
+    }
+}
+
+fn axis(mode: KeyLayoutAxisMode, axis: u32) -> KeyLayoutAxisInfo {
+    KeyLayoutAxisInfo {
+        mode,
+        axis,
+        high_axis: None,
+        split_value: None,
+        flat_override: None,
+    }
+}
+
+fn event(event_code: EventCode, value: i32) -> InputEvent {
+    InputEvent::new(&TimeVal::new(1, 500), &event_code, value)
+}
+
+fn abs_x(value: i32) -> InputEvent {
+    event(EventCode::EV_ABS(EV_ABS::ABS_X), value)
+}
+
+fn axis_value(axis: u32, value: f32) -> AndroidAxisValue {
+    AndroidAxisValue { axis, value }
+}
+
+#[test]
+fn test_centered_axis_is_scaled_to_minus_one_to_one() {
+    let axis_info = axis(KeyLayoutAxisMode::Normal, AMOTION_EVENT_AXIS_X);
+    let range = abs_info(0, 200, 0);
+
+    for (raw, expected) in [(0, -1.0), (100, 0.0), (150, 0.5), (200, 1.0)] {
+        assert_eq!(
+            map_axis_event(Some(&axis_info), Some(&range), &abs_x(raw)),
+            vec![axis_value(AMOTION_EVENT_AXIS_X, expected)]
+        );
+    }
+}
+
+#[test]
+fn test_trigger_axis_is_scaled_to_zero_to_one() {
+    let axis_info = axis(KeyLayoutAxisMode::Normal, AMOTION_EVENT_AXIS_LTRIGGER);
+    let range = abs_info(0, 255, 0);
+
+    assert_eq!(
+        map_axis_event(
+            Some(&axis_info),
+            Some(&range),
+            &event(EventCode::EV_ABS(EV_ABS::ABS_BRAKE), 255)
+        ),
+        vec![axis_value(AMOTION_EVENT_AXIS_LTRIGGER, 1.0)]
+    );
+}
+
+#[test]
+fn test_inverted_axis() {
+    let axis_info = axis(KeyLayoutAxisMode::Invert, AMOTION_EVENT_AXIS_X);
+    let range = abs_info(0, 200, 0);
+
+    assert_eq!(
+        map_axis_event(Some(&axis_info), Some(&range), &abs_x(0)),
+        vec![axis_value(AMOTION_EVENT_AXIS_X, 1.0)]
+    );
+}
+
+#[test]
+fn test_split_axis_reports_both_halves() {
+    let axis_info = KeyLayoutAxisInfo {
+        high_axis: Some(AMOTION_EVENT_AXIS_GAS),
+        split_value: Some(100),
+        ..axis(KeyLayoutAxisMode::Split, AMOTION_EVENT_AXIS_BRAKE)
+    };
+    let range = abs_info(0, 200, 0);
+
+    assert_eq!(
+        map_axis_event(Some(&axis_info), Some(&range), &abs_x(50)),
+        vec![
+            axis_value(AMOTION_EVENT_AXIS_BRAKE, 0.5),
+            axis_value(AMOTION_EVENT_AXIS_GAS, 0.0)
+        ]
+    );
+    assert_eq!(
+        map_axis_event(Some(&axis_info), Some(&range), &abs_x(200)),
+        vec![
+            axis_value(AMOTION_EVENT_AXIS_BRAKE, 0.0),
+            axis_value(AMOTION_EVENT_AXIS_GAS, 1.0)
+        ]
+    );
+}
+
+#[test]
+fn test_values_in_flat_region_are_centered() {
+    let axis_info = axis(KeyLayoutAxisMode::Normal, AMOTION_EVENT_AXIS_X);
+    let range = abs_info(0, 200, 10);
+
+    assert_eq!(
+        map_axis_event(Some(&axis_info), Some(&range), &abs_x(105)),
+        vec![axis_value(AMOTION_EVENT_AXIS_X, 0.0)]
+    );
+}
+
+#[test]
+fn test_flat_override_replaces_device_flat() {
+    let axis_info = KeyLayoutAxisInfo {
+        flat_override: Some(0),
+        ..axis(KeyLayoutAxisMode::Normal, AMOTION_EVENT_AXIS_X)
+    };
+    let range = abs_info(0, 200, 60);
+
+    assert_eq!(
+        map_axis_event(Some(&axis_info), Some(&range), &abs_x(150)),
+        vec![axis_value(AMOTION_EVENT_AXIS_X, 0.5)]
+    );
+}
+
+#[test]
+fn test_abs_event_without_layout_axis_is_not_mapped() {
+    assert_eq!(
+        map_axis_event(None, Some(&abs_info(0, 200, 0)), &abs_x(100)),
+        vec![]
+    );
+}
+
+#[test]
+fn test_rel_events_are_mapped_like_a_mouse() {
+    assert_eq!(
+        map_axis_event(None, None, &event(EventCode::EV_REL(EV_REL::REL_X), -3)),
+        vec![axis_value(AMOTION_EVENT_AXIS_RELATIVE_X, -3.0)]
+    );
+    assert_eq!(
+        map_axis_event(None, None, &event(EventCode::EV_REL(EV_REL::REL_WHEEL), 1)),
+        vec![axis_value(AMOTION_EVENT_AXIS_VSCROLL, 1.0)]
+    );
+    assert_eq!(
+        map_axis_event(
+            None,
+            None,
+            &event(EventCode::EV_REL(EV_REL::REL_HWHEEL), -1)
+        ),
+        vec![axis_value(AMOTION_EVENT_AXIS_HSCROLL, -1.0)]
+    );
+    assert_eq!(
+        map_axis_event(None, None, &event(EventCode::EV_REL(EV_REL::REL_DIAL), 1)),
+        vec![]
+    );
+}
+
+fn device_info() -> EvdevDeviceInfo {
+    EvdevDeviceInfo {
+        name: "Test Gamepad".to_string(),
+        bus: 0x0003,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    }
+}
+
+fn axis_forwarding() -> AxisForwarding {
+    AxisForwarding {
+        abs_info: BTreeMap::from([(EV_ABS::ABS_X as u32, abs_info(0, 200, 0))]),
+    }
+}
+
+fn process(
+    callback: &FakeCallback,
+    axis_forwarding: Option<&AxisForwarding>,
+    sink: &FakeSink,
+    event: &InputEvent,
+) {
+    process_grabbed_device_event(
+        callback,
+        0,
+        &device_info(),
+        &Mutex::new(KeyRemapper::default()),
+        axis_forwarding,
+        sink,
+        event,
+        None,
+    );
+}
+
+#[test]
+fn test_axis_events_are_forwarded_with_their_range() {
+    let callback = FakeCallback::new(false, true);
+    let sink = FakeSink::default();
+    let forwarding = axis_forwarding();
+
+    process(&callback, Some(&forwarding), &sink, &abs_x(50));
+    process(
+        &callback,
+        Some(&forwarding),
+        &sink,
+        &event(EventCode::EV_REL(EV_REL::REL_WHEEL), 1),
+    );
+
+    assert_eq!(
+        callback.axis_events(),
+        vec![
+            (abs_x(50), Some(200)),
+            (event(EventCode::EV_REL(EV_REL::REL_WHEEL), 1), None)
+        ]
+    );
+    assert_eq!(sink.events(), vec![]);
+}
+
+#[test]
+fn test_axis_events_that_are_not_consumed_are_passed_through() {
+    let callback = FakeCallback::new(false, false);
+    let sink = FakeSink::default();
+
+    process(&callback, Some(&axis_forwarding()), &sink, &abs_x(50));
+
+    assert_eq!(sink.events(), vec![(3, EV_ABS::ABS_X as u32, 50)]);
+}
+
+#[test]
+fn test_axis_events_are_not_forwarded_without_opting_in() {
+    let callback = FakeCallback::new(false, true);
+    let sink = FakeSink::default();
+
+    process(&callback, None, &sink, &abs_x(50));
+    process(
+        &callback,
+        Some(&axis_forwarding()),
+        &sink,
+        &event(EventCode::EV_KEY(EV_KEY::BTN_SOUTH), 1),
+    );
+
+    assert_eq!(callback.axis_events(), vec![]);
+    assert_eq!(
+        sink.events(),
+        vec![
+            (3, EV_ABS::ABS_X as u32, 50),
+            (1, EV_KEY::BTN_SOUTH as u32, 1)
+        ]
+    );
+}
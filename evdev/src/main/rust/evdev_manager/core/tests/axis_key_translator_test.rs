@@ -0,0 +1,259 @@
+//! Tests for translating axis movement into key events.
+use evdev::enums::{EventCode, EV_ABS, EV_KEY, EV_SYN};
+use evdev::{AbsInfo, InputEvent, TimeVal};
+use evdev_manager_core::android::android_codes::{
+    AMOTION_EVENT_AXIS_BRAKE, AMOTION_EVENT_AXIS_GAS, AMOTION_EVENT_AXIS_HAT_X,
+    AMOTION_EVENT_AXIS_LTRIGGER, AMOTION_EVENT_AXIS_X,
+};
+use evdev_manager_core::android::keylayout::key_layout_map::{
+    KeyLayoutAxisInfo, KeyLayoutAxisMode,
+};
+use evdev_manager_core::axis_key_mapping::AxisKeyMapping;
+use evdev_manager_core::axis_key_translator::{AxisKeyTranslation, AxisKeyTranslator};
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+const LOW_KEY: EV_KEY = EV_KEY::BTN_DPAD_LEFT;
+const HIGH_KEY: EV_KEY = EV_KEY::BTN_DPAD_RIGHT;
+
+fn translation(
+    abs_code: EV_ABS,
+    axis_info: KeyLayoutAxisInfo,
+    minimum: i32,
+    maximum: i32,
+    flat: i32,
+) -> AxisKeyTranslation {
+    AxisKeyTranslation {
+        mapping: AxisKeyMapping {
+            abs_code: abs_code as u32,
+            low_scan_code: Some(LOW_KEY as u32),
+            high_scan_code: Some(HIGH_KEY as u32),
+            press_threshold: 0.5,
+            release_threshold: 0.4,
+        },
+        axis_info,
+        abs_info: AbsInfo {
+            value: 0,
+            minimum,
+            maximum,
+            fuzz: 0,
+            flat,
+            resolution: 0,
+        },
+    }
+}
+
+fn normal_axis(axis: u32) -> KeyLayoutAxisInfo {
+    KeyLayoutAxisInfo {
+        mode: KeyLayoutAxisMode::Normal,
+        axis,
+        high_axis: None,
+        split_value: None,
+        flat_override: None,
+    }
+}
+
+fn translator(translation: AxisKeyTranslation) -> AxisKeyTranslator {
+    let mut translator = AxisKeyTranslator::new();
+    assert_eq!(translator.set_translations(vec![translation]), vec![]);
+    translator
+}
+
+fn abs(abs_code: EV_ABS, value: i32) -> InputEvent {
+    InputEvent::new(&TimeVal::new(1, 0), &EventCode::EV_ABS(abs_code), value)
+}
+
+fn key(key: EV_KEY, value: i32) -> InputEvent {
+    InputEvent::new(&TimeVal::new(1, 0), &EventCode::EV_KEY(key), value)
+}
+
+#[test]
+fn test_key_is_pressed_and_released_when_crossing_thresholds() {
+    let mut translator = translator(translation(
+        EV_ABS::ABS_X,
+        normal_axis(AMOTION_EVENT_AXIS_X),
+        0,
+        200,
+        0,
+    ));
+
+    assert_eq!(translator.on_event(&abs(EV_ABS::ABS_X, 140)), vec![]);
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_X, 150)),
+        vec![key(HIGH_KEY, 1)]
+    );
+    assert_eq!(translator.on_event(&abs(EV_ABS::ABS_X, 200)), vec![]);
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_X, 100)),
+        vec![key(HIGH_KEY, 0)]
+    );
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_X, 0)),
+        vec![key(LOW_KEY, 1)]
+    );
+}
+
+#[test]
+fn test_jitter_around_threshold_does_not_bounce_key() {
+    let mut translator = translator(translation(
+        EV_ABS::ABS_X,
+        normal_axis(AMOTION_EVENT_AXIS_X),
+        0,
+        200,
+        0,
+    ));
+
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_X, 151)),
+        vec![key(HIGH_KEY, 1)]
+    );
+
+    // Between the release threshold (0.4) and the press threshold (0.5).
+    for value in [149, 145, 151, 142, 150] {
+        assert_eq!(translator.on_event(&abs(EV_ABS::ABS_X, value)), vec![]);
+    }
+
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_X, 139)),
+        vec![key(HIGH_KEY, 0)]
+    );
+
+    for value in [141, 145, 149] {
+        assert_eq!(translator.on_event(&abs(EV_ABS::ABS_X, value)), vec![]);
+    }
+}
+
+#[test]
+fn test_hat_switch_releases_before_pressing_opposite_direction() {
+    let mut translator = translator(translation(
+        EV_ABS::ABS_HAT0X,
+        normal_axis(AMOTION_EVENT_AXIS_HAT_X),
+        -1,
+        1,
+        0,
+    ));
+
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_HAT0X, -1)),
+        vec![key(LOW_KEY, 1)]
+    );
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_HAT0X, 1)),
+        vec![key(LOW_KEY, 0), key(HIGH_KEY, 1)]
+    );
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_HAT0X, 0)),
+        vec![key(HIGH_KEY, 0)]
+    );
+}
+
+#[test]
+fn test_trigger_only_presses_high_key() {
+    let mut translator = translator(translation(
+        EV_ABS::ABS_Z,
+        normal_axis(AMOTION_EVENT_AXIS_LTRIGGER),
+        0,
+        255,
+        0,
+    ));
+
+    assert_eq!(translator.on_event(&abs(EV_ABS::ABS_Z, 0)), vec![]);
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_Z, 200)),
+        vec![key(HIGH_KEY, 1)]
+    );
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_Z, 0)),
+        vec![key(HIGH_KEY, 0)]
+    );
+}
+
+#[test]
+fn test_split_axis_presses_key_for_each_half() {
+    let axis_info = KeyLayoutAxisInfo {
+        mode: KeyLayoutAxisMode::Split,
+        axis: AMOTION_EVENT_AXIS_BRAKE,
+        high_axis: Some(AMOTION_EVENT_AXIS_GAS),
+        split_value: Some(50),
+        flat_override: None,
+    };
+    let mut translator = translator(translation(EV_ABS::ABS_Y, axis_info, 0, 250, 0));
+
+    // The low half is 0..50 so it is pressed past 25.
+    assert_eq!(translator.on_event(&abs(EV_ABS::ABS_Y, 30)), vec![]);
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_Y, 20)),
+        vec![key(LOW_KEY, 1)]
+    );
+
+    // The high half is 50..250 so it is pressed past 150.
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_Y, 140)),
+        vec![key(LOW_KEY, 0)]
+    );
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_Y, 150)),
+        vec![key(HIGH_KEY, 1)]
+    );
+}
+
+#[test]
+fn test_flat_region_does_not_press_key() {
+    let mut translator = translator(translation(
+        EV_ABS::ABS_X,
+        KeyLayoutAxisInfo {
+            flat_override: Some(60),
+            ..normal_axis(AMOTION_EVENT_AXIS_X)
+        },
+        0,
+        200,
+        0,
+    ));
+
+    // 0.55 from rest is past the press threshold but inside the flat region.
+    assert_eq!(translator.on_event(&abs(EV_ABS::ABS_X, 155)), vec![]);
+    assert_eq!(
+        translator.on_event(&abs(EV_ABS::ABS_X, 170)),
+        vec![key(HIGH_KEY, 1)]
+    );
+}
+
+#[test]
+fn test_other_events_are_ignored() {
+    let mut translator = translator(translation(
+        EV_ABS::ABS_X,
+        normal_axis(AMOTION_EVENT_AXIS_X),
+        0,
+        200,
+        0,
+    ));
+
+    assert_eq!(translator.on_event(&abs(EV_ABS::ABS_Y, 200)), vec![]);
+    assert_eq!(translator.on_event(&key(EV_KEY::BTN_SOUTH, 1)), vec![]);
+}
+
+#[test]
+fn test_replacing_translations_releases_pressed_keys() {
+    let mut translator = translator(translation(
+        EV_ABS::ABS_X,
+        normal_axis(AMOTION_EVENT_AXIS_X),
+        0,
+        200,
+        0,
+    ));
+
+    translator.on_event(&abs(EV_ABS::ABS_X, 0));
+
+    assert_eq!(
+        translator.set_translations(vec![]),
+        vec![
+            key(LOW_KEY, 0),
+            InputEvent::new(
+                &TimeVal::new(1, 0),
+                &EventCode::EV_SYN(EV_SYN::SYN_REPORT),
+                0
+            )
+        ]
+    );
+    assert_eq!(translator.on_event(&abs(EV_ABS::ABS_X, 200)), vec![]);
+}
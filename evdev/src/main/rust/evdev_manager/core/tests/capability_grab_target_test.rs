@@ -0,0 +1,73 @@
+//! Tests for matching devices to capability classes by the event codes they support.
+use evdev::enums::{EventCode, EV_KEY, EV_REL};
+use evdev_manager_core::capability_grab_target::DeviceCapabilityClass;
+use evdev_manager_core::device_capabilities::DeviceCapabilities;
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+/// The letter keys of a full keyboard.
+fn letter_keys() -> Vec<EventCode> {
+    (EV_KEY::KEY_Q as u32..=EV_KEY::KEY_P as u32)
+        .chain(EV_KEY::KEY_A as u32..=EV_KEY::KEY_L as u32)
+        .chain(EV_KEY::KEY_Z as u32..=EV_KEY::KEY_M as u32)
+        .map(|code| evdev::util::int_to_event_code(1, code))
+        .collect()
+}
+
+#[test]
+fn test_keyboard_with_every_letter_is_alphabetic() {
+    let capabilities = DeviceCapabilities::new(&letter_keys());
+
+    assert!(DeviceCapabilityClass::AlphabeticKeyboard.matches(&capabilities));
+}
+
+#[test]
+fn test_keyboard_missing_a_letter_is_not_alphabetic() {
+    let mut event_codes = letter_keys();
+    event_codes.retain(|code| *code != EventCode::EV_KEY(EV_KEY::KEY_Z));
+    let capabilities = DeviceCapabilities::new(&event_codes);
+
+    assert!(!DeviceCapabilityClass::AlphabeticKeyboard.matches(&capabilities));
+}
+
+#[test]
+fn test_gamepad_has_btn_south() {
+    let gamepad = DeviceCapabilities::new(&[EventCode::EV_KEY(EV_KEY::BTN_SOUTH)]);
+    let keyboard = DeviceCapabilities::new(&letter_keys());
+
+    assert!(DeviceCapabilityClass::Gamepad.matches(&gamepad));
+    assert!(!DeviceCapabilityClass::Gamepad.matches(&keyboard));
+}
+
+#[test]
+fn test_event_code_class_checks_type_and_code() {
+    let mouse = DeviceCapabilities::new(&[
+        EventCode::EV_REL(EV_REL::REL_X),
+        EventCode::EV_REL(EV_REL::REL_Y),
+    ]);
+    let class = DeviceCapabilityClass::EventCode(EventCode::EV_REL(EV_REL::REL_X));
+
+    assert!(class.matches(&mouse));
+    assert!(
+        !DeviceCapabilityClass::EventCode(EventCode::EV_KEY(EV_KEY::KEY_VOLUMEUP)).matches(&mouse)
+    );
+}
+
+#[test]
+fn test_capabilities_list_event_codes_in_order() {
+    let capabilities = DeviceCapabilities::new(&[
+        EventCode::EV_REL(EV_REL::REL_X),
+        EventCode::EV_KEY(EV_KEY::BTN_LEFT),
+        EventCode::EV_KEY(EV_KEY::KEY_A),
+        EventCode::EV_KEY(EV_KEY::KEY_A),
+    ]);
+
+    assert_eq!(
+        capabilities.event_codes(),
+        vec![
+            EventCode::EV_KEY(EV_KEY::KEY_A),
+            EventCode::EV_KEY(EV_KEY::BTN_LEFT),
+            EventCode::EV_REL(EV_REL::REL_X),
+        ]
+    );
+}
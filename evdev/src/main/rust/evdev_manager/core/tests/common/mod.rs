@@ -0,0 +1,156 @@
+//! Fakes that are shared by the integration tests.
+// Every test crate compiles this module but only uses some of the fakes.
+#![allow(dead_code)]
+
+use evdev::{AbsInfo, InputEvent};
+use evdev_manager_core::clock::Clock;
+use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
+use evdev_manager_core::event_loop::EvdevCallback;
+use evdev_manager_core::grabbed_device_handle::GrabbedDeviceHandle;
+use evdev_manager_core::input_event_sink::InputEventSink;
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Callback that records what it receives and consumes the key and axis events if
+/// configured to.
+#[derive(Default)]
+pub struct FakeCallback {
+    consume_events: bool,
+    consume_axis_events: bool,
+    events: Mutex<Vec<(usize, InputEvent)>>,
+    read_keys: Mutex<Vec<InputEvent>>,
+    axis_events: Mutex<Vec<(InputEvent, Option<i32>)>>,
+    fired_triggers: Mutex<Vec<(usize, u32)>>,
+    pub grabbed_devices: Mutex<Vec<Vec<GrabbedDeviceHandle>>>,
+    pub evdev_devices: Mutex<Vec<Vec<EvdevDeviceInfo>>>,
+}
+
+impl FakeCallback {
+    pub fn new(consume_events: bool, consume_axis_events: bool) -> Self {
+        Self {
+            consume_events,
+            consume_axis_events,
+            ..Self::default()
+        }
+    }
+
+    /// The key events with the id of the device they came from.
+    pub fn events(&self) -> Vec<(usize, InputEvent)> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// The key events that were read from the devices before they were processed.
+    pub fn read_keys(&self) -> Vec<InputEvent> {
+        self.read_keys.lock().unwrap().clone()
+    }
+
+    /// The axis events with the maximum of the axis range that was passed with them.
+    pub fn axis_events(&self) -> Vec<(InputEvent, Option<i32>)> {
+        self.axis_events.lock().unwrap().clone()
+    }
+
+    /// The ids of the key triggers that fired with the id of the device they fired on.
+    pub fn fired_triggers(&self) -> Vec<(usize, u32)> {
+        self.fired_triggers.lock().unwrap().clone()
+    }
+}
+
+impl EvdevCallback for FakeCallback {
+    fn on_evdev_event(
+        &self,
+        device_id: usize,
+        _device_identifier: &EvdevDeviceInfo,
+        event: &InputEvent,
+        _usage_code: Option<u32>,
+    ) -> bool {
+        self.events.lock().unwrap().push((device_id, event.clone()));
+        self.consume_events
+    }
+
+    fn on_evdev_key_read(
+        &self,
+        _device_id: usize,
+        _device_identifier: &EvdevDeviceInfo,
+        event: &InputEvent,
+        _usage_code: Option<u32>,
+    ) {
+        self.read_keys.lock().unwrap().push(event.clone());
+    }
+
+    fn on_evdev_axis_event(
+        &self,
+        _device_id: usize,
+        _device_identifier: &EvdevDeviceInfo,
+        event: &InputEvent,
+        abs_info: Option<&AbsInfo>,
+    ) -> bool {
+        self.axis_events
+            .lock()
+            .unwrap()
+            .push((event.clone(), abs_info.map(|info| info.maximum)));
+        self.consume_axis_events
+    }
+
+    fn on_key_trigger_fired(
+        &self,
+        device_id: usize,
+        _device_identifier: &EvdevDeviceInfo,
+        trigger_id: u32,
+    ) {
+        self.fired_triggers
+            .lock()
+            .unwrap()
+            .push((device_id, trigger_id));
+    }
+
+    fn on_grabbed_devices_changed(&self, grabbed_devices: Vec<GrabbedDeviceHandle>) {
+        self.grabbed_devices.lock().unwrap().push(grabbed_devices);
+    }
+
+    fn on_evdev_devices_changed(&self, devices: Vec<EvdevDeviceInfo>) {
+        self.evdev_devices.lock().unwrap().push(devices);
+    }
+}
+
+/// Sink that records the events written to it.
+#[derive(Default)]
+pub struct FakeSink {
+    events: Mutex<Vec<(u32, u32, i32)>>,
+}
+
+impl FakeSink {
+    pub fn events(&self) -> Vec<(u32, u32, i32)> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+impl InputEventSink for FakeSink {
+    fn write_event(&self, event_type: u32, code: u32, value: i32) -> io::Result<()> {
+        self.events.lock().unwrap().push((event_type, code, value));
+        Ok(())
+    }
+}
+
+/// Clock that only moves forward when the test advances it.
+pub struct FakeClock {
+    now: Mutex<Instant>,
+}
+
+impl FakeClock {
+    pub fn new() -> Self {
+        Self {
+            now: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
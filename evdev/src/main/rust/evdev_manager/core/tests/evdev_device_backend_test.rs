@@ -0,0 +1,167 @@
+//! Tests for finding and watching input devices in configurable device roots.
+use evdev_manager_core::device_backend::{DeviceBackend, EvdevDeviceBackend};
+use evdev_manager_core::evdev_devices_watcher::{EvdevDevicesWatcher, InotifyCallback};
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A temporary directory that is deleted when it is dropped.
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "evdev_device_backend_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+
+        fs::remove_dir_all(&path).ok();
+        fs::create_dir_all(&path).unwrap();
+
+        // Resolve the temporary directory in case it is a symlink itself.
+        Self {
+            path: fs::canonicalize(path).unwrap(),
+        }
+    }
+
+    /// Create an empty file that stands in for a device node.
+    fn add_node(&self, name: &str) -> PathBuf {
+        let path = self.path.join(name);
+        fs::write(&path, "").unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.path).ok();
+    }
+}
+
+fn sorted(mut paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths.sort();
+    paths
+}
+
+#[test]
+fn test_device_paths_in_root() {
+    let dir = TempDir::new("root");
+    let event0 = dir.add_node("event0");
+    let event1 = dir.add_node("event1");
+    fs::create_dir(dir.path.join("by-id")).unwrap();
+
+    let backend = EvdevDeviceBackend::new(vec![dir.path.clone()]);
+
+    // Directories are not devices.
+    assert_eq!(
+        sorted(backend.get_device_paths().unwrap()),
+        vec![event0, event1]
+    );
+}
+
+#[test]
+fn test_symlinks_resolve_to_device_nodes() {
+    let nodes = TempDir::new("symlink_nodes");
+    let aliases = TempDir::new("symlink_aliases");
+    let event0 = nodes.add_node("event0");
+    symlink(&event0, aliases.path.join("usb-Keyboard-event-kbd")).unwrap();
+
+    let backend = EvdevDeviceBackend::new(vec![aliases.path.clone()]);
+
+    assert_eq!(backend.get_device_paths().unwrap(), vec![event0]);
+}
+
+#[test]
+fn test_device_in_several_roots_is_returned_once() {
+    let nodes = TempDir::new("several_nodes");
+    let aliases = TempDir::new("several_aliases");
+    let event0 = nodes.add_node("event0");
+    let event1 = nodes.add_node("event1");
+    symlink(&event0, aliases.path.join("usb-Keyboard-event-kbd")).unwrap();
+
+    let backend = EvdevDeviceBackend::new(vec![nodes.path.clone(), aliases.path.clone()]);
+
+    assert_eq!(
+        sorted(backend.get_device_paths().unwrap()),
+        vec![event0, event1]
+    );
+}
+
+#[test]
+fn test_broken_symlinks_are_ignored() {
+    let aliases = TempDir::new("broken_aliases");
+    symlink(
+        Path::new("/nonexistent/event0"),
+        aliases.path.join("broken"),
+    )
+    .unwrap();
+
+    let backend = EvdevDeviceBackend::new(vec![aliases.path.clone()]);
+
+    assert_eq!(backend.get_device_paths().unwrap(), Vec::<PathBuf>::new());
+}
+
+#[test]
+fn test_missing_root_is_skipped() {
+    let nodes = TempDir::new("missing");
+    let event0 = nodes.add_node("event0");
+
+    let backend =
+        EvdevDeviceBackend::new(vec![nodes.path.clone(), nodes.path.join("does-not-exist")]);
+
+    assert_eq!(backend.get_device_paths().unwrap(), vec![event0]);
+    assert_eq!(
+        backend.get_device_roots(),
+        vec![nodes.path.clone(), nodes.path.join("does-not-exist")]
+    );
+}
+
+struct ChannelCallback {
+    sender: Mutex<mpsc::Sender<Vec<PathBuf>>>,
+}
+
+impl InotifyCallback for ChannelCallback {
+    fn on_inotify_dev_input(&self, paths: &[PathBuf]) {
+        self.sender.lock().unwrap().send(paths.to_vec()).ok();
+    }
+}
+
+#[test]
+fn test_watcher_notifies_changes_in_every_root() {
+    let first = TempDir::new("watch_first");
+    let second = TempDir::new("watch_second");
+    let (sender, receiver) = mpsc::channel();
+
+    let watcher = EvdevDevicesWatcher::new(vec![
+        first.path.clone(),
+        second.path.clone(),
+        first.path.join("does-not-exist"),
+    ]);
+    watcher
+        .start(Arc::new(ChannelCallback {
+            sender: Mutex::new(sender),
+        }))
+        .unwrap();
+
+    let event0 = first.add_node("event0");
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+        vec![event0]
+    );
+
+    let event1 = second.add_node("event1");
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+        vec![event1]
+    );
+
+    watcher.stop().unwrap();
+}
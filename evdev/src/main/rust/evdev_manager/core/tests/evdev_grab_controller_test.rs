@@ -0,0 +1,1300 @@
+//! Tests for grabbing, regrabbing and ungrabbing devices with an in-memory device backend.
+mod common;
+
+use common::FakeCallback;
+use evdev::enums::{EventCode, EventType, EV_KEY, EV_LED, EV_SYN};
+use evdev::{InputEvent, TimeVal};
+use evdev_manager_core::capability_grab_target::{CapabilityGrabTarget, DeviceCapabilityClass};
+use evdev_manager_core::device_backend::DeviceBackend;
+use evdev_manager_core::device_capabilities::DeviceCapabilities;
+use evdev_manager_core::device_name_matcher::DeviceNameMatcher;
+use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
+use evdev_manager_core::evdev_devices_watcher::InotifyCallback;
+use evdev_manager_core::evdev_grab_controller::EvdevGrabController;
+use evdev_manager_core::evemu_recording::EvemuRecording;
+use evdev_manager_core::grab_target::GrabTarget;
+use evdev_manager_core::in_memory_device_backend::{InMemoryDeviceBackend, InMemoryDeviceFailures};
+use evdev_manager_core::key_remap_table::{KeyRemapTable, ScanCodeRemap};
+use evdev_manager_core::virtual_device::VirtualDeviceDescription;
+use mio::{Events, Poll, Token};
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+struct Fixture {
+    poll: Poll,
+    backend: InMemoryDeviceBackend,
+    callback: Arc<FakeCallback>,
+    controller: EvdevGrabController,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let poll = Poll::new().unwrap();
+        let registry = Arc::new(poll.registry().try_clone().unwrap());
+        let backend = InMemoryDeviceBackend::new();
+        let callback = Arc::new(FakeCallback::default());
+
+        let controller = EvdevGrabController::with_backend(
+            registry,
+            callback.clone(),
+            Arc::new(backend.clone()),
+        );
+
+        Self {
+            poll,
+            backend,
+            callback,
+            controller,
+        }
+    }
+
+    /// Add a device that supports the event codes.
+    fn add_device_with_codes(&self, path: &str, name: &str, event_codes: &[EventCode]) {
+        self.backend.add_device(path, device_info(name));
+        self.backend.set_event_codes(Path::new(path), event_codes);
+    }
+
+    /// Simulate the devices watcher seeing a change to the device at the path.
+    fn hotplug(&self, path: &str) {
+        self.controller.on_inotify_dev_input(&[PathBuf::from(path)]);
+    }
+
+    fn grabbed_device_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+
+        self.controller.for_each_grabbed_device(|_, device| {
+            names.push(device.device_info.name.clone());
+        });
+
+        names
+    }
+
+    fn virtual_device_path(&self, virtual_device_id: usize) -> PathBuf {
+        self.controller
+            .with_virtual_device(virtual_device_id, |device| device.output.devnode())
+            .flatten()
+            .unwrap()
+    }
+
+    fn poll_tokens(&mut self) -> Vec<Token> {
+        let mut events = Events::with_capacity(16);
+        self.poll
+            .poll(&mut events, Some(Duration::from_millis(100)))
+            .unwrap();
+        events.iter().map(|event| event.token()).collect()
+    }
+}
+
+fn device_info(name: &str) -> EvdevDeviceInfo {
+    EvdevDeviceInfo {
+        name: name.to_string(),
+        bus: 0x0003,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 1,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    }
+}
+
+fn target(name: &str, extra_event_codes: Vec<EventCode>) -> GrabTarget {
+    let info = device_info(name);
+
+    GrabTarget {
+        name: DeviceNameMatcher::exact(&info.name),
+        bus: info.bus,
+        vendor: info.vendor,
+        product: info.product,
+        version: None,
+        phys_prefix: None,
+        uniq: None,
+        extra_event_codes,
+        forward_axes: false,
+    }
+}
+
+fn virtual_gamepad() -> VirtualDeviceDescription {
+    VirtualDeviceDescription {
+        name: "Virtual Gamepad".to_string(),
+        bus: 0x0006,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 1,
+        capabilities: DeviceCapabilities::new(&GAMEPAD_CODES),
+    }
+}
+
+fn capability_target(
+    class: DeviceCapabilityClass,
+    extra_event_codes: Vec<EventCode>,
+) -> CapabilityGrabTarget {
+    CapabilityGrabTarget {
+        class,
+        extra_event_codes,
+        forward_axes: false,
+    }
+}
+
+const GAMEPAD_CODES: [EventCode; 2] = [
+    EventCode::EV_KEY(EV_KEY::BTN_SOUTH),
+    EventCode::EV_KEY(EV_KEY::BTN_EAST),
+];
+
+fn path(path: &str) -> &Path {
+    Path::new(path)
+}
+
+#[test]
+fn test_grab_connected_target() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    fixture
+        .backend
+        .add_device("/dev/input/event1", device_info("Mouse"));
+
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    assert_eq!(handles.len(), 1);
+    assert_eq!(handles[0].device_info, device_info("Keyboard"));
+    assert!(fixture.backend.is_grabbed(path("/dev/input/event0")));
+    assert!(!fixture.backend.is_grabbed(path("/dev/input/event1")));
+    assert_eq!(
+        *fixture.callback.grabbed_devices.lock().unwrap(),
+        vec![handles]
+    );
+}
+
+#[test]
+fn test_target_that_is_not_connected_is_not_grabbed() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Mouse"));
+
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    assert_eq!(handles, vec![]);
+    assert!(!fixture.backend.is_grabbed(path("/dev/input/event0")));
+}
+
+#[test]
+fn test_ungrab_when_target_is_removed() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+    let output_path = fixture
+        .backend
+        .output_device_path(path("/dev/input/event0"))
+        .unwrap();
+
+    let handles = fixture.controller.set_grab_targets(vec![]);
+
+    assert_eq!(handles, vec![]);
+    assert!(!fixture.backend.is_grabbed(path("/dev/input/event0")));
+    assert!(!fixture.backend.has_device(&output_path));
+    assert_eq!(
+        fixture.controller.get_real_devices().unwrap(),
+        vec![device_info("Keyboard")]
+    );
+}
+
+#[test]
+fn test_extra_event_codes_are_enabled() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+
+    let extra_event_codes = vec![EventCode::EV_KEY(EV_KEY::KEY_VOLUMEUP)];
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", extra_event_codes.clone())]);
+
+    assert_eq!(
+        fixture
+            .backend
+            .enabled_event_codes(path("/dev/input/event0")),
+        extra_event_codes
+    );
+}
+
+#[test]
+fn test_grabbed_device_capabilities_do_not_include_extra_event_codes() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes(
+        "/dev/input/event0",
+        "Keyboard",
+        &[EventCode::EV_KEY(EV_KEY::KEY_A)],
+    );
+
+    let handles = fixture.controller.set_grab_targets(vec![target(
+        "Keyboard",
+        vec![EventCode::EV_KEY(EV_KEY::KEY_VOLUMEUP)],
+    )]);
+
+    fixture
+        .controller
+        .with_grabbed_device(handles[0].id, |device| {
+            assert!(device
+                .capabilities
+                .has_event_code(&EventCode::EV_KEY(EV_KEY::KEY_A)));
+            assert!(!device
+                .capabilities
+                .has_event_code(&EventCode::EV_KEY(EV_KEY::KEY_VOLUMEUP)));
+        })
+        .unwrap();
+}
+
+#[test]
+fn test_output_capabilities_include_extra_event_codes() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes(
+        "/dev/input/event0",
+        "Keyboard",
+        &[EventCode::EV_KEY(EV_KEY::KEY_A)],
+    );
+
+    let handles = fixture.controller.set_grab_targets(vec![target(
+        "Keyboard",
+        vec![EventCode::EV_KEY(EV_KEY::KEY_VOLUMEUP)],
+    )]);
+
+    let output_capabilities = fixture
+        .controller
+        .with_grabbed_device(handles[0].id, |device| device.output_capabilities())
+        .unwrap();
+
+    assert!(output_capabilities.has_event_code(&EventCode::EV_KEY(EV_KEY::KEY_A)));
+    assert!(output_capabilities.has_event_code(&EventCode::EV_KEY(EV_KEY::KEY_VOLUMEUP)));
+}
+
+#[test]
+fn test_changing_remap_table_releases_held_remapped_key() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes(
+        "/dev/input/event0",
+        "Keyboard",
+        &[EventCode::EV_KEY(EV_KEY::KEY_CAPSLOCK)],
+    );
+
+    let handles = fixture.controller.set_grab_targets(vec![target(
+        "Keyboard",
+        vec![EventCode::EV_KEY(EV_KEY::KEY_ESC)],
+    )]);
+
+    fixture
+        .controller
+        .with_grabbed_device(handles[0].id, |device| {
+            device
+                .set_remap_table(KeyRemapTable::new(HashMap::from([(
+                    EV_KEY::KEY_CAPSLOCK as u32,
+                    ScanCodeRemap::Remap(EV_KEY::KEY_ESC as u32),
+                )])))
+                .unwrap();
+
+            let press = InputEvent::new(
+                &TimeVal::new(0, 0),
+                &EventCode::EV_KEY(EV_KEY::KEY_CAPSLOCK),
+                1,
+            );
+            device.key_remapper.lock().unwrap().apply(&press);
+
+            device.set_remap_table(KeyRemapTable::default()).unwrap();
+        })
+        .unwrap();
+
+    assert_eq!(
+        fixture.backend.output_events(path("/dev/input/event0")),
+        vec![
+            (EventType::EV_KEY as u32, EV_KEY::KEY_ESC as u32, 0),
+            (EventType::EV_SYN as u32, EV_SYN::SYN_REPORT as u32, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_recording_does_not_include_extra_event_codes() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes(
+        "/dev/input/event0",
+        "Keyboard",
+        &[EventCode::EV_KEY(EV_KEY::KEY_A)],
+    );
+
+    let handles = fixture.controller.set_grab_targets(vec![target(
+        "Keyboard",
+        vec![EventCode::EV_KEY(EV_KEY::KEY_VOLUMEUP)],
+    )]);
+
+    let recording_path = std::env::temp_dir().join(format!(
+        "evdev_grab_controller_test_recording_{}.evemu",
+        std::process::id()
+    ));
+
+    fixture
+        .controller
+        .with_grabbed_device(handles[0].id, |device| {
+            device.start_recording(&recording_path).unwrap();
+            device.stop_recording().unwrap();
+        })
+        .unwrap();
+
+    let contents = std::fs::read_to_string(&recording_path).unwrap();
+    std::fs::remove_file(&recording_path).unwrap();
+    let recording = EvemuRecording::parse(&contents).unwrap();
+
+    assert_eq!(recording.device.name, "Keyboard");
+    assert_eq!(
+        recording.device.event_codes,
+        BTreeMap::from([(
+            EventType::EV_KEY as u32,
+            BTreeSet::from([EV_KEY::KEY_A as u32])
+        )])
+    );
+}
+
+#[test]
+fn test_regrab_when_extra_event_codes_change() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+    let first_output_path = fixture
+        .backend
+        .output_device_path(path("/dev/input/event0"))
+        .unwrap();
+
+    let extra_event_codes = vec![EventCode::EV_KEY(EV_KEY::KEY_VOLUMEDOWN)];
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", extra_event_codes.clone())]);
+
+    assert_eq!(handles.len(), 1);
+    assert!(fixture.backend.is_grabbed(path("/dev/input/event0")));
+    assert_eq!(
+        fixture
+            .backend
+            .enabled_event_codes(path("/dev/input/event0")),
+        extra_event_codes
+    );
+
+    let second_output_path = fixture
+        .backend
+        .output_device_path(path("/dev/input/event0"))
+        .unwrap();
+    assert_ne!(first_output_path, second_output_path);
+    assert!(!fixture.backend.has_device(&first_output_path));
+}
+
+#[test]
+fn test_setting_same_targets_keeps_device_grabbed() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+
+    let first_handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+    let output_path = fixture
+        .backend
+        .output_device_path(path("/dev/input/event0"));
+
+    let second_handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    assert_eq!(first_handles, second_handles);
+    assert_eq!(
+        fixture
+            .backend
+            .output_device_path(path("/dev/input/event0")),
+        output_path
+    );
+}
+
+#[test]
+fn test_hotplugged_target_is_grabbed() {
+    let fixture = Fixture::new();
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    fixture.hotplug("/dev/input/event0");
+
+    assert!(fixture.backend.is_grabbed(path("/dev/input/event0")));
+    assert_eq!(fixture.grabbed_device_names(), vec!["Keyboard"]);
+    assert_eq!(
+        fixture.callback.evdev_devices.lock().unwrap().last(),
+        Some(&vec![device_info("Keyboard")])
+    );
+}
+
+#[test]
+fn test_unplugged_device_is_ungrabbed() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    fixture.backend.remove_device(path("/dev/input/event0"));
+    fixture.hotplug("/dev/input/event0");
+
+    assert_eq!(fixture.grabbed_device_names(), Vec::<String>::new());
+    assert_eq!(
+        fixture.callback.grabbed_devices.lock().unwrap().last(),
+        Some(&vec![])
+    );
+    assert_eq!(
+        fixture.callback.evdev_devices.lock().unwrap().last(),
+        Some(&vec![])
+    );
+}
+
+#[test]
+fn test_device_replugged_at_same_path_is_regrabbed() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![]), target("Mouse", vec![])]);
+
+    // A different device is plugged in and given the path of the unplugged keyboard.
+    fixture.backend.remove_device(path("/dev/input/event0"));
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Mouse"));
+    fixture.hotplug("/dev/input/event0");
+
+    assert_eq!(fixture.grabbed_device_names(), vec!["Mouse"]);
+    assert!(fixture.backend.is_grabbed(path("/dev/input/event0")));
+}
+
+#[test]
+fn test_own_output_devices_are_not_grabbed_or_reported() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    let output_path = fixture
+        .backend
+        .output_device_path(path("/dev/input/event0"))
+        .unwrap();
+    fixture.hotplug(output_path.to_str().unwrap());
+
+    // The output device has the same name and ids as the grabbed device.
+    assert!(!fixture.backend.is_grabbed(&output_path));
+    assert_eq!(fixture.grabbed_device_names(), vec!["Keyboard"]);
+    assert_eq!(
+        fixture.controller.get_real_devices().unwrap(),
+        vec![device_info("Keyboard")]
+    );
+    // Events for the output device are ignored.
+    assert_eq!(fixture.callback.grabbed_devices.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_device_that_fails_to_open_is_not_grabbed() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    fixture.backend.set_failures(
+        path("/dev/input/event0"),
+        InMemoryDeviceFailures {
+            open: true,
+            ..Default::default()
+        },
+    );
+
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    assert_eq!(handles, vec![]);
+}
+
+#[test]
+fn test_device_that_fails_to_grab_is_not_grabbed() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    fixture
+        .backend
+        .add_device("/dev/input/event1", device_info("Mouse"));
+    fixture.backend.set_failures(
+        path("/dev/input/event0"),
+        InMemoryDeviceFailures {
+            grab: true,
+            ..Default::default()
+        },
+    );
+
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![]), target("Mouse", vec![])]);
+
+    assert_eq!(handles.len(), 1);
+    assert_eq!(handles[0].device_info, device_info("Mouse"));
+    assert!(!fixture.backend.is_grabbed(path("/dev/input/event0")));
+}
+
+#[test]
+fn test_failure_to_create_output_device_releases_grab() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    fixture.backend.set_failures(
+        path("/dev/input/event0"),
+        InMemoryDeviceFailures {
+            create_output: true,
+            ..Default::default()
+        },
+    );
+
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    assert_eq!(handles, vec![]);
+    assert!(!fixture.backend.is_grabbed(path("/dev/input/event0")));
+}
+
+#[test]
+fn test_device_is_grabbed_after_failure_is_resolved() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    fixture.backend.set_failures(
+        path("/dev/input/event0"),
+        InMemoryDeviceFailures {
+            open: true,
+            ..Default::default()
+        },
+    );
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    fixture
+        .backend
+        .set_failures(path("/dev/input/event0"), InMemoryDeviceFailures::default());
+    fixture.hotplug("/dev/input/event0");
+
+    assert_eq!(fixture.grabbed_device_names(), vec!["Keyboard"]);
+}
+
+#[test]
+fn test_grabbed_device_is_registered_for_polling() {
+    let mut fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    fixture.backend.push_event(
+        path("/dev/input/event0"),
+        EventCode::EV_KEY(EV_KEY::KEY_A),
+        1,
+    );
+
+    assert_eq!(fixture.poll_tokens(), vec![Token(handles[0].id)]);
+
+    let event = fixture
+        .controller
+        .with_grabbed_device(handles[0].id, |device| {
+            let mut source = device.source.lock().unwrap();
+            let event = source.next_event();
+            assert_eq!(source.next_event(), None);
+            event
+        })
+        .unwrap();
+
+    assert_eq!(
+        event.map(|event| event.event_code),
+        Some(EventCode::EV_KEY(EV_KEY::KEY_A))
+    );
+}
+
+#[test]
+fn test_events_are_written_to_output_device() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    fixture
+        .controller
+        .with_grabbed_device(handles[0].id, |device| {
+            device
+                .output
+                .write_event(EventType::EV_KEY as u32, EV_KEY::KEY_B as u32, 1)
+                .unwrap();
+            device
+                .output
+                .write_event(EventType::EV_SYN as u32, EV_SYN::SYN_REPORT as u32, 0)
+                .unwrap();
+        });
+
+    assert_eq!(
+        fixture.backend.output_events(path("/dev/input/event0")),
+        vec![
+            (EventType::EV_KEY as u32, EV_KEY::KEY_B as u32, 1),
+            (EventType::EV_SYN as u32, EV_SYN::SYN_REPORT as u32, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_identical_devices_are_all_grabbed() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Gamepad"));
+    fixture
+        .backend
+        .add_device("/dev/input/event1", device_info("Gamepad"));
+
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Gamepad", vec![])]);
+
+    assert_eq!(
+        handles
+            .iter()
+            .map(|handle| handle.device_path.clone())
+            .collect::<Vec<_>>(),
+        vec![
+            PathBuf::from("/dev/input/event0"),
+            PathBuf::from("/dev/input/event1")
+        ]
+    );
+    assert_ne!(handles[0].id, handles[1].id);
+    assert!(fixture.backend.is_grabbed(path("/dev/input/event0")));
+    assert!(fixture.backend.is_grabbed(path("/dev/input/event1")));
+}
+
+#[test]
+fn test_unplugging_one_identical_device_keeps_the_other_grabbed() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Gamepad"));
+    fixture
+        .backend
+        .add_device("/dev/input/event1", device_info("Gamepad"));
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Gamepad", vec![])]);
+
+    fixture.backend.remove_device(path("/dev/input/event0"));
+    fixture.hotplug("/dev/input/event0");
+
+    let remaining_handles = fixture
+        .callback
+        .grabbed_devices
+        .lock()
+        .unwrap()
+        .last()
+        .cloned();
+    assert_eq!(remaining_handles, Some(vec![handles[1].clone()]));
+}
+
+#[test]
+fn test_identical_device_plugged_in_later_is_grabbed() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Remote"));
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Remote", vec![])]);
+
+    fixture
+        .backend
+        .add_device("/dev/input/event1", device_info("Remote"));
+    fixture.hotplug("/dev/input/event1");
+
+    let latest_handles = fixture
+        .callback
+        .grabbed_devices
+        .lock()
+        .unwrap()
+        .last()
+        .cloned()
+        .unwrap();
+
+    assert_eq!(latest_handles.len(), 2);
+    // The device that was already grabbed keeps its ID.
+    assert_eq!(latest_handles[0], handles[0]);
+    assert_eq!(
+        latest_handles[1].device_path,
+        PathBuf::from("/dev/input/event1")
+    );
+}
+
+#[test]
+fn test_events_from_identical_devices_are_told_apart() {
+    let mut fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Gamepad"));
+    fixture
+        .backend
+        .add_device("/dev/input/event1", device_info("Gamepad"));
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Gamepad", vec![])]);
+
+    fixture.backend.push_event(
+        path("/dev/input/event1"),
+        EventCode::EV_KEY(EV_KEY::BTN_SOUTH),
+        1,
+    );
+
+    assert_eq!(fixture.poll_tokens(), vec![Token(handles[1].id)]);
+}
+
+#[test]
+fn test_identical_devices_are_told_apart_by_uniq() {
+    let fixture = Fixture::new();
+    let first = EvdevDeviceInfo {
+        uniq: "aa:bb:cc:dd:ee:01".to_string(),
+        ..device_info("Remote")
+    };
+    let second = EvdevDeviceInfo {
+        uniq: "aa:bb:cc:dd:ee:02".to_string(),
+        ..device_info("Remote")
+    };
+    fixture
+        .backend
+        .add_device("/dev/input/event0", first.clone());
+    fixture
+        .backend
+        .add_device("/dev/input/event1", second.clone());
+
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Remote", vec![])]);
+
+    assert_eq!(
+        handles
+            .iter()
+            .map(|handle| handle.device_info.clone())
+            .collect::<Vec<_>>(),
+        vec![first.clone(), second.clone()]
+    );
+    assert_eq!(
+        fixture.controller.get_real_devices().unwrap(),
+        vec![first, second]
+    );
+}
+
+#[test]
+fn test_only_device_with_matching_uniq_is_grabbed() {
+    let fixture = Fixture::new();
+    let first = EvdevDeviceInfo {
+        uniq: "aa:bb:cc:dd:ee:01".to_string(),
+        ..device_info("Remote")
+    };
+    let second = EvdevDeviceInfo {
+        uniq: "aa:bb:cc:dd:ee:02".to_string(),
+        ..device_info("Remote")
+    };
+    fixture.backend.add_device("/dev/input/event0", first);
+    fixture
+        .backend
+        .add_device("/dev/input/event1", second.clone());
+
+    let handles = fixture.controller.set_grab_targets(vec![GrabTarget {
+        uniq: Some("aa:bb:cc:dd:ee:02".to_string()),
+        ..target("Remote", vec![])
+    }]);
+
+    assert_eq!(handles.len(), 1);
+    assert_eq!(handles[0].device_info, second);
+}
+
+#[test]
+fn test_renamed_device_stays_grabbed_with_wildcard_name() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("BT Remote v1.2"));
+    fixture.controller.set_grab_targets(vec![GrabTarget {
+        name: DeviceNameMatcher::wildcard("BT Remote*"),
+        ..target("", vec![])
+    }]);
+
+    // The remote reconnects with a different name suffix.
+    fixture.backend.remove_device(path("/dev/input/event0"));
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("BT Remote v1.3"));
+    fixture.hotplug("/dev/input/event0");
+
+    assert_eq!(fixture.grabbed_device_names(), vec!["BT Remote v1.3"]);
+}
+
+#[test]
+fn test_capability_class_grabs_every_matching_device() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes("/dev/input/event0", "Gamepad 1", &GAMEPAD_CODES);
+    fixture.add_device_with_codes(
+        "/dev/input/event1",
+        "Mouse",
+        &[EventCode::EV_KEY(EV_KEY::BTN_LEFT)],
+    );
+    fixture.add_device_with_codes("/dev/input/event2", "Gamepad 2", &GAMEPAD_CODES);
+
+    let handles = fixture
+        .controller
+        .set_capability_grab_targets(vec![capability_target(
+            DeviceCapabilityClass::Gamepad,
+            vec![],
+        )]);
+
+    assert_eq!(handles.len(), 2);
+    assert_eq!(
+        fixture.grabbed_device_names(),
+        vec!["Gamepad 1", "Gamepad 2"]
+    );
+    assert!(!fixture.backend.is_grabbed(path("/dev/input/event1")));
+}
+
+#[test]
+fn test_device_with_event_code_is_grabbed() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes(
+        "/dev/input/event0",
+        "Volume Buttons",
+        &[
+            EventCode::EV_KEY(EV_KEY::KEY_VOLUMEUP),
+            EventCode::EV_KEY(EV_KEY::KEY_VOLUMEDOWN),
+        ],
+    );
+    fixture.add_device_with_codes(
+        "/dev/input/event1",
+        "Power Button",
+        &[EventCode::EV_KEY(EV_KEY::KEY_POWER)],
+    );
+
+    fixture
+        .controller
+        .set_capability_grab_targets(vec![capability_target(
+            DeviceCapabilityClass::EventCode(EventCode::EV_KEY(EV_KEY::KEY_VOLUMEUP)),
+            vec![],
+        )]);
+
+    assert_eq!(fixture.grabbed_device_names(), vec!["Volume Buttons"]);
+}
+
+#[test]
+fn test_device_in_capability_class_plugged_in_later_is_grabbed() {
+    let fixture = Fixture::new();
+    fixture
+        .controller
+        .set_capability_grab_targets(vec![capability_target(
+            DeviceCapabilityClass::Gamepad,
+            vec![],
+        )]);
+
+    fixture.add_device_with_codes("/dev/input/event0", "Gamepad", &GAMEPAD_CODES);
+    fixture.hotplug("/dev/input/event0");
+
+    assert_eq!(fixture.grabbed_device_names(), vec!["Gamepad"]);
+    assert!(fixture.backend.is_grabbed(path("/dev/input/event0")));
+}
+
+#[test]
+fn test_output_device_of_capability_class_device_is_not_grabbed() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes("/dev/input/event0", "Gamepad", &GAMEPAD_CODES);
+    fixture
+        .controller
+        .set_capability_grab_targets(vec![capability_target(
+            DeviceCapabilityClass::Gamepad,
+            vec![],
+        )]);
+
+    // The output device has the same capabilities as the gamepad.
+    let output_path = fixture
+        .backend
+        .output_device_path(path("/dev/input/event0"))
+        .unwrap();
+    fixture.hotplug(output_path.to_str().unwrap());
+
+    assert_eq!(fixture.grabbed_device_names(), vec!["Gamepad"]);
+}
+
+#[test]
+fn test_grab_target_takes_precedence_over_capability_class() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes("/dev/input/event0", "Gamepad", &GAMEPAD_CODES);
+    let extra_codes = vec![EventCode::EV_KEY(EV_KEY::KEY_A)];
+
+    fixture
+        .controller
+        .set_capability_grab_targets(vec![capability_target(
+            DeviceCapabilityClass::Gamepad,
+            vec![],
+        )]);
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Gamepad", extra_codes.clone())]);
+
+    assert_eq!(
+        fixture
+            .backend
+            .enabled_event_codes(path("/dev/input/event0")),
+        extra_codes
+    );
+
+    // The gamepad is still in the class when its own target is removed.
+    fixture.controller.set_grab_targets(vec![]);
+
+    assert_eq!(fixture.grabbed_device_names(), vec!["Gamepad"]);
+    assert_eq!(
+        fixture
+            .backend
+            .enabled_event_codes(path("/dev/input/event0")),
+        vec![]
+    );
+}
+
+#[test]
+fn test_ungrab_when_capability_class_is_removed() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes("/dev/input/event0", "Gamepad", &GAMEPAD_CODES);
+    fixture
+        .controller
+        .set_capability_grab_targets(vec![capability_target(
+            DeviceCapabilityClass::Gamepad,
+            vec![],
+        )]);
+
+    let handles = fixture.controller.set_capability_grab_targets(vec![]);
+
+    assert_eq!(handles, vec![]);
+    assert!(!fixture.backend.is_grabbed(path("/dev/input/event0")));
+}
+
+#[test]
+fn test_capabilities_of_device_that_is_not_grabbed() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes(
+        "/dev/input/event0",
+        "Keyboard",
+        &[
+            EventCode::EV_KEY(EV_KEY::KEY_A),
+            EventCode::EV_LED(EV_LED::LED_CAPSL),
+            EventCode::EV_LED(EV_LED::LED_NUML),
+        ],
+    );
+
+    let capabilities = fixture
+        .controller
+        .get_device_capabilities(path("/dev/input/event0"))
+        .unwrap();
+
+    assert_eq!(
+        capabilities.event_types(),
+        vec![EventType::EV_KEY as u32, EventType::EV_LED as u32]
+    );
+    assert_eq!(
+        capabilities.leds(),
+        vec![EV_LED::LED_NUML as u32, EV_LED::LED_CAPSL as u32]
+    );
+    assert!(!fixture.backend.is_grabbed(path("/dev/input/event0")));
+}
+
+#[test]
+fn test_capabilities_of_grabbed_device() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes("/dev/input/event0", "Gamepad", &GAMEPAD_CODES);
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Gamepad", vec![])]);
+
+    let capabilities = fixture
+        .controller
+        .get_device_capabilities(path("/dev/input/event0"))
+        .unwrap();
+
+    assert_eq!(capabilities.event_codes(), GAMEPAD_CODES.to_vec());
+}
+
+#[test]
+fn test_capabilities_of_output_device_are_not_returned() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes("/dev/input/event0", "Gamepad", &GAMEPAD_CODES);
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Gamepad", vec![])]);
+    let output_path = fixture
+        .backend
+        .output_device_path(path("/dev/input/event0"))
+        .unwrap();
+
+    assert!(fixture
+        .controller
+        .get_device_capabilities(&output_path)
+        .is_err());
+    assert!(fixture
+        .controller
+        .get_device_capabilities(path("/dev/input/event9"))
+        .is_err());
+}
+
+#[test]
+fn test_capabilities_of_device_symlink() {
+    let fixture = Fixture::new();
+
+    // The device node and its symlink must exist to be resolved.
+    let dir = std::env::temp_dir().join(format!(
+        "evdev_grab_controller_test_symlink_{}",
+        std::process::id()
+    ));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(dir.join("by-id")).unwrap();
+    let dir = std::fs::canonicalize(dir).unwrap();
+
+    let device_path = dir.join("event0");
+    let symlink_path = dir.join("by-id").join("usb-Gamepad-event-joystick");
+    std::fs::write(&device_path, "").unwrap();
+    std::os::unix::fs::symlink(&device_path, &symlink_path).unwrap();
+
+    fixture.add_device_with_codes(device_path.to_str().unwrap(), "Gamepad", &GAMEPAD_CODES);
+
+    let capabilities = fixture.controller.get_device_capabilities(&symlink_path);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(capabilities.unwrap().event_codes(), GAMEPAD_CODES.to_vec());
+}
+
+#[test]
+fn test_virtual_device_is_created_with_description() {
+    let fixture = Fixture::new();
+
+    let virtual_device_id = fixture
+        .controller
+        .create_virtual_device(virtual_gamepad())
+        .unwrap();
+    let virtual_device_path = fixture.virtual_device_path(virtual_device_id);
+
+    assert!(fixture.backend.has_device(&virtual_device_path));
+    assert_eq!(
+        fixture
+            .backend
+            .get_device_info(&virtual_device_path)
+            .unwrap(),
+        virtual_gamepad().device_info()
+    );
+    assert_eq!(
+        fixture
+            .backend
+            .get_device_capabilities(&virtual_device_path)
+            .unwrap()
+            .event_codes(),
+        GAMEPAD_CODES.to_vec()
+    );
+}
+
+#[test]
+fn test_events_are_written_to_virtual_device() {
+    let fixture = Fixture::new();
+    let virtual_device_id = fixture
+        .controller
+        .create_virtual_device(virtual_gamepad())
+        .unwrap();
+
+    fixture
+        .controller
+        .with_virtual_device(virtual_device_id, |device| {
+            device
+                .output
+                .write_event(EventType::EV_KEY as u32, EV_KEY::BTN_SOUTH as u32, 1)
+        })
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        fixture
+            .backend
+            .written_events(&fixture.virtual_device_path(virtual_device_id)),
+        vec![(EventType::EV_KEY as u32, EV_KEY::BTN_SOUTH as u32, 1)]
+    );
+}
+
+#[test]
+fn test_virtual_device_is_not_grabbed_or_reported() {
+    let fixture = Fixture::new();
+    fixture
+        .controller
+        .set_capability_grab_targets(vec![capability_target(
+            DeviceCapabilityClass::Gamepad,
+            vec![],
+        )]);
+
+    let virtual_device_id = fixture
+        .controller
+        .create_virtual_device(virtual_gamepad())
+        .unwrap();
+    let virtual_device_path = fixture.virtual_device_path(virtual_device_id);
+    fixture.hotplug(virtual_device_path.to_str().unwrap());
+
+    assert!(fixture.grabbed_device_names().is_empty());
+    assert!(!fixture.backend.is_grabbed(&virtual_device_path));
+    assert_eq!(fixture.controller.get_real_devices().unwrap(), vec![]);
+    assert!(fixture
+        .controller
+        .get_device_capabilities(&virtual_device_path)
+        .is_err());
+    // Events for the virtual device are ignored.
+    assert_eq!(fixture.callback.grabbed_devices.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_virtual_device_works_without_grabbed_devices() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes("/dev/input/event0", "Gamepad", &GAMEPAD_CODES);
+
+    let virtual_device_id = fixture
+        .controller
+        .create_virtual_device(virtual_gamepad())
+        .unwrap();
+
+    // Grabbing and ungrabbing devices does not affect the virtual device.
+    fixture
+        .controller
+        .set_grab_targets(vec![target("Gamepad", vec![])]);
+    fixture.controller.set_grab_targets(vec![]);
+
+    assert!(fixture
+        .backend
+        .has_device(&fixture.virtual_device_path(virtual_device_id)));
+}
+
+#[test]
+fn test_destroy_virtual_device() {
+    let fixture = Fixture::new();
+    let virtual_device_id = fixture
+        .controller
+        .create_virtual_device(virtual_gamepad())
+        .unwrap();
+    let virtual_device_path = fixture.virtual_device_path(virtual_device_id);
+
+    assert!(fixture.controller.destroy_virtual_device(virtual_device_id));
+
+    assert!(!fixture.backend.has_device(&virtual_device_path));
+    assert!(fixture
+        .controller
+        .with_virtual_device(virtual_device_id, |_| ())
+        .is_none());
+    assert!(!fixture.controller.destroy_virtual_device(virtual_device_id));
+}
+
+#[test]
+fn test_virtual_device_ids_are_separate_from_grabbed_device_ids() {
+    let fixture = Fixture::new();
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info("Keyboard"));
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target("Keyboard", vec![])]);
+
+    let virtual_device_id = fixture
+        .controller
+        .create_virtual_device(virtual_gamepad())
+        .unwrap();
+
+    assert_eq!(handles[0].id, 0);
+    assert_eq!(virtual_device_id, 0);
+    assert!(fixture.controller.destroy_virtual_device(virtual_device_id));
+    assert_eq!(fixture.grabbed_device_names(), vec!["Keyboard"]);
+}
+
+#[test]
+fn test_failure_to_create_virtual_device() {
+    let fixture = Fixture::new();
+    fixture.backend.set_virtual_device_failure(true);
+
+    assert!(fixture
+        .controller
+        .create_virtual_device(virtual_gamepad())
+        .is_err());
+    assert!(fixture.controller.with_virtual_device(0, |_| ()).is_none());
+}
+
+#[test]
+fn test_setting_targets_while_devices_change_does_not_deadlock() {
+    let fixture = Fixture::new();
+    fixture.add_device_with_codes("/dev/input/event0", "Gamepad", &GAMEPAD_CODES);
+    let controller = &fixture.controller;
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for _ in 0..200 {
+                controller.set_grab_targets(vec![target("Gamepad", vec![])]);
+            }
+        });
+
+        scope.spawn(|| {
+            for _ in 0..200 {
+                controller.set_capability_grab_targets(vec![capability_target(
+                    DeviceCapabilityClass::Gamepad,
+                    vec![],
+                )]);
+            }
+        });
+
+        scope.spawn(|| {
+            for _ in 0..200 {
+                controller.on_inotify_dev_input(&[PathBuf::from("/dev/input/event0")]);
+            }
+        });
+    });
+
+    assert_eq!(fixture.grabbed_device_names(), vec!["Gamepad"]);
+}
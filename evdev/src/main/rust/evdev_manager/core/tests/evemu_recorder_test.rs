@@ -0,0 +1,187 @@
+//! Tests for writing devices and events in the evemu-record format.
+use evdev::enums::{EventCode, EventType, InputProp, EV_ABS, EV_KEY, EV_SYN};
+use evdev::{AbsInfo, InputEvent, TimeVal};
+use evdev_manager_core::evemu_device::EvemuDevice;
+use evdev_manager_core::evemu_recorder::EvemuRecorder;
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::collections::{BTreeMap, BTreeSet};
+
+fn keyboard() -> EvemuDevice {
+    EvemuDevice {
+        name: "Test Remote".to_string(),
+        bus: 0x0005,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 0x0001,
+        properties: BTreeSet::new(),
+        event_codes: BTreeMap::from([(
+            EventType::EV_KEY as u32,
+            BTreeSet::from([EV_KEY::KEY_ESC as u32, EV_KEY::KEY_A as u32]),
+        )]),
+        abs_info: BTreeMap::new(),
+    }
+}
+
+fn record(device: &EvemuDevice, events: &[InputEvent]) -> Vec<String> {
+    let mut recorder = EvemuRecorder::new(Vec::new(), device).unwrap();
+
+    for event in events {
+        recorder.write_event(event).unwrap();
+    }
+
+    let bytes = recorder.finish().unwrap();
+    String::from_utf8(bytes)
+        .unwrap()
+        .lines()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn lines_with_prefix(lines: &[String], prefix: &str) -> Vec<String> {
+    lines
+        .iter()
+        .filter(|line| line.starts_with(prefix))
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn test_header() {
+    let lines = record(&keyboard(), &[]);
+
+    assert_eq!(lines[0], "# EVEMU 1.3");
+    assert_eq!(lines_with_prefix(&lines, "N: "), vec!["N: Test Remote"]);
+    assert_eq!(
+        lines_with_prefix(&lines, "I: "),
+        vec!["I: 0005 1234 5678 0001"]
+    );
+    assert_eq!(
+        lines_with_prefix(&lines, "P: "),
+        vec!["P: 00 00 00 00 00 00 00 00"]
+    );
+    assert_eq!(lines_with_prefix(&lines, "A: "), Vec::<String>::new());
+}
+
+#[test]
+fn test_event_type_and_code_masks() {
+    let lines = record(&keyboard(), &[]);
+
+    // EV_SYN and EV_KEY are supported.
+    assert_eq!(
+        lines_with_prefix(&lines, "B: 00 "),
+        vec!["B: 00 03 00 00 00 00 00 00 00"]
+    );
+
+    // KEY_MAX is 0x2ff so there are 96 bytes on 12 lines. KEY_ESC is 1 and KEY_A is 30.
+    let key_lines = lines_with_prefix(&lines, "B: 01 ");
+    assert_eq!(key_lines.len(), 12);
+    assert_eq!(key_lines[0], "B: 01 02 00 00 40 00 00 00 00");
+    assert_eq!(key_lines[1], "B: 01 00 00 00 00 00 00 00 00");
+
+    assert_eq!(
+        lines_with_prefix(&lines, "B: 02 "),
+        vec!["B: 02 00 00 00 00 00 00 00 00"]
+    );
+}
+
+#[test]
+fn test_properties_and_abs_info() {
+    let mut device = keyboard();
+    device
+        .properties
+        .insert(InputProp::INPUT_PROP_DIRECT as u32);
+    device.event_codes.insert(
+        EventType::EV_ABS as u32,
+        BTreeSet::from([EV_ABS::ABS_X as u32, EV_ABS::ABS_MT_SLOT as u32]),
+    );
+    device.abs_info.insert(
+        EV_ABS::ABS_X as u32,
+        AbsInfo {
+            value: 0,
+            minimum: 0,
+            maximum: 1920,
+            fuzz: 0,
+            flat: 0,
+            resolution: 12,
+        },
+    );
+    device.abs_info.insert(
+        EV_ABS::ABS_MT_SLOT as u32,
+        AbsInfo {
+            value: 0,
+            minimum: 0,
+            maximum: 9,
+            fuzz: 0,
+            flat: 0,
+            resolution: 0,
+        },
+    );
+
+    let lines = record(&device, &[]);
+
+    assert_eq!(
+        lines_with_prefix(&lines, "P: "),
+        vec!["P: 02 00 00 00 00 00 00 00"]
+    );
+    assert_eq!(
+        lines_with_prefix(&lines, "B: 00 "),
+        vec!["B: 00 0b 00 00 00 00 00 00 00"]
+    );
+    assert_eq!(
+        lines_with_prefix(&lines, "B: 03 "),
+        vec!["B: 03 01 00 00 00 00 80 00 00"]
+    );
+    assert_eq!(
+        lines_with_prefix(&lines, "A: "),
+        vec!["A: 00 0 1920 0 0 12", "A: 2f 0 9 0 0 0"]
+    );
+}
+
+#[test]
+fn test_event_times_are_relative_to_first_event() {
+    let events = [
+        InputEvent::new(
+            &TimeVal::new(100, 999_000),
+            &EventCode::EV_KEY(EV_KEY::KEY_A),
+            1,
+        ),
+        InputEvent::new(
+            &TimeVal::new(100, 999_000),
+            &EventCode::EV_SYN(EV_SYN::SYN_REPORT),
+            0,
+        ),
+        InputEvent::new(
+            &TimeVal::new(102, 1_500),
+            &EventCode::EV_KEY(EV_KEY::KEY_A),
+            0,
+        ),
+    ];
+
+    let lines = record(&keyboard(), &events);
+
+    assert_eq!(
+        lines_with_prefix(&lines, "E: "),
+        vec![
+            "E: 0.000000 0001 001e 0001",
+            "E: 0.000000 0000 0000 0000",
+            "E: 1.002500 0001 001e 0000",
+        ]
+    );
+}
+
+#[test]
+fn test_negative_values() {
+    let events = [InputEvent::new(
+        &TimeVal::new(0, 0),
+        &EventCode::EV_ABS(EV_ABS::ABS_MT_TRACKING_ID),
+        -1,
+    )];
+
+    let lines = record(&keyboard(), &events);
+
+    assert_eq!(
+        lines_with_prefix(&lines, "E: "),
+        vec!["E: 0.000000 0003 0039 -001"]
+    );
+}
@@ -0,0 +1,251 @@
+//! Tests for parsing evemu-record files and replaying their events.
+use evdev::enums::{EventCode, EventType, EV_KEY, EV_SYN};
+use evdev::{InputEvent, TimeVal};
+use evdev_manager_core::evemu_device::EvemuDevice;
+use evdev_manager_core::evemu_player::replay_events;
+use evdev_manager_core::evemu_recorder::EvemuRecorder;
+use evdev_manager_core::evemu_recording::{EvemuEvent, EvemuRecording};
+use evdev_manager_core::input_event_sink::InputEventSink;
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const RECORDING: &str = "# EVEMU 1.3
+# Kernel: 6.1.0
+# Input device name: \"Remote: Living Room\"
+# Input device ID: bus 0x05 vendor 0x1234 product 0x5678 version 0x01
+N: Remote: Living Room
+I: 0005 1234 5678 0001
+P: 00 00 00 00 00 00 00 00
+B: 00 13 00 00 00 00 00 00 00
+B: 01 02 00 00 40 00 00 00 00
+B: 01 00 00 00 00 00 00 00 00
+B: 02 00 00 00 00 00 00 00 00
+B: 03 01 00 00 00 00 00 00 00
+B: 04 10 00 00 00 00 00 00 00
+A: 00 -32768 32767 16 128
+A: 01 0 255 0 0 4
+L: 00 0
+################################
+#      Waiting for events      #
+################################
+E: 0.000000 0004 0004 458792	# EV_MSC / MSC_SCAN             458792
+E: 0.000000 0001 001e 0001	# EV_KEY / KEY_A                1
+E: 0.000000 0000 0000 0000	# ------------ SYN_REPORT (0) ---------- +0ms
+E: 0.120500 0001 001e 0000	# EV_KEY / KEY_A                0
+E: 0.120500 0000 0000 0000	# ------------ SYN_REPORT (0) ---------- +120ms
+";
+
+fn event(micros: u64, event_type: u32, code: u32, value: i32) -> EvemuEvent {
+    EvemuEvent {
+        time: Duration::from_micros(micros),
+        event_type,
+        code,
+        value,
+    }
+}
+
+/// Sink that records the time each event was written since it was created.
+struct TimedSink {
+    start: tokio::time::Instant,
+    events: Mutex<Vec<(Duration, u32, u32, i32)>>,
+}
+
+impl TimedSink {
+    fn new() -> Self {
+        Self {
+            start: tokio::time::Instant::now(),
+            events: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl InputEventSink for TimedSink {
+    fn write_event(&self, event_type: u32, code: u32, value: i32) -> io::Result<()> {
+        self.events
+            .lock()
+            .unwrap()
+            .push((self.start.elapsed(), event_type, code, value));
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_device() {
+    let recording = EvemuRecording::parse(RECORDING).unwrap();
+    let device = recording.device;
+
+    assert_eq!(device.name, "Remote: Living Room");
+    assert_eq!(
+        (device.bus, device.vendor, device.product, device.version),
+        (0x05, 0x1234, 0x5678, 0x01)
+    );
+    assert_eq!(device.properties, BTreeSet::new());
+    assert_eq!(
+        device.event_codes,
+        BTreeMap::from([
+            (
+                EventType::EV_KEY as u32,
+                BTreeSet::from([EV_KEY::KEY_ESC as u32, EV_KEY::KEY_A as u32])
+            ),
+            (EventType::EV_ABS as u32, BTreeSet::from([0])),
+            (EventType::EV_MSC as u32, BTreeSet::from([4])),
+        ])
+    );
+}
+
+#[test]
+fn test_parse_abs_info_with_and_without_resolution() {
+    let recording = EvemuRecording::parse(RECORDING).unwrap();
+    let abs_info = &recording.device.abs_info;
+
+    let x = abs_info[&0];
+    assert_eq!(
+        (x.minimum, x.maximum, x.fuzz, x.flat, x.resolution),
+        (-32768, 32767, 16, 128, 0)
+    );
+
+    let y = abs_info[&1];
+    assert_eq!(
+        (y.minimum, y.maximum, y.fuzz, y.flat, y.resolution),
+        (0, 255, 0, 0, 4)
+    );
+}
+
+#[test]
+fn test_parse_events() {
+    let recording = EvemuRecording::parse(RECORDING).unwrap();
+
+    assert_eq!(
+        recording.events,
+        vec![
+            event(0, 4, 4, 458792),
+            event(0, 1, 30, 1),
+            event(0, 0, 0, 0),
+            event(120_500, 1, 30, 0),
+            event(120_500, 0, 0, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_errors_have_line_numbers() {
+    let result = EvemuRecording::parse("N: Test\nI: 0005 1234 5678\n");
+    assert_eq!(result.unwrap_err(), "line 2: Expected 4 ids, got 3.");
+
+    let result = EvemuRecording::parse("N: Test\nE: 0.1 0001 001e\n");
+    assert_eq!(result.unwrap_err(), "line 2: Expected 4 values, got 3.");
+
+    let result = EvemuRecording::parse("X: 1 2 3\n");
+    assert_eq!(result.unwrap_err(), "line 1: Unknown line type 'X'.");
+}
+
+#[test]
+fn test_parse_rejects_unknown_event_types() {
+    let recording = RECORDING.replace("B: 04 10", "B: 20 10");
+    assert_eq!(
+        EvemuRecording::parse(&recording).unwrap_err(),
+        "line 13: Unknown event type '20'."
+    );
+
+    let recording = RECORDING.replace("E: 0.120500 0001 001e 0000", "E: 0.120500 0040 001e 0000");
+    assert_eq!(
+        EvemuRecording::parse(&recording).unwrap_err(),
+        "line 23: Unknown event type '0040'."
+    );
+}
+
+#[test]
+fn test_recording_round_trip() {
+    let device = EvemuDevice {
+        name: "Test Keyboard".to_string(),
+        bus: 0x0003,
+        vendor: 0x046d,
+        product: 0xc31c,
+        version: 0x0110,
+        properties: BTreeSet::new(),
+        event_codes: BTreeMap::from([(
+            EventType::EV_KEY as u32,
+            BTreeSet::from([EV_KEY::KEY_Q as u32, EV_KEY::KEY_VOLUMEUP as u32]),
+        )]),
+        abs_info: BTreeMap::new(),
+    };
+
+    let mut recorder = EvemuRecorder::new(Vec::new(), &device).unwrap();
+    recorder
+        .write_event(&InputEvent::new(
+            &TimeVal::new(5, 0),
+            &EventCode::EV_KEY(EV_KEY::KEY_VOLUMEUP),
+            1,
+        ))
+        .unwrap();
+    recorder
+        .write_event(&InputEvent::new(
+            &TimeVal::new(5, 250),
+            &EventCode::EV_SYN(EV_SYN::SYN_REPORT),
+            0,
+        ))
+        .unwrap();
+    let contents = String::from_utf8(recorder.finish().unwrap()).unwrap();
+
+    let recording = EvemuRecording::parse(&contents).unwrap();
+
+    assert_eq!(recording.device.name, device.name);
+    assert_eq!(recording.device.product, device.product);
+    assert_eq!(recording.device.event_codes, device.event_codes);
+    assert_eq!(
+        recording.events,
+        vec![
+            event(0, 1, EV_KEY::KEY_VOLUMEUP as u32, 1),
+            event(250, 0, 0, 0)
+        ]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_replay_keeps_relative_timing() {
+    let recording = EvemuRecording::parse(RECORDING).unwrap();
+    let sink = TimedSink::new();
+
+    replay_events(&recording.events, &sink, 1.0).await.unwrap();
+
+    let events = sink.events.lock().unwrap();
+    assert_eq!(events.len(), recording.events.len());
+
+    // Tokio timers have millisecond granularity so events can be up to 1ms late.
+    for ((time, _, _, _), recorded) in events.iter().zip(&recording.events) {
+        assert!(*time >= recorded.time, "{:?} < {:?}", time, recorded.time);
+        assert!(*time < recorded.time + Duration::from_millis(1));
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_replay_speed_scaling() {
+    let events = vec![event(0, 1, 30, 1), event(1_000_000, 1, 30, 0)];
+    let sink = TimedSink::new();
+
+    replay_events(&events, &sink, 4.0).await.unwrap();
+
+    assert_eq!(
+        *sink.events.lock().unwrap(),
+        vec![
+            (Duration::ZERO, 1, 30, 1),
+            (Duration::from_millis(250), 1, 30, 0)
+        ]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_replay_rejects_invalid_speed() {
+    let sink = TimedSink::new();
+
+    for speed in [0.0, -1.0, f64::NAN] {
+        let result = replay_events(&[event(0, 1, 30, 1)], &sink, speed).await;
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    assert_eq!(sink.events.lock().unwrap().len(), 0);
+}
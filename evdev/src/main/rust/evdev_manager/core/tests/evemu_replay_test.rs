@@ -0,0 +1,166 @@
+//! Tests for replaying recordings through the grab controller with an in-memory device backend.
+mod common;
+
+use common::FakeCallback;
+use evdev::enums::{EventCode, EventType, EV_KEY, EV_MSC, EV_SYN};
+use evdev_manager_core::device_backend::DeviceBackend;
+use evdev_manager_core::device_name_matcher::DeviceNameMatcher;
+use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
+use evdev_manager_core::evdev_grab_controller::EvdevGrabController;
+use evdev_manager_core::evemu_player::{replay_events, ReplayTarget};
+use evdev_manager_core::evemu_recording::EvemuRecording;
+use evdev_manager_core::grab_target::GrabTarget;
+use evdev_manager_core::in_memory_device_backend::InMemoryDeviceBackend;
+use evdev_manager_core::virtual_device::VirtualDeviceDescription;
+use mio::Poll;
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+struct Fixture {
+    // The registry is cloned from the poll so keep it alive.
+    _poll: Poll,
+    backend: InMemoryDeviceBackend,
+    controller: Arc<EvdevGrabController>,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let poll = Poll::new().unwrap();
+        let registry = Arc::new(poll.registry().try_clone().unwrap());
+        let backend = InMemoryDeviceBackend::new();
+
+        let controller = EvdevGrabController::with_backend(
+            registry,
+            Arc::new(FakeCallback::default()),
+            Arc::new(backend.clone()),
+        );
+
+        Self {
+            _poll: poll,
+            backend,
+            controller: Arc::new(controller),
+        }
+    }
+}
+
+fn recording() -> EvemuRecording {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("test_data")
+        .join("Keyboard.evemu");
+
+    EvemuRecording::load_from_file(&path).unwrap()
+}
+
+fn target(info: &EvdevDeviceInfo) -> GrabTarget {
+    GrabTarget {
+        name: DeviceNameMatcher::exact(&info.name),
+        bus: info.bus,
+        vendor: info.vendor,
+        product: info.product,
+        version: None,
+        phys_prefix: None,
+        uniq: None,
+        extra_event_codes: vec![],
+        forward_axes: false,
+    }
+}
+
+fn recorded_events() -> Vec<(u32, u32, i32)> {
+    let msc_scan = (EventType::EV_MSC as u32, EV_MSC::MSC_SCAN as u32, 458756);
+    let key_a = EventType::EV_KEY as u32;
+    let syn_report = (EventType::EV_SYN as u32, EV_SYN::SYN_REPORT as u32, 0);
+
+    vec![
+        msc_scan,
+        (key_a, EV_KEY::KEY_A as u32, 1),
+        syn_report,
+        msc_scan,
+        (key_a, EV_KEY::KEY_A as u32, 0),
+        syn_report,
+    ]
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_replay_to_grabbed_device() {
+    let fixture = Fixture::new();
+    let recording = recording();
+    let device_info =
+        VirtualDeviceDescription::from_evemu_device(recording.device.clone()).device_info();
+
+    fixture
+        .backend
+        .add_device("/dev/input/event0", device_info.clone());
+    fixture.backend.set_event_codes(
+        Path::new("/dev/input/event0"),
+        &[EventCode::EV_KEY(EV_KEY::KEY_A)],
+    );
+
+    let handles = fixture
+        .controller
+        .set_grab_targets(vec![target(&device_info)]);
+
+    let sink = fixture
+        .controller
+        .create_replay_sink(
+            ReplayTarget::GrabbedDevice(handles[0].id),
+            &recording.device,
+        )
+        .unwrap();
+
+    replay_events(&recording.events, sink.as_ref(), 1.0)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        fixture
+            .backend
+            .output_events(Path::new("/dev/input/event0")),
+        recorded_events()
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_replay_to_new_device() {
+    let fixture = Fixture::new();
+    let recording = recording();
+
+    let sink = fixture
+        .controller
+        .create_replay_sink(ReplayTarget::NewDevice, &recording.device)
+        .unwrap();
+    let devnode = sink.devnode().unwrap();
+
+    let device_info = fixture.backend.get_device_info(&devnode).unwrap();
+    assert_eq!(device_info.name, "Replay Keyboard");
+    assert_eq!(
+        (device_info.bus, device_info.vendor, device_info.product),
+        (0x03, 0x1234, 0x5678)
+    );
+
+    replay_events(&recording.events, sink.as_ref(), 1.0)
+        .await
+        .unwrap();
+
+    assert_eq!(fixture.backend.written_events(&devnode), recorded_events());
+
+    // The new device is grabbed like a real device.
+    fixture
+        .controller
+        .set_grab_targets(vec![target(&device_info)]);
+
+    assert!(fixture.backend.is_grabbed(&devnode));
+}
+
+#[test]
+fn test_replay_to_missing_grabbed_device_fails() {
+    let fixture = Fixture::new();
+    let recording = recording();
+
+    assert!(fixture
+        .controller
+        .create_replay_sink(ReplayTarget::GrabbedDevice(7), &recording.device)
+        .is_err());
+}
@@ -0,0 +1,65 @@
+//! Tests for replaying recordings through a real uinput device.
+//!
+//! Run these on a Linux device with access to /dev/uinput:
+//! `cargo test -p evdev_manager_core --features uinput-tests --test evemu_uinput_test`
+#![cfg(feature = "uinput-tests")]
+
+use evdev::enums::{EventCode, EV_KEY, EV_SYN};
+use evdev::{Device, ReadFlag, ReadStatus};
+use evdev_manager_core::evemu_player::{create_uinput_device, replay_events};
+use evdev_manager_core::evemu_recording::EvemuRecording;
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::fs::OpenOptions;
+use std::os::unix::fs::OpenOptionsExt;
+use std::time::{Duration, Instant};
+
+const RECORDING: &str = "# EVEMU 1.3
+N: Key Mapper Replay Test
+I: 0003 1234 5678 0001
+P: 00 00 00 00 00 00 00 00
+B: 00 03 00 00 00 00 00 00 00
+B: 01 00 00 00 40 00 00 00 00
+E: 0.000000 0001 001e 0001
+E: 0.000000 0000 0000 0000
+E: 0.200000 0001 001e 0000
+E: 0.200000 0000 0000 0000
+";
+
+#[tokio::test]
+async fn test_replay_through_uinput() {
+    let recording = EvemuRecording::parse(RECORDING).unwrap();
+    let uinput = create_uinput_device(&recording.device).unwrap();
+
+    let devnode = uinput.devnode().expect("uinput device has no devnode");
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(devnode)
+        .unwrap();
+    let device = Device::new_from_file(file).unwrap();
+
+    let start = Instant::now();
+    replay_events(&recording.events, &uinput, 2.0)
+        .await
+        .unwrap();
+
+    // The key up was recorded 200ms after the key down so at double speed it takes 100ms.
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(100), "{:?}", elapsed);
+
+    let mut events = Vec::new();
+    while let Ok((ReadStatus::Success, event)) = device.next_event(ReadFlag::NORMAL) {
+        events.push((event.event_code, event.value));
+    }
+
+    assert_eq!(
+        events,
+        vec![
+            (EventCode::EV_KEY(EV_KEY::KEY_A), 1),
+            (EventCode::EV_SYN(EV_SYN::SYN_REPORT), 0),
+            (EventCode::EV_KEY(EV_KEY::KEY_A), 0),
+            (EventCode::EV_SYN(EV_SYN::SYN_REPORT), 0),
+        ]
+    );
+}
@@ -0,0 +1,271 @@
+//! Tests for processing the events read from grabbed devices through the key triggers, key
+//! behaviours and remap table.
+mod common;
+
+use common::FakeCallback;
+use evdev::enums::{EventCode, EventType, EV_KEY};
+use evdev::{InputEvent, TimeVal};
+use evdev_manager_core::device_name_matcher::DeviceNameMatcher;
+use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
+use evdev_manager_core::evdev_grab_controller::EvdevGrabController;
+use evdev_manager_core::event_loop::process_device_event;
+use evdev_manager_core::grab_target::GrabTarget;
+use evdev_manager_core::in_memory_device_backend::InMemoryDeviceBackend;
+use evdev_manager_core::key_behaviour::{KeyBehaviour, KeyBehaviourKind};
+use evdev_manager_core::key_remap_table::{KeyRemapTable, ScanCodeRemap};
+use evdev_manager_core::key_trigger::{KeyTrigger, KeyTriggerKind};
+use mio::Poll;
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEVICE_PATH: &str = "/dev/input/event0";
+
+struct Fixture {
+    // The registry is cloned from the poll so keep it alive.
+    _poll: Poll,
+    backend: InMemoryDeviceBackend,
+    callback: Arc<FakeCallback>,
+    controller: EvdevGrabController,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let poll = Poll::new().unwrap();
+        let registry = Arc::new(poll.registry().try_clone().unwrap());
+        let backend = InMemoryDeviceBackend::new();
+        let callback = Arc::new(FakeCallback::default());
+
+        let controller = EvdevGrabController::with_backend(
+            registry,
+            callback.clone(),
+            Arc::new(backend.clone()),
+        );
+
+        Self {
+            _poll: poll,
+            backend,
+            callback,
+            controller,
+        }
+    }
+
+    /// Grab a keyboard with the keys and return its device id.
+    fn grab_keyboard(&self, keys: &[EV_KEY]) -> usize {
+        let event_codes: Vec<EventCode> = keys.iter().map(|key| EventCode::EV_KEY(*key)).collect();
+
+        self.backend.add_device(DEVICE_PATH, device_info());
+        self.backend
+            .set_event_codes(Path::new(DEVICE_PATH), &event_codes);
+
+        let handles = self.controller.set_grab_targets(vec![GrabTarget {
+            name: DeviceNameMatcher::exact("Keyboard"),
+            bus: 0x0003,
+            vendor: 0x1234,
+            product: 0x5678,
+            version: None,
+            phys_prefix: None,
+            uniq: None,
+            extra_event_codes: event_codes,
+            forward_axes: false,
+        }]);
+
+        handles[0].id
+    }
+
+    /// Process events as if they were read from the grabbed device.
+    fn process(&self, device_id: usize, events: &[InputEvent]) {
+        self.controller
+            .with_grabbed_device(device_id, |device| {
+                for event in events {
+                    process_device_event(self.callback.as_ref(), device_id, device, event);
+                }
+            })
+            .unwrap();
+    }
+
+    fn set_key_triggers(&self, device_id: usize, triggers: Vec<KeyTrigger>) {
+        self.controller
+            .with_grabbed_device(device_id, |device| {
+                device
+                    .key_trigger_detector
+                    .lock()
+                    .unwrap()
+                    .set_triggers(triggers)
+            })
+            .unwrap();
+    }
+
+    fn output_events(&self) -> Vec<(u32, u32, i32)> {
+        self.backend.output_events(Path::new(DEVICE_PATH))
+    }
+}
+
+fn device_info() -> EvdevDeviceInfo {
+    EvdevDeviceInfo {
+        name: "Keyboard".to_string(),
+        bus: 0x0003,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    }
+}
+
+fn key(key: EV_KEY, value: i32) -> InputEvent {
+    InputEvent::new(&TimeVal::new(0, 0), &EventCode::EV_KEY(key), value)
+}
+
+/// Long enough that the triggers and behaviours do not time out while the test runs.
+const TRIGGER_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn chord(id: u32, keys: &[EV_KEY]) -> KeyTrigger {
+    KeyTrigger {
+        id,
+        kind: KeyTriggerKind::Chord {
+            scan_codes: keys.iter().map(|key| *key as u32).collect(),
+            window: TRIGGER_TIMEOUT,
+        },
+    }
+}
+
+fn sequence(id: u32, keys: &[EV_KEY]) -> KeyTrigger {
+    KeyTrigger {
+        id,
+        kind: KeyTriggerKind::Sequence {
+            scan_codes: keys.iter().map(|key| *key as u32).collect(),
+            timeout: TRIGGER_TIMEOUT,
+        },
+    }
+}
+
+#[test]
+fn test_remapped_power_key_is_still_read() {
+    let fixture = Fixture::new();
+    let device_id = fixture.grab_keyboard(&[EV_KEY::KEY_POWER, EV_KEY::KEY_A]);
+
+    fixture
+        .controller
+        .with_grabbed_device(device_id, |device| {
+            device
+                .set_remap_table(KeyRemapTable::new(HashMap::from([(
+                    EV_KEY::KEY_POWER as u32,
+                    ScanCodeRemap::Remap(EV_KEY::KEY_A as u32),
+                )])))
+                .unwrap()
+        })
+        .unwrap();
+
+    let events = [key(EV_KEY::KEY_POWER, 1), key(EV_KEY::KEY_POWER, 0)];
+    fixture.process(device_id, &events);
+
+    assert_eq!(fixture.callback.read_keys(), events.to_vec());
+    assert_eq!(fixture.callback.events(), vec![]);
+    assert_eq!(
+        fixture.output_events(),
+        vec![
+            (EventType::EV_KEY as u32, EV_KEY::KEY_A as u32, 1),
+            (EventType::EV_KEY as u32, EV_KEY::KEY_A as u32, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_fired_chord_is_sent_to_callback() {
+    let fixture = Fixture::new();
+    let device_id = fixture.grab_keyboard(&[EV_KEY::KEY_A, EV_KEY::KEY_B]);
+    fixture.set_key_triggers(device_id, vec![chord(1, &[EV_KEY::KEY_A, EV_KEY::KEY_B])]);
+
+    fixture.process(device_id, &[key(EV_KEY::KEY_A, 1), key(EV_KEY::KEY_B, 1)]);
+
+    assert_eq!(fixture.callback.fired_triggers(), vec![(device_id, 1)]);
+    assert_eq!(fixture.callback.events(), vec![]);
+}
+
+#[test]
+fn test_fired_sequence_is_sent_to_callback() {
+    let fixture = Fixture::new();
+    let device_id = fixture.grab_keyboard(&[EV_KEY::KEY_A, EV_KEY::KEY_B]);
+    fixture.set_key_triggers(
+        device_id,
+        vec![sequence(2, &[EV_KEY::KEY_A, EV_KEY::KEY_B])],
+    );
+
+    fixture.process(
+        device_id,
+        &[
+            key(EV_KEY::KEY_A, 1),
+            key(EV_KEY::KEY_A, 0),
+            key(EV_KEY::KEY_B, 1),
+        ],
+    );
+
+    assert_eq!(fixture.callback.fired_triggers(), vec![(device_id, 2)]);
+    // The keys before the last one are forwarded as normal.
+    assert_eq!(
+        fixture.callback.events(),
+        vec![
+            (device_id, key(EV_KEY::KEY_A, 1)),
+            (device_id, key(EV_KEY::KEY_A, 0))
+        ]
+    );
+}
+
+#[test]
+fn test_power_key_in_fired_chord_is_still_read() {
+    let fixture = Fixture::new();
+    let device_id = fixture.grab_keyboard(&[EV_KEY::KEY_POWER, EV_KEY::KEY_VOLUMEDOWN]);
+    fixture.set_key_triggers(
+        device_id,
+        vec![chord(1, &[EV_KEY::KEY_POWER, EV_KEY::KEY_VOLUMEDOWN])],
+    );
+
+    let events = [
+        key(EV_KEY::KEY_POWER, 1),
+        key(EV_KEY::KEY_VOLUMEDOWN, 1),
+        key(EV_KEY::KEY_VOLUMEDOWN, 0),
+        key(EV_KEY::KEY_POWER, 0),
+    ];
+    fixture.process(device_id, &events);
+
+    assert_eq!(fixture.callback.fired_triggers(), vec![(device_id, 1)]);
+    assert_eq!(fixture.callback.read_keys(), events.to_vec());
+}
+
+#[test]
+fn test_power_key_with_tap_hold_behaviour_is_still_read() {
+    let fixture = Fixture::new();
+    let device_id = fixture.grab_keyboard(&[EV_KEY::KEY_POWER, EV_KEY::KEY_A, EV_KEY::KEY_B]);
+
+    fixture
+        .controller
+        .with_grabbed_device(device_id, |device| {
+            device
+                .key_behaviour_resolver
+                .lock()
+                .unwrap()
+                .set_behaviours(vec![KeyBehaviour {
+                    scan_code: EV_KEY::KEY_POWER as u32,
+                    kind: KeyBehaviourKind::TapHold {
+                        tap_scan_code: EV_KEY::KEY_A as u32,
+                        hold_scan_code: EV_KEY::KEY_B as u32,
+                        hold_timeout: TRIGGER_TIMEOUT,
+                    },
+                }])
+        })
+        .unwrap();
+
+    let events = [key(EV_KEY::KEY_POWER, 1), key(EV_KEY::KEY_POWER, 0)];
+    fixture.process(device_id, &events);
+
+    assert_eq!(fixture.callback.read_keys(), events.to_vec());
+    assert_eq!(fixture.callback.events(), vec![]);
+    assert!(fixture
+        .output_events()
+        .contains(&(EventType::EV_KEY as u32, EV_KEY::KEY_A as u32, 1)));
+}
@@ -0,0 +1,132 @@
+//! Tests for matching devices against grab targets.
+use evdev_manager_core::device_name_matcher::DeviceNameMatcher;
+use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
+use evdev_manager_core::grab_target::GrabTarget;
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+fn device_info() -> EvdevDeviceInfo {
+    EvdevDeviceInfo {
+        name: "Remote Control 1A2B".to_string(),
+        bus: 0x0005,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 0x0101,
+        phys: "usb-0000:00:14.0-1/input0".to_string(),
+        uniq: "aa:bb:cc:dd:ee:ff".to_string(),
+        sysfs_path: None,
+    }
+}
+
+fn target(name: DeviceNameMatcher) -> GrabTarget {
+    GrabTarget {
+        name,
+        bus: 0x0005,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: None,
+        phys_prefix: None,
+        uniq: None,
+        extra_event_codes: vec![],
+        forward_axes: false,
+    }
+}
+
+#[test]
+fn test_exact_name() {
+    assert!(
+        target(DeviceNameMatcher::exact("Remote Control 1A2B")).matches_device_info(&device_info())
+    );
+    assert!(!target(DeviceNameMatcher::exact("Remote Control")).matches_device_info(&device_info()));
+}
+
+#[test]
+fn test_wildcard_name() {
+    let matcher = DeviceNameMatcher::wildcard("Remote Control *");
+    assert!(matcher.matches("Remote Control 1A2B"));
+    assert!(matcher.matches("Remote Control "));
+    assert!(!matcher.matches("My Remote Control 1A2B"));
+
+    let matcher = DeviceNameMatcher::wildcard("Remote?");
+    assert!(matcher.matches("Remote1"));
+    assert!(!matcher.matches("Remote"));
+    assert!(!matcher.matches("Remote12"));
+}
+
+#[test]
+fn test_wildcard_name_escapes_regex_characters() {
+    let matcher = DeviceNameMatcher::wildcard("Pad (v1.0)*");
+    assert!(matcher.matches("Pad (v1.0) Pro"));
+    assert!(!matcher.matches("Pad xv1x0x Pro"));
+}
+
+#[test]
+fn test_regex_name_matches_whole_name() {
+    let matcher = DeviceNameMatcher::regex("Remote Control [0-9A-F]{4}").unwrap();
+    assert!(matcher.matches("Remote Control 1A2B"));
+    assert!(!matcher.matches("Remote Control 1A2B5"));
+
+    let matcher = DeviceNameMatcher::regex("Remote|Keyboard").unwrap();
+    assert!(matcher.matches("Keyboard"));
+    assert!(!matcher.matches("Remote Keyboard"));
+}
+
+#[test]
+fn test_invalid_regex_name() {
+    let error = DeviceNameMatcher::regex("Remote (").unwrap_err();
+    assert!(error.starts_with("Invalid device name regex 'Remote ('"));
+}
+
+#[test]
+fn test_matchers_compare_by_pattern() {
+    assert_eq!(DeviceNameMatcher::regex("a.*").unwrap().pattern(), "a.*");
+    assert_eq!(
+        DeviceNameMatcher::regex("a.*").unwrap(),
+        DeviceNameMatcher::regex("a.*").unwrap()
+    );
+    assert_ne!(
+        DeviceNameMatcher::wildcard("a*"),
+        DeviceNameMatcher::exact("a*")
+    );
+}
+
+#[test]
+fn test_ids_must_match() {
+    let mut target = target(DeviceNameMatcher::wildcard("*"));
+    target.product = 0x0001;
+
+    assert!(!target.matches_device_info(&device_info()));
+}
+
+#[test]
+fn test_version() {
+    let mut target = target(DeviceNameMatcher::wildcard("*"));
+
+    target.version = Some(0x0101);
+    assert!(target.matches_device_info(&device_info()));
+
+    target.version = Some(0x0102);
+    assert!(!target.matches_device_info(&device_info()));
+}
+
+#[test]
+fn test_phys_prefix() {
+    let mut target = target(DeviceNameMatcher::wildcard("*"));
+
+    target.phys_prefix = Some("usb-0000:00:14.0".to_string());
+    assert!(target.matches_device_info(&device_info()));
+
+    target.phys_prefix = Some("usb-0000:00:15.0".to_string());
+    assert!(!target.matches_device_info(&device_info()));
+}
+
+#[test]
+fn test_uniq() {
+    let mut target = target(DeviceNameMatcher::wildcard("*"));
+
+    target.uniq = Some("aa:bb:cc:dd:ee:ff".to_string());
+    assert!(target.matches_device_info(&device_info()));
+
+    target.uniq = Some("aa:bb:cc:dd:ee:00".to_string());
+    assert!(!target.matches_device_info(&device_info()));
+}
@@ -0,0 +1,91 @@
+//! Tests for pairing EV_MSC/MSC_SCAN events with the key events that follow them.
+use evdev::enums::{EventCode, EV_KEY, EV_MSC, EV_SYN};
+use evdev::{InputEvent, TimeVal};
+use evdev_manager_core::hid_usage_tracker::HidUsageTracker;
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+fn event(event_code: EventCode, value: i32) -> InputEvent {
+    InputEvent::new(&TimeVal::new(0, 0), &event_code, value)
+}
+
+fn msc_scan(usage_code: i32) -> InputEvent {
+    event(EventCode::EV_MSC(EV_MSC::MSC_SCAN), usage_code)
+}
+
+fn key(key: EV_KEY, value: i32) -> InputEvent {
+    event(EventCode::EV_KEY(key), value)
+}
+
+fn syn() -> InputEvent {
+    event(EventCode::EV_SYN(EV_SYN::SYN_REPORT), 0)
+}
+
+#[test]
+fn test_no_usage_without_msc_scan() {
+    let mut tracker = HidUsageTracker::new();
+
+    tracker.on_event(&key(EV_KEY::KEY_BRIGHTNESSUP, 1));
+    tracker.on_event(&syn());
+
+    assert_eq!(tracker.usage_code(EV_KEY::KEY_BRIGHTNESSUP as u32), None);
+}
+
+#[test]
+fn test_msc_scan_is_paired_with_next_key() {
+    let mut tracker = HidUsageTracker::new();
+
+    tracker.on_event(&msc_scan(0x0c006f));
+    tracker.on_event(&key(EV_KEY::KEY_BRIGHTNESSUP, 1));
+    tracker.on_event(&key(EV_KEY::KEY_A, 1));
+    tracker.on_event(&syn());
+
+    assert_eq!(
+        tracker.usage_code(EV_KEY::KEY_BRIGHTNESSUP as u32),
+        Some(0x0c006f)
+    );
+    assert_eq!(tracker.usage_code(EV_KEY::KEY_A as u32), None);
+}
+
+#[test]
+fn test_syn_report_clears_pending_usage() {
+    let mut tracker = HidUsageTracker::new();
+
+    tracker.on_event(&msc_scan(0x0c006f));
+    tracker.on_event(&syn());
+    tracker.on_event(&key(EV_KEY::KEY_A, 1));
+
+    assert_eq!(tracker.usage_code(EV_KEY::KEY_A as u32), None);
+}
+
+#[test]
+fn test_usage_is_kept_after_release() {
+    let mut tracker = HidUsageTracker::new();
+
+    tracker.on_event(&msc_scan(0x0c0070));
+    tracker.on_event(&key(EV_KEY::KEY_BRIGHTNESSDOWN, 1));
+    tracker.on_event(&syn());
+    tracker.on_event(&key(EV_KEY::KEY_BRIGHTNESSDOWN, 0));
+    tracker.on_event(&syn());
+
+    assert_eq!(
+        tracker.usage_code(EV_KEY::KEY_BRIGHTNESSDOWN as u32),
+        Some(0x0c0070)
+    );
+}
+
+#[test]
+fn test_unknown_key_codes_are_paired() {
+    let mut tracker = HidUsageTracker::new();
+
+    tracker.on_event(&msc_scan(0x0c019c));
+    tracker.on_event(&event(
+        EventCode::EV_UNK {
+            event_type: 1,
+            event_code: 0x2ff,
+        },
+        1,
+    ));
+
+    assert_eq!(tracker.usage_code(0x2ff), Some(0x0c019c));
+}
@@ -0,0 +1,63 @@
+//! Integration tests for input device configuration parsing.
+use evdev_manager_core::android::keylayout::input_device_configuration::InputDeviceConfiguration;
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::path::PathBuf;
+
+#[test]
+fn test_load_from_file() {
+    let file_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("test_data")
+        .join("Custom_Keyboard.idc");
+
+    let configuration = InputDeviceConfiguration::load_from_file(file_path).unwrap();
+
+    assert_eq!(configuration.keyboard_layout(), Some("Custom_Layout"));
+    assert_eq!(configuration.keyboard_character_map(), Some("Custom_Chars"));
+    assert_eq!(configuration.touch_device_type(), Some("touchPad"));
+    assert_eq!(configuration.device_type(), None);
+    assert_eq!(configuration.get_bool("keyboard.builtIn"), Some(true));
+    assert_eq!(configuration.get_bool("touch.orientationAware"), Some(true));
+    assert_eq!(configuration.get_float("touch.size.scale"), Some(0.5));
+}
+
+#[test]
+fn test_typed_getters() {
+    let configuration = InputDeviceConfiguration::load_from_contents(
+        "device.type = rotaryEncoder\ndevice.internal = 0\nrotary_encoder.resolution=12\n",
+    )
+    .unwrap();
+
+    assert_eq!(configuration.device_type(), Some("rotaryEncoder"));
+    assert_eq!(configuration.get_bool("device.internal"), Some(false));
+    assert_eq!(configuration.get_int("rotary_encoder.resolution"), Some(12));
+    assert_eq!(configuration.get_int("device.type"), None);
+    assert_eq!(configuration.get_string("missing"), None);
+}
+
+#[test]
+fn test_comments_and_blank_lines_are_ignored() {
+    let configuration =
+        InputDeviceConfiguration::load_from_contents("# Comment\n\n  \nkeyboard.layout = A\n")
+            .unwrap();
+
+    assert_eq!(configuration.keyboard_layout(), Some("A"));
+}
+
+#[test]
+fn test_invalid_files_are_errors() {
+    // Missing '='
+    assert!(InputDeviceConfiguration::load_from_contents("keyboard.layout A\n").is_err());
+    // Missing key
+    assert!(InputDeviceConfiguration::load_from_contents("= A\n").is_err());
+    // Trailing tokens
+    assert!(InputDeviceConfiguration::load_from_contents("keyboard.layout = A B\n").is_err());
+    // Reserved characters
+    assert!(InputDeviceConfiguration::load_from_contents("keyboard.layout = \"A\"\n").is_err());
+    // Duplicate key
+    assert!(InputDeviceConfiguration::load_from_contents(
+        "device.internal = 1\ndevice.internal = 0\n"
+    )
+    .is_err());
+}
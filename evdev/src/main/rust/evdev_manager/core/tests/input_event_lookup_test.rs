@@ -1,7 +1,8 @@
 //! Integration tests for key layout map parsing.
 
 use evdev_manager_core::android::android_codes::{
-    AKEYCODE_A, AKEYCODE_FUNCTION, AMOTION_EVENT_AXIS_X, POLICY_FLAG_VIRTUAL,
+    AKEYCODE_A, AKEYCODE_FUNCTION, ALED_CAPS_LOCK, AMOTION_EVENT_AXIS_X, ASENSOR_TYPE_GYROSCOPE,
+    POLICY_FLAG_VIRTUAL, POLICY_FLAG_WAKE,
 };
 use evdev_manager_core::android::keylayout::input_event_lookup;
 #[cfg(test)]
@@ -30,3 +31,36 @@ fn test_axis_lookup() {
     let code = input_event_lookup::get_axis_by_label("X");
     assert_eq!(code, Some(AMOTION_EVENT_AXIS_X));
 }
+
+#[test]
+fn test_label_lookup() {
+    assert_eq!(
+        input_event_lookup::get_key_code_label(AKEYCODE_A),
+        Some("A".to_string())
+    );
+    assert_eq!(
+        input_event_lookup::get_axis_label(AMOTION_EVENT_AXIS_X),
+        Some("X".to_string())
+    );
+    assert_eq!(
+        input_event_lookup::get_led_label(ALED_CAPS_LOCK),
+        Some("CAPS_LOCK".to_string())
+    );
+    assert_eq!(
+        input_event_lookup::get_sensor_type_label(ASENSOR_TYPE_GYROSCOPE),
+        Some("GYROSCOPE".to_string())
+    );
+    assert_eq!(input_event_lookup::get_key_code_label(0xFFFF), None);
+}
+
+#[test]
+fn test_key_flag_labels() {
+    assert_eq!(
+        input_event_lookup::get_key_flag_labels(POLICY_FLAG_WAKE | POLICY_FLAG_VIRTUAL),
+        vec!["VIRTUAL".to_string(), "WAKE".to_string()]
+    );
+    assert_eq!(
+        input_event_lookup::get_key_flag_labels(0),
+        Vec::<String>::new()
+    );
+}
@@ -0,0 +1,349 @@
+//! Tests for resolving tap/hold, long press and double tap key behaviours.
+mod common;
+
+use common::FakeClock;
+use evdev::enums::{EventCode, EV_KEY, EV_SYN};
+use evdev::{InputEvent, TimeVal};
+use evdev_manager_core::clock::Clock;
+use evdev_manager_core::key_behaviour::{KeyBehaviour, KeyBehaviourKind};
+use evdev_manager_core::key_behaviour_resolver::{KeyBehaviourOutput, KeyBehaviourResolver};
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::sync::Arc;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_millis(200);
+
+fn key(key: EV_KEY, value: i32) -> InputEvent {
+    InputEvent::new(&TimeVal::new(0, 0), &EventCode::EV_KEY(key), value)
+}
+
+fn syn_report() -> InputEvent {
+    InputEvent::new(
+        &TimeVal::new(0, 0),
+        &EventCode::EV_SYN(EV_SYN::SYN_REPORT),
+        0,
+    )
+}
+
+fn forward(event: InputEvent) -> KeyBehaviourOutput {
+    KeyBehaviourOutput::Forward(event)
+}
+
+/// The outputs for a key event that the resolver emits followed by its SYN_REPORT.
+fn emit(key: EV_KEY, value: i32) -> Vec<KeyBehaviourOutput> {
+    vec![
+        KeyBehaviourOutput::Emit(self::key(key, value)),
+        KeyBehaviourOutput::Emit(syn_report()),
+    ]
+}
+
+fn emit_tap(key: EV_KEY) -> Vec<KeyBehaviourOutput> {
+    [emit(key, 1), emit(key, 0)].concat()
+}
+
+/// Caps lock is escape when tapped and left control when held.
+fn tap_hold() -> KeyBehaviour {
+    KeyBehaviour {
+        scan_code: EV_KEY::KEY_CAPSLOCK as u32,
+        kind: KeyBehaviourKind::TapHold {
+            tap_scan_code: EV_KEY::KEY_ESC as u32,
+            hold_scan_code: EV_KEY::KEY_LEFTCTRL as u32,
+            hold_timeout: TIMEOUT,
+        },
+    }
+}
+
+fn long_press() -> KeyBehaviour {
+    KeyBehaviour {
+        scan_code: EV_KEY::KEY_A as u32,
+        kind: KeyBehaviourKind::LongPress {
+            short_press_scan_code: EV_KEY::KEY_A as u32,
+            long_press_scan_code: EV_KEY::KEY_1 as u32,
+            long_press_timeout: TIMEOUT,
+        },
+    }
+}
+
+fn double_tap() -> KeyBehaviour {
+    KeyBehaviour {
+        scan_code: EV_KEY::KEY_RIGHTSHIFT as u32,
+        kind: KeyBehaviourKind::DoubleTap {
+            single_tap_scan_code: EV_KEY::KEY_RIGHTSHIFT as u32,
+            double_tap_scan_code: EV_KEY::KEY_CAPSLOCK as u32,
+            double_tap_timeout: TIMEOUT,
+        },
+    }
+}
+
+fn create_resolver(behaviours: Vec<KeyBehaviour>) -> (Arc<FakeClock>, KeyBehaviourResolver) {
+    let clock = Arc::new(FakeClock::new());
+    let mut resolver = KeyBehaviourResolver::new(clock.clone());
+    assert_eq!(resolver.set_behaviours(behaviours), vec![]);
+    (clock, resolver)
+}
+
+fn feed(resolver: &mut KeyBehaviourResolver, events: &[InputEvent]) -> Vec<KeyBehaviourOutput> {
+    events
+        .iter()
+        .flat_map(|event| resolver.on_event(event))
+        .collect()
+}
+
+#[test]
+fn test_no_behaviours_forwards_everything() {
+    let (_, mut resolver) = create_resolver(vec![]);
+
+    let outputs = feed(
+        &mut resolver,
+        &[key(EV_KEY::KEY_CAPSLOCK, 1), key(EV_KEY::KEY_CAPSLOCK, 0)],
+    );
+
+    assert_eq!(
+        outputs,
+        vec![
+            forward(key(EV_KEY::KEY_CAPSLOCK, 1)),
+            forward(key(EV_KEY::KEY_CAPSLOCK, 0))
+        ]
+    );
+}
+
+#[test]
+fn test_tap_hold_tap_inputs_tap_key() {
+    let (clock, mut resolver) = create_resolver(vec![tap_hold()]);
+
+    assert_eq!(feed(&mut resolver, &[key(EV_KEY::KEY_CAPSLOCK, 1)]), vec![]);
+    assert!(resolver.next_deadline().is_some());
+
+    clock.advance(Duration::from_millis(100));
+    let outputs = feed(&mut resolver, &[key(EV_KEY::KEY_CAPSLOCK, 0)]);
+
+    assert_eq!(outputs, emit_tap(EV_KEY::KEY_ESC));
+    assert_eq!(resolver.next_deadline(), None);
+}
+
+#[test]
+fn test_tap_hold_timeout_holds_hold_key_until_release() {
+    let (clock, mut resolver) = create_resolver(vec![tap_hold()]);
+
+    feed(&mut resolver, &[key(EV_KEY::KEY_CAPSLOCK, 1)]);
+
+    clock.advance(Duration::from_millis(199));
+    assert_eq!(resolver.on_timeout(), vec![]);
+
+    clock.advance(Duration::from_millis(1));
+    assert_eq!(resolver.on_timeout(), emit(EV_KEY::KEY_LEFTCTRL, 1));
+    assert_eq!(resolver.next_deadline(), None);
+
+    assert_eq!(
+        feed(&mut resolver, &[key(EV_KEY::KEY_CAPSLOCK, 2)]),
+        emit(EV_KEY::KEY_LEFTCTRL, 2)
+    );
+    assert_eq!(
+        feed(&mut resolver, &[key(EV_KEY::KEY_CAPSLOCK, 0)]),
+        emit(EV_KEY::KEY_LEFTCTRL, 0)
+    );
+}
+
+#[test]
+fn test_tap_hold_repeats_are_consumed_before_decided() {
+    let (_, mut resolver) = create_resolver(vec![tap_hold()]);
+
+    let outputs = feed(
+        &mut resolver,
+        &[key(EV_KEY::KEY_CAPSLOCK, 1), key(EV_KEY::KEY_CAPSLOCK, 2)],
+    );
+
+    assert_eq!(outputs, vec![]);
+}
+
+#[test]
+fn test_tap_hold_other_key_press_holds_before_other_key() {
+    let (_, mut resolver) = create_resolver(vec![tap_hold()]);
+
+    let outputs = feed(
+        &mut resolver,
+        &[
+            key(EV_KEY::KEY_CAPSLOCK, 1),
+            key(EV_KEY::KEY_C, 1),
+            key(EV_KEY::KEY_C, 0),
+            key(EV_KEY::KEY_CAPSLOCK, 0),
+        ],
+    );
+
+    assert_eq!(
+        outputs,
+        [
+            emit(EV_KEY::KEY_LEFTCTRL, 1),
+            vec![
+                forward(key(EV_KEY::KEY_C, 1)),
+                forward(key(EV_KEY::KEY_C, 0))
+            ],
+            emit(EV_KEY::KEY_LEFTCTRL, 0),
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn test_expired_timeout_is_decided_before_next_event() {
+    let (clock, mut resolver) = create_resolver(vec![tap_hold()]);
+
+    feed(&mut resolver, &[key(EV_KEY::KEY_CAPSLOCK, 1)]);
+    clock.advance(Duration::from_millis(300));
+
+    // on_timeout was not called in time so releasing the key must not input a tap.
+    let outputs = feed(&mut resolver, &[key(EV_KEY::KEY_CAPSLOCK, 0)]);
+
+    assert_eq!(
+        outputs,
+        [emit(EV_KEY::KEY_LEFTCTRL, 1), emit(EV_KEY::KEY_LEFTCTRL, 0)].concat()
+    );
+}
+
+#[test]
+fn test_long_press_short_press() {
+    let (_, mut resolver) = create_resolver(vec![long_press()]);
+
+    let outputs = feed(
+        &mut resolver,
+        &[key(EV_KEY::KEY_A, 1), key(EV_KEY::KEY_A, 0)],
+    );
+
+    assert_eq!(outputs, emit_tap(EV_KEY::KEY_A));
+}
+
+#[test]
+fn test_long_press_timeout() {
+    let (clock, mut resolver) = create_resolver(vec![long_press()]);
+
+    feed(&mut resolver, &[key(EV_KEY::KEY_A, 1)]);
+    clock.advance(TIMEOUT);
+
+    assert_eq!(resolver.on_timeout(), emit(EV_KEY::KEY_1, 1));
+    assert_eq!(
+        feed(&mut resolver, &[key(EV_KEY::KEY_A, 0)]),
+        emit(EV_KEY::KEY_1, 0)
+    );
+}
+
+#[test]
+fn test_long_press_is_not_decided_by_other_keys() {
+    let (_, mut resolver) = create_resolver(vec![long_press()]);
+
+    let outputs = feed(
+        &mut resolver,
+        &[key(EV_KEY::KEY_A, 1), key(EV_KEY::KEY_B, 1)],
+    );
+
+    assert_eq!(outputs, vec![forward(key(EV_KEY::KEY_B, 1))]);
+}
+
+#[test]
+fn test_double_tap() {
+    let (clock, mut resolver) = create_resolver(vec![double_tap()]);
+
+    let mut outputs = feed(
+        &mut resolver,
+        &[
+            key(EV_KEY::KEY_RIGHTSHIFT, 1),
+            key(EV_KEY::KEY_RIGHTSHIFT, 0),
+        ],
+    );
+    assert_eq!(outputs, vec![]);
+
+    clock.advance(Duration::from_millis(150));
+    outputs = feed(
+        &mut resolver,
+        &[
+            key(EV_KEY::KEY_RIGHTSHIFT, 1),
+            key(EV_KEY::KEY_RIGHTSHIFT, 0),
+        ],
+    );
+
+    assert_eq!(outputs, emit_tap(EV_KEY::KEY_CAPSLOCK));
+}
+
+#[test]
+fn test_double_tap_timeout_inputs_single_tap() {
+    let (clock, mut resolver) = create_resolver(vec![double_tap()]);
+
+    feed(
+        &mut resolver,
+        &[
+            key(EV_KEY::KEY_RIGHTSHIFT, 1),
+            key(EV_KEY::KEY_RIGHTSHIFT, 0),
+        ],
+    );
+    clock.advance(TIMEOUT);
+
+    assert_eq!(resolver.on_timeout(), emit_tap(EV_KEY::KEY_RIGHTSHIFT));
+    assert_eq!(resolver.next_deadline(), None);
+}
+
+#[test]
+fn test_double_tap_other_key_inputs_single_tap_first() {
+    let (_, mut resolver) = create_resolver(vec![double_tap()]);
+
+    let outputs = feed(
+        &mut resolver,
+        &[
+            key(EV_KEY::KEY_RIGHTSHIFT, 1),
+            key(EV_KEY::KEY_RIGHTSHIFT, 0),
+            key(EV_KEY::KEY_X, 1),
+        ],
+    );
+
+    assert_eq!(
+        outputs,
+        [
+            emit_tap(EV_KEY::KEY_RIGHTSHIFT),
+            vec![forward(key(EV_KEY::KEY_X, 1))]
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn test_double_tap_key_held_is_single_key() {
+    let (clock, mut resolver) = create_resolver(vec![double_tap()]);
+
+    feed(&mut resolver, &[key(EV_KEY::KEY_RIGHTSHIFT, 1)]);
+    clock.advance(TIMEOUT);
+
+    assert_eq!(resolver.on_timeout(), emit(EV_KEY::KEY_RIGHTSHIFT, 1));
+}
+
+#[test]
+fn test_timeouts_are_per_key() {
+    let mut slow_tap_hold = tap_hold();
+    slow_tap_hold.kind = KeyBehaviourKind::TapHold {
+        tap_scan_code: EV_KEY::KEY_ESC as u32,
+        hold_scan_code: EV_KEY::KEY_LEFTCTRL as u32,
+        hold_timeout: Duration::from_millis(500),
+    };
+
+    let (clock, mut resolver) = create_resolver(vec![slow_tap_hold, long_press()]);
+
+    feed(&mut resolver, &[key(EV_KEY::KEY_CAPSLOCK, 1)]);
+    let pressed_at = clock.now();
+    feed(&mut resolver, &[key(EV_KEY::KEY_A, 1)]);
+
+    // Pressing A decided caps lock so only the long press deadline is left.
+    assert_eq!(resolver.next_deadline(), Some(pressed_at + TIMEOUT));
+}
+
+#[test]
+fn test_replacing_behaviours_resolves_pending_keys_as_tap() {
+    let (_, mut resolver) = create_resolver(vec![tap_hold()]);
+
+    feed(&mut resolver, &[key(EV_KEY::KEY_CAPSLOCK, 1)]);
+
+    assert_eq!(resolver.set_behaviours(vec![]), emit(EV_KEY::KEY_ESC, 1));
+
+    // The resolved key is still released even though the behaviour was removed.
+    assert_eq!(
+        feed(&mut resolver, &[key(EV_KEY::KEY_CAPSLOCK, 0)]),
+        emit(EV_KEY::KEY_ESC, 0)
+    );
+}
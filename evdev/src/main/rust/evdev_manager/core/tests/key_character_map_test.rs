@@ -0,0 +1,195 @@
+//! Integration tests for key character map parsing.
+use evdev_manager_core::android::android_codes::{
+    AKEYCODE_1, AKEYCODE_A, AKEYCODE_APOSTROPHE, AKEYCODE_C, AKEYCODE_COMMA, AKEYCODE_DPAD_CENTER,
+    AKEYCODE_ENTER, AKEYCODE_ESCAPE, AKEYCODE_HOME, AKEYCODE_MENU, AKEYCODE_Q, AKEYCODE_SEARCH,
+    AKEYCODE_SPACE, AKEYCODE_TAB, AMETA_ALT_LEFT_ON, AMETA_ALT_ON, AMETA_CAPS_LOCK_ON,
+    AMETA_CTRL_ON, AMETA_META_ON, AMETA_NONE, AMETA_SHIFT_LEFT_ON, AMETA_SHIFT_ON,
+};
+use evdev_manager_core::android::keylayout::key_character_map::{
+    FallbackAction, KeyCharacterMap, KeyboardType,
+};
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::path::PathBuf;
+
+fn load_generic() -> KeyCharacterMap {
+    let file_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("test_data")
+        .join("Generic.kcm");
+
+    KeyCharacterMap::load_from_file(file_path).unwrap()
+}
+
+fn load_key(properties: &str) -> Result<KeyCharacterMap, String> {
+    KeyCharacterMap::load_from_contents(&format!("type FULL\nkey A {{\n{}\n}}\n", properties))
+}
+
+#[test]
+fn test_load_generic() {
+    let map = load_generic();
+
+    assert_eq!(map.keyboard_type(), KeyboardType::Full);
+    assert_eq!(map.get_display_label(AKEYCODE_A), Some('A'));
+    assert_eq!(map.get_character(AKEYCODE_A, AMETA_NONE), Some('a'));
+    assert_eq!(map.get_character(AKEYCODE_C, AMETA_ALT_ON), Some('ç'));
+    assert_eq!(
+        map.get_character(AKEYCODE_C, AMETA_ALT_ON | AMETA_SHIFT_ON),
+        Some('Ç')
+    );
+    assert_eq!(
+        map.get_character(AKEYCODE_APOSTROPHE, AMETA_NONE),
+        Some('\'')
+    );
+    assert_eq!(map.get_character(AKEYCODE_ENTER, AMETA_NONE), Some('\n'));
+    assert_eq!(map.get_display_label(AKEYCODE_DPAD_CENTER), None);
+}
+
+#[test]
+fn test_shift_and_caps_lock() {
+    let map = load_generic();
+
+    assert_eq!(map.get_character(AKEYCODE_A, AMETA_SHIFT_ON), Some('A'));
+    assert_eq!(map.get_character(AKEYCODE_A, AMETA_CAPS_LOCK_ON), Some('A'));
+    assert_eq!(
+        map.get_character(AKEYCODE_A, AMETA_SHIFT_ON | AMETA_CAPS_LOCK_ON),
+        Some('a')
+    );
+    assert_eq!(map.get_character(AKEYCODE_1, AMETA_SHIFT_ON), Some('!'));
+}
+
+#[test]
+fn test_left_and_right_modifiers_match_generic_modifier() {
+    let map = load_generic();
+
+    assert_eq!(
+        map.get_character(AKEYCODE_A, AMETA_SHIFT_LEFT_ON),
+        Some('A')
+    );
+    assert_eq!(map.get_character(AKEYCODE_C, AMETA_ALT_LEFT_ON), Some('ç'));
+}
+
+#[test]
+fn test_ctrl_must_match_exactly() {
+    let map = load_generic();
+
+    // There is no ctrl behavior for A so nothing matches, not even base.
+    assert_eq!(map.get_character(AKEYCODE_A, AMETA_CTRL_ON), None);
+    // Shift does not have to match exactly so the base behavior of 1 still applies with
+    // caps lock on.
+    assert_eq!(map.get_character(AKEYCODE_1, AMETA_CAPS_LOCK_ON), Some('1'));
+}
+
+#[test]
+fn test_fallback_actions() {
+    let map = load_generic();
+
+    assert_eq!(
+        map.get_fallback_action(AKEYCODE_ESCAPE, AMETA_ALT_ON),
+        Some(FallbackAction {
+            key_code: AKEYCODE_HOME,
+            meta_state: AMETA_NONE
+        })
+    );
+    assert_eq!(
+        map.get_fallback_action(AKEYCODE_ESCAPE, AMETA_CTRL_ON | AMETA_SHIFT_ON),
+        Some(FallbackAction {
+            key_code: AKEYCODE_MENU,
+            meta_state: AMETA_SHIFT_ON
+        })
+    );
+    assert_eq!(
+        map.get_fallback_action(AKEYCODE_SPACE, AMETA_META_ON),
+        Some(FallbackAction {
+            key_code: AKEYCODE_SEARCH,
+            meta_state: AMETA_NONE
+        })
+    );
+    assert_eq!(map.get_fallback_action(AKEYCODE_ESCAPE, AMETA_NONE), None);
+}
+
+#[test]
+fn test_none_behavior_types_nothing() {
+    let map = load_generic();
+
+    assert_eq!(map.get_character(AKEYCODE_TAB, AMETA_META_ON), None);
+    assert_eq!(
+        map.get_key_behavior(AKEYCODE_TAB, AMETA_META_ON)
+            .map(|behavior| behavior.meta_state),
+        Some(AMETA_META_ON)
+    );
+}
+
+#[test]
+fn test_number_is_derived_from_behaviors() {
+    let map = load_generic();
+
+    assert_eq!(map.get_number(AKEYCODE_1), Some('1'));
+    assert_eq!(map.get_number(AKEYCODE_COMMA), Some(','));
+    assert_eq!(map.get_number(AKEYCODE_A), None);
+}
+
+#[test]
+fn test_explicit_number() {
+    let map = load_key("number: '2'\nbase: 'a'").unwrap();
+    assert_eq!(map.get_number(AKEYCODE_A), Some('2'));
+}
+
+#[test]
+fn test_unicode_escape() {
+    let map = load_key("base: '\\u00e4'").unwrap();
+    assert_eq!(map.get_character(AKEYCODE_A, AMETA_NONE), Some('ä'));
+}
+
+#[test]
+fn test_replace_behavior() {
+    let map = load_key("fn: replace Q").unwrap();
+
+    assert_eq!(
+        map.get_key_behavior(AKEYCODE_A, 0x08)
+            .and_then(|behavior| behavior.replacement_key_code),
+        Some(AKEYCODE_Q)
+    );
+}
+
+#[test]
+fn test_map_key() {
+    let map =
+        KeyCharacterMap::load_from_contents("type OVERLAY\nmap key 0x10 A\nmap usage 0x070004 A\n")
+            .unwrap();
+
+    assert_eq!(map.keyboard_type(), KeyboardType::Overlay);
+    assert_eq!(map.map_key(0x10), Some(AKEYCODE_A));
+    assert_eq!(map.map_key(0x11), None);
+}
+
+#[test]
+fn test_missing_type_is_error() {
+    assert!(KeyCharacterMap::load_from_contents("key A {\n base: 'a'\n}\n").is_err());
+}
+
+#[test]
+fn test_unterminated_key_is_error() {
+    assert!(KeyCharacterMap::load_from_contents("type FULL\nkey A {\n base: 'a'\n").is_err());
+}
+
+#[test]
+fn test_invalid_properties_are_errors() {
+    // Unknown modifier
+    assert!(load_key("hyper: 'a'").is_err());
+    // Duplicate modifier in a combination
+    assert!(load_key("shift+shift: 'a'").is_err());
+    // Duplicate behavior for the same modifiers
+    assert!(load_key("base: 'a'\nbase: 'b'").is_err());
+    // Two characters
+    assert!(load_key("base: 'a' 'b'").is_err());
+    // Character with replace
+    assert!(load_key("base: 'a' replace B").is_err());
+    // Unknown key code
+    assert!(load_key("base: fallback NOT_A_KEY").is_err());
+    // Malformed character literal
+    assert!(load_key("base: 'ab'").is_err());
+    assert!(load_key("base: '\\q'").is_err());
+    // Missing colon
+    assert!(load_key("base 'a'").is_err());
+}
@@ -0,0 +1,297 @@
+//! Tests for reloading key layout files when they change on disk.
+use evdev_manager_core::android::android_codes::{
+    AKEYCODE_BACK, AKEYCODE_HOME, AKEYCODE_MOVE_HOME,
+};
+use evdev_manager_core::android::keylayout::key_layout_file_finder::KeyLayoutFileFinder;
+use evdev_manager_core::android::keylayout::key_layout_file_watcher::KeyLayoutFileWatcher;
+use evdev_manager_core::android::keylayout::key_layout_map_manager::KeyLayoutMapManager;
+use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A temporary directory that is deleted when it is dropped.
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "key_layout_file_watcher_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+
+        fs::remove_dir_all(&path).ok();
+        fs::create_dir_all(&path).unwrap();
+
+        // Resolve the temporary directory in case it is a symlink itself.
+        Self {
+            path: fs::canonicalize(path).unwrap(),
+        }
+    }
+
+    fn write_key_layout(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.path.join(format!("{}.kl", name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn write_input_device_configuration(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.path.join(format!("{}.idc", name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.path).ok();
+    }
+}
+
+/// Finds key layout and input device configuration files in a single directory.
+struct DirectoryFileFinder {
+    directory: PathBuf,
+}
+
+impl KeyLayoutFileFinder for DirectoryFileFinder {
+    fn find_system_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        let path = self.directory.join(format!("{}.kl", name));
+        path.is_file().then_some(path)
+    }
+
+    fn find_key_mapper_key_layout_file_by_name(&self, _name: &str) -> Option<PathBuf> {
+        None
+    }
+
+    fn find_user_key_layout_file_by_name(&self, _name: &str) -> Option<PathBuf> {
+        None
+    }
+
+    fn key_layout_directories(&self) -> Vec<PathBuf> {
+        vec![self.directory.clone()]
+    }
+
+    fn find_system_key_character_map_file_by_name(&self, _name: &str) -> Option<PathBuf> {
+        None
+    }
+
+    fn find_system_input_device_configuration_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        let path = self.directory.join(format!("{}.idc", name));
+        path.is_file().then_some(path)
+    }
+
+    fn input_device_configuration_directories(&self) -> Vec<PathBuf> {
+        vec![self.directory.clone()]
+    }
+}
+
+fn create_manager(dir: &TempDir) -> Arc<KeyLayoutMapManager> {
+    Arc::new(KeyLayoutMapManager::with_file_finder(Arc::new(
+        DirectoryFileFinder {
+            directory: dir.path.clone(),
+        },
+    )))
+}
+
+fn device_info() -> EvdevDeviceInfo {
+    EvdevDeviceInfo {
+        name: "Cheap Remote".to_string(),
+        bus: 0x0003,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 0x0000,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    }
+}
+
+#[test]
+fn test_changed_file_reloads_device_map() {
+    let dir = TempDir::new("changed");
+    let manager = create_manager(&dir);
+    let device = device_info();
+
+    // Generic.kl maps 102 to MOVE_HOME
+    assert_eq!(
+        manager.map_key(&device, 102).unwrap(),
+        Some(AKEYCODE_MOVE_HOME)
+    );
+
+    let path = dir.write_key_layout("Vendor_1234_Product_5678", "key 102 HOME");
+
+    // The generic fallback is cached until the manager is told about the new file.
+    assert_eq!(
+        manager.map_key(&device, 102).unwrap(),
+        Some(AKEYCODE_MOVE_HOME)
+    );
+
+    manager.on_key_layout_files_changed(std::slice::from_ref(&path));
+    assert_eq!(manager.map_key(&device, 102).unwrap(), Some(AKEYCODE_HOME));
+
+    dir.write_key_layout("Vendor_1234_Product_5678", "key 102 BACK");
+    manager.on_key_layout_files_changed(std::slice::from_ref(&path));
+    assert_eq!(manager.map_key(&device, 102).unwrap(), Some(AKEYCODE_BACK));
+
+    fs::remove_file(&path).unwrap();
+    manager.on_key_layout_files_changed(&[path]);
+    assert_eq!(
+        manager.map_key(&device, 102).unwrap(),
+        Some(AKEYCODE_MOVE_HOME)
+    );
+}
+
+#[test]
+fn test_unrelated_file_keeps_cached_map() {
+    let dir = TempDir::new("unrelated");
+    let manager = create_manager(&dir);
+    let device = device_info();
+
+    manager.map_key(&device, 102).unwrap();
+
+    let path = dir.write_key_layout("Vendor_9999_Product_9999", "key 102 HOME");
+    manager.on_key_layout_files_changed(&[path]);
+
+    assert!(manager
+        .key_layout_maps
+        .lock()
+        .unwrap()
+        .contains_key(&device));
+}
+
+#[test]
+fn test_generic_file_reloads_every_device() {
+    let dir = TempDir::new("generic");
+    let manager = create_manager(&dir);
+    let device = device_info();
+
+    manager.map_key(&device, 102).unwrap();
+
+    let path = dir.write_key_layout("Generic", "key 102 BACK");
+    manager.on_key_layout_files_changed(&[path]);
+
+    assert!(!manager
+        .key_layout_maps
+        .lock()
+        .unwrap()
+        .contains_key(&device));
+    assert_eq!(manager.map_key(&device, 102).unwrap(), Some(AKEYCODE_BACK));
+}
+
+#[test]
+fn test_changed_input_device_configuration_reloads_device() {
+    let dir = TempDir::new("configuration");
+    let manager = create_manager(&dir);
+    let device = device_info();
+
+    assert!(manager.get_input_device_configuration(&device).is_none());
+    assert_eq!(
+        manager.map_key(&device, 102).unwrap(),
+        Some(AKEYCODE_MOVE_HOME)
+    );
+
+    dir.write_key_layout("Remote", "key 102 HOME");
+    let path = dir
+        .write_input_device_configuration("Vendor_1234_Product_5678", "keyboard.layout = Remote");
+    manager.on_key_layout_files_changed(&[path]);
+
+    assert_eq!(
+        manager
+            .get_input_device_configuration(&device)
+            .unwrap()
+            .keyboard_layout(),
+        Some("Remote")
+    );
+    assert_eq!(manager.map_key(&device, 102).unwrap(), Some(AKEYCODE_HOME));
+}
+
+#[test]
+fn test_watcher_reloads_added_file() {
+    let dir = TempDir::new("watch");
+    let manager = create_manager(&dir);
+    let device = device_info();
+
+    assert_eq!(
+        manager.map_key(&device, 102).unwrap(),
+        Some(AKEYCODE_MOVE_HOME)
+    );
+
+    manager.start_watching().unwrap();
+    dir.write_key_layout("Vendor_1234_Product_5678", "key 102 HOME");
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    while manager.map_key(&device, 102).unwrap() != Some(AKEYCODE_HOME) {
+        assert!(
+            Instant::now() < deadline,
+            "Key layout file was not reloaded"
+        );
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    manager.stop_watching();
+}
+
+#[test]
+fn test_watcher_only_reports_key_layout_files() {
+    let dir = TempDir::new("filter");
+    let watcher = KeyLayoutFileWatcher::new();
+    let (sender, receiver) = mpsc::channel();
+
+    watcher
+        .start(std::slice::from_ref(&dir.path), move |paths| {
+            sender.send(paths.to_vec()).ok();
+        })
+        .unwrap();
+
+    fs::write(dir.path.join("notes.txt"), "").unwrap();
+    let path = dir.write_key_layout("Generic", "key 1 ESCAPE");
+
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+        vec![path]
+    );
+
+    watcher.stop();
+}
+
+#[test]
+fn test_watcher_reports_files_in_directory_created_later() {
+    let dir = TempDir::new("created_later");
+    let directory = dir.path.join("keylayout").join("vendor");
+    let watcher = KeyLayoutFileWatcher::new();
+    let (sender, receiver) = mpsc::channel();
+
+    watcher
+        .start(std::slice::from_ref(&directory), move |paths| {
+            sender.send(paths.to_vec()).ok();
+        })
+        .unwrap();
+
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("Generic.kl");
+    fs::write(&path, "key 1 ESCAPE").unwrap();
+
+    // The file is reported by the new watch or when the directory is first watched.
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let paths = receiver
+            .recv_timeout(remaining)
+            .expect("File in the created directory was not reported");
+
+        if paths.contains(&path) {
+            break;
+        }
+    }
+
+    watcher.stop();
+}
@@ -1,12 +1,18 @@
 //! Tests for KeyLayoutMapManager file finding logic.
 use assertables::{assert_iter_eq, assert_some, assert_some_eq};
+use evdev::util::int_to_event_code;
 use evdev_manager_core::android::android_codes::{
-    AKEYCODE_ESCAPE, AKEYCODE_HOME, AKEYCODE_MINUS, AKEYCODE_MOVE_HOME, AKEYCODE_SPACE,
+    AKEYCODE_A, AKEYCODE_BRIGHTNESS_UP, AKEYCODE_ESCAPE, AKEYCODE_HOME, AKEYCODE_MINUS,
+    AKEYCODE_MOVE_HOME, AKEYCODE_SPACE, ALED_CAPS_LOCK, ALED_CONTROLLER_1,
+    AMOTION_EVENT_AXIS_RTRIGGER, ASENSOR_TYPE_GYROSCOPE,
+};
+use evdev_manager_core::android::keylayout::key_layout_file_finder::{
+    AndroidKeyLayoutFileFinder, KeyLayoutFileFinder,
 };
-use evdev_manager_core::android::keylayout::key_layout_file_finder::KeyLayoutFileFinder;
 use evdev_manager_core::android::keylayout::key_layout_map_manager::{
     get_generic_key_layout_map, KeyLayoutMapManager,
 };
+use evdev_manager_core::device_capabilities::DeviceCapabilities;
 use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
 #[cfg(test)]
 use pretty_assertions::assert_eq;
@@ -18,6 +24,9 @@ use std::sync::Arc;
 struct MockFileFinder {
     system_files: HashMap<String, PathBuf>,
     key_mapper_files: HashMap<String, PathBuf>,
+    user_files: HashMap<String, PathBuf>,
+    key_character_map_files: HashMap<String, PathBuf>,
+    input_device_configuration_files: HashMap<String, PathBuf>,
 }
 
 impl MockFileFinder {
@@ -25,6 +34,9 @@ impl MockFileFinder {
         Self {
             system_files: HashMap::new(),
             key_mapper_files: HashMap::new(),
+            user_files: HashMap::new(),
+            key_character_map_files: HashMap::new(),
+            input_device_configuration_files: HashMap::new(),
         }
     }
 
@@ -32,6 +44,27 @@ impl MockFileFinder {
         self.system_files.insert(name.to_string(), path);
         self
     }
+
+    fn add_key_mapper_file(mut self, name: &str, path: PathBuf) -> Self {
+        self.key_mapper_files.insert(name.to_string(), path);
+        self
+    }
+
+    fn add_user_file(mut self, name: &str, path: PathBuf) -> Self {
+        self.user_files.insert(name.to_string(), path);
+        self
+    }
+
+    fn add_key_character_map_file(mut self, name: &str, path: PathBuf) -> Self {
+        self.key_character_map_files.insert(name.to_string(), path);
+        self
+    }
+
+    fn add_input_device_configuration_file(mut self, name: &str, path: PathBuf) -> Self {
+        self.input_device_configuration_files
+            .insert(name.to_string(), path);
+        self
+    }
 }
 
 impl KeyLayoutFileFinder for MockFileFinder {
@@ -42,6 +75,26 @@ impl KeyLayoutFileFinder for MockFileFinder {
     fn find_key_mapper_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf> {
         self.key_mapper_files.get(name).cloned()
     }
+
+    fn find_user_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        self.user_files.get(name).cloned()
+    }
+
+    fn key_layout_directories(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    fn find_system_key_character_map_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        self.key_character_map_files.get(name).cloned()
+    }
+
+    fn find_system_input_device_configuration_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        self.input_device_configuration_files.get(name).cloned()
+    }
+
+    fn input_device_configuration_directories(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
 }
 
 fn find_key_layout_files_str(
@@ -72,6 +125,9 @@ fn test_find_no_files() {
         vendor: 0x1234,
         product: 0x5678,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     let files: Vec<String> = find_key_layout_files_str(mock_finder, &device);
@@ -92,6 +148,9 @@ fn test_only_find_generic_file() {
         vendor: 0x1234,
         product: 0x5678,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     let files: Vec<String> = find_key_layout_files_str(mock_finder, &device);
@@ -126,6 +185,9 @@ fn test_find_key_layout_files_priority_order() {
         vendor: 0x1234,
         product: 0x5678,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     let files: Vec<String> = find_key_layout_files_str(mock_finder, &device);
@@ -155,6 +217,9 @@ fn test_preload_key_layout_map_returns_cached_result() {
         vendor: 0x0000,
         product: 0x0000,
         version: 0x0000,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     let result1 = manager.preload_key_layout_map(&device).unwrap();
@@ -182,6 +247,9 @@ fn test_preload_key_layout_map_saves_some_when_found() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     assert_some!(manager.preload_key_layout_map(&device).unwrap());
@@ -203,6 +271,9 @@ fn test_preload_key_layout_map_uses_fallback_when_not_found() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // Should return the hardcoded fallback, not None
@@ -227,6 +298,9 @@ fn test_map_key_reads_from_cache() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     let cached_key_layout_map = manager.preload_key_layout_map(&device).unwrap().unwrap();
@@ -253,6 +327,9 @@ fn test_map_key_saves_to_cache() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // Verify the cached value is returned
@@ -277,6 +354,9 @@ fn test_map_key_finds_file_if_cache_miss() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // Verify the key is found and has the correct key code
@@ -300,6 +380,9 @@ fn test_map_key_reads_first_found_path() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // Verify the correct key code is returned (device-specific file takes priority)
@@ -323,6 +406,9 @@ fn test_map_key_reads_generic_if_device_specific_not_found() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // Verify the fallback to Generic.kl works
@@ -373,6 +459,9 @@ fn test_fallback_to_hardcoded_generic_when_no_files_found() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // Should still return a valid key layout map (the hardcoded fallback)
@@ -399,6 +488,9 @@ fn test_fallback_map_key_works_when_no_files_found() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // map_key should work using the hardcoded fallback
@@ -426,6 +518,9 @@ fn test_fallback_is_cached() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // First call should use the fallback
@@ -453,6 +548,9 @@ fn test_fallback_uses_static_generic_map() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // Get the fallback via manager
@@ -485,6 +583,9 @@ fn test_map_key_falls_back_to_generic_for_missing_scan_code() {
         vendor: 0x0000,
         product: 0x0000,
         version: 0x0000,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // Scan code 102 is in gpio-keys.kl and maps to HOME
@@ -517,6 +618,9 @@ fn test_find_scan_code_for_key_falls_back_to_generic_for_missing_key_code() {
         vendor: 0x0000,
         product: 0x0000,
         version: 0x0000,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // HOME is in gpio-keys.kl, should return scan code 102
@@ -538,6 +642,75 @@ fn test_find_scan_code_for_key_falls_back_to_generic_for_missing_key_code() {
     assert_eq!(scan_code, Some(57));
 }
 
+#[test]
+fn test_find_scan_codes_for_key_does_not_mix_in_generic() {
+    let mock_finder = Arc::new(
+        MockFileFinder::new()
+            .add_system_file("gpio-keys", get_test_data_path().join("6t/gpio-keys.kl")),
+    );
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+    let device = gpio_keys();
+
+    // Generic.kl maps HOME to 172 but writing 172 would not be HOME for this device.
+    let scan_codes: Vec<u32> = manager
+        .find_scan_codes_for_key(&device, AKEYCODE_HOME)
+        .unwrap()
+        .into_iter()
+        .map(|(scan_code, _)| scan_code)
+        .collect();
+    assert_eq!(scan_codes, vec![102]);
+
+    let scan_codes: Vec<u32> = manager
+        .find_scan_codes_for_key(&device, AKEYCODE_ESCAPE)
+        .unwrap()
+        .into_iter()
+        .map(|(scan_code, _)| scan_code)
+        .collect();
+    assert_eq!(scan_codes, vec![1, 465]);
+}
+
+#[test]
+fn test_find_supported_scan_code_for_key_prefers_supported_scan_code() {
+    let manager = KeyLayoutMapManager::with_file_finder(Arc::new(MockFileFinder::new()));
+    let device = gpio_keys();
+    let escape_scan_code = |capabilities: &DeviceCapabilities| {
+        manager
+            .find_supported_scan_code_for_key(&device, capabilities, AKEYCODE_ESCAPE)
+            .unwrap()
+    };
+
+    // Generic.kl maps ESCAPE to 1 and to 465 with the FUNCTION flag.
+    let function_keys_only = DeviceCapabilities::new(&[int_to_event_code(1, 465)]);
+    assert_eq!(escape_scan_code(&function_keys_only), Some(465));
+
+    let both = DeviceCapabilities::new(&[int_to_event_code(1, 465), int_to_event_code(1, 1)]);
+    assert_eq!(escape_scan_code(&both), Some(1));
+
+    // The first scan code is used if the device supports none of them.
+    assert_eq!(escape_scan_code(&DeviceCapabilities::default()), Some(1));
+
+    assert_eq!(
+        manager
+            .find_supported_scan_code_for_key(&device, &both, 9999)
+            .unwrap(),
+        None
+    );
+}
+
+fn gpio_keys() -> EvdevDeviceInfo {
+    EvdevDeviceInfo {
+        name: "gpio-keys".to_string(),
+        bus: 0x0003,
+        vendor: 0x0000,
+        product: 0x0000,
+        version: 0x0000,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    }
+}
+
 #[test]
 fn test_find_scan_code_for_key_reads_from_cache() {
     let test_kl_path = get_test_data_path().join("Generic.kl");
@@ -552,6 +725,9 @@ fn test_find_scan_code_for_key_reads_from_cache() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     let cached_key_layout_map = manager.preload_key_layout_map(&device).unwrap().unwrap();
@@ -585,6 +761,9 @@ fn test_find_scan_code_for_key_saves_to_cache() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // First call loads and caches the map
@@ -615,6 +794,9 @@ fn test_find_scan_code_for_key_finds_file_if_cache_miss() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // MINUS key code should map to scan code 12 in Generic.kl
@@ -641,6 +823,9 @@ fn test_find_scan_code_for_key_reads_first_found_path() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // Device-specific file (gpio-keys.kl) should take priority
@@ -666,6 +851,9 @@ fn test_find_scan_code_for_key_reads_generic_if_device_not_found() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // No device-specific file found, fallback to Generic.kl
@@ -690,9 +878,464 @@ fn test_find_scan_code_for_key_returns_none_for_unknown_key() {
         vendor: 0x9999,
         product: 0x8888,
         version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
     };
 
     // Unknown key code should return None
     let scan_code = manager.find_scan_code_for_key(&device, 99999).unwrap();
     assert_eq!(scan_code, None);
 }
+
+#[test]
+fn test_map_axis_reads_generic_if_device_not_found() {
+    let mock_finder = Arc::new(MockFileFinder::new());
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+
+    let device = EvdevDeviceInfo {
+        name: "Unknown Gamepad".to_string(),
+        bus: 0x0003,
+        vendor: 0x9999,
+        product: 0x8888,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    };
+
+    // In Generic.kl, ABS_GAS (0x09) maps to RTRIGGER
+    let axis_info = manager.map_axis(&device, 0x09).unwrap().unwrap();
+    assert_eq!(axis_info.axis, AMOTION_EVENT_AXIS_RTRIGGER);
+
+    assert!(manager.map_axis(&device, 0x30).unwrap().is_none());
+}
+
+#[test]
+fn test_map_key_with_usage_reads_generic_if_device_not_found() {
+    let mock_finder = Arc::new(MockFileFinder::new());
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+
+    let device = EvdevDeviceInfo {
+        name: "Unknown Keyboard".to_string(),
+        bus: 0x0003,
+        vendor: 0x9999,
+        product: 0x8888,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    };
+
+    // In Generic.kl, usage 0x0c006F maps to BRIGHTNESS_UP and scan code 1 to ESCAPE
+    assert_eq!(
+        manager
+            .map_key_with_usage(&device, 1, Some(0x0c006f))
+            .unwrap(),
+        Some(AKEYCODE_BRIGHTNESS_UP)
+    );
+    assert_eq!(
+        manager.map_key_with_usage(&device, 1, None).unwrap(),
+        Some(AKEYCODE_ESCAPE)
+    );
+    assert_eq!(
+        manager
+            .map_key_with_usage(&device, 1, Some(0x0c0001))
+            .unwrap(),
+        Some(AKEYCODE_ESCAPE)
+    );
+}
+
+#[test]
+fn test_led_and_sensor_lookups_read_generic_if_device_not_found() {
+    let mock_finder = Arc::new(MockFileFinder::new());
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+
+    let device = EvdevDeviceInfo {
+        name: "Unknown Keyboard".to_string(),
+        bus: 0x0003,
+        vendor: 0x9999,
+        product: 0x8888,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    };
+
+    assert_eq!(
+        manager
+            .find_scan_code_for_led(&device, ALED_CAPS_LOCK)
+            .unwrap(),
+        Some(0x01)
+    );
+    assert_eq!(
+        manager
+            .find_scan_code_for_led(&device, ALED_CONTROLLER_1)
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        manager
+            .find_abs_code_for_sensor(&device, ASENSOR_TYPE_GYROSCOPE, 2)
+            .unwrap(),
+        Some(0x05)
+    );
+}
+
+#[test]
+fn test_find_scan_code_for_led_reads_device_map() {
+    let mock_finder = MockFileFinder::new().add_system_file(
+        "Vendor_18d1_Product_2c40",
+        get_test_data_path().join("pixel-7/Vendor_18d1_Product_2c40.kl"),
+    );
+
+    let manager = KeyLayoutMapManager::with_file_finder(Arc::new(mock_finder));
+
+    let device = EvdevDeviceInfo {
+        name: "ADT-1 Controller".to_string(),
+        bus: 0x0003,
+        vendor: 0x18d1,
+        product: 0x2c40,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    };
+
+    assert_eq!(
+        manager
+            .find_scan_code_for_led(&device, ALED_CONTROLLER_1)
+            .unwrap(),
+        Some(0x00)
+    );
+}
+
+#[test]
+fn test_find_key_layout_files_searches_user_then_system_then_key_mapper() {
+    let mock_finder = Arc::new(
+        MockFileFinder::new()
+            .add_user_file(
+                "Vendor_1234_Product_5678",
+                PathBuf::from("/user/Vendor_1234_Product_5678.kl"),
+            )
+            .add_system_file(
+                "Vendor_1234_Product_5678",
+                PathBuf::from("/system/usr/keylayout/Vendor_1234_Product_5678.kl"),
+            )
+            .add_key_mapper_file(
+                "Vendor_1234_Product_5678",
+                PathBuf::from("/bundled/Vendor_1234_Product_5678.kl"),
+            )
+            .add_system_file("Generic", PathBuf::from("/system/usr/keylayout/Generic.kl"))
+            .add_key_mapper_file("Generic", PathBuf::from("/bundled/Generic.kl")),
+    );
+
+    let device = EvdevDeviceInfo {
+        name: "Test Device".to_string(),
+        bus: 0x0003,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 0x0000,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    };
+
+    let result = find_key_layout_files_str(mock_finder, &device);
+
+    assert_eq!(
+        result,
+        vec![
+            "/user/Vendor_1234_Product_5678.kl",
+            "/system/usr/keylayout/Vendor_1234_Product_5678.kl",
+            "/bundled/Vendor_1234_Product_5678.kl",
+            "/system/usr/keylayout/Generic.kl",
+            "/bundled/Generic.kl",
+        ]
+    );
+}
+
+#[test]
+fn test_map_key_uses_key_mapper_file_before_system_generic() {
+    let mock_finder = MockFileFinder::new()
+        .add_system_file("Generic", get_test_data_path().join("Generic.kl"))
+        .add_key_mapper_file("gpio-keys", get_test_data_path().join("6t/gpio-keys.kl"));
+
+    let manager = KeyLayoutMapManager::with_file_finder(Arc::new(mock_finder));
+
+    let device = EvdevDeviceInfo {
+        name: "gpio-keys".to_string(),
+        bus: 0x0003,
+        vendor: 0x9999,
+        product: 0x8888,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    };
+
+    // In gpio-keys.kl this is HOME and in Generic.kl this is MOVE_HOME
+    assert_eq!(manager.map_key(&device, 102).unwrap(), Some(AKEYCODE_HOME));
+}
+
+#[test]
+fn test_android_file_finder_searches_key_mapper_and_user_directories() {
+    let finder = AndroidKeyLayoutFileFinder::new(Some(get_test_data_path().join("6t")), None);
+
+    assert_eq!(
+        finder.find_key_mapper_key_layout_file_by_name("gpio-keys"),
+        Some(get_test_data_path().join("6t/gpio-keys.kl"))
+    );
+    assert_eq!(
+        finder.find_key_mapper_key_layout_file_by_name("Generic"),
+        None
+    );
+    assert_eq!(finder.find_user_key_layout_file_by_name("gpio-keys"), None);
+
+    let finder = AndroidKeyLayoutFileFinder::new(None, Some(get_test_data_path()));
+
+    assert_eq!(
+        finder.find_user_key_layout_file_by_name("Generic"),
+        Some(get_test_data_path().join("Generic.kl"))
+    );
+    assert_eq!(
+        finder.find_key_mapper_key_layout_file_by_name("Generic"),
+        None
+    );
+}
+
+#[test]
+fn test_find_key_character_map_files_priority_order() {
+    let mock_finder = Arc::new(
+        MockFileFinder::new()
+            .add_system_file(
+                "Vendor_1234_Product_5678",
+                PathBuf::from("/system/Vendor.kl"),
+            )
+            .add_key_character_map_file(
+                "Vendor_1234_Product_5678_Version_0001",
+                PathBuf::from("/system/Version.kcm"),
+            )
+            .add_key_character_map_file(
+                "Vendor_1234_Product_5678",
+                PathBuf::from("/system/Vendor.kcm"),
+            )
+            .add_key_character_map_file("My_Keyboard", PathBuf::from("/system/Name.kcm"))
+            .add_key_character_map_file("Generic", PathBuf::from("/system/Generic.kcm")),
+    );
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+
+    let device = EvdevDeviceInfo {
+        name: "My Keyboard".to_string(),
+        bus: 0x0003,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    };
+
+    assert_eq!(
+        manager.find_key_character_map_files(&device),
+        vec![
+            PathBuf::from("/system/Version.kcm"),
+            PathBuf::from("/system/Vendor.kcm"),
+            PathBuf::from("/system/Name.kcm"),
+            PathBuf::from("/system/Generic.kcm"),
+        ]
+    );
+}
+
+#[test]
+fn test_get_key_character_map_loads_first_found_file() {
+    let mock_finder = Arc::new(
+        MockFileFinder::new()
+            .add_key_character_map_file("Generic", get_test_data_path().join("Generic.kcm")),
+    );
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+
+    let device = EvdevDeviceInfo {
+        name: "My Keyboard".to_string(),
+        bus: 0x0003,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    };
+
+    let key_character_map = manager.get_key_character_map(&device).unwrap();
+    assert_eq!(key_character_map.get_character(AKEYCODE_A, 0), Some('a'));
+}
+
+#[test]
+fn test_get_key_character_map_returns_none_when_not_found() {
+    let manager = KeyLayoutMapManager::with_file_finder(Arc::new(MockFileFinder::new()));
+
+    let device = EvdevDeviceInfo {
+        name: "My Keyboard".to_string(),
+        bus: 0x0003,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    };
+
+    assert!(manager.get_key_character_map(&device).is_none());
+}
+
+fn custom_keyboard() -> EvdevDeviceInfo {
+    EvdevDeviceInfo {
+        name: "Custom Keyboard".to_string(),
+        bus: 0x0003,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    }
+}
+
+#[test]
+fn test_find_key_layout_files_prefers_idc_keyboard_layout() {
+    let mock_finder = Arc::new(
+        MockFileFinder::new()
+            .add_input_device_configuration_file(
+                "Custom_Keyboard",
+                get_test_data_path().join("Custom_Keyboard.idc"),
+            )
+            .add_system_file("Custom_Layout", PathBuf::from("/system/Custom_Layout.kl"))
+            .add_system_file(
+                "Vendor_1234_Product_5678",
+                PathBuf::from("/system/Vendor.kl"),
+            )
+            .add_system_file("Generic", PathBuf::from("/system/Generic.kl"))
+            .add_key_character_map_file("Custom_Chars", PathBuf::from("/system/Custom_Chars.kcm"))
+            .add_key_character_map_file("Generic", PathBuf::from("/system/Generic.kcm")),
+    );
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+
+    assert_eq!(
+        manager.find_key_layout_files(&custom_keyboard()),
+        vec![
+            PathBuf::from("/system/Custom_Layout.kl"),
+            PathBuf::from("/system/Vendor.kl"),
+            PathBuf::from("/system/Generic.kl"),
+        ]
+    );
+    assert_eq!(
+        manager.find_key_character_map_files(&custom_keyboard()),
+        vec![
+            PathBuf::from("/system/Custom_Chars.kcm"),
+            PathBuf::from("/system/Generic.kcm"),
+        ]
+    );
+}
+
+#[test]
+fn test_find_key_layout_files_falls_back_if_idc_layout_not_found() {
+    let mock_finder = Arc::new(
+        MockFileFinder::new()
+            .add_input_device_configuration_file(
+                "Vendor_1234_Product_5678",
+                get_test_data_path().join("Custom_Keyboard.idc"),
+            )
+            .add_system_file("Generic", PathBuf::from("/system/Generic.kl")),
+    );
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+
+    assert_eq!(
+        manager.find_key_layout_files(&custom_keyboard()),
+        vec![PathBuf::from("/system/Generic.kl")]
+    );
+}
+
+#[test]
+fn test_idc_keyboard_layout_is_not_duplicated() {
+    let mock_finder = Arc::new(
+        MockFileFinder::new()
+            .add_input_device_configuration_file(
+                "Custom_Keyboard",
+                get_test_data_path().join("Custom_Keyboard.idc"),
+            )
+            .add_system_file("Custom_Layout", PathBuf::from("/system/Custom_Layout.kl"))
+            .add_system_file("Custom_Keyboard", PathBuf::from("/system/Custom_Layout.kl")),
+    );
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+
+    assert_eq!(
+        manager.find_key_layout_files(&custom_keyboard()),
+        vec![PathBuf::from("/system/Custom_Layout.kl")]
+    );
+}
+
+#[test]
+fn test_map_key_uses_idc_keyboard_layout() {
+    let mock_finder = Arc::new(
+        MockFileFinder::new()
+            .add_input_device_configuration_file(
+                "Custom_Keyboard",
+                get_test_data_path().join("Custom_Keyboard.idc"),
+            )
+            .add_system_file(
+                "Custom_Layout",
+                get_test_data_path().join("6t").join("gpio-keys.kl"),
+            )
+            .add_system_file("Generic", get_test_data_path().join("Generic.kl")),
+    );
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+
+    // gpio-keys.kl maps scan code 102 to HOME while Generic.kl maps it to MOVE_HOME.
+    assert_eq!(
+        manager.map_key(&custom_keyboard(), 102).unwrap(),
+        Some(AKEYCODE_HOME)
+    );
+}
+
+#[test]
+fn test_get_input_device_configuration() {
+    let mock_finder = Arc::new(MockFileFinder::new().add_input_device_configuration_file(
+        "Vendor_1234_Product_5678_Version_0001",
+        get_test_data_path().join("Custom_Keyboard.idc"),
+    ));
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+
+    let configuration = manager
+        .get_input_device_configuration(&custom_keyboard())
+        .unwrap();
+    assert_eq!(configuration.touch_device_type(), Some("touchPad"));
+
+    let manager = KeyLayoutMapManager::with_file_finder(Arc::new(MockFileFinder::new()));
+    assert!(manager
+        .get_input_device_configuration(&custom_keyboard())
+        .is_none());
+}
+
+#[test]
+fn test_input_device_configuration_does_not_fall_back_to_generic() {
+    let mock_finder = Arc::new(MockFileFinder::new().add_input_device_configuration_file(
+        "Generic",
+        get_test_data_path().join("Custom_Keyboard.idc"),
+    ));
+
+    let manager = KeyLayoutMapManager::with_file_finder(mock_finder);
+
+    assert!(manager
+        .get_input_device_configuration(&custom_keyboard())
+        .is_none());
+}
@@ -1,7 +1,16 @@
 //! Integration tests for key layout map parsing.
-use evdev_manager_core::android::android_codes::{AKEYCODE_F4, AKEYCODE_FUNCTION};
+use evdev_manager_core::android::android_codes::{
+    AKEYCODE_A, AKEYCODE_B, AKEYCODE_BRIGHTNESS_DOWN, AKEYCODE_BRIGHTNESS_UP, AKEYCODE_C,
+    AKEYCODE_ESCAPE, AKEYCODE_F4, AKEYCODE_FUNCTION, AKEYCODE_MEDIA_PLAY, AKEYCODE_WINDOW,
+    ALED_CAPS_LOCK, ALED_CONTROLLER_1, ALED_NUM_LOCK, ALED_SCROLL_LOCK, ASENSOR_TYPE_ACCELEROMETER,
+    ASENSOR_TYPE_GYROSCOPE, ASENSOR_TYPE_LIGHT, POLICY_FLAG_FALLBACK_USAGE_MAPPING,
+    POLICY_FLAG_FUNCTION, POLICY_FLAG_VIRTUAL, POLICY_FLAG_WAKE,
+};
 use evdev_manager_core::android::keylayout::key_layout_map;
-use evdev_manager_core::android::keylayout::key_layout_map::{KeyLayoutAxisMode, KeyLayoutMap};
+use evdev_manager_core::android::keylayout::key_layout_map::{
+    KeyLayoutAxisMode, KeyLayoutKey, KeyLayoutMap, KeyLayoutSensorInfo,
+};
+use evdev_manager_core::android::keylayout::key_layout_parse_error::KeyLayoutParseErrorKind;
 use glob::glob;
 #[cfg(test)]
 use pretty_assertions::assert_eq;
@@ -90,8 +99,235 @@ fn test_brightness_usage_key_lines() {
     key usage 0x0c006F BRIGHTNESS_UP
     key usage 0x0c0070 BRIGHTNESS_DOWN";
 
-    KeyLayoutMap::load_from_contents(content).unwrap();
-    // Just do not crash because it should be skipped.
+    let map = KeyLayoutMap::load_from_contents(content).unwrap();
+
+    assert_eq!(map.map_usage(0x0c0067), Some(AKEYCODE_WINDOW));
+    assert_eq!(map.map_usage(0x0c006f), Some(AKEYCODE_BRIGHTNESS_UP));
+    assert_eq!(map.map_usage(0x0c0070), Some(AKEYCODE_BRIGHTNESS_DOWN));
+}
+
+#[test]
+fn test_usage_entries_are_not_scan_codes() {
+    let content = "key usage 0x0c006F BRIGHTNESS_UP";
+
+    let map = KeyLayoutMap::load_from_contents(content).unwrap();
+
+    assert_eq!(map.map_key(0x0c006f), None);
+    assert_eq!(map.find_scan_code_for_key(AKEYCODE_BRIGHTNESS_UP), None);
+}
+
+#[test]
+fn test_map_key_with_usage_prefers_usage() {
+    let content = "\
+    key 225 BRIGHTNESS_DOWN
+    key usage 0x0c006F BRIGHTNESS_UP";
+
+    let map = KeyLayoutMap::load_from_contents(content).unwrap();
+
+    assert_eq!(
+        map.map_key_with_usage(225, Some(0x0c006f)),
+        Some(AKEYCODE_BRIGHTNESS_UP)
+    );
+    assert_eq!(
+        map.map_key_with_usage(225, Some(0x0c0070)),
+        Some(AKEYCODE_BRIGHTNESS_DOWN)
+    );
+    assert_eq!(
+        map.map_key_with_usage(225, None),
+        Some(AKEYCODE_BRIGHTNESS_DOWN)
+    );
+}
+
+#[test]
+fn test_duplicate_usage_is_an_error() {
+    let content = "\
+    key usage 0x0c006F BRIGHTNESS_UP
+    key usage 0x0c006F BRIGHTNESS_DOWN";
+
+    let error = KeyLayoutMap::load_from_contents(content).unwrap_err();
+
+    assert!(
+        error.message.contains("Duplicate entry for key usage"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_invalid_usage_is_an_error() {
+    let error = KeyLayoutMap::load_from_contents("key usage abc WINDOW").unwrap_err();
+
+    assert!(
+        error.message.contains("Expected key usage number"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_key_flags_are_kept() {
+    let content = "\
+    key 464   FUNCTION          FUNCTION
+    key 142   SLEEP             WAKE
+    key 158   BACK              WAKE VIRTUAL
+    key 30    A";
+
+    let map = KeyLayoutMap::load_from_contents(content).unwrap();
+
+    assert_eq!(
+        map.map_key_with_flags(464, None),
+        Some(KeyLayoutKey {
+            key_code: AKEYCODE_FUNCTION,
+            flags: POLICY_FLAG_FUNCTION,
+        })
+    );
+    assert_eq!(
+        map.map_key_with_flags(142, None).map(|key| key.flags),
+        Some(POLICY_FLAG_WAKE)
+    );
+    assert_eq!(
+        map.map_key_with_flags(158, None).map(|key| key.flags),
+        Some(POLICY_FLAG_WAKE | POLICY_FLAG_VIRTUAL)
+    );
+    assert_eq!(
+        map.map_key_with_flags(30, None).map(|key| key.flags),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_usage_key_flags_are_kept() {
+    let content = "key usage 0x0c006F BRIGHTNESS_UP FALLBACK_USAGE_MAPPING";
+
+    let map = KeyLayoutMap::load_from_contents(content).unwrap();
+
+    assert_eq!(
+        map.map_key_with_flags(0, Some(0x0c006f)),
+        Some(KeyLayoutKey {
+            key_code: AKEYCODE_BRIGHTNESS_UP,
+            flags: POLICY_FLAG_FALLBACK_USAGE_MAPPING,
+        })
+    );
+}
+
+#[test]
+fn test_unknown_key_flags_are_ignored() {
+    let map = KeyLayoutMap::load_from_contents("key 752 A WAKE_DROPPED WAKE").unwrap();
+
+    assert_eq!(
+        map.map_key_with_flags(752, None),
+        Some(KeyLayoutKey {
+            key_code: AKEYCODE_A,
+            flags: POLICY_FLAG_WAKE,
+        })
+    );
+}
+
+#[test]
+fn test_duplicate_key_flag_is_an_error() {
+    let error = KeyLayoutMap::load_from_contents("key 142 SLEEP WAKE WAKE").unwrap_err();
+
+    assert!(
+        error.message.contains("Duplicate key flag 'WAKE'"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_parse_leds() {
+    let content = "\
+    led 0x00 NUM_LOCK
+    led 0x01 CAPS_LOCK
+    led usage 0x080003 SCROLL_LOCK";
+
+    let map = KeyLayoutMap::load_from_contents(content).unwrap();
+
+    assert_eq!(map.map_led(0x00), Some(ALED_NUM_LOCK));
+    assert_eq!(map.map_led(0x01), Some(ALED_CAPS_LOCK));
+    assert_eq!(map.map_led(0x02), None);
+    assert_eq!(map.map_led_usage(0x080003), Some(ALED_SCROLL_LOCK));
+
+    assert_eq!(map.find_scan_code_for_led(ALED_CAPS_LOCK), Some(0x01));
+    assert_eq!(map.find_scan_code_for_led(ALED_SCROLL_LOCK), None);
+    assert_eq!(
+        map.find_usage_code_for_led(ALED_SCROLL_LOCK),
+        Some(0x080003)
+    );
+}
+
+#[test]
+fn test_find_scan_code_for_led_returns_lowest() {
+    let content = "\
+    led 0x03 CONTROLLER_1
+    led 0x01 CONTROLLER_1";
+
+    let map = KeyLayoutMap::load_from_contents(content).unwrap();
+
+    assert_eq!(map.find_scan_code_for_led(ALED_CONTROLLER_1), Some(0x01));
+}
+
+#[test]
+fn test_invalid_led_is_an_error() {
+    let error = KeyLayoutMap::load_from_contents("led 0x00 DISCO").unwrap_err();
+    assert!(
+        error.message.contains("Expected LED code label"),
+        "{}",
+        error
+    );
+
+    let content = "\
+    led 0x00 NUM_LOCK
+    led 0x00 CAPS_LOCK";
+    let error = KeyLayoutMap::load_from_contents(content).unwrap_err();
+    assert!(
+        error.message.contains("Duplicate entry for led scan code"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_parse_sensors() {
+    let map = load_key_layout_map("pixel-7/Vendor_054c_Product_05c4.kl");
+
+    assert_eq!(
+        map.map_sensor(0x00),
+        Some(KeyLayoutSensorInfo {
+            sensor_type: ASENSOR_TYPE_ACCELEROMETER,
+            data_index: 0,
+        })
+    );
+    assert_eq!(
+        map.map_sensor(0x05),
+        Some(KeyLayoutSensorInfo {
+            sensor_type: ASENSOR_TYPE_GYROSCOPE,
+            data_index: 2,
+        })
+    );
+
+    assert_eq!(
+        map.find_abs_code_for_sensor(ASENSOR_TYPE_GYROSCOPE, 1),
+        Some(0x04)
+    );
+    assert_eq!(map.find_abs_code_for_sensor(ASENSOR_TYPE_LIGHT, 0), None);
+}
+
+#[test]
+fn test_invalid_sensor_is_an_error() {
+    let error = KeyLayoutMap::load_from_contents("sensor 0x00 ACCELEROMETER W").unwrap_err();
+    assert!(
+        error.message.contains("Expected sensor data index label"),
+        "{}",
+        error
+    );
+
+    let error = KeyLayoutMap::load_from_contents("sensor 0x00 SMELL X").unwrap_err();
+    assert!(
+        error.message.contains("Expected sensor code label"),
+        "{}",
+        error
+    );
 }
 
 #[test]
@@ -218,6 +454,49 @@ fn test_find_scan_code_for_key() {
     assert!(map.find_scan_code_for_key(9999).is_none());
 }
 
+#[test]
+fn test_find_scan_codes_for_key() {
+    let map = load_key_layout_map("Generic.kl");
+
+    assert_eq!(
+        map.find_scan_codes_for_key(AKEYCODE_ESCAPE),
+        vec![
+            (
+                1,
+                KeyLayoutKey {
+                    key_code: AKEYCODE_ESCAPE,
+                    flags: 0,
+                }
+            ),
+            (
+                465,
+                KeyLayoutKey {
+                    key_code: AKEYCODE_ESCAPE,
+                    flags: POLICY_FLAG_FUNCTION,
+                }
+            ),
+        ]
+    );
+    assert_eq!(map.find_scan_codes_for_key(9999), Vec::new());
+}
+
+#[test]
+fn test_find_scan_codes_for_key_keeps_file_order() {
+    let map = KeyLayoutMap::load_from_contents(
+        "key 200 MEDIA_PLAY\nkey 164 MEDIA_PLAY\nkey 207 MEDIA_PLAY",
+    )
+    .unwrap();
+
+    let scan_codes: Vec<u32> = map
+        .find_scan_codes_for_key(AKEYCODE_MEDIA_PLAY)
+        .into_iter()
+        .map(|(scan_code, _)| scan_code)
+        .collect();
+
+    assert_eq!(scan_codes, vec![200, 164, 207]);
+    assert_eq!(map.find_scan_code_for_key(AKEYCODE_MEDIA_PLAY), Some(200));
+}
+
 #[test]
 fn test_reverse_mapping_basic() {
     let contents = "key 1 ESCAPE\nkey 2 1\nkey 100 ESCAPE\n";
@@ -277,6 +556,122 @@ fn test_bidirectional_mapping() {
     assert_eq!(map.find_scan_code_for_key(66), Some(28)); // ENTER -> 28
 }
 
+#[test]
+fn test_parse_error_location() {
+    let content = "\
+key 1 ESCAPE
+# Comment
+key   abc TAB";
+
+    let error = KeyLayoutMap::load_from_contents(content).unwrap_err();
+
+    assert_eq!(error.kind, KeyLayoutParseErrorKind::InvalidCode);
+    assert_eq!(error.line, 3);
+    assert_eq!(error.column, 7);
+    assert_eq!(error.token, "abc");
+    assert_eq!(
+        error.to_string(),
+        "3:7: Expected key scan code number, got 'abc'."
+    );
+}
+
+#[test]
+fn test_trailing_text_error() {
+    let error = KeyLayoutMap::load_from_contents("axis 0x00 X flat 4 Y").unwrap_err();
+    assert_eq!(error.kind, KeyLayoutParseErrorKind::UnknownKeyword);
+    assert_eq!(error.token, "Y");
+    assert_eq!(error.column, 20);
+
+    let error = KeyLayoutMap::load_from_contents("led 0x00 NUM_LOCK  extra").unwrap_err();
+    assert_eq!(error.kind, KeyLayoutParseErrorKind::TrailingText);
+    assert_eq!(error.token, "extra");
+    assert_eq!(error.column, 20);
+}
+
+#[test]
+fn test_missing_file_is_an_io_error() {
+    let path = PathBuf::from(get_test_data_path()).join("Missing.kl");
+
+    let error = KeyLayoutMap::load_from_file(path.clone()).unwrap_err();
+    assert_eq!(error.kind, KeyLayoutParseErrorKind::Io);
+    assert_eq!(error.file_path, path);
+    assert_eq!(error.line, 0);
+
+    let error = KeyLayoutMap::load_lenient_from_file(path).unwrap_err();
+    assert_eq!(error.kind, KeyLayoutParseErrorKind::Io);
+}
+
+#[test]
+fn test_unknown_key_code_is_a_warning() {
+    let report = KeyLayoutMap::load_lenient_from_contents("key 1 NOT_A_KEY");
+
+    assert!(!report.has_errors());
+    assert_eq!(report.diagnostics.len(), 1);
+    assert_eq!(
+        report.diagnostics[0].kind,
+        KeyLayoutParseErrorKind::UnknownKeyCode
+    );
+    assert_eq!(report.diagnostics[0].token, "NOT_A_KEY");
+    assert!(report.diagnostics[0].is_warning());
+    assert_eq!(report.map.map_key(1), None);
+
+    // The strict parser still loads the file.
+    assert!(KeyLayoutMap::load_from_contents("key 1 NOT_A_KEY").is_ok());
+}
+
+#[test]
+fn test_lenient_parse_collects_every_problem() {
+    let content = "\
+key 1 ESCAPE
+key 1 TAB
+key 2 NOT_A_KEY
+key 3 A WAKE WAKE
+key 4 B OEM_FLAG
+banana
+axis 0x00 X
+axis 0x01 W
+key 5 C";
+
+    let report = KeyLayoutMap::load_lenient_from_contents(content);
+
+    let problems: Vec<_> = report
+        .diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.kind, diagnostic.token.as_str()))
+        .collect();
+
+    assert_eq!(
+        problems,
+        vec![
+            (2, KeyLayoutParseErrorKind::DuplicateEntry, "1"),
+            (3, KeyLayoutParseErrorKind::UnknownKeyCode, "NOT_A_KEY"),
+            (4, KeyLayoutParseErrorKind::DuplicateKeyFlag, "WAKE"),
+            (5, KeyLayoutParseErrorKind::UnknownKeyFlag, "OEM_FLAG"),
+            (6, KeyLayoutParseErrorKind::UnknownKeyword, "banana"),
+            (8, KeyLayoutParseErrorKind::UnknownAxis, "W"),
+        ]
+    );
+    assert!(report.has_errors());
+    assert_eq!(report.errors().count(), 4);
+
+    // The entries without errors are still mapped, and the first entry wins a duplicate.
+    assert_eq!(report.map.map_key(1), Some(AKEYCODE_ESCAPE));
+    assert_eq!(report.map.map_key(3), None);
+    assert_eq!(report.map.map_key(4), Some(AKEYCODE_B));
+    assert_eq!(report.map.map_key(5), Some(AKEYCODE_C));
+    assert!(report.map.map_axis(0x00).is_some());
+    assert!(report.map.map_axis(0x01).is_none());
+}
+
+#[test]
+fn test_lenient_parse_of_valid_file_has_no_diagnostics() {
+    let path = PathBuf::from(get_test_data_path()).join("Generic.kl");
+    let report = KeyLayoutMap::load_lenient_from_file(path).unwrap();
+
+    assert_eq!(report.diagnostics, Vec::new());
+    assert_eq!(report.map.map_key(30), Some(AKEYCODE_A));
+}
+
 #[test]
 fn test_load_all_files_in_test_data() {
     let test_data_path = get_test_data_path();
@@ -0,0 +1,51 @@
+//! Tests for formatting key layout parse errors.
+use evdev_manager_core::android::keylayout::key_layout_parse_error::{
+    KeyLayoutParseError, KeyLayoutParseErrorKind, KeyLayoutParseSeverity,
+};
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::path::PathBuf;
+
+fn create_error(file_path: &str, line: usize, column: usize) -> KeyLayoutParseError {
+    KeyLayoutParseError {
+        file_path: PathBuf::from(file_path),
+        line,
+        column,
+        kind: KeyLayoutParseErrorKind::InvalidCode,
+        token: "abc".to_string(),
+        message: "Expected key scan code number, got 'abc'.".to_string(),
+    }
+}
+
+#[test]
+fn test_display_includes_location() {
+    assert_eq!(
+        create_error("/data/keylayout/Vendor_1234_Product_5678.kl", 3, 9).to_string(),
+        "/data/keylayout/Vendor_1234_Product_5678.kl:3:9: Expected key scan code number, got 'abc'."
+    );
+}
+
+#[test]
+fn test_display_without_path_or_line() {
+    assert_eq!(
+        create_error("", 3, 9).to_string(),
+        "3:9: Expected key scan code number, got 'abc'."
+    );
+    assert_eq!(
+        create_error("Generic.kl", 0, 0).to_string(),
+        "Generic.kl: Expected key scan code number, got 'abc'."
+    );
+}
+
+#[test]
+fn test_severity() {
+    assert_eq!(
+        KeyLayoutParseErrorKind::UnknownKeyFlag.severity(),
+        KeyLayoutParseSeverity::Warning
+    );
+    assert_eq!(
+        KeyLayoutParseErrorKind::DuplicateEntry.severity(),
+        KeyLayoutParseSeverity::Error
+    );
+    assert!(!create_error("", 1, 1).is_warning());
+}
@@ -0,0 +1,263 @@
+//! Tests for applying scan code remap tables in the event processing path.
+mod common;
+
+use common::{FakeCallback, FakeSink};
+use evdev::enums::{EventCode, EV_KEY, EV_REL, EV_SYN};
+use evdev::{InputEvent, TimeVal};
+use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
+use evdev_manager_core::event_loop::process_grabbed_device_event;
+use evdev_manager_core::key_remap_table::{KeyRemapTable, KeyRemapper, RemapResult, ScanCodeRemap};
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+fn device_info() -> EvdevDeviceInfo {
+    EvdevDeviceInfo {
+        name: "Test Keyboard".to_string(),
+        bus: 0x0003,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 0x0001,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    }
+}
+
+fn key_event(key: EV_KEY, value: i32) -> InputEvent {
+    InputEvent::new(&TimeVal::new(1, 500), &EventCode::EV_KEY(key), value)
+}
+
+fn remap_table(remaps: &[(EV_KEY, ScanCodeRemap)]) -> KeyRemapTable {
+    KeyRemapTable::new(
+        remaps
+            .iter()
+            .map(|(key, remap)| (*key as u32, *remap))
+            .collect::<HashMap<u32, ScanCodeRemap>>(),
+    )
+}
+
+fn process(callback: &FakeCallback, table: &KeyRemapTable, sink: &FakeSink, events: &[InputEvent]) {
+    let key_remapper = Mutex::new(KeyRemapper::new(table.clone()));
+
+    for event in events {
+        process_grabbed_device_event(
+            callback,
+            3,
+            &device_info(),
+            &key_remapper,
+            None,
+            sink,
+            event,
+            None,
+        );
+    }
+}
+
+fn caps_lock_to_esc() -> KeyRemapTable {
+    remap_table(&[(
+        EV_KEY::KEY_CAPSLOCK,
+        ScanCodeRemap::Remap(EV_KEY::KEY_ESC as u32),
+    )])
+}
+
+#[test]
+fn test_empty_table_does_not_remap() {
+    let table = KeyRemapTable::default();
+
+    assert_eq!(
+        table.apply(&key_event(EV_KEY::KEY_A, 1)),
+        RemapResult::Unmapped
+    );
+}
+
+#[test]
+fn test_apply_remap_keeps_time_and_value() {
+    let table = remap_table(&[(
+        EV_KEY::KEY_CAPSLOCK,
+        ScanCodeRemap::Remap(EV_KEY::KEY_ESC as u32),
+    )]);
+
+    assert_eq!(
+        table.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 2)),
+        RemapResult::Remapped(key_event(EV_KEY::KEY_ESC, 2))
+    );
+}
+
+#[test]
+fn test_apply_ignores_non_key_events() {
+    let table = remap_table(&[(EV_KEY::KEY_A, ScanCodeRemap::Drop)]);
+
+    // REL_X has the same code as KEY_RESERVED and must never be looked up as a key.
+    let event = InputEvent::new(&TimeVal::new(0, 0), &EventCode::EV_REL(EV_REL::REL_X), 5);
+    assert_eq!(table.apply(&event), RemapResult::Unmapped);
+}
+
+#[test]
+fn test_remapped_key_bypasses_callback() {
+    let callback = FakeCallback::new(true, false);
+    let sink = FakeSink::default();
+    let table = remap_table(&[(
+        EV_KEY::KEY_CAPSLOCK,
+        ScanCodeRemap::Remap(EV_KEY::KEY_ESC as u32),
+    )]);
+
+    process(
+        &callback,
+        &table,
+        &sink,
+        &[
+            key_event(EV_KEY::KEY_CAPSLOCK, 1),
+            key_event(EV_KEY::KEY_CAPSLOCK, 0),
+        ],
+    );
+
+    assert_eq!(callback.events(), vec![]);
+    assert_eq!(
+        sink.events(),
+        vec![
+            (1, EV_KEY::KEY_ESC as u32, 1),
+            (1, EV_KEY::KEY_ESC as u32, 0)
+        ]
+    );
+}
+
+#[test]
+fn test_dropped_key_is_not_sent_anywhere() {
+    let callback = FakeCallback::new(false, false);
+    let sink = FakeSink::default();
+    let table = remap_table(&[(EV_KEY::KEY_INSERT, ScanCodeRemap::Drop)]);
+
+    process(
+        &callback,
+        &table,
+        &sink,
+        &[key_event(EV_KEY::KEY_INSERT, 1)],
+    );
+
+    assert_eq!(callback.events(), vec![]);
+    assert_eq!(sink.events(), vec![]);
+}
+
+#[test]
+fn test_unmapped_key_is_sent_to_callback() {
+    let callback = FakeCallback::new(true, false);
+    let sink = FakeSink::default();
+    let table = remap_table(&[(EV_KEY::KEY_INSERT, ScanCodeRemap::Drop)]);
+
+    process(&callback, &table, &sink, &[key_event(EV_KEY::KEY_B, 1)]);
+
+    assert_eq!(callback.events(), vec![(3, key_event(EV_KEY::KEY_B, 1))]);
+    assert_eq!(sink.events(), vec![]);
+}
+
+#[test]
+fn test_unconsumed_key_is_passed_through() {
+    let callback = FakeCallback::new(false, false);
+    let sink = FakeSink::default();
+    let table = KeyRemapTable::default();
+
+    process(&callback, &table, &sink, &[key_event(EV_KEY::KEY_B, 1)]);
+
+    assert_eq!(callback.events().len(), 1);
+    assert_eq!(sink.events(), vec![(1, EV_KEY::KEY_B as u32, 1)]);
+}
+
+#[test]
+fn test_sync_events_are_passed_through_without_callback() {
+    let callback = FakeCallback::new(true, false);
+    let sink = FakeSink::default();
+    let table = remap_table(&[(EV_KEY::KEY_A, ScanCodeRemap::Drop)]);
+
+    let syn = InputEvent::new(
+        &TimeVal::new(0, 0),
+        &EventCode::EV_SYN(EV_SYN::SYN_REPORT),
+        0,
+    );
+    process(&callback, &table, &sink, &[syn]);
+
+    assert_eq!(callback.events(), vec![]);
+    assert_eq!(sink.events(), vec![(0, EV_SYN::SYN_REPORT as u32, 0)]);
+}
+
+#[test]
+fn test_held_key_is_repeated_and_released_with_its_press_remap() {
+    let mut key_remapper = KeyRemapper::new(caps_lock_to_esc());
+
+    assert_eq!(
+        key_remapper.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 1)),
+        RemapResult::Remapped(key_event(EV_KEY::KEY_ESC, 1))
+    );
+
+    let released = key_remapper.set_table(remap_table(&[(
+        EV_KEY::KEY_CAPSLOCK,
+        ScanCodeRemap::Remap(EV_KEY::KEY_ESC as u32),
+    )]));
+    assert_eq!(released, vec![]);
+
+    assert_eq!(
+        key_remapper.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 2)),
+        RemapResult::Remapped(key_event(EV_KEY::KEY_ESC, 2))
+    );
+    assert_eq!(
+        key_remapper.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 0)),
+        RemapResult::Remapped(key_event(EV_KEY::KEY_ESC, 0))
+    );
+}
+
+#[test]
+fn test_clearing_table_releases_held_remapped_key() {
+    let mut key_remapper = KeyRemapper::new(caps_lock_to_esc());
+    key_remapper.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 1));
+
+    let released = key_remapper.set_table(KeyRemapTable::default());
+    assert_eq!(released, vec![EV_KEY::KEY_ESC as u32]);
+
+    // The key was already released so the rest of its events are dropped.
+    assert_eq!(
+        key_remapper.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 2)),
+        RemapResult::Dropped
+    );
+    assert_eq!(
+        key_remapper.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 0)),
+        RemapResult::Dropped
+    );
+
+    // The next press uses the new table.
+    assert_eq!(
+        key_remapper.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 1)),
+        RemapResult::Unmapped
+    );
+}
+
+#[test]
+fn test_replacing_table_releases_held_key_with_old_remap() {
+    let mut key_remapper = KeyRemapper::new(caps_lock_to_esc());
+    key_remapper.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 1));
+
+    let released = key_remapper.set_table(remap_table(&[(
+        EV_KEY::KEY_CAPSLOCK,
+        ScanCodeRemap::Remap(EV_KEY::KEY_LEFTCTRL as u32),
+    )]));
+
+    assert_eq!(released, vec![EV_KEY::KEY_ESC as u32]);
+    assert_eq!(
+        key_remapper.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 0)),
+        RemapResult::Dropped
+    );
+}
+
+#[test]
+fn test_key_pressed_before_it_was_remapped_is_released_unmapped() {
+    let mut key_remapper = KeyRemapper::default();
+    key_remapper.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 1));
+
+    let released = key_remapper.set_table(caps_lock_to_esc());
+    assert_eq!(released, vec![]);
+
+    assert_eq!(
+        key_remapper.apply(&key_event(EV_KEY::KEY_CAPSLOCK, 0)),
+        RemapResult::Unmapped
+    );
+}
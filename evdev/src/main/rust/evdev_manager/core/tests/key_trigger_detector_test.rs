@@ -0,0 +1,412 @@
+//! Tests for detecting chord and sequence triggers in streams of key events.
+mod common;
+
+use common::FakeClock;
+use evdev::enums::{EventCode, EV_KEY, EV_MSC, EV_SYN};
+use evdev::{InputEvent, TimeVal};
+use evdev_manager_core::key_trigger::{KeyTrigger, KeyTriggerKind};
+use evdev_manager_core::key_trigger_detector::{KeyTriggerDetector, KeyTriggerOutput};
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::sync::Arc;
+use std::time::Duration;
+
+const CHORD_ID: u32 = 1;
+const SEQUENCE_ID: u32 = 2;
+const WINDOW: Duration = Duration::from_millis(50);
+
+fn key(key: EV_KEY, value: i32) -> InputEvent {
+    InputEvent::new(&TimeVal::new(0, 0), &EventCode::EV_KEY(key), value)
+}
+
+fn syn_report() -> InputEvent {
+    InputEvent::new(
+        &TimeVal::new(0, 0),
+        &EventCode::EV_SYN(EV_SYN::SYN_REPORT),
+        0,
+    )
+}
+
+fn forward(event: InputEvent) -> KeyTriggerOutput {
+    KeyTriggerOutput::Forward(event)
+}
+
+fn chord(id: u32, keys: &[EV_KEY]) -> KeyTrigger {
+    KeyTrigger {
+        id,
+        kind: KeyTriggerKind::Chord {
+            scan_codes: keys.iter().map(|key| *key as u32).collect(),
+            window: WINDOW,
+        },
+    }
+}
+
+fn sequence(id: u32, keys: &[EV_KEY], timeout: Duration) -> KeyTrigger {
+    KeyTrigger {
+        id,
+        kind: KeyTriggerKind::Sequence {
+            scan_codes: keys.iter().map(|key| *key as u32).collect(),
+            timeout,
+        },
+    }
+}
+
+fn create_detector(triggers: Vec<KeyTrigger>) -> (Arc<FakeClock>, KeyTriggerDetector) {
+    let clock = Arc::new(FakeClock::new());
+    let mut detector = KeyTriggerDetector::new(clock.clone());
+    assert_eq!(detector.set_triggers(triggers), vec![]);
+    (clock, detector)
+}
+
+fn feed(detector: &mut KeyTriggerDetector, events: &[InputEvent]) -> Vec<KeyTriggerOutput> {
+    events
+        .iter()
+        .flat_map(|event| detector.on_event(event))
+        .collect()
+}
+
+#[test]
+fn test_no_triggers_forwards_everything() {
+    let (_, mut detector) = create_detector(vec![]);
+
+    let outputs = feed(
+        &mut detector,
+        &[key(EV_KEY::KEY_A, 1), key(EV_KEY::KEY_A, 0)],
+    );
+
+    assert_eq!(
+        outputs,
+        vec![
+            forward(key(EV_KEY::KEY_A, 1)),
+            forward(key(EV_KEY::KEY_A, 0))
+        ]
+    );
+}
+
+#[test]
+fn test_chord_fires_once_and_consumes_keys() {
+    let (clock, mut detector) = create_detector(vec![chord(
+        CHORD_ID,
+        &[EV_KEY::KEY_LEFTCTRL, EV_KEY::KEY_J],
+    )]);
+
+    let mut outputs = feed(&mut detector, &[key(EV_KEY::KEY_LEFTCTRL, 1)]);
+    assert_eq!(outputs, vec![]);
+    assert!(detector.next_deadline().is_some());
+
+    clock.advance(Duration::from_millis(20));
+    outputs.extend(feed(
+        &mut detector,
+        &[
+            key(EV_KEY::KEY_J, 1),
+            key(EV_KEY::KEY_J, 2),
+            key(EV_KEY::KEY_J, 0),
+            key(EV_KEY::KEY_LEFTCTRL, 0),
+        ],
+    ));
+
+    assert_eq!(outputs, vec![KeyTriggerOutput::Fired(CHORD_ID)]);
+    assert_eq!(detector.next_deadline(), None);
+}
+
+#[test]
+fn test_chord_keys_can_be_pressed_in_any_order() {
+    let (_, mut detector) = create_detector(vec![chord(
+        CHORD_ID,
+        &[EV_KEY::KEY_LEFTCTRL, EV_KEY::KEY_J],
+    )]);
+
+    let outputs = feed(
+        &mut detector,
+        &[key(EV_KEY::KEY_J, 1), key(EV_KEY::KEY_LEFTCTRL, 1)],
+    );
+
+    assert_eq!(outputs, vec![KeyTriggerOutput::Fired(CHORD_ID)]);
+}
+
+#[test]
+fn test_chord_window_expiring_releases_held_keys() {
+    let (clock, mut detector) = create_detector(vec![chord(
+        CHORD_ID,
+        &[EV_KEY::KEY_LEFTCTRL, EV_KEY::KEY_J],
+    )]);
+
+    assert_eq!(feed(&mut detector, &[key(EV_KEY::KEY_LEFTCTRL, 1)]), vec![]);
+
+    clock.advance(Duration::from_millis(49));
+    assert_eq!(detector.on_timeout(), vec![]);
+
+    clock.advance(Duration::from_millis(1));
+    assert_eq!(
+        detector.on_timeout(),
+        vec![forward(key(EV_KEY::KEY_LEFTCTRL, 1)), forward(syn_report())]
+    );
+
+    // The late key is held again because it may start a new chord, so it must not fire.
+    assert_eq!(feed(&mut detector, &[key(EV_KEY::KEY_J, 1)]), vec![]);
+}
+
+#[test]
+fn test_expired_window_is_released_before_next_event() {
+    let (clock, mut detector) = create_detector(vec![chord(
+        CHORD_ID,
+        &[EV_KEY::KEY_LEFTCTRL, EV_KEY::KEY_J],
+    )]);
+
+    feed(&mut detector, &[key(EV_KEY::KEY_LEFTCTRL, 1)]);
+    clock.advance(Duration::from_millis(100));
+
+    // on_timeout was not called in time so the next event releases the held key first.
+    let outputs = feed(&mut detector, &[key(EV_KEY::KEY_A, 1)]);
+
+    assert_eq!(
+        outputs,
+        vec![
+            forward(key(EV_KEY::KEY_LEFTCTRL, 1)),
+            forward(syn_report()),
+            forward(key(EV_KEY::KEY_A, 1)),
+        ]
+    );
+}
+
+#[test]
+fn test_releasing_held_key_releases_it_in_order() {
+    let (_, mut detector) = create_detector(vec![chord(
+        CHORD_ID,
+        &[EV_KEY::KEY_LEFTCTRL, EV_KEY::KEY_J],
+    )]);
+
+    let outputs = feed(
+        &mut detector,
+        &[key(EV_KEY::KEY_J, 1), key(EV_KEY::KEY_J, 0)],
+    );
+
+    assert_eq!(
+        outputs,
+        vec![
+            forward(key(EV_KEY::KEY_J, 1)),
+            forward(syn_report()),
+            forward(key(EV_KEY::KEY_J, 0)),
+        ]
+    );
+}
+
+#[test]
+fn test_non_chord_key_releases_held_keys() {
+    let (_, mut detector) = create_detector(vec![chord(
+        CHORD_ID,
+        &[EV_KEY::KEY_LEFTCTRL, EV_KEY::KEY_J],
+    )]);
+
+    let outputs = feed(
+        &mut detector,
+        &[key(EV_KEY::KEY_LEFTCTRL, 1), key(EV_KEY::KEY_C, 1)],
+    );
+
+    assert_eq!(
+        outputs,
+        vec![
+            forward(key(EV_KEY::KEY_LEFTCTRL, 1)),
+            forward(syn_report()),
+            forward(key(EV_KEY::KEY_C, 1)),
+        ]
+    );
+}
+
+#[test]
+fn test_non_key_events_are_not_held() {
+    let (_, mut detector) = create_detector(vec![chord(
+        CHORD_ID,
+        &[EV_KEY::KEY_LEFTCTRL, EV_KEY::KEY_J],
+    )]);
+
+    let scan = InputEvent::new(
+        &TimeVal::new(0, 0),
+        &EventCode::EV_MSC(EV_MSC::MSC_SCAN),
+        29,
+    );
+    let outputs = feed(
+        &mut detector,
+        &[scan.clone(), key(EV_KEY::KEY_LEFTCTRL, 1), syn_report()],
+    );
+
+    assert_eq!(outputs, vec![forward(scan), forward(syn_report())]);
+}
+
+#[test]
+fn test_overlapping_chords_fire_the_completed_one() {
+    let (_, mut detector) = create_detector(vec![
+        chord(CHORD_ID, &[EV_KEY::KEY_LEFTCTRL, EV_KEY::KEY_J]),
+        chord(
+            CHORD_ID + 10,
+            &[EV_KEY::KEY_LEFTCTRL, EV_KEY::KEY_LEFTSHIFT, EV_KEY::KEY_J],
+        ),
+    ]);
+
+    let outputs = feed(
+        &mut detector,
+        &[
+            key(EV_KEY::KEY_LEFTCTRL, 1),
+            key(EV_KEY::KEY_LEFTSHIFT, 1),
+            key(EV_KEY::KEY_J, 1),
+        ],
+    );
+
+    assert_eq!(outputs, vec![KeyTriggerOutput::Fired(CHORD_ID + 10)]);
+}
+
+#[test]
+fn test_sequence_fires_on_last_key() {
+    let timeout = Duration::from_millis(500);
+    let (clock, mut detector) = create_detector(vec![sequence(
+        SEQUENCE_ID,
+        &[EV_KEY::KEY_G, EV_KEY::KEY_H],
+        timeout,
+    )]);
+
+    let mut outputs = feed(
+        &mut detector,
+        &[key(EV_KEY::KEY_G, 1), key(EV_KEY::KEY_G, 0)],
+    );
+    clock.advance(Duration::from_millis(200));
+    outputs.extend(feed(
+        &mut detector,
+        &[key(EV_KEY::KEY_H, 1), key(EV_KEY::KEY_H, 0)],
+    ));
+
+    assert_eq!(
+        outputs,
+        vec![
+            forward(key(EV_KEY::KEY_G, 1)),
+            forward(key(EV_KEY::KEY_G, 0)),
+            KeyTriggerOutput::Fired(SEQUENCE_ID),
+        ]
+    );
+}
+
+#[test]
+fn test_sequence_times_out_between_keys() {
+    let (clock, mut detector) = create_detector(vec![sequence(
+        SEQUENCE_ID,
+        &[EV_KEY::KEY_G, EV_KEY::KEY_H],
+        Duration::from_millis(500),
+    )]);
+
+    feed(
+        &mut detector,
+        &[key(EV_KEY::KEY_G, 1), key(EV_KEY::KEY_G, 0)],
+    );
+    clock.advance(Duration::from_millis(501));
+    let outputs = feed(&mut detector, &[key(EV_KEY::KEY_H, 1)]);
+
+    assert_eq!(outputs, vec![forward(key(EV_KEY::KEY_H, 1))]);
+}
+
+#[test]
+fn test_sequence_resets_on_wrong_key() {
+    let (_, mut detector) = create_detector(vec![sequence(
+        SEQUENCE_ID,
+        &[EV_KEY::KEY_G, EV_KEY::KEY_H],
+        Duration::from_millis(500),
+    )]);
+
+    let outputs = feed(
+        &mut detector,
+        &[
+            key(EV_KEY::KEY_G, 1),
+            key(EV_KEY::KEY_X, 1),
+            key(EV_KEY::KEY_H, 1),
+        ],
+    );
+
+    assert!(!outputs.contains(&KeyTriggerOutput::Fired(SEQUENCE_ID)));
+}
+
+#[test]
+fn test_sequence_restarts_when_first_key_repeated() {
+    let (_, mut detector) = create_detector(vec![sequence(
+        SEQUENCE_ID,
+        &[EV_KEY::KEY_G, EV_KEY::KEY_H],
+        Duration::from_millis(500),
+    )]);
+
+    let outputs = feed(
+        &mut detector,
+        &[
+            key(EV_KEY::KEY_G, 1),
+            key(EV_KEY::KEY_G, 1),
+            key(EV_KEY::KEY_H, 1),
+        ],
+    );
+
+    assert_eq!(outputs.last(), Some(&KeyTriggerOutput::Fired(SEQUENCE_ID)));
+}
+
+#[test]
+fn test_sequence_with_repeated_prefix_fires_after_extra_key() {
+    let (_, mut detector) = create_detector(vec![sequence(
+        SEQUENCE_ID,
+        &[EV_KEY::KEY_A, EV_KEY::KEY_A, EV_KEY::KEY_B],
+        Duration::from_millis(500),
+    )]);
+
+    let outputs = feed(
+        &mut detector,
+        &[
+            key(EV_KEY::KEY_A, 1),
+            key(EV_KEY::KEY_A, 1),
+            key(EV_KEY::KEY_A, 1),
+            key(EV_KEY::KEY_B, 1),
+        ],
+    );
+
+    assert_eq!(outputs.last(), Some(&KeyTriggerOutput::Fired(SEQUENCE_ID)));
+}
+
+#[test]
+fn test_sequence_falls_back_to_overlapping_prefix() {
+    // After A, B, A, B the next key C can only complete the sequence if the last A, B is
+    // kept as the start of a new match.
+    let (_, mut detector) = create_detector(vec![sequence(
+        SEQUENCE_ID,
+        &[EV_KEY::KEY_A, EV_KEY::KEY_B, EV_KEY::KEY_A, EV_KEY::KEY_C],
+        Duration::from_millis(500),
+    )]);
+
+    let outputs = feed(
+        &mut detector,
+        &[
+            key(EV_KEY::KEY_A, 1),
+            key(EV_KEY::KEY_B, 1),
+            key(EV_KEY::KEY_A, 1),
+            key(EV_KEY::KEY_B, 1),
+            key(EV_KEY::KEY_A, 1),
+            key(EV_KEY::KEY_C, 1),
+        ],
+    );
+
+    assert_eq!(
+        outputs
+            .iter()
+            .filter(|output| **output == KeyTriggerOutput::Fired(SEQUENCE_ID))
+            .count(),
+        1
+    );
+    assert_eq!(outputs.last(), Some(&KeyTriggerOutput::Fired(SEQUENCE_ID)));
+}
+
+#[test]
+fn test_replacing_triggers_releases_held_keys() {
+    let (_, mut detector) = create_detector(vec![chord(
+        CHORD_ID,
+        &[EV_KEY::KEY_LEFTCTRL, EV_KEY::KEY_J],
+    )]);
+
+    feed(&mut detector, &[key(EV_KEY::KEY_LEFTCTRL, 1)]);
+
+    assert_eq!(
+        detector.set_triggers(vec![]),
+        vec![forward(key(EV_KEY::KEY_LEFTCTRL, 1)), forward(syn_report())]
+    );
+    assert_eq!(detector.next_deadline(), None);
+}
@@ -143,3 +143,21 @@ fn test_multiple_lines_sequential() {
     assert_eq!(tokenizer.next_token(" \t"), "3");
     assert_eq!(tokenizer.next_token(" \t"), "C");
 }
+
+#[test]
+fn test_token_column() {
+    let mut tokenizer = Tokenizer::from_contents(PathBuf::new(), "key  1\n\tkey ESCAPE");
+    assert_eq!(tokenizer.next_token(" \t"), "key");
+    assert_eq!(tokenizer.get_token_column(), 1);
+    assert_eq!(tokenizer.next_token(" \t"), "1");
+    assert_eq!(tokenizer.get_token_column(), 6);
+    assert_eq!(tokenizer.get_column(), 7);
+
+    tokenizer.next_line();
+    assert_eq!(tokenizer.get_line_number(), 2);
+    assert_eq!(tokenizer.get_column(), 1);
+
+    tokenizer.next_token(" \t");
+    assert_eq!(tokenizer.next_token(" \t"), "ESCAPE");
+    assert_eq!(tokenizer.get_token_column(), 6);
+}
@@ -0,0 +1,56 @@
+//! Tests for creating virtual devices with a real uinput device.
+//!
+//! Run these on a Linux device with access to /dev/uinput:
+//! `cargo test -p evdev_manager_core --features uinput-tests --test virtual_device_uinput_test`
+#![cfg(feature = "uinput-tests")]
+
+use evdev::enums::{EventCode, EV_ABS, EV_KEY};
+use evdev::{AbsInfo, Device, DeviceWrapper};
+use evdev_manager_core::device_capabilities::DeviceCapabilities;
+use evdev_manager_core::virtual_device::{create_uinput_device, VirtualDeviceDescription};
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_create_virtual_device_from_description() {
+    let abs_x = AbsInfo {
+        value: 0,
+        minimum: -32768,
+        maximum: 32767,
+        fuzz: 16,
+        flat: 128,
+        resolution: 0,
+    };
+
+    let mut capabilities = DeviceCapabilities::new(&[
+        EventCode::EV_KEY(EV_KEY::BTN_SOUTH),
+        EventCode::EV_ABS(EV_ABS::ABS_X),
+    ]);
+    capabilities.abs_info.insert(EV_ABS::ABS_X as u32, abs_x);
+
+    let description = VirtualDeviceDescription {
+        name: "Key Mapper Virtual Device Test".to_string(),
+        bus: 0x0006,
+        vendor: 0x1234,
+        product: 0x5678,
+        version: 1,
+        capabilities,
+    };
+
+    let uinput = create_uinput_device(&description).unwrap();
+    let devnode = uinput.devnode().expect("uinput device has no devnode");
+    let device = Device::new_from_path(devnode).unwrap();
+
+    assert_eq!(device.name(), Some("Key Mapper Virtual Device Test"));
+    assert_eq!(device.bustype(), 0x0006);
+    assert_eq!(device.vendor_id(), 0x1234);
+    assert_eq!(device.product_id(), 0x5678);
+    assert!(device.has(EventCode::EV_KEY(EV_KEY::BTN_SOUTH)));
+    assert!(!device.has(EventCode::EV_KEY(EV_KEY::KEY_A)));
+
+    let info = device.abs_info(&EventCode::EV_ABS(EV_ABS::ABS_X)).unwrap();
+    assert_eq!(
+        (info.minimum, info.maximum, info.fuzz, info.flat),
+        (-32768, 32767, 16, 128)
+    );
+}
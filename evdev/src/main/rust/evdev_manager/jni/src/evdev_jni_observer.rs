@@ -1,10 +1,13 @@
-use evdev::{util::event_code_to_int, InputEvent};
+use crate::jni_bridge::create_java_evdev_device_info;
+use evdev::enums::EventCode;
+use evdev::{util::event_code_to_int, AbsInfo, InputEvent};
 use evdev_manager_core::android::android_codes;
 use evdev_manager_core::android::android_codes::AKEYCODE_UNKNOWN;
+use evdev_manager_core::android::axis_mapper::map_axis_event;
 use evdev_manager_core::android::keylayout::key_layout_map_manager::KeyLayoutMapManager;
 use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
 use evdev_manager_core::grabbed_device_handle::GrabbedDeviceHandle;
-use jni::objects::{GlobalRef, JValue};
+use jni::objects::{GlobalRef, JObject, JValue};
 use jni::JavaVM;
 use std::process;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -102,6 +105,26 @@ impl EvdevJniObserver {
         }
     }
 
+    /// Handle power button emergency kill for every key read from a grabbed device, even if
+    /// it is then remapped or used by a key trigger or key behaviour.
+    pub fn on_key_read(
+        &self,
+        device_identifier: &EvdevDeviceInfo,
+        event: &InputEvent,
+        usage_code: Option<u32>,
+    ) {
+        let (_, ev_code) = event_code_to_int(&event.event_code);
+
+        let android_code = self
+            .key_layout_map_manager
+            .map_key_with_flags(device_identifier, ev_code, usage_code)
+            .ok()
+            .flatten()
+            .map_or(AKEYCODE_UNKNOWN, |key| key.key_code);
+
+        self.handle_power_button(ev_code, android_code, event.value);
+    }
+
     pub fn on_grabbed_devices_changed(&self, grabbed_devices: Vec<GrabbedDeviceHandle>) {
         let mut env = self
             .jvm
@@ -139,15 +162,24 @@ impl EvdevJniObserver {
                 }
             };
 
+            let path_str = match env.new_string(device_handle.device_path.to_string_lossy()) {
+                Ok(s) => s,
+                Err(e) => {
+                    error!("Failed to create device path string: {:?}", e);
+                    continue;
+                }
+            };
+
             let handle = match env.new_object(
                 &handle_class,
-                "(ILjava/lang/String;III)V",
+                "(ILjava/lang/String;IIILjava/lang/String;)V",
                 &[
                     JValue::Int(device_handle.id as i32),
                     JValue::Object(&name_str.into()),
                     JValue::Int(device_handle.device_info.bus as i32),
                     JValue::Int(device_handle.device_info.vendor as i32),
                     JValue::Int(device_handle.device_info.product as i32),
+                    JValue::Object(&path_str.into()),
                 ],
             ) {
                 Ok(h) => h,
@@ -178,6 +210,7 @@ impl EvdevJniObserver {
         device_id: usize,
         device_identifier: &EvdevDeviceInfo,
         event: &InputEvent,
+        usage_code: Option<u32>,
     ) -> bool {
         let mut env = self
             .jvm
@@ -187,25 +220,22 @@ impl EvdevJniObserver {
         // Extract event type and code from EventCode
         let (ev_type, ev_code) = event_code_to_int(&event.event_code);
 
-        let key_result = self
-            .key_layout_map_manager
-            .map_key(device_identifier, ev_code);
+        let key_result =
+            self.key_layout_map_manager
+                .map_key_with_flags(device_identifier, ev_code, usage_code);
 
-        // Convert raw evdev code to Android keycode
-        let android_code = match key_result {
-            Ok(Some(key_code)) => key_code,
-            Ok(None) | Err(_) => AKEYCODE_UNKNOWN,
+        // Convert raw evdev code to Android keycode and the POLICY_FLAG_* bits of the key
+        let (android_code, flags) = match key_result {
+            Ok(Some(key)) => (key.key_code, key.flags),
+            Ok(None) | Err(_) => (AKEYCODE_UNKNOWN, 0),
         };
 
-        // Handle power button emergency kill
-        self.handle_power_button(ev_code, android_code, event.value);
-
         // Call BaseSystemBridge.onEvdevEvent() via JNI
 
         let result = env.call_method(
             &self.system_bridge,
             "onEvdevEvent",
-            "(IJJIIII)Z",
+            "(IJJIIIII)Z",
             &[
                 JValue::Int(device_id as i32),
                 #[allow(clippy::unnecessary_cast)]
@@ -216,6 +246,7 @@ impl EvdevJniObserver {
                 JValue::Int(ev_code as i32),
                 JValue::Int(event.value),
                 JValue::Int(android_code as i32),
+                JValue::Int(flags as i32),
             ],
         );
 
@@ -235,6 +266,115 @@ impl EvdevJniObserver {
         }
     }
 
+    /// Convert an axis event to Android axis values with the device's key layout and send
+    /// it to BaseSystemBridge. Returns true if it was consumed.
+    pub fn on_axis_event(
+        &self,
+        device_id: usize,
+        device_identifier: &EvdevDeviceInfo,
+        event: &InputEvent,
+        abs_info: Option<&AbsInfo>,
+    ) -> bool {
+        let (ev_type, ev_code) = event_code_to_int(&event.event_code);
+
+        let axis_info = match event.event_code {
+            EventCode::EV_ABS(_) => self
+                .key_layout_map_manager
+                .map_axis(device_identifier, ev_code)
+                .inspect_err(|e| error!("Failed to map axis {}: {:?}", ev_code, e))
+                .ok()
+                .flatten(),
+            _ => None,
+        };
+
+        let axis_values = map_axis_event(axis_info.as_ref(), abs_info, event);
+
+        // Do not send axes that Android does not know about.
+        if axis_values.is_empty() {
+            return false;
+        }
+
+        let mut env = self
+            .jvm
+            .attach_current_thread_permanently()
+            .expect("Failed to attach to JVM thread");
+
+        let axes: Vec<i32> = axis_values.iter().map(|value| value.axis as i32).collect();
+        let values: Vec<f32> = axis_values.iter().map(|value| value.value).collect();
+
+        let arrays = env.new_int_array(axes.len() as i32).and_then(|axes_array| {
+            env.set_int_array_region(&axes_array, 0, &axes)?;
+            let values_array = env.new_float_array(values.len() as i32)?;
+            env.set_float_array_region(&values_array, 0, &values)?;
+            Ok((axes_array, values_array))
+        });
+
+        let (axes_array, values_array) = match arrays {
+            Ok(arrays) => arrays,
+            Err(e) => {
+                error!("Failed to create axis arrays: {:?}", e);
+                return false;
+            }
+        };
+
+        // This thread stays attached so local references are only freed when they are
+        // deleted. Delete the arrays after the call so they do not fill the local reference
+        // table at the rate that axis events arrive.
+        let axes_array = env.auto_local(axes_array);
+        let values_array = env.auto_local(values_array);
+
+        // Call BaseSystemBridge.onEvdevAxisEvent() via JNI
+        let result = env.call_method(
+            &self.system_bridge,
+            "onEvdevAxisEvent",
+            "(IJJIII[I[F)Z",
+            &[
+                JValue::Int(device_id as i32),
+                #[allow(clippy::unnecessary_cast)]
+                // When building for 32 bit the tv_sec type may be i32
+                JValue::Long(event.time.tv_sec as i64),
+                #[allow(clippy::useless_conversion)]
+                JValue::Long(event.time.tv_usec.into()),
+                JValue::Int(ev_type as i32),
+                JValue::Int(ev_code as i32),
+                JValue::Int(event.value),
+                JValue::Object(&axes_array),
+                JValue::Object(&values_array),
+            ],
+        );
+
+        match result {
+            Ok(value) => value.z().unwrap_or_else(|e| {
+                error!("Failed to extract boolean from result: {:?}", e);
+                false
+            }),
+            Err(e) => {
+                error!("Failed to call onEvdevAxisEvent: {:?}", e);
+                false
+            }
+        }
+    }
+
+    pub fn on_key_trigger_fired(&self, device_id: usize, trigger_id: u32) {
+        let mut env = self
+            .jvm
+            .attach_current_thread_permanently()
+            .expect("Failed to attach to JVM thread");
+
+        // Call SystemBridge.onEvdevKeyTriggerFired() via JNI
+        if let Err(e) = env.call_method(
+            &self.system_bridge,
+            "onEvdevKeyTriggerFired",
+            "(II)V",
+            &[
+                JValue::Int(device_id as i32),
+                JValue::Int(trigger_id as i32),
+            ],
+        ) {
+            error!("Failed to call onEvdevKeyTriggerFired: {:?}", e);
+        }
+    }
+
     pub fn on_evdev_devices_changed(&self, devices: Vec<EvdevDeviceInfo>) {
         let mut env = self
             .jvm
@@ -264,25 +404,8 @@ impl EvdevJniObserver {
         };
 
         for (i, device_info) in devices.iter().enumerate() {
-            let name_str = match env.new_string(&device_info.name) {
-                Ok(s) => s,
-                Err(e) => {
-                    error!("Failed to create device name string: {:?}", e);
-                    continue;
-                }
-            };
-
-            let info = match env.new_object(
-                &info_class,
-                "(Ljava/lang/String;III)V",
-                &[
-                    JValue::Object(&name_str.into()),
-                    JValue::Int(device_info.bus as i32),
-                    JValue::Int(device_info.vendor as i32),
-                    JValue::Int(device_info.product as i32),
-                ],
-            ) {
-                Ok(i) => i,
+            let info = match create_java_evdev_device_info(&mut env, device_info) {
+                Ok(i) => unsafe { JObject::from_raw(i) },
                 Err(e) => {
                     error!("Failed to create EvdevDeviceInfo: {:?}", e);
                     continue;
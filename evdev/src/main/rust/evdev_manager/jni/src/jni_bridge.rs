@@ -1,17 +1,63 @@
 use crate::evdev_jni_observer::EvdevJniObserver;
 use crate::logging::{AndroidLogLevel, KeyMapperLogger};
-use evdev::InputEvent;
+use evdev::enums::{int_to_event_type, EventCode};
+use evdev::util::int_to_event_code;
+use evdev::{AbsInfo, InputEvent};
+use evdev_manager_core::android::keylayout::key_layout_file_finder::AndroidKeyLayoutFileFinder;
+use evdev_manager_core::android::keylayout::key_layout_map::KeyLayoutMap;
 use evdev_manager_core::android::keylayout::key_layout_map_manager::KeyLayoutMapManager;
+use evdev_manager_core::android::keylayout::key_layout_parse_error::KeyLayoutParseSeverity;
+use evdev_manager_core::axis_key_mapping::AxisKeyMapping;
+use evdev_manager_core::capability_grab_target::DeviceCapabilityClass;
+use evdev_manager_core::capability_grab_target_key_code::CapabilityGrabTargetKeyCode;
+use evdev_manager_core::device_backend::DEFAULT_INPUT_DEVICE_ROOT;
+use evdev_manager_core::device_capabilities::DeviceCapabilities;
+use evdev_manager_core::device_name_matcher::DeviceNameMatcher;
 use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
+use evdev_manager_core::evemu_player::ReplayTarget;
 use evdev_manager_core::event_loop::{EvdevCallback, EventLoopManager};
 use evdev_manager_core::grab_target_key_code::GrabTargetKeyCode;
 use evdev_manager_core::grabbed_device_handle::GrabbedDeviceHandle;
+use evdev_manager_core::key_behaviour::{KeyBehaviour, KeyBehaviourKind};
+use evdev_manager_core::key_remap_table::{KeyRemapTable, ScanCodeRemap};
+use evdev_manager_core::key_trigger::{KeyTrigger, KeyTriggerKind};
+use evdev_manager_core::virtual_device::VirtualDeviceDescription;
 use jni::objects::{JClass, JIntArray, JObject, JObjectArray, JString, JValue};
-use jni::sys::{jboolean, jint, jobject, jobjectArray};
+use jni::sys::{jboolean, jfloat, jint, jobject, jobjectArray};
 use jni::JNIEnv;
+use std::collections::HashMap;
+use std::error::Error;
 use std::ffi::CString;
+use std::path::PathBuf;
 use std::ptr;
 use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// Must match EvdevKeyTrigger.TYPE_CHORD in Kotlin.
+const KEY_TRIGGER_TYPE_CHORD: i32 = 0;
+/// Must match EvdevKeyTrigger.TYPE_SEQUENCE in Kotlin.
+const KEY_TRIGGER_TYPE_SEQUENCE: i32 = 1;
+
+/// Must match EvdevKeyBehaviour.TYPE_TAP_HOLD in Kotlin.
+const KEY_BEHAVIOUR_TYPE_TAP_HOLD: i32 = 0;
+/// Must match EvdevKeyBehaviour.TYPE_LONG_PRESS in Kotlin.
+const KEY_BEHAVIOUR_TYPE_LONG_PRESS: i32 = 1;
+/// Must match EvdevKeyBehaviour.TYPE_DOUBLE_TAP in Kotlin.
+const KEY_BEHAVIOUR_TYPE_DOUBLE_TAP: i32 = 2;
+
+/// Must match GrabTargetKeyCode.NAME_MATCH_EXACT in Kotlin.
+const NAME_MATCH_EXACT: i32 = 0;
+/// Must match GrabTargetKeyCode.NAME_MATCH_WILDCARD in Kotlin.
+const NAME_MATCH_WILDCARD: i32 = 1;
+/// Must match GrabTargetKeyCode.NAME_MATCH_REGEX in Kotlin.
+const NAME_MATCH_REGEX: i32 = 2;
+
+/// Must match CapabilityGrabTargetKeyCode.CLASS_ALPHABETIC_KEYBOARD in Kotlin.
+const CAPABILITY_CLASS_ALPHABETIC_KEYBOARD: i32 = 0;
+/// Must match CapabilityGrabTargetKeyCode.CLASS_GAMEPAD in Kotlin.
+const CAPABILITY_CLASS_GAMEPAD: i32 = 1;
+/// Must match CapabilityGrabTargetKeyCode.CLASS_EVENT_CODE in Kotlin.
+const CAPABILITY_CLASS_EVENT_CODE: i32 = 2;
 
 static JNI_OBSERVER: OnceLock<EvdevJniObserver> = OnceLock::new();
 
@@ -28,8 +74,38 @@ impl EvdevCallback for JniEvdevCallback {
         device_id: usize,
         device_identifier: &EvdevDeviceInfo,
         event: &InputEvent,
+        usage_code: Option<u32>,
+    ) -> bool {
+        get_jni_observer().on_event(device_id, device_identifier, event, usage_code)
+    }
+
+    fn on_evdev_key_read(
+        &self,
+        _device_id: usize,
+        device_identifier: &EvdevDeviceInfo,
+        event: &InputEvent,
+        usage_code: Option<u32>,
+    ) {
+        get_jni_observer().on_key_read(device_identifier, event, usage_code)
+    }
+
+    fn on_evdev_axis_event(
+        &self,
+        device_id: usize,
+        device_identifier: &EvdevDeviceInfo,
+        event: &InputEvent,
+        abs_info: Option<&AbsInfo>,
     ) -> bool {
-        get_jni_observer().on_event(device_id, device_identifier, event)
+        get_jni_observer().on_axis_event(device_id, device_identifier, event, abs_info)
+    }
+
+    fn on_key_trigger_fired(
+        &self,
+        device_id: usize,
+        _device_identifier: &EvdevDeviceInfo,
+        trigger_id: u32,
+    ) {
+        get_jni_observer().on_key_trigger_fired(device_id, trigger_id)
     }
 
     fn on_grabbed_devices_changed(&self, grabbed_devices: Vec<GrabbedDeviceHandle>) {
@@ -42,10 +118,14 @@ impl EvdevCallback for JniEvdevCallback {
 }
 
 /// MUST only be called once in the lifetime of the process.
+/// The key layout directories are optional. Key layout files supplied by the user are
+/// searched before the system ones, and the files shipped with Key Mapper after them.
 #[no_mangle]
 pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_initEvdevManager(
-    env: JNIEnv,
+    mut env: JNIEnv,
     this: JObject,
+    j_bundled_key_layout_dir: JString,
+    j_user_key_layout_dir: JString,
 ) {
     // Get the JavaVM
     let jvm = env.get_java_vm().expect("Failed to get JavaVM");
@@ -72,8 +152,27 @@ pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemB
         .new_global_ref(this)
         .expect("Failed to create global reference to SystemBridge");
 
+    let bundled_key_layout_dir = get_optional_path(&mut env, &j_bundled_key_layout_dir);
+    let user_key_layout_dir = get_optional_path(&mut env, &j_user_key_layout_dir);
+
+    info!(
+        "Key layout directories: bundled {:?}, user {:?}",
+        bundled_key_layout_dir, user_key_layout_dir
+    );
+
+    KeyLayoutMapManager::init(Arc::new(AndroidKeyLayoutFileFinder::new(
+        bundled_key_layout_dir,
+        user_key_layout_dir,
+    )));
+
     // Initialize the JNI observer
     let key_layout_manager = KeyLayoutMapManager::get();
+
+    key_layout_manager
+        .start_watching()
+        .inspect_err(|e| error!("Failed to watch key layout files: {:?}", e))
+        .ok();
+
     let observer = EvdevJniObserver::new(jvm_arc, system_bridge, key_layout_manager);
 
     if JNI_OBSERVER.set(observer).is_err() {
@@ -81,7 +180,10 @@ pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemB
     }
 
     // Initialize and start the event loop with the callback
-    EventLoopManager::init(Arc::new(JniEvdevCallback));
+    EventLoopManager::init(
+        Arc::new(JniEvdevCallback),
+        vec![PathBuf::from(DEFAULT_INPUT_DEVICE_ROOT)],
+    );
 
     EventLoopManager::get()
         .start()
@@ -96,6 +198,8 @@ pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemB
 ) {
     info!("Destroying evdev manager");
 
+    KeyLayoutMapManager::get().stop_watching();
+
     EventLoopManager::get()
         .stop()
         .inspect_err(|e| error!("Failed to stop event loop: {:?}", e))
@@ -164,6 +268,49 @@ pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemB
     create_java_grabbed_device_handle_array(&mut env, grabbed_devices)
 }
 
+/// Set the classes of devices to grab. Takes an array of CapabilityGrabTargetKeyCode and returns an array of GrabbedDeviceHandle.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_setCapabilityGrabTargetsNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    j_targets: jobjectArray,
+) -> jobjectArray {
+    let mut targets: Vec<CapabilityGrabTargetKeyCode> = Vec::new();
+
+    let targets_array: JObjectArray = unsafe { JObjectArray::from_raw(j_targets) };
+
+    let array_length = match env.get_array_length(&targets_array) {
+        Ok(len) => len,
+        Err(e) => {
+            error!("Failed to get array length: {:?}", e);
+            return ptr::null_mut();
+        }
+    };
+
+    for i in 0..array_length {
+        let obj = match env.get_object_array_element(&targets_array, i) {
+            Ok(o) => o,
+            Err(e) => {
+                error!("Failed to get array element {}: {:?}", i, e);
+                continue;
+            }
+        };
+
+        match parse_capability_grab_target_key_code(&mut env, &obj) {
+            Ok(target) => targets.push(target),
+            Err(e) => {
+                error!(
+                    "Failed to parse CapabilityGrabTargetKeyCode at index {}: {:?}",
+                    i, e
+                );
+            }
+        }
+    }
+
+    let grabbed_devices = EventLoopManager::get().set_capability_grab_targets(targets);
+    create_java_grabbed_device_handle_array(&mut env, grabbed_devices)
+}
+
 /// Write an event to a grabbed device using its device ID
 #[no_mangle]
 pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_writeEvdevEventNative(
@@ -193,6 +340,245 @@ pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemB
         .is_ok() as jboolean
 }
 
+/// Replace the scan code remap table of a grabbed device. Each scan code in j_from_scan_codes
+/// is remapped to the scan code at the same index in j_to_scan_codes. A negative
+/// destination scan code drops the key instead.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_setRemapTableNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    j_device_id: jint,
+    j_from_scan_codes: JIntArray,
+    j_to_scan_codes: JIntArray,
+) -> jboolean {
+    let remap_table = match parse_remap_table(&mut env, &j_from_scan_codes, &j_to_scan_codes) {
+        Ok(table) => table,
+        Err(e) => {
+            error!("Failed to parse remap table: {:?}", e);
+            return false as jboolean;
+        }
+    };
+
+    EventLoopManager::get()
+        .set_remap_table(j_device_id as usize, remap_table)
+        .inspect_err(|e| error!("Failed to set remap table: {:?}", e))
+        .is_ok() as jboolean
+}
+
+/// Replace the chord and sequence triggers of a grabbed device. Takes an array of EvdevKeyTrigger.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_setKeyTriggersNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    j_device_id: jint,
+    j_triggers: jobjectArray,
+) -> jboolean {
+    let triggers_array: JObjectArray = unsafe { JObjectArray::from_raw(j_triggers) };
+
+    let array_length = match env.get_array_length(&triggers_array) {
+        Ok(len) => len,
+        Err(e) => {
+            error!("Failed to get array length: {:?}", e);
+            return false as jboolean;
+        }
+    };
+
+    let mut triggers: Vec<KeyTrigger> = Vec::with_capacity(array_length as usize);
+
+    for i in 0..array_length {
+        let obj = match env.get_object_array_element(&triggers_array, i) {
+            Ok(o) => o,
+            Err(e) => {
+                error!("Failed to get array element {}: {:?}", i, e);
+                continue;
+            }
+        };
+
+        match parse_key_trigger(&mut env, &obj) {
+            Ok(Some(trigger)) => triggers.push(trigger),
+            Ok(None) => error!("Unknown EvdevKeyTrigger type at index {}", i),
+            Err(e) => {
+                error!("Failed to parse EvdevKeyTrigger at index {}: {:?}", i, e);
+            }
+        }
+    }
+
+    EventLoopManager::get()
+        .set_key_triggers(j_device_id as usize, triggers)
+        .inspect_err(|e| error!("Failed to set key triggers: {:?}", e))
+        .is_ok() as jboolean
+}
+
+/// Replace the key behaviours of a grabbed device. Takes an array of EvdevKeyBehaviour.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_setKeyBehavioursNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    j_device_id: jint,
+    j_behaviours: jobjectArray,
+) -> jboolean {
+    let behaviours_array: JObjectArray = unsafe { JObjectArray::from_raw(j_behaviours) };
+
+    let array_length = match env.get_array_length(&behaviours_array) {
+        Ok(len) => len,
+        Err(e) => {
+            error!("Failed to get array length: {:?}", e);
+            return false as jboolean;
+        }
+    };
+
+    let mut behaviours: Vec<KeyBehaviour> = Vec::with_capacity(array_length as usize);
+
+    for i in 0..array_length {
+        let obj = match env.get_object_array_element(&behaviours_array, i) {
+            Ok(o) => o,
+            Err(e) => {
+                error!("Failed to get array element {}: {:?}", i, e);
+                return false as jboolean;
+            }
+        };
+
+        match parse_key_behaviour(&mut env, &obj) {
+            Ok(Some(behaviour)) => behaviours.push(behaviour),
+            Ok(None) => {
+                error!("Unknown EvdevKeyBehaviour type at index {}", i);
+                return false as jboolean;
+            }
+            Err(e) => {
+                error!("Failed to parse EvdevKeyBehaviour at index {}: {:?}", i, e);
+                return false as jboolean;
+            }
+        }
+    }
+
+    EventLoopManager::get()
+        .set_key_behaviours(j_device_id as usize, behaviours)
+        .inspect_err(|e| error!("Failed to set key behaviours: {:?}", e))
+        .is_ok() as jboolean
+}
+
+/// Replace the axes of a grabbed device that press keys. Takes an array of
+/// EvdevAxisKeyMapping.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_setAxisKeyMappingsNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    j_device_id: jint,
+    j_mappings: jobjectArray,
+) -> jboolean {
+    let mappings_array: JObjectArray = unsafe { JObjectArray::from_raw(j_mappings) };
+
+    let array_length = match env.get_array_length(&mappings_array) {
+        Ok(len) => len,
+        Err(e) => {
+            error!("Failed to get array length: {:?}", e);
+            return false as jboolean;
+        }
+    };
+
+    let mut mappings: Vec<AxisKeyMapping> = Vec::with_capacity(array_length as usize);
+
+    for i in 0..array_length {
+        let obj = match env.get_object_array_element(&mappings_array, i) {
+            Ok(o) => o,
+            Err(e) => {
+                error!("Failed to get array element {}: {:?}", i, e);
+                return false as jboolean;
+            }
+        };
+
+        match parse_axis_key_mapping(&mut env, &obj) {
+            Ok(mapping) => mappings.push(mapping),
+            Err(e) => {
+                error!(
+                    "Failed to parse EvdevAxisKeyMapping at index {}: {:?}",
+                    i, e
+                );
+                return false as jboolean;
+            }
+        }
+    }
+
+    EventLoopManager::get()
+        .set_axis_key_mappings(j_device_id as usize, mappings)
+        .inspect_err(|e| error!("Failed to set axis key mappings: {:?}", e))
+        .is_ok() as jboolean
+}
+
+/// Start recording the events of a grabbed device to an evemu-record file at the given path.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_startEvdevRecordingNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    j_device_id: jint,
+    j_path: JString,
+) -> jboolean {
+    let path: String = match env.get_string(&j_path) {
+        Ok(path) => path.into(),
+        Err(e) => {
+            error!("Failed to get recording path: {:?}", e);
+            return false as jboolean;
+        }
+    };
+
+    EventLoopManager::get()
+        .start_recording(j_device_id as usize, &PathBuf::from(path))
+        .inspect_err(|e| error!("Failed to start recording: {:?}", e))
+        .is_ok() as jboolean
+}
+
+/// Stop recording the events of a grabbed device. Returns false if it was not being recorded.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_stopEvdevRecordingNative(
+    _env: JNIEnv,
+    _class: JClass,
+    j_device_id: jint,
+) -> jboolean {
+    EventLoopManager::get()
+        .stop_recording(j_device_id as usize)
+        .inspect_err(|e| error!("Failed to stop recording: {:?}", e))
+        .unwrap_or(false) as jboolean
+}
+
+/// Replay an evemu-record file with its original timing divided by the speed. A negative
+/// device ID replays it through a new device that is created from the recorded description.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_startEvdevReplayNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    j_path: JString,
+    j_device_id: jint,
+    j_speed: jfloat,
+) -> jboolean {
+    let path: String = match env.get_string(&j_path) {
+        Ok(path) => path.into(),
+        Err(e) => {
+            error!("Failed to get replay path: {:?}", e);
+            return false as jboolean;
+        }
+    };
+
+    let target = if j_device_id < 0 {
+        ReplayTarget::NewDevice
+    } else {
+        ReplayTarget::GrabbedDevice(j_device_id as usize)
+    };
+
+    EventLoopManager::get()
+        .start_replay(&PathBuf::from(path), target, j_speed as f64)
+        .inspect_err(|e| error!("Failed to start replay: {:?}", e))
+        .is_ok() as jboolean
+}
+
+/// Stop the replay that is in progress.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_stopEvdevReplayNative(
+    _env: JNIEnv,
+    _class: JClass,
+) {
+    EventLoopManager::get().stop_replay();
+}
+
 /// Get all available evdev devices (returns EvdevDeviceInfo array)
 #[no_mangle]
 pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_getEvdevDevicesNative(
@@ -206,13 +592,7 @@ pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemB
         Ok(devices) => {
             for device in devices {
                 // Create EvdevDeviceInfo
-                match create_java_evdev_device_info(
-                    &mut env,
-                    &device.name,
-                    device.bus as i32,
-                    device.vendor as i32,
-                    device.product as i32,
-                ) {
+                match create_java_evdev_device_info(&mut env, &device) {
                     Ok(info) => device_infos.push(info),
                     Err(e) => {
                         error!("Failed to create EvdevDeviceInfo: {:?}", e);
@@ -254,11 +634,187 @@ pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemB
     array.into_raw()
 }
 
+/// Get everything that the device at the path, or the device that the path links to, can
+/// report. Returns null if there is no input device at the path.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_getEvdevDeviceCapabilitiesNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    j_device_path: JString,
+) -> jobject {
+    let device_path: String = match env.get_string(&j_device_path) {
+        Ok(path) => path.into(),
+        Err(e) => {
+            error!("Failed to get device path: {:?}", e);
+            return ptr::null_mut();
+        }
+    };
+
+    let capabilities =
+        match EventLoopManager::get().get_device_capabilities(&PathBuf::from(&device_path)) {
+            Ok(capabilities) => capabilities,
+            Err(e) => {
+                error!(
+                    "Failed to get capabilities of device {}: {:?}",
+                    device_path, e
+                );
+                return ptr::null_mut();
+            }
+        };
+
+    match create_java_evdev_device_capabilities(&mut env, &capabilities) {
+        Ok(obj) => obj,
+        Err(e) => {
+            error!("Failed to create EvdevDeviceCapabilities: {:?}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Check a key layout file for problems so they can be shown to the user. Returns every
+/// error and warning in the file formatted like "error: path:line:column: message". The
+/// array is empty if the file has no problems.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_checkKeyLayoutFileNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    j_path: JString,
+) -> jobjectArray {
+    let path: String = match env.get_string(&j_path) {
+        Ok(path) => path.into(),
+        Err(e) => {
+            error!("Failed to get key layout file path: {:?}", e);
+            return ptr::null_mut();
+        }
+    };
+
+    let diagnostics = match KeyLayoutMap::load_lenient_from_file(PathBuf::from(path)) {
+        Ok(report) => report.diagnostics,
+        Err(e) => vec![e],
+    };
+
+    let messages: Vec<String> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let severity = match diagnostic.severity() {
+                KeyLayoutParseSeverity::Error => "error",
+                KeyLayoutParseSeverity::Warning => "warning",
+            };
+
+            format!("{}: {}", severity, diagnostic)
+        })
+        .collect();
+
+    create_java_string_array(&mut env, &messages)
+        .inspect_err(|e| error!("Failed to create key layout diagnostics array: {:?}", e))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Create a virtual input device that is not tied to a grabbed device, such as a keyboard to
+/// inject keys with when no device is grabbed. Takes the name, ids and an
+/// EvdevDeviceCapabilities. Returns the ID of the virtual device or -1 if it could not be
+/// created.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_createVirtualDeviceNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    j_name: JString,
+    j_bus: jint,
+    j_vendor: jint,
+    j_product: jint,
+    j_version: jint,
+    j_capabilities: JObject,
+) -> jint {
+    let name: String = match env.get_string(&j_name) {
+        Ok(name) => name.into(),
+        Err(e) => {
+            error!("Failed to get virtual device name: {:?}", e);
+            return -1;
+        }
+    };
+
+    let capabilities = match parse_evdev_device_capabilities(&mut env, &j_capabilities) {
+        Ok(capabilities) => capabilities,
+        Err(e) => {
+            error!("Failed to parse EvdevDeviceCapabilities: {:?}", e);
+            return -1;
+        }
+    };
+
+    let description = VirtualDeviceDescription {
+        name,
+        bus: j_bus as u16,
+        vendor: j_vendor as u16,
+        product: j_product as u16,
+        version: j_version as u16,
+        capabilities,
+    };
+
+    match EventLoopManager::get().create_virtual_device(description) {
+        Ok(virtual_device_id) => virtual_device_id as jint,
+        Err(e) => {
+            error!("Failed to create virtual device: {:?}", e);
+            -1
+        }
+    }
+}
+
+/// Destroy a virtual device. Returns false if there is no virtual device with the ID.
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_destroyVirtualDeviceNative(
+    _env: JNIEnv,
+    _class: JClass,
+    j_virtual_device_id: jint,
+) -> jboolean {
+    EventLoopManager::get()
+        .destroy_virtual_device(j_virtual_device_id as usize)
+        .inspect_err(|e| error!("Failed to destroy virtual device: {:?}", e))
+        .is_ok() as jboolean
+}
+
+/// Write an event to a virtual device using its virtual device ID
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_writeVirtualDeviceEventNative(
+    _env: JNIEnv,
+    _class: JClass,
+    j_virtual_device_id: jint,
+    j_type: jint,
+    j_code: jint,
+    j_value: jint,
+) -> jboolean {
+    EventLoopManager::get()
+        .write_virtual_device_event(
+            j_virtual_device_id as usize,
+            j_type as u32,
+            j_code as u32,
+            j_value,
+        )
+        .is_ok() as jboolean
+}
+
+/// Write an Android key code to a virtual device using its virtual device ID
+#[no_mangle]
+pub extern "system" fn Java_io_github_sds100_keymapper_sysbridge_service_SystemBridge_writeVirtualDeviceKeyCodeNative(
+    _env: JNIEnv,
+    _class: JClass,
+    j_virtual_device_id: jint,
+    j_key_code: jint,
+    j_value: jint,
+) -> jboolean {
+    EventLoopManager::get()
+        .write_virtual_device_key_code_event(
+            j_virtual_device_id as usize,
+            j_key_code as u32,
+            j_value,
+        )
+        .is_ok() as jboolean
+}
+
 /// Parse a Java GrabTargetKeyCode object into a Rust GrabTargetKeyCode
 fn parse_grab_target_key_code(
     env: &mut JNIEnv,
     obj: &JObject,
-) -> Result<GrabTargetKeyCode, jni::errors::Error> {
+) -> Result<GrabTargetKeyCode, Box<dyn Error>> {
     // Get name field
     let name_field = env.get_field(obj, "name", "Ljava/lang/String;")?;
     let name_obj = name_field.l()?;
@@ -277,27 +833,364 @@ fn parse_grab_target_key_code(
     // Get product field
     let product = env.get_field(obj, "product", "I")?.i()?;
 
-    // Get the extraKeyCodes field (int[])
-    let extra_codes_field = env.get_field(obj, "extraKeyCodes", "[I")?;
-    let extra_codes_obj = extra_codes_field.l()?;
-    let extra_codes_array = JIntArray::from(extra_codes_obj);
+    // Get nameMatchType field
+    let name_match_type = env.get_field(obj, "nameMatchType", "I")?.i()?;
 
-    // Convert Java int[] to Vec<u32>
-    let array_length = env.get_array_length(&extra_codes_array)? as usize;
-    let mut extra_key_codes: Vec<u32> = Vec::with_capacity(array_length);
-    let mut buffer = vec![0i32; array_length];
-    env.get_int_array_region(&extra_codes_array, 0, &mut buffer)?;
-    extra_key_codes.extend(buffer.iter().map(|&v| v as u32));
+    let name = match name_match_type {
+        NAME_MATCH_EXACT => DeviceNameMatcher::exact(&name),
+        NAME_MATCH_WILDCARD => DeviceNameMatcher::wildcard(&name),
+        NAME_MATCH_REGEX => DeviceNameMatcher::regex(&name)?,
+        _ => return Err(format!("Unknown name match type {}", name_match_type).into()),
+    };
+
+    // Get the optional version field (Integer)
+    let version_obj = env.get_field(obj, "version", "Ljava/lang/Integer;")?.l()?;
+    let version = if version_obj.is_null() {
+        None
+    } else {
+        Some(env.call_method(&version_obj, "intValue", "()I", &[])?.i()? as u16)
+    };
+
+    let phys_prefix = get_optional_string_field(env, obj, "physPrefix")?;
+    let uniq = get_optional_string_field(env, obj, "uniq")?;
+
+    let extra_key_codes = get_extra_key_codes(env, obj)?;
+    let forward_axes = env.get_field(obj, "forwardAxes", "Z")?.z()?;
 
     Ok(GrabTargetKeyCode {
         name,
         bus: bus as u16,
         vendor: vendor as u16,
         product: product as u16,
+        version,
+        phys_prefix,
+        uniq,
+        extra_key_codes,
+        forward_axes,
+    })
+}
+
+/// Parse a Java CapabilityGrabTargetKeyCode object into a Rust CapabilityGrabTargetKeyCode
+fn parse_capability_grab_target_key_code(
+    env: &mut JNIEnv,
+    obj: &JObject,
+) -> Result<CapabilityGrabTargetKeyCode, Box<dyn Error>> {
+    let class_type = env.get_field(obj, "type", "I")?.i()?;
+
+    let class = match class_type {
+        CAPABILITY_CLASS_ALPHABETIC_KEYBOARD => DeviceCapabilityClass::AlphabeticKeyboard,
+        CAPABILITY_CLASS_GAMEPAD => DeviceCapabilityClass::Gamepad,
+        CAPABILITY_CLASS_EVENT_CODE => {
+            let event_type = env.get_field(obj, "eventType", "I")?.i()?;
+            let event_code = env.get_field(obj, "eventCode", "I")?.i()?;
+
+            // int_to_event_code panics if the type is unknown.
+            if int_to_event_type(event_type as u32).is_none() {
+                return Err(format!("Unknown event type {}", event_type).into());
+            }
+
+            let event_code = match int_to_event_code(event_type as u32, event_code as u32) {
+                EventCode::EV_UNK { .. } => {
+                    return Err(format!(
+                        "Unknown event code {} for type {}",
+                        event_code, event_type
+                    )
+                    .into())
+                }
+                event_code => event_code,
+            };
+
+            DeviceCapabilityClass::EventCode(event_code)
+        }
+        _ => return Err(format!("Unknown capability class {}", class_type).into()),
+    };
+
+    let extra_key_codes = get_extra_key_codes(env, obj)?;
+    let forward_axes = env.get_field(obj, "forwardAxes", "Z")?.z()?;
+
+    Ok(CapabilityGrabTargetKeyCode {
+        class,
         extra_key_codes,
+        forward_axes,
     })
 }
 
+/// Get the extraKeyCodes field (int[]) of a grab target.
+fn get_extra_key_codes(env: &mut JNIEnv, obj: &JObject) -> Result<Vec<u32>, jni::errors::Error> {
+    let extra_codes_field = env.get_field(obj, "extraKeyCodes", "[I")?;
+    let extra_codes_obj = extra_codes_field.l()?;
+    let extra_codes_array = JIntArray::from(extra_codes_obj);
+
+    Ok(get_int_array(env, &extra_codes_array)?
+        .into_iter()
+        .map(|v| v as u32)
+        .collect())
+}
+
+/// Convert a nullable Java string to a path. Returns None if it is null or can not be read.
+fn get_optional_path(env: &mut JNIEnv, j_path: &JString) -> Option<PathBuf> {
+    if j_path.is_null() {
+        return None;
+    }
+
+    env.get_string(j_path)
+        .inspect_err(|e| error!("Failed to get path: {:?}", e))
+        .ok()
+        .map(|path| PathBuf::from(String::from(path)))
+}
+
+fn get_optional_string_field(
+    env: &mut JNIEnv,
+    obj: &JObject,
+    name: &str,
+) -> Result<Option<String>, jni::errors::Error> {
+    let field_obj = env.get_field(obj, name, "Ljava/lang/String;")?.l()?;
+
+    if field_obj.is_null() {
+        return Ok(None);
+    }
+
+    let value = env
+        .get_string(&JString::from(field_obj))?
+        .to_string_lossy()
+        .into_owned();
+
+    Ok(Some(value))
+}
+
+fn parse_remap_table(
+    env: &mut JNIEnv,
+    j_from_scan_codes: &JIntArray,
+    j_to_scan_codes: &JIntArray,
+) -> Result<KeyRemapTable, Box<dyn Error>> {
+    let from_scan_codes = get_int_array(env, j_from_scan_codes)?;
+    let to_scan_codes = get_int_array(env, j_to_scan_codes)?;
+
+    if from_scan_codes.len() != to_scan_codes.len() {
+        return Err(format!(
+            "Remap table has {} source scan codes but {} destination scan codes",
+            from_scan_codes.len(),
+            to_scan_codes.len()
+        )
+        .into());
+    }
+
+    let remaps: HashMap<u32, ScanCodeRemap> = from_scan_codes
+        .iter()
+        .zip(to_scan_codes.iter())
+        .map(|(&from, &to)| {
+            let remap = if to < 0 {
+                ScanCodeRemap::Drop
+            } else {
+                ScanCodeRemap::Remap(to as u32)
+            };
+
+            (from as u32, remap)
+        })
+        .collect();
+
+    Ok(KeyRemapTable::new(remaps))
+}
+
+/// Parse a Java EvdevKeyTrigger object into a Rust KeyTrigger. Returns None if the type is unknown.
+fn parse_key_trigger(
+    env: &mut JNIEnv,
+    obj: &JObject,
+) -> Result<Option<KeyTrigger>, jni::errors::Error> {
+    let id = env.get_field(obj, "id", "I")?.i()?;
+    let trigger_type = env.get_field(obj, "type", "I")?.i()?;
+    let timeout_millis = env.get_field(obj, "timeoutMillis", "J")?.j()?;
+
+    let scan_codes_obj = env.get_field(obj, "scanCodes", "[I")?.l()?;
+    let scan_codes: Vec<u32> = get_int_array(env, &JIntArray::from(scan_codes_obj))?
+        .iter()
+        .map(|&v| v as u32)
+        .collect();
+
+    let duration = Duration::from_millis(timeout_millis.max(0) as u64);
+
+    let kind = match trigger_type {
+        KEY_TRIGGER_TYPE_CHORD => KeyTriggerKind::Chord {
+            scan_codes,
+            window: duration,
+        },
+        KEY_TRIGGER_TYPE_SEQUENCE => KeyTriggerKind::Sequence {
+            scan_codes,
+            timeout: duration,
+        },
+        _ => return Ok(None),
+    };
+
+    Ok(Some(KeyTrigger {
+        id: id as u32,
+        kind,
+    }))
+}
+
+/// Parse a Java EvdevKeyBehaviour object into a Rust KeyBehaviour. Returns None if the type
+/// is unknown.
+fn parse_key_behaviour(
+    env: &mut JNIEnv,
+    obj: &JObject,
+) -> Result<Option<KeyBehaviour>, jni::errors::Error> {
+    let scan_code = env.get_field(obj, "scanCode", "I")?.i()? as u32;
+    let behaviour_type = env.get_field(obj, "type", "I")?.i()?;
+    let tap_scan_code = env.get_field(obj, "tapScanCode", "I")?.i()? as u32;
+    let alternate_scan_code = env.get_field(obj, "alternateScanCode", "I")?.i()? as u32;
+    let timeout_millis = env.get_field(obj, "timeoutMillis", "J")?.j()?;
+
+    let timeout = Duration::from_millis(timeout_millis.max(0) as u64);
+
+    let kind = match behaviour_type {
+        KEY_BEHAVIOUR_TYPE_TAP_HOLD => KeyBehaviourKind::TapHold {
+            tap_scan_code,
+            hold_scan_code: alternate_scan_code,
+            hold_timeout: timeout,
+        },
+        KEY_BEHAVIOUR_TYPE_LONG_PRESS => KeyBehaviourKind::LongPress {
+            short_press_scan_code: tap_scan_code,
+            long_press_scan_code: alternate_scan_code,
+            long_press_timeout: timeout,
+        },
+        KEY_BEHAVIOUR_TYPE_DOUBLE_TAP => KeyBehaviourKind::DoubleTap {
+            single_tap_scan_code: tap_scan_code,
+            double_tap_scan_code: alternate_scan_code,
+            double_tap_timeout: timeout,
+        },
+        _ => return Ok(None),
+    };
+
+    Ok(Some(KeyBehaviour { scan_code, kind }))
+}
+
+/// Parse a Java EvdevAxisKeyMapping object into a Rust AxisKeyMapping.
+fn parse_axis_key_mapping(
+    env: &mut JNIEnv,
+    obj: &JObject,
+) -> Result<AxisKeyMapping, jni::errors::Error> {
+    let abs_code = env.get_field(obj, "absCode", "I")?.i()? as u32;
+    let low_scan_code = get_optional_int_field(env, obj, "lowScanCode")?;
+    let high_scan_code = get_optional_int_field(env, obj, "highScanCode")?;
+    let press_threshold = env.get_field(obj, "pressThreshold", "F")?.f()?;
+    let release_threshold = env.get_field(obj, "releaseThreshold", "F")?.f()?;
+
+    Ok(AxisKeyMapping {
+        abs_code,
+        low_scan_code: low_scan_code.map(|code| code as u32),
+        high_scan_code: high_scan_code.map(|code| code as u32),
+        press_threshold,
+        release_threshold,
+    })
+}
+
+/// Parse a Java EvdevDeviceCapabilities object into Rust DeviceCapabilities. The event
+/// types and LEDs are left out because they are derived from the event codes.
+fn parse_evdev_device_capabilities(
+    env: &mut JNIEnv,
+    obj: &JObject,
+) -> Result<DeviceCapabilities, Box<dyn Error>> {
+    let event_code_types_obj = env.get_field(obj, "eventCodeTypes", "[I")?.l()?;
+    let event_code_types = get_int_array(env, &JIntArray::from(event_code_types_obj))?;
+
+    let event_codes_obj = env.get_field(obj, "eventCodes", "[I")?.l()?;
+    let event_codes = get_int_array(env, &JIntArray::from(event_codes_obj))?;
+
+    let properties_obj = env.get_field(obj, "properties", "[I")?.l()?;
+    let properties = get_int_array(env, &JIntArray::from(properties_obj))?;
+
+    if event_code_types.len() != event_codes.len() {
+        return Err(format!(
+            "Capabilities have {} event code types but {} event codes",
+            event_code_types.len(),
+            event_codes.len()
+        )
+        .into());
+    }
+
+    let mut capabilities = DeviceCapabilities::default();
+
+    for (event_type, code) in event_code_types.iter().zip(event_codes.iter()) {
+        // int_to_event_code panics if the type is unknown.
+        if int_to_event_type(*event_type as u32).is_none() {
+            return Err(format!("Unknown event type {}", event_type).into());
+        }
+
+        capabilities
+            .event_codes
+            .entry(*event_type as u32)
+            .or_default()
+            .insert(*code as u32);
+    }
+
+    capabilities.properties = properties.iter().map(|prop| *prop as u32).collect();
+
+    let abs_info_obj = env
+        .get_field(
+            obj,
+            "absInfo",
+            "[Lio/github/sds100/keymapper/common/models/EvdevAbsInfo;",
+        )?
+        .l()?;
+    let abs_info_array = JObjectArray::from(abs_info_obj);
+
+    for i in 0..env.get_array_length(&abs_info_array)? {
+        let abs_info = env.get_object_array_element(&abs_info_array, i)?;
+        let code = env.get_field(&abs_info, "code", "I")?.i()? as u32;
+
+        let info = AbsInfo {
+            value: env.get_field(&abs_info, "value", "I")?.i()?,
+            minimum: env.get_field(&abs_info, "minimum", "I")?.i()?,
+            maximum: env.get_field(&abs_info, "maximum", "I")?.i()?,
+            fuzz: env.get_field(&abs_info, "fuzz", "I")?.i()?,
+            flat: env.get_field(&abs_info, "flat", "I")?.i()?,
+            resolution: env.get_field(&abs_info, "resolution", "I")?.i()?,
+        };
+
+        capabilities.abs_info.insert(code, info);
+    }
+
+    Ok(capabilities)
+}
+
+fn get_optional_int_field(
+    env: &mut JNIEnv,
+    obj: &JObject,
+    name: &str,
+) -> Result<Option<i32>, jni::errors::Error> {
+    let field_obj = env.get_field(obj, name, "Ljava/lang/Integer;")?.l()?;
+
+    if field_obj.is_null() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        env.call_method(&field_obj, "intValue", "()I", &[])?.i()?,
+    ))
+}
+
+/// Create a Java String[] from the strings.
+fn create_java_string_array(
+    env: &mut JNIEnv,
+    strings: &[String],
+) -> Result<jobjectArray, jni::errors::Error> {
+    let array = env.new_object_array(strings.len() as i32, "java/lang/String", JObject::null())?;
+
+    for (i, string) in strings.iter().enumerate() {
+        let j_string = env.new_string(string)?;
+        env.set_object_array_element(&array, i as i32, j_string)?;
+    }
+
+    Ok(array.into_raw())
+}
+
+/// Copy a Java int[] into a Vec
+fn get_int_array(env: &mut JNIEnv, array: &JIntArray) -> Result<Vec<i32>, jni::errors::Error> {
+    let array_length = env.get_array_length(array)? as usize;
+    let mut buffer = vec![0i32; array_length];
+    env.get_int_array_region(array, 0, &mut buffer)?;
+    Ok(buffer)
+}
+
 fn create_java_grabbed_device_handle_array(
     env: &mut JNIEnv,
     grabbed_devices: Vec<GrabbedDeviceHandle>,
@@ -328,6 +1221,7 @@ fn create_java_grabbed_device_handle_array(
             handle.device_info.bus as i32,
             handle.device_info.vendor as i32,
             handle.device_info.product as i32,
+            &handle.device_path.to_string_lossy(),
         ) {
             Ok(handle) => {
                 if let Err(e) = env.set_object_array_element(&array, i as i32, unsafe {
@@ -345,25 +1239,136 @@ fn create_java_grabbed_device_handle_array(
     array.into_raw()
 }
 
+/// Create a Java EvdevDeviceCapabilities object
+fn create_java_evdev_device_capabilities(
+    env: &mut JNIEnv,
+    capabilities: &DeviceCapabilities,
+) -> Result<jobject, jni::errors::Error> {
+    let event_types: Vec<i32> = capabilities
+        .event_types()
+        .iter()
+        .map(|event_type| *event_type as i32)
+        .collect();
+
+    let mut event_code_types: Vec<i32> = Vec::new();
+    let mut event_codes: Vec<i32> = Vec::new();
+
+    for (event_type, codes) in &capabilities.event_codes {
+        for code in codes {
+            event_code_types.push(*event_type as i32);
+            event_codes.push(*code as i32);
+        }
+    }
+
+    let properties: Vec<i32> = capabilities
+        .properties
+        .iter()
+        .map(|prop| *prop as i32)
+        .collect();
+    let leds: Vec<i32> = capabilities.leds().iter().map(|led| *led as i32).collect();
+
+    let abs_info_class = env.find_class("io/github/sds100/keymapper/common/models/EvdevAbsInfo")?;
+    let abs_info_array = env.new_object_array(
+        capabilities.abs_info.len() as i32,
+        &abs_info_class,
+        JObject::null(),
+    )?;
+
+    for (i, (code, info)) in capabilities.abs_info.iter().enumerate() {
+        let abs_info = env.new_object(
+            &abs_info_class,
+            "(IIIIIII)V",
+            &[
+                JValue::Int(*code as i32),
+                JValue::Int(info.value),
+                JValue::Int(info.minimum),
+                JValue::Int(info.maximum),
+                JValue::Int(info.fuzz),
+                JValue::Int(info.flat),
+                JValue::Int(info.resolution),
+            ],
+        )?;
+        env.set_object_array_element(&abs_info_array, i as i32, abs_info)?;
+    }
+
+    let (key_repeat_delay, key_repeat_period) = match capabilities.key_repeat {
+        Some(key_repeat) => (
+            new_java_integer(env, key_repeat.delay)?,
+            new_java_integer(env, key_repeat.period)?,
+        ),
+        None => (JObject::null(), JObject::null()),
+    };
+
+    let event_types_array = new_java_int_array(env, &event_types)?;
+    let event_code_types_array = new_java_int_array(env, &event_code_types)?;
+    let event_codes_array = new_java_int_array(env, &event_codes)?;
+    let properties_array = new_java_int_array(env, &properties)?;
+    let leds_array = new_java_int_array(env, &leds)?;
+
+    let class =
+        env.find_class("io/github/sds100/keymapper/common/models/EvdevDeviceCapabilities")?;
+    let obj = env.new_object(
+        class,
+        "([I[I[I[Lio/github/sds100/keymapper/common/models/EvdevAbsInfo;[ILjava/lang/Integer;Ljava/lang/Integer;[I)V",
+        &[
+            JValue::Object(&event_types_array),
+            JValue::Object(&event_code_types_array),
+            JValue::Object(&event_codes_array),
+            JValue::Object(&abs_info_array),
+            JValue::Object(&properties_array),
+            JValue::Object(&key_repeat_delay),
+            JValue::Object(&key_repeat_period),
+            JValue::Object(&leds_array),
+        ],
+    )?;
+
+    Ok(obj.into_raw())
+}
+
+fn new_java_int_array<'local>(
+    env: &mut JNIEnv<'local>,
+    values: &[i32],
+) -> Result<JIntArray<'local>, jni::errors::Error> {
+    let array = env.new_int_array(values.len() as i32)?;
+    env.set_int_array_region(&array, 0, values)?;
+    Ok(array)
+}
+
+fn new_java_integer<'local>(
+    env: &mut JNIEnv<'local>,
+    value: i32,
+) -> Result<JObject<'local>, jni::errors::Error> {
+    env.new_object("java/lang/Integer", "(I)V", &[JValue::Int(value)])
+}
+
 /// Create a Java EvdevDeviceInfo object
-fn create_java_evdev_device_info(
+pub(crate) fn create_java_evdev_device_info(
     env: &mut JNIEnv,
-    name: &str,
-    bus: i32,
-    vendor: i32,
-    product: i32,
+    device: &EvdevDeviceInfo,
 ) -> Result<jobject, jni::errors::Error> {
     let class = env.find_class("io/github/sds100/keymapper/common/models/EvdevDeviceInfo")?;
-    let name_str = env.new_string(name)?;
+    let name_str = env.new_string(&device.name)?;
+    let phys_str = env.new_string(&device.phys)?;
+    let uniq_str = env.new_string(&device.uniq)?;
+    let sysfs_path_str = env.new_string(
+        device
+            .sysfs_path
+            .as_ref()
+            .map(|path| path.to_string_lossy())
+            .unwrap_or_default(),
+    )?;
 
     let obj = env.new_object(
         class,
-        "(Ljava/lang/String;III)V",
+        "(Ljava/lang/String;IIILjava/lang/String;Ljava/lang/String;Ljava/lang/String;)V",
         &[
             JValue::Object(&name_str.into()),
-            JValue::Int(bus),
-            JValue::Int(vendor),
-            JValue::Int(product),
+            JValue::Int(device.bus as i32),
+            JValue::Int(device.vendor as i32),
+            JValue::Int(device.product as i32),
+            JValue::Object(&phys_str.into()),
+            JValue::Object(&uniq_str.into()),
+            JValue::Object(&sysfs_path_str.into()),
         ],
     )?;
 
@@ -378,19 +1383,22 @@ fn create_java_grabbed_device_handle(
     bus: i32,
     vendor: i32,
     product: i32,
+    path: &str,
 ) -> Result<jobject, jni::errors::Error> {
     let class = env.find_class("io/github/sds100/keymapper/common/models/GrabbedDeviceHandle")?;
     let name_str = env.new_string(name)?;
+    let path_str = env.new_string(path)?;
 
     let obj = env.new_object(
         class,
-        "(ILjava/lang/String;III)V",
+        "(ILjava/lang/String;IIILjava/lang/String;)V",
         &[
             JValue::Int(id),
             JValue::Object(&name_str.into()),
             JValue::Int(bus),
             JValue::Int(vendor),
             JValue::Int(product),
+            JValue::Object(&path_str.into()),
         ],
     )?;
 
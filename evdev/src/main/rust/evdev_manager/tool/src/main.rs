@@ -0,0 +1,348 @@
+//! Command line tool for checking and inspecting Android key layout (.kl) files with the same
+//! parser and search order that the evdev manager uses.
+
+mod tree_file_finder;
+
+use crate::tree_file_finder::TreeFileFinder;
+use evdev_manager_core::android::keylayout::input_event_lookup::{
+    get_axis_label, get_key_code_label, get_key_flag_labels, get_led_label, get_sensor_type_label,
+};
+use evdev_manager_core::android::keylayout::key_layout_map::{
+    KeyLayoutAxisMode, KeyLayoutKey, KeyLayoutMap,
+};
+use evdev_manager_core::android::keylayout::key_layout_map_manager::KeyLayoutMapManager;
+use evdev_manager_core::android::keylayout::key_layout_parse_error::{
+    KeyLayoutParseError, KeyLayoutParseSeverity,
+};
+use evdev_manager_core::evdev_device_info::EvdevDeviceInfo;
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+const USAGE: &str = "\
+Usage:
+  key_layout_tool lint [--strict] <file.kl>...
+      Report every error and warning in the files. Fails if a file has errors, or
+      warnings too with --strict.
+
+  key_layout_tool dump <file.kl>
+      Print the keys, axes, LEDs and sensors that a file maps, and the scan codes that
+      each key code maps back to.
+
+  key_layout_tool find --root <dir> --vendor <id> --product <id> [--version <id>]
+                       [--name <device name>] [--user-dir <dir>] [--bundled-dir <dir>]
+      Show which key layout files a device would use, in order of priority. The root is
+      searched like an Android file system, such as <root>/system/usr/keylayout. The ids
+      are hexadecimal.
+";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match run(&args) {
+        Ok(exit_code) => exit_code,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Run a command. Returns an error message if the arguments are invalid.
+fn run(args: &[String]) -> Result<ExitCode, String> {
+    let (command, args) = args
+        .split_first()
+        .ok_or_else(|| "Expected a command.".to_string())?;
+
+    match command.as_str() {
+        "lint" => lint(args),
+        "dump" => dump(args),
+        "find" => find(args),
+        "help" | "--help" | "-h" => {
+            print!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        }
+        _ => Err(format!("Unknown command '{}'.", command)),
+    }
+}
+
+fn lint(args: &[String]) -> Result<ExitCode, String> {
+    let mut strict = false;
+    let mut paths = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "--strict" => strict = true,
+            _ if arg.starts_with("--") => return Err(format!("Unknown option '{}'.", arg)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if paths.is_empty() {
+        return Err("Expected at least one key layout file.".to_string());
+    }
+
+    let file_count = paths.len();
+    let mut error_count = 0;
+    let mut warning_count = 0;
+
+    for path in paths {
+        for diagnostic in load_diagnostics(path) {
+            println!("{}", format_diagnostic(&diagnostic));
+
+            if diagnostic.is_warning() {
+                warning_count += 1;
+            } else {
+                error_count += 1;
+            }
+        }
+    }
+
+    println!(
+        "{} error(s) and {} warning(s) in {} file(s).",
+        error_count, warning_count, file_count
+    );
+
+    if error_count > 0 || (strict && warning_count > 0) {
+        Ok(ExitCode::FAILURE)
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn load_diagnostics(path: PathBuf) -> Vec<KeyLayoutParseError> {
+    match KeyLayoutMap::load_lenient_from_file(path) {
+        Ok(report) => report.diagnostics,
+        Err(err) => vec![err],
+    }
+}
+
+fn format_diagnostic(diagnostic: &KeyLayoutParseError) -> String {
+    let severity = match diagnostic.severity() {
+        KeyLayoutParseSeverity::Error => "error",
+        KeyLayoutParseSeverity::Warning => "warning",
+    };
+
+    format!("{}: {}", severity, diagnostic)
+}
+
+/// Print the map in the key layout file syntax so it can be compared with the file. The
+/// entries with errors are skipped and the errors are printed to stderr.
+fn dump(args: &[String]) -> Result<ExitCode, String> {
+    let [path] = args else {
+        return Err("Expected one key layout file.".to_string());
+    };
+
+    let report = match KeyLayoutMap::load_lenient_from_file(PathBuf::from(path)) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("{}", format_diagnostic(&err));
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    for diagnostic in &report.diagnostics {
+        eprintln!("{}", format_diagnostic(diagnostic));
+    }
+
+    let map = &report.map;
+
+    println!("# Keys");
+    for (scan_code, key) in sorted(map.keys_by_scan_code()) {
+        println!("key {} {}", scan_code, format_key(key));
+    }
+
+    println!("\n# Keys by HID usage");
+    for (usage_code, key) in sorted(map.keys_by_usage_code()) {
+        println!("key usage {:#x} {}", usage_code, format_key(key));
+    }
+
+    println!("\n# Axes");
+    for (scan_code, axis_info) in sorted(map.axes()) {
+        let mut line = match axis_info.mode {
+            KeyLayoutAxisMode::Normal => format_axis(axis_info.axis),
+            KeyLayoutAxisMode::Invert => format!("invert {}", format_axis(axis_info.axis)),
+            KeyLayoutAxisMode::Split => format!(
+                "split {} {} {}",
+                axis_info.split_value.unwrap_or_default(),
+                format_axis(axis_info.axis),
+                axis_info.high_axis.map(format_axis).unwrap_or_default()
+            ),
+        };
+
+        if let Some(flat) = axis_info.flat_override {
+            line.push_str(&format!(" flat {}", flat));
+        }
+
+        println!("axis {:#04x} {}", scan_code, line);
+    }
+
+    println!("\n# LEDs");
+    for (scan_code, led_code) in sorted(map.leds_by_scan_code()) {
+        println!("led {:#04x} {}", scan_code, format_led(*led_code));
+    }
+    for (usage_code, led_code) in sorted(map.leds_by_usage_code()) {
+        println!("led usage {:#x} {}", usage_code, format_led(*led_code));
+    }
+
+    println!("\n# Sensors");
+    for (abs_code, sensor_info) in sorted(map.sensors_by_abs_code()) {
+        let sensor_type = get_sensor_type_label(sensor_info.sensor_type)
+            .unwrap_or_else(|| sensor_info.sensor_type.to_string());
+        let data_index = ["X", "Y", "Z"]
+            .get(sensor_info.data_index as usize)
+            .unwrap_or(&"?");
+
+        println!("sensor {:#04x} {} {}", abs_code, sensor_type, data_index);
+    }
+
+    println!("\n# Scan codes of each key code");
+    for (key_code, scan_codes) in sorted(map.scan_codes_by_key_code()) {
+        let scan_codes: Vec<String> = scan_codes.iter().map(u32::to_string).collect();
+        println!(
+            "# {} -> {}",
+            format_key_code(*key_code),
+            scan_codes.join(", ")
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The entries of a map sorted by their code.
+fn sorted<T>(map: &HashMap<u32, T>) -> Vec<(&u32, &T)> {
+    let mut entries: Vec<(&u32, &T)> = map.iter().collect();
+    entries.sort_by_key(|(code, _)| **code);
+    entries
+}
+
+fn format_key(key: &KeyLayoutKey) -> String {
+    let mut labels = vec![format_key_code(key.key_code)];
+    labels.extend(get_key_flag_labels(key.flags));
+    labels.join(" ")
+}
+
+fn format_key_code(key_code: u32) -> String {
+    get_key_code_label(key_code).unwrap_or_else(|| key_code.to_string())
+}
+
+fn format_axis(axis: u32) -> String {
+    get_axis_label(axis).unwrap_or_else(|| axis.to_string())
+}
+
+fn format_led(led_code: u32) -> String {
+    get_led_label(led_code).unwrap_or_else(|| led_code.to_string())
+}
+
+/// Print the key layout files that `KeyLayoutMapManager` would try for a device and which
+/// one it would use.
+fn find(args: &[String]) -> Result<ExitCode, String> {
+    let mut options = parse_options(
+        args,
+        &[
+            "--root",
+            "--vendor",
+            "--product",
+            "--version",
+            "--name",
+            "--user-dir",
+            "--bundled-dir",
+        ],
+    )?;
+
+    let root = options
+        .remove("--root")
+        .ok_or_else(|| "Expected --root.".to_string())?;
+
+    let device_info = EvdevDeviceInfo {
+        name: options.remove("--name").unwrap_or_default(),
+        bus: 0,
+        vendor: parse_id(options.remove("--vendor"), "--vendor")?,
+        product: parse_id(options.remove("--product"), "--product")?,
+        version: parse_id(
+            options.remove("--version").or(Some("0".to_string())),
+            "--version",
+        )?,
+        phys: String::new(),
+        uniq: String::new(),
+        sysfs_path: None,
+    };
+
+    let file_finder = TreeFileFinder::new(
+        PathBuf::from(root),
+        options.remove("--user-dir").map(PathBuf::from),
+        options.remove("--bundled-dir").map(PathBuf::from),
+    );
+    let manager = KeyLayoutMapManager::with_file_finder(Arc::new(file_finder));
+
+    match manager
+        .get_input_device_configuration(&device_info)
+        .and_then(|configuration| configuration.keyboard_layout().map(str::to_string))
+    {
+        Some(layout) => println!(
+            "Input device configuration sets keyboard.layout = {}",
+            layout
+        ),
+        None => println!("No input device configuration sets keyboard.layout"),
+    }
+
+    let paths = manager.find_key_layout_files(&device_info);
+    let mut used = false;
+
+    println!("Key layout files in order of priority:");
+
+    for path in paths {
+        let status = if used {
+            String::new()
+        } else {
+            match KeyLayoutMap::load_from_file(path.clone()) {
+                Ok(_) => {
+                    used = true;
+                    " (used)".to_string()
+                }
+                Err(err) => format!(" (rejected: {})", err),
+            }
+        };
+
+        println!("  {}{}", path.display(), status);
+    }
+
+    if !used {
+        println!("No key layout file can be used so the built-in Generic layout is used.");
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Parse "--option value" pairs. Returns an error if an option is not allowed or has no value.
+fn parse_options(args: &[String], allowed: &[&str]) -> Result<HashMap<String, String>, String> {
+    let mut options = HashMap::new();
+    let mut args = args.iter();
+
+    while let Some(option) = args.next() {
+        if !allowed.contains(&option.as_str()) {
+            return Err(format!("Unknown option '{}'.", option));
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Expected a value for {}.", option))?;
+
+        options.insert(option.clone(), value.clone());
+    }
+
+    Ok(options)
+}
+
+fn parse_id(value: Option<String>, option: &str) -> Result<u16, String> {
+    let value = value.ok_or_else(|| format!("Expected {}.", option))?;
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(&value);
+
+    u16::from_str_radix(digits, 16)
+        .map_err(|_| format!("Expected a hexadecimal id for {}, got '{}'.", option, value))
+}
@@ -0,0 +1,86 @@
+use evdev_manager_core::android::keylayout::key_layout_file_finder::{
+    KeyLayoutFileFinder, SYSTEM_PATH_PREFIXES,
+};
+use std::path::{Path, PathBuf};
+
+/// Finds files in a copy of an Android file system, such as the directories pulled from a
+/// device with adb. The system directories are searched inside the root, so
+/// /system/usr/keylayout is <root>/system/usr/keylayout.
+pub struct TreeFileFinder {
+    root: PathBuf,
+    user_key_layout_dir: Option<PathBuf>,
+    bundled_key_layout_dir: Option<PathBuf>,
+}
+
+impl TreeFileFinder {
+    pub fn new(
+        root: PathBuf,
+        user_key_layout_dir: Option<PathBuf>,
+        bundled_key_layout_dir: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            root,
+            user_key_layout_dir,
+            bundled_key_layout_dir,
+        }
+    }
+
+    fn system_directories(&self, directory: &str) -> Vec<PathBuf> {
+        SYSTEM_PATH_PREFIXES
+            .iter()
+            .map(|prefix| {
+                self.root
+                    .join(prefix.trim_start_matches('/'))
+                    .join(directory)
+            })
+            .collect()
+    }
+
+    fn find_system_file(&self, directory: &str, extension: &str, name: &str) -> Option<PathBuf> {
+        self.system_directories(directory)
+            .iter()
+            .find_map(|directory| find_file_in_directory(directory, extension, name))
+    }
+}
+
+impl KeyLayoutFileFinder for TreeFileFinder {
+    fn find_system_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        self.find_system_file("keylayout", "kl", name)
+    }
+
+    fn find_key_mapper_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        let directory = self.bundled_key_layout_dir.as_ref()?;
+        find_file_in_directory(directory, "kl", name)
+    }
+
+    fn find_user_key_layout_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        let directory = self.user_key_layout_dir.as_ref()?;
+        find_file_in_directory(directory, "kl", name)
+    }
+
+    fn key_layout_directories(&self) -> Vec<PathBuf> {
+        self.user_key_layout_dir
+            .iter()
+            .cloned()
+            .chain(self.system_directories("keylayout"))
+            .chain(self.bundled_key_layout_dir.iter().cloned())
+            .collect()
+    }
+
+    fn find_system_key_character_map_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        self.find_system_file("keychars", "kcm", name)
+    }
+
+    fn find_system_input_device_configuration_file_by_name(&self, name: &str) -> Option<PathBuf> {
+        self.find_system_file("idc", "idc", name)
+    }
+
+    fn input_device_configuration_directories(&self) -> Vec<PathBuf> {
+        self.system_directories("idc")
+    }
+}
+
+fn find_file_in_directory(directory: &Path, extension: &str, name: &str) -> Option<PathBuf> {
+    let path = directory.join(format!("{}.{}", name, extension));
+    path.is_file().then_some(path)
+}
@@ -0,0 +1,191 @@
+//! Tests that run the key layout tool binary.
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A temporary directory that is deleted when it is dropped.
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "key_layout_tool_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+
+        fs::remove_dir_all(&path).ok();
+        fs::create_dir_all(&path).unwrap();
+
+        Self { path }
+    }
+
+    fn write(&self, relative_path: &str, contents: &str) -> PathBuf {
+        let path = self.path.join(relative_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.path).ok();
+    }
+}
+
+fn test_data_file(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../core/tests/test_data")
+        .join(name)
+}
+
+fn run_tool(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_key_layout_tool"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_lint_valid_file() {
+    let path = test_data_file("Generic.kl");
+    let output = run_tool(&["lint", path.to_str().unwrap()]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "0 error(s) and 0 warning(s) in 1 file(s).\n"
+    );
+}
+
+#[test]
+fn test_lint_reports_every_problem() {
+    let dir = TempDir::new("lint");
+    let path = dir.write(
+        "Vendor_1234_Product_5678.kl",
+        "key 1 ESCAPE\nkey 1 TAB\nkey 2 NOT_A_KEY\n",
+    );
+    let path = path.to_str().unwrap();
+
+    let output = run_tool(&["lint", path]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "error: {path}:2:5: Duplicate entry for key scan code '1'.\n\
+             warning: {path}:3:7: Ignoring key scan code '2' with unknown key code label 'NOT_A_KEY'.\n\
+             1 error(s) and 1 warning(s) in 1 file(s).\n"
+        )
+    );
+}
+
+#[test]
+fn test_lint_strict_fails_on_warnings() {
+    let dir = TempDir::new("strict");
+    let path = dir.write("Warning.kl", "key 1 ESCAPE OEM_FLAG\n");
+    let path = path.to_str().unwrap();
+
+    assert!(run_tool(&["lint", path]).status.success());
+    assert_eq!(run_tool(&["lint", "--strict", path]).status.code(), Some(1));
+}
+
+#[test]
+fn test_dump_prints_key_layout() {
+    let dir = TempDir::new("dump");
+    let path = dir.write(
+        "Dump.kl",
+        "key 1 ESCAPE\nkey 142 SLEEP WAKE\nkey 143 SLEEP\naxis 0x05 split 0x7f LTRIGGER RTRIGGER flat 4\nled 0x00 NUM_LOCK\n",
+    );
+
+    let output = run_tool(&["dump", path.to_str().unwrap()]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "\
+# Keys
+key 1 ESCAPE
+key 142 SLEEP WAKE
+key 143 SLEEP
+
+# Keys by HID usage
+
+# Axes
+axis 0x05 split 127 LTRIGGER RTRIGGER flat 4
+
+# LEDs
+led 0x00 NUM_LOCK
+
+# Sensors
+
+# Scan codes of each key code
+# ESCAPE -> 1
+# SLEEP -> 142, 143
+"
+    );
+}
+
+#[test]
+fn test_find_key_layout_files() {
+    let dir = TempDir::new("find");
+    let vendor_file = dir.write(
+        "vendor/usr/keylayout/Vendor_1234_Product_5678.kl",
+        "key 1 ESCAPE\n",
+    );
+    let generic_file = dir.write("system/usr/keylayout/Generic.kl", "key 1 ESCAPE\n");
+    let user_file = dir.write("user/Vendor_1234_Product_5678.kl", "key abc ESCAPE\n");
+    let user_dir = dir.path.join("user");
+
+    let output = run_tool(&[
+        "find",
+        "--root",
+        dir.path.to_str().unwrap(),
+        "--vendor",
+        "0x1234",
+        "--product",
+        "5678",
+        "--user-dir",
+        user_dir.to_str().unwrap(),
+    ]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "No input device configuration sets keyboard.layout\n\
+             Key layout files in order of priority:\n  \
+             {}\n  \
+             {} (used)\n  \
+             {}\n",
+            format_args!(
+                "{} (rejected: {}:1:5: Expected key scan code number, got 'abc'.)",
+                user_file.display(),
+                user_file.display()
+            ),
+            vendor_file.display(),
+            generic_file.display()
+        )
+    );
+}
+
+#[test]
+fn test_invalid_arguments() {
+    assert_eq!(run_tool(&[]).status.code(), Some(2));
+    assert_eq!(run_tool(&["lint"]).status.code(), Some(2));
+    assert_eq!(
+        run_tool(&["find", "--root", "/", "--vendor", "xyz", "--product", "1"])
+            .status
+            .code(),
+        Some(2)
+    );
+}
@@ -40,7 +40,12 @@ import android.util.Log
 import android.view.InputEvent
 import androidx.annotation.RequiresApi
 import com.android.internal.telephony.ITelephony
+import io.github.sds100.keymapper.common.models.CapabilityGrabTargetKeyCode
+import io.github.sds100.keymapper.common.models.EvdevAxisKeyMapping
+import io.github.sds100.keymapper.common.models.EvdevDeviceCapabilities
 import io.github.sds100.keymapper.common.models.EvdevDeviceInfo
+import io.github.sds100.keymapper.common.models.EvdevKeyBehaviour
+import io.github.sds100.keymapper.common.models.EvdevKeyTrigger
 import io.github.sds100.keymapper.common.models.GrabTargetKeyCode
 import io.github.sds100.keymapper.common.models.GrabbedDeviceHandle
 import io.github.sds100.keymapper.common.models.ShellResult
@@ -51,7 +56,9 @@ import io.github.sds100.keymapper.sysbridge.ISystemBridge
 import io.github.sds100.keymapper.sysbridge.provider.BinderContainer
 import io.github.sds100.keymapper.sysbridge.provider.SystemBridgeBinderProvider
 import io.github.sds100.keymapper.sysbridge.utils.IContentProviderUtils
+import java.io.File
 import java.io.InterruptedIOException
+import java.util.zip.ZipFile
 import kotlin.system.exitProcess
 import kotlinx.coroutines.CoroutineScope
 import kotlinx.coroutines.Dispatchers
@@ -73,17 +80,90 @@ class SystemBridge : ISystemBridge.Stub() {
         devices: Array<GrabTargetKeyCode>,
     ): Array<GrabbedDeviceHandle>
 
+    @Suppress("KotlinJniMissingFunction")
+    external fun setCapabilityGrabTargetsNative(
+        targets: Array<CapabilityGrabTargetKeyCode>,
+    ): Array<GrabbedDeviceHandle>
+
     @Suppress("KotlinJniMissingFunction")
     external fun writeEvdevEventNative(deviceId: Int, type: Int, code: Int, value: Int): Boolean
 
     @Suppress("KotlinJniMissingFunction")
     external fun writeEvdevEventKeyCodeNative(deviceId: Int, keyCode: Int, value: Int): Boolean
 
+    @Suppress("KotlinJniMissingFunction")
+    external fun setRemapTableNative(
+        deviceId: Int,
+        fromScanCodes: IntArray,
+        toScanCodes: IntArray,
+    ): Boolean
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun setKeyTriggersNative(deviceId: Int, triggers: Array<EvdevKeyTrigger>): Boolean
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun setKeyBehavioursNative(
+        deviceId: Int,
+        behaviours: Array<EvdevKeyBehaviour>,
+    ): Boolean
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun setAxisKeyMappingsNative(
+        deviceId: Int,
+        mappings: Array<EvdevAxisKeyMapping>,
+    ): Boolean
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun startEvdevRecordingNative(deviceId: Int, path: String): Boolean
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun stopEvdevRecordingNative(deviceId: Int): Boolean
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun startEvdevReplayNative(path: String, deviceId: Int, speed: Float): Boolean
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun stopEvdevReplayNative()
+
     @Suppress("KotlinJniMissingFunction")
     external fun getEvdevDevicesNative(): Array<EvdevDeviceInfo>
 
     @Suppress("KotlinJniMissingFunction")
-    external fun initEvdevManager()
+    external fun getEvdevDeviceCapabilitiesNative(devicePath: String): EvdevDeviceCapabilities?
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun checkKeyLayoutFileNative(path: String): Array<String>?
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun createVirtualDeviceNative(
+        name: String,
+        bus: Int,
+        vendor: Int,
+        product: Int,
+        version: Int,
+        capabilities: EvdevDeviceCapabilities,
+    ): Int
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun destroyVirtualDeviceNative(virtualDeviceId: Int): Boolean
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun writeVirtualDeviceEventNative(
+        virtualDeviceId: Int,
+        type: Int,
+        code: Int,
+        value: Int,
+    ): Boolean
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun writeVirtualDeviceKeyCodeNative(
+        virtualDeviceId: Int,
+        keyCode: Int,
+        value: Int,
+    ): Boolean
+
+    @Suppress("KotlinJniMissingFunction")
+    external fun initEvdevManager(bundledKeyLayoutDir: String?, userKeyLayoutDir: String?)
 
     @Suppress("KotlinJniMissingFunction")
     external fun destroyEvdevManager()
@@ -107,11 +187,56 @@ class SystemBridge : ISystemBridge.Stub() {
         code: Int,
         value: Int,
         androidCode: Int,
+        flags: Int,
+    ): Boolean {
+        synchronized(evdevCallbackLock) {
+            val callback = evdevCallback ?: return false
+            return try {
+                callback.onEvdevEvent(
+                    deviceId,
+                    timeSec,
+                    timeUsec,
+                    type,
+                    code,
+                    value,
+                    androidCode,
+                    flags,
+                )
+            } catch (e: Exception) {
+                Log.e(TAG, "Error calling evdev callback", e)
+                false
+            }
+        }
+    }
+
+    /**
+     * Called from Rust via JNI when a grabbed device whose grab target forwards axes
+     * reports an EV_ABS or EV_REL event.
+     */
+    @Suppress("unused")
+    fun onEvdevAxisEvent(
+        deviceId: Int,
+        timeSec: Long,
+        timeUsec: Long,
+        type: Int,
+        code: Int,
+        value: Int,
+        axes: IntArray,
+        axisValues: FloatArray,
     ): Boolean {
         synchronized(evdevCallbackLock) {
             val callback = evdevCallback ?: return false
             return try {
-                callback.onEvdevEvent(deviceId, timeSec, timeUsec, type, code, value, androidCode)
+                callback.onEvdevAxisEvent(
+                    deviceId,
+                    timeSec,
+                    timeUsec,
+                    type,
+                    code,
+                    value,
+                    axes,
+                    axisValues,
+                )
             } catch (e: Exception) {
                 Log.e(TAG, "Error calling evdev callback", e)
                 false
@@ -119,6 +244,22 @@ class SystemBridge : ISystemBridge.Stub() {
         }
     }
 
+    /**
+     * Called from Rust via JNI when a chord or sequence trigger registered with
+     * [setKeyTriggersNative] fires.
+     */
+    @Suppress("unused")
+    fun onEvdevKeyTriggerFired(deviceId: Int, triggerId: Int) {
+        synchronized(evdevCallbackLock) {
+            val callback = evdevCallback ?: return
+            try {
+                callback.onEvdevKeyTriggerFired(deviceId, triggerId)
+            } catch (e: Exception) {
+                Log.e(TAG, "Error calling evdev callback", e)
+            }
+        }
+    }
+
     @Suppress("unused")
     fun onGrabbedDevicesChanged(devices: Array<GrabbedDeviceHandle>) {
         synchronized(evdevCallbackLock) {
@@ -181,6 +322,18 @@ class SystemBridge : ISystemBridge.Stub() {
         }
 
         private const val KEYMAPPER_CHECK_INTERVAL_MS = 60 * 1000L // 1 minute
+
+        /**
+         * The key layout files shipped in the APK are extracted here so the evdev manager
+         * can read them. They are searched after the system key layout files.
+         */
+        private const val BUNDLED_KEY_LAYOUT_DIR =
+            "/data/local/tmp/keymapper_sysbridge/bundled_keylayout"
+
+        /**
+         * Users can put their own key layout files here with adb to override the system ones.
+         */
+        private const val USER_KEY_LAYOUT_DIR = "/data/local/tmp/keymapper_sysbridge/keylayout"
         private const val DATA_ENABLED_REASON_USER: Int = 0
         private const val TETHERING_WIFI: Int = 0
 
@@ -395,7 +548,9 @@ class SystemBridge : ISystemBridge.Stub() {
             sendBinderToApp()
         }
 
-        initEvdevManager()
+        // Create the user directory so key layout files added to it later are watched.
+        File(USER_KEY_LAYOUT_DIR).mkdirs()
+        initEvdevManager(extractBundledKeyLayouts(), USER_KEY_LAYOUT_DIR)
 
         waitSystemService(Context.USER_SERVICE)
         waitSystemService(Context.APP_OPS_SERVICE)
@@ -403,6 +558,43 @@ class SystemBridge : ISystemBridge.Stub() {
         Log.i(TAG, "SystemBridge started complete. Version code $versionCode")
     }
 
+    /**
+     * Copy the key layout files in the assets/keylayout directory of the APK to a directory
+     * that the evdev manager can read. Returns the directory or null if they could not be copied.
+     */
+    private fun extractBundledKeyLayouts(): String? {
+        val apkPath = System.getProperty("java.class.path") ?: return null
+        val outputDir = File(BUNDLED_KEY_LAYOUT_DIR)
+
+        return try {
+            // Delete the files from the previous version of the app.
+            outputDir.deleteRecursively()
+            outputDir.mkdirs()
+
+            ZipFile(apkPath).use { zipFile ->
+                for (entry in zipFile.entries()) {
+                    if (entry.isDirectory ||
+                        !entry.name.startsWith("assets/keylayout/") ||
+                        !entry.name.endsWith(".kl")
+                    ) {
+                        continue
+                    }
+
+                    val outputFile = File(outputDir, entry.name.substringAfterLast('/'))
+
+                    zipFile.getInputStream(entry).use { input ->
+                        outputFile.outputStream().use { output -> input.copyTo(output) }
+                    }
+                }
+            }
+
+            outputDir.absolutePath
+        } catch (e: Exception) {
+            Log.e(TAG, "Failed to extract bundled key layout files", e)
+            null
+        }
+    }
+
     private fun hasSystemFeature(name: String): Boolean {
         return packageManager.hasSystemFeature(name, 0)
     }
@@ -488,6 +680,14 @@ class SystemBridge : ISystemBridge.Stub() {
         return setGrabTargetsNative(devices?.filterNotNull()?.toTypedArray() ?: emptyArray())
     }
 
+    override fun setCapabilityGrabTargets(
+        targets: Array<out CapabilityGrabTargetKeyCode?>?,
+    ): Array<out GrabbedDeviceHandle?> {
+        return setCapabilityGrabTargetsNative(
+            targets?.filterNotNull()?.toTypedArray() ?: emptyArray(),
+        )
+    }
+
     override fun injectInputEvent(event: InputEvent?, mode: Int): Boolean {
         try {
             return inputManager.injectInputEvent(event, mode)
@@ -501,6 +701,11 @@ class SystemBridge : ISystemBridge.Stub() {
         return getEvdevDevicesNative()
     }
 
+    override fun getEvdevDeviceCapabilities(devicePath: String?): EvdevDeviceCapabilities? {
+        devicePath ?: return null
+        return getEvdevDeviceCapabilitiesNative(devicePath)
+    }
+
     override fun setWifiEnabled(enable: Boolean): Boolean {
         if (wifiManager == null) {
             throw UnsupportedOperationException("WiFi not supported")
@@ -517,6 +722,98 @@ class SystemBridge : ISystemBridge.Stub() {
         return writeEvdevEventKeyCodeNative(deviceId, keyCode, value)
     }
 
+    override fun setRemapTable(
+        deviceId: Int,
+        fromScanCodes: IntArray?,
+        toScanCodes: IntArray?,
+    ): Boolean {
+        return setRemapTableNative(
+            deviceId,
+            fromScanCodes ?: IntArray(0),
+            toScanCodes ?: IntArray(0),
+        )
+    }
+
+    override fun setKeyTriggers(deviceId: Int, triggers: Array<out EvdevKeyTrigger?>?): Boolean {
+        return setKeyTriggersNative(
+            deviceId,
+            triggers?.filterNotNull()?.toTypedArray() ?: emptyArray(),
+        )
+    }
+
+    override fun setKeyBehaviours(
+        deviceId: Int,
+        behaviours: Array<out EvdevKeyBehaviour?>?,
+    ): Boolean {
+        return setKeyBehavioursNative(
+            deviceId,
+            behaviours?.filterNotNull()?.toTypedArray() ?: emptyArray(),
+        )
+    }
+
+    override fun setAxisKeyMappings(
+        deviceId: Int,
+        mappings: Array<out EvdevAxisKeyMapping?>?,
+    ): Boolean {
+        return setAxisKeyMappingsNative(
+            deviceId,
+            mappings?.filterNotNull()?.toTypedArray() ?: emptyArray(),
+        )
+    }
+
+    override fun checkKeyLayoutFile(path: String?): Array<String> {
+        path ?: return emptyArray()
+        return checkKeyLayoutFileNative(path) ?: emptyArray()
+    }
+
+    override fun createVirtualDevice(
+        name: String?,
+        bus: Int,
+        vendor: Int,
+        product: Int,
+        version: Int,
+        capabilities: EvdevDeviceCapabilities?,
+    ): Int {
+        name ?: return -1
+        capabilities ?: return -1
+        return createVirtualDeviceNative(name, bus, vendor, product, version, capabilities)
+    }
+
+    override fun destroyVirtualDevice(virtualDeviceId: Int): Boolean {
+        return destroyVirtualDeviceNative(virtualDeviceId)
+    }
+
+    override fun writeVirtualDeviceEvent(
+        virtualDeviceId: Int,
+        type: Int,
+        code: Int,
+        value: Int,
+    ): Boolean {
+        return writeVirtualDeviceEventNative(virtualDeviceId, type, code, value)
+    }
+
+    override fun writeVirtualDeviceKeyCode(virtualDeviceId: Int, keyCode: Int, value: Int): Boolean {
+        return writeVirtualDeviceKeyCodeNative(virtualDeviceId, keyCode, value)
+    }
+
+    override fun startEvdevRecording(deviceId: Int, path: String?): Boolean {
+        path ?: return false
+        return startEvdevRecordingNative(deviceId, path)
+    }
+
+    override fun stopEvdevRecording(deviceId: Int): Boolean {
+        return stopEvdevRecordingNative(deviceId)
+    }
+
+    override fun startEvdevReplay(path: String?, deviceId: Int, speed: Float): Boolean {
+        path ?: return false
+        return startEvdevReplayNative(path, deviceId, speed)
+    }
+
+    override fun stopEvdevReplay() {
+        stopEvdevReplayNative()
+    }
+
     override fun getProcessUid(): Int {
         return Process.myUid()
     }
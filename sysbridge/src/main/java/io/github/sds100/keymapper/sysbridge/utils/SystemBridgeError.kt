@@ -5,4 +5,5 @@ import io.github.sds100.keymapper.common.utils.KMError
 sealed class SystemBridgeError : KMError() {
     data object Disconnected : SystemBridgeError()
     data object WriteEvdevEventFailed : SystemBridgeError()
+    data object SetKeyTriggersFailed : SystemBridgeError()
 }
@@ -11,6 +11,10 @@ data class KMEvdevEvent(
     val androidCode: Int,
     val timeSec: Long,
     val timeUsec: Long,
+    /**
+     * The POLICY_FLAG_* bits of the key in the device's key layout file.
+     */
+    val flags: Int = 0,
 ) : KMInputEvent {
 
     companion object {
@@ -20,6 +24,10 @@ data class KMEvdevEvent(
 
         const val VALUE_DOWN = 1
         const val VALUE_UP = 0
+
+        // Look at Input.h in AOSP for where these are defined.
+        const val POLICY_FLAG_WAKE = 0x00000001
+        const val POLICY_FLAG_FUNCTION = 0x00000004
     }
 
     // Look at input-event-codes.h for where these are defined.
@@ -34,4 +42,14 @@ data class KMEvdevEvent(
 
     val isDownEvent: Boolean = isKeyEvent && value == VALUE_DOWN
     val isUpEvent: Boolean = isKeyEvent && value == VALUE_UP
+
+    /**
+     * Whether the key is on the Fn layer of the keyboard.
+     */
+    val isFunctionKey: Boolean = flags and POLICY_FLAG_FUNCTION != 0
+
+    /**
+     * Whether the key should wake the device when it is pressed.
+     */
+    val isWakeKey: Boolean = flags and POLICY_FLAG_WAKE != 0
 }